@@ -11,6 +11,95 @@ pub struct Config {
     pub bind_ip: String,
     pub tuf_repo_port: u16,
     pub external_ip: Option<String>,
+    #[serde(default = "default_buddy_capacity")]
+    pub buddy_capacity: u8,
+    #[serde(default = "default_trunk_put_fee")]
+    pub trunk_put_fee: u32,
+    #[serde(default)]
+    pub trunk_get_fee: u32,
+    #[serde(default = "default_autosave_interval_secs")]
+    pub autosave_interval_secs: u64,
+    #[serde(default = "default_cash_shop_port")]
+    pub cash_shop_port: u16,
+    /// Worlds of the server, if empty `num_worlds` worlds with `num_channels` are used
+    #[serde(default)]
+    pub worlds: Vec<WorldConfig>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WorldFlag {
+    #[default]
+    Normal,
+    Hot,
+    New,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct WorldConfig {
+    pub name: String,
+    #[serde(default)]
+    pub flag: WorldFlag,
+    #[serde(default)]
+    pub event_message: String,
+    /// Exp rate in percent
+    #[serde(default = "default_rate")]
+    pub exp_rate: u16,
+    /// Drop rate in percent
+    #[serde(default = "default_rate")]
+    pub drop_rate: u16,
+    /// Number of channels, defaults to `num_channels`
+    pub channels: Option<u16>,
+    /// Port of the first channel, defaults to the port after the previous world
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub block_char_creation: bool,
+}
+
+impl Config {
+    /// Returns the configured worlds
+    pub fn worlds(&self) -> Vec<WorldConfig> {
+        if !self.worlds.is_empty() {
+            return self.worlds.clone();
+        }
+
+        (0..self.num_worlds)
+            .map(|id| WorldConfig {
+                name: if id == 0 {
+                    self.server_name.clone()
+                } else {
+                    format!("{}-{}", self.server_name, id + 1)
+                },
+                flag: WorldFlag::Normal,
+                event_message: String::new(),
+                exp_rate: default_rate(),
+                drop_rate: default_rate(),
+                channels: None,
+                port: None,
+                block_char_creation: false,
+            })
+            .collect()
+    }
+}
+
+fn default_buddy_capacity() -> u8 {
+    20
+}
+
+fn default_trunk_put_fee() -> u32 {
+    100
+}
+
+fn default_rate() -> u16 {
+    100
+}
+
+fn default_autosave_interval_secs() -> u64 {
+    5 * 60
+}
+
+fn default_cash_shop_port() -> u16 {
+    8600
 }
 
 pub fn get_configuration(data_dir: impl AsRef<Path>) -> Result<Config, config::ConfigError> {
@@ -4,10 +4,12 @@ use dotenv::dotenv;
 
 use shroom_data::services::{server_service::ServerInfo, DataProvider};
 use shroom_game::{
-    services::shared::{PacketEOFHandler, Services, SharedServices},
+    cash_shop::CashShopSession,
+    services::shared::{GameConfig, PacketEOFHandler, Services, SharedServices},
     system::{GameCodec, GameSystem},
 };
 use shroom_login::LoginService;
+use shroom_proto95::login::{ChannelId, WorldId, WorldState};
 
 use shroom_meta::id::job_id::JobId;
 use shroom_srv::{act::system::SystemConfig, net::system::NetSystemHandler};
@@ -15,7 +17,7 @@ use shroom_srv::{act::system::SystemConfig, net::system::NetSystemHandler};
 use shroom_srv::runtime::{RuntimeConfig, RuntimeHandler, ServerRuntime};
 use tokio::time::interval;
 
-use crate::config::Environment;
+use crate::config::{Config, Environment, WorldFlag};
 
 mod config;
 
@@ -33,10 +35,9 @@ static BANNER: &str = r#"
 pub struct Mono {
     data_dir: PathBuf,
     env: Environment,
-    external_ip: IpAddr,
     login_port: u16,
-    game_ports: std::ops::RangeInclusive<u16>,
-    server_name: String,
+    servers: Vec<ServerInfo>,
+    game_config: GameConfig,
 }
 
 impl Mono {
@@ -52,13 +53,6 @@ impl Mono {
 
         let static_meta = Box::leak(meta);
 
-        let servers = [ServerInfo::new(
-            self.external_ip,
-            self.login_port,
-            self.server_name.clone(),
-            self.game_ports.clone().count(),
-        )];
-
         let data_services = match self.env {
             Environment::Local => DataProvider::seeded_in_memory(static_meta).await?,
             _ => {
@@ -101,18 +95,52 @@ impl Mono {
         let eof_handler = PacketEOFHandler::new(File::create("packets_eof.log")?);
         Ok(Services::new_with_eof(
             data_services,
-            servers,
+            self.servers.clone(),
             static_meta,
+            self.game_config.clone(),
             eof_handler,
         ))
     }
 }
 
+/// Lays out the worlds, the channels of the worlds listen on consecutive ports after the login
+fn build_worlds(settings: &Config, ip: IpAddr) -> Vec<ServerInfo> {
+    let mut next_port = settings.base_port + 1;
+    settings
+        .worlds()
+        .into_iter()
+        .map(|world| {
+            let channels = world.channels.unwrap_or(settings.num_channels);
+            let port = world.port.unwrap_or(next_port);
+            next_port = port + channels;
+
+            let mut info = ServerInfo::with_channel_port(
+                ip,
+                settings.base_port,
+                port,
+                world.name,
+                channels as usize,
+            );
+            info.state = match world.flag {
+                WorldFlag::Normal => WorldState::Normal,
+                WorldFlag::Hot => WorldState::Hot,
+                WorldFlag::New => WorldState::New,
+            };
+            info.event_desc = world.event_message;
+            info.exp_rate = world.exp_rate;
+            info.drop_rate = world.drop_rate;
+            info.block_char_creation = world.block_char_creation;
+            info
+        })
+        .collect()
+}
+
 pub struct MonoRuntime {}
 
 impl RuntimeHandler for MonoRuntime {
     type Ctx = SharedServices;
     type LoginService = LoginService<<GameSystem as NetSystemHandler>::Codec>;
+    type CashShopService = CashShopSession<<GameSystem as NetSystemHandler>::Codec>;
     type NetHandler = GameSystem;
 }
 
@@ -163,7 +191,7 @@ async fn run() -> anyhow::Result<()> {
 
     let ext_ip = std::env::var("EXTERNAL_IP")
         .ok()
-        .or(settings.external_ip)
+        .or(settings.external_ip.clone())
         .ok_or_else(|| anyhow::format_err!("No external IP set"))?;
 
     log::info!("External IP: {0}", ext_ip);
@@ -178,19 +206,31 @@ async fn run() -> anyhow::Result<()> {
     let mono = Mono {
         data_dir,
         env: config::get_environment(),
-        external_ip: server_addr,
-        login_port: 8484,
-        game_ports: 8485..=8485 + (settings.num_channels),
-        server_name: settings.server_name.clone(),
+        login_port: settings.base_port,
+        servers: build_worlds(&settings, server_addr),
+        game_config: GameConfig {
+            buddy_capacity: settings.buddy_capacity,
+            trunk_put_fee: settings.trunk_put_fee,
+            trunk_get_fee: settings.trunk_get_fee,
+            autosave_interval: Duration::from_secs(settings.autosave_interval_secs),
+            cash_shop_port: settings.cash_shop_port,
+        },
     };
     let services = Box::pin(mono.build_services()).await?;
+    // Hired merchants stay open across restarts
+    services.employee.load(&services.data).await?;
+    services.guild.load(&services.data).await?;
     let services = Arc::new(services);
     let cfg = RuntimeConfig {
         bind_addr,
-        login_port: 8484,
-        game_ports: 8485..=8486,
+        login_port: mono.login_port,
+        cash_shop_port: settings.cash_shop_port,
+        game_ports: mono
+            .servers
+            .iter()
+            .flat_map(|world| world.channels.iter().map(|ch| ch.port))
+            .collect(),
     };
-    let cdc_sys = build_codec(settings.client_version);
     let cdc_runtime = build_codec(settings.client_version);
     let svc = services.clone();
 
@@ -214,13 +254,43 @@ async fn run() -> anyhow::Result<()> {
         }
     });
 
-    let svc = services.clone();
-    let sys =
-        shroom_srv::act::system::System::new(GameSystem { services: svc }, SystemConfig::default());
-
-    let svc = services.clone();
-    let net_sys = shroom_srv::net::system::NetSystem::new(cdc_sys, GameSystem { services }, sys);
-    let runtime = ServerRuntime::<MonoRuntime>::new(&cfg, net_sys, cdc_runtime, svc);
+    // Each channel runs It's own system, so fields are not shared between channels
+    let channels = mono
+        .servers
+        .iter()
+        .enumerate()
+        .flat_map(|(world_id, world)| {
+            (0..world.channels.len())
+                .map(move |channel_id| (world_id as WorldId, channel_id as ChannelId))
+        })
+        .map(|(world_id, channel_id)| {
+            let sys = shroom_srv::act::system::System::new(
+                GameSystem {
+                    services: services.clone(),
+                    world_id,
+                    channel_id,
+                },
+                SystemConfig::default(),
+            );
+            shroom_srv::net::system::NetSystem::new(
+                build_codec(settings.client_version),
+                GameSystem {
+                    services: services.clone(),
+                    world_id,
+                    channel_id,
+                },
+                sys,
+            )
+        })
+        .collect();
+
+    let runtime = ServerRuntime::<MonoRuntime>::new(
+        &cfg,
+        channels,
+        cdc_runtime,
+        build_codec(settings.client_version),
+        services,
+    );
     log::info!("Spawning system...");
     runtime.run().await?;
 
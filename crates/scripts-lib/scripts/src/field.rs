@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use shroom_meta::{id::MobId, twod::Vec2};
+use shroom_proto95::game::field::FieldEffectResp;
+use shroom_script::field::{FieldCtx, FieldScriptEvent};
+
+pub fn field_boss_intro(api: &mut dyn FieldCtx) -> anyhow::Result<()> {
+    const MANO: MobId = MobId(2220000);
+    if api.event() != FieldScriptEvent::FirstUserEnter {
+        return Ok(());
+    }
+
+    api.show_effect(FieldEffectResp::ChangeBgm("Bgm14/HonTale".to_string()))?;
+    api.spawn_mob(MANO, Vec2::new(0, 0))?;
+    api.start_clock(Duration::from_secs(10 * 60))?;
+    api.broadcast_message("Mano appeared, defeat it within 10 minutes!")
+}
+
+/// Maple Island field without an enter script, it's looked up by the field id
+pub fn field_maple_island(api: &mut dyn FieldCtx) -> anyhow::Result<()> {
+    if api.event() != FieldScriptEvent::UserEnter {
+        return Ok(());
+    }
+
+    api.message("Welcome to Maple Island, talk to the npcs to learn the basics.")
+}
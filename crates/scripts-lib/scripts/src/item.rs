@@ -0,0 +1,51 @@
+use rand::seq::SliceRandom;
+use shroom_meta::id::{FieldId, ItemId};
+use shroom_script::npc::NpcCtx;
+
+const HENESYS: FieldId = FieldId(100000000);
+
+pub async fn item_event_box(mut api: NpcCtx) -> anyhow::Result<()> {
+    const REWARDS: [(ItemId, usize); 3] = [
+        (ItemId(2000000), 50),
+        (ItemId(2000003), 50),
+        (ItemId(2040002), 1),
+    ];
+    api.wait_for_start().await?;
+
+    let (item, count) = *REWARDS.choose(&mut rand::thread_rng()).unwrap();
+    if !api.try_give_item(item, count)? {
+        api.say_end("Please make some room in your inventory first.")
+            .await?;
+        return Ok(());
+    }
+
+    // Hand the reward back, if the box is gone already
+    if !api.consume_item()? {
+        api.try_take_item(item, count)?;
+        api.say_end("You don't have the box anymore.").await?;
+        return Ok(());
+    }
+
+    api.say_end("You opened the box and found something inside!")
+        .await?;
+    Ok(())
+}
+
+pub async fn item_teleport_ticket(mut api: NpcCtx) -> anyhow::Result<()> {
+    api.wait_for_start().await?;
+
+    if !api
+        .ask_yes_no("Do you want to use the ticket to go to Henesys?")
+        .await?
+    {
+        return Ok(());
+    }
+
+    if !api.consume_item()? {
+        api.say_end("You don't have the ticket anymore.").await?;
+        return Ok(());
+    }
+
+    api.transfer_field(HENESYS)?;
+    Ok(())
+}
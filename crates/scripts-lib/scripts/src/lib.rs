@@ -1,10 +1,23 @@
-use shroom_script::{npc::FutureNpcPlugin, BoxedNpcPlugin, PluginBundle, PluginId};
+use shroom_script::{
+    npc::FutureNpcPlugin, BoxedFieldPlugin, BoxedNpcPlugin, BoxedPortalPlugin, BoxedReactorPlugin,
+    PluginBundle, PluginId,
+};
 
+pub mod field;
+pub mod item;
 pub mod job_adv;
+pub mod portal;
+pub mod quest;
+pub mod reactor;
 pub mod samples;
 
 macro_rules! plugin_bundle {
-    ($name:ident, $(($id:expr, $pname:ident, $pfn:path)),*,$fallback:path) => {
+    ($name:ident, $(($id:expr, $pname:ident, $pfn:path)),*,$fallback:path,
+        quest: [$(($quest:ident, $quest_fn:path)),*],
+        item: [$(($item:ident, $item_fn:path)),*],
+        portal: [$(($portal:ident, $portal_fn:path)),*],
+        reactor: [$(($reactor:ident, $reactor_fn:path)),*],
+        field: [$(($field:ident, $field_fn:path)),*]) => {
         pub struct $name;
 
         impl Default for $name {
@@ -31,6 +44,41 @@ macro_rules! plugin_bundle {
                     _ => return None,
                 })
             }
+
+            fn get_quest_plugin(&self, script: &str) -> Option<BoxedNpcPlugin> {
+                Some(match script {
+                    $(stringify!($quest) => FutureNpcPlugin::launch($quest_fn),)*
+                    _ => return None,
+                })
+            }
+
+            fn get_item_plugin(&self, script: &str) -> Option<BoxedNpcPlugin> {
+                Some(match script {
+                    $(stringify!($item) => FutureNpcPlugin::launch($item_fn),)*
+                    _ => return None,
+                })
+            }
+
+            fn get_portal_plugin(&self, script: &str) -> Option<BoxedPortalPlugin> {
+                Some(match script {
+                    $(stringify!($portal) => Box::new($portal_fn) as BoxedPortalPlugin,)*
+                    _ => return None,
+                })
+            }
+
+            fn get_reactor_plugin(&self, script: &str) -> Option<BoxedReactorPlugin> {
+                Some(match script {
+                    $(stringify!($reactor) => Box::new($reactor_fn) as BoxedReactorPlugin,)*
+                    _ => return None,
+                })
+            }
+
+            fn get_field_plugin(&self, script: &str) -> Option<BoxedFieldPlugin> {
+                Some(match script {
+                    $(stringify!($field) => Box::new($field_fn) as BoxedFieldPlugin,)*
+                    _ => return None,
+                })
+            }
         }
     };
 }
@@ -54,7 +102,29 @@ plugin_bundle!(
     (13, third_job_exit, job_adv::npc_script_mirror_inside),
     (14, holy_stone, job_adv::npc_script_holy_stone),
     (15, warrior4, job_adv::npc_script_priest),
-    samples::npc_fallback
+    samples::npc_fallback,
+    quest: [
+        (q_snail_start, quest::quest_snail_start),
+        (q_snail_end, quest::quest_snail_end)
+    ],
+    item: [
+        (event_box, item::item_event_box),
+        (teleport_ticket, item::item_teleport_ticket)
+    ],
+    portal: [
+        (market00, portal::portal_market),
+        (quest_gate, portal::portal_quest_gate),
+        (item_gate, portal::portal_item_gate)
+    ],
+    reactor: [
+        (reactor_drop, reactor::reactor_drop),
+        (mob_box, reactor::reactor_mob_box),
+        (gate, reactor::reactor_gate)
+    ],
+    field: [
+        (boss_intro, field::field_boss_intro),
+        (field_1010000, field::field_maple_island)
+    ]
 );
 
 #[no_mangle]
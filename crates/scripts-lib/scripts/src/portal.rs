@@ -0,0 +1,31 @@
+use shroom_meta::id::{FieldId, ItemId, QuestId};
+use shroom_script::portal::PortalCtx;
+
+const FREE_MARKET_ENTRANCE: FieldId = FieldId(910000000);
+const HENESYS: FieldId = FieldId(100000000);
+
+pub fn portal_market(api: &mut PortalCtx) -> anyhow::Result<()> {
+    api.warp(FREE_MARKET_ENTRANCE);
+    Ok(())
+}
+
+pub fn portal_quest_gate(api: &mut PortalCtx) -> anyhow::Result<()> {
+    // Maple Island: "Roger's Apple"
+    const QUEST: QuestId = QuestId(1021);
+    if api.has_completed_quest(QUEST) {
+        api.warp_to(HENESYS, "sp");
+    } else {
+        api.message("You have to complete Roger's quest first.");
+    }
+    Ok(())
+}
+
+pub fn portal_item_gate(api: &mut PortalCtx) -> anyhow::Result<()> {
+    const PASS: ItemId = ItemId(4031013);
+    if api.has_item_quantity(PASS, 30) {
+        api.warp(HENESYS);
+    } else {
+        api.message("The portal only opens for those carrying 30 Dark Marbles.");
+    }
+    Ok(())
+}
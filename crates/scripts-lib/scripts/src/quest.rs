@@ -0,0 +1,51 @@
+use anyhow::Context;
+use shroom_meta::id::ItemId;
+use shroom_script::npc::NpcCtx;
+
+const SNAIL_SHELL: ItemId = ItemId(4000019);
+
+pub async fn quest_snail_start(mut api: NpcCtx) -> anyhow::Result<()> {
+    api.wait_for_start().await?;
+    let quest = api.quest_id().context("Not a quest script")?;
+
+    if !api
+        .ask_yes_no("The snails keep eating my herbs, could you bring me 10 of their shells?")
+        .await?
+    {
+        api.say_end("Come back if you change your mind.").await?;
+        return Ok(());
+    }
+
+    if api.start_quest(quest)? {
+        api.say_end("Thank you! Come back once you have the shells.")
+            .await?;
+    } else {
+        api.say_end("You are not ready for this yet.").await?;
+    }
+    Ok(())
+}
+
+pub async fn quest_snail_end(mut api: NpcCtx) -> anyhow::Result<()> {
+    api.wait_for_start().await?;
+    let quest = api.quest_id().context("Not a quest script")?;
+
+    if !api.has_item_quantity(SNAIL_SHELL, 10) {
+        if api
+            .ask_yes_no("You don't have the shells yet. Do you want to give up?")
+            .await?
+        {
+            api.forfeit_quest(quest)?;
+            api.say_end("Too bad, maybe next time.").await?;
+        }
+        return Ok(());
+    }
+
+    if !api.try_take_item(SNAIL_SHELL, 10)? {
+        api.say_end("You don't have the shells yet.").await?;
+        return Ok(());
+    }
+    api.complete_quest(quest)?;
+    api.say_end("My herbs are safe now, please take this as a reward.")
+        .await?;
+    Ok(())
+}
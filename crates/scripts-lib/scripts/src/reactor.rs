@@ -0,0 +1,21 @@
+use shroom_meta::id::MobId;
+use shroom_script::reactor::ReactorCtx;
+
+pub fn reactor_drop(api: &mut dyn ReactorCtx) -> anyhow::Result<()> {
+    api.drop_items()
+}
+
+pub fn reactor_mob_box(api: &mut dyn ReactorCtx) -> anyhow::Result<()> {
+    // A box which releases a few snails
+    const SNAIL: MobId = MobId(100100);
+    let pos = api.reactor_pos();
+    for _ in 0..3 {
+        api.spawn_mob(SNAIL, pos)?;
+    }
+    Ok(())
+}
+
+pub fn reactor_gate(api: &mut dyn ReactorCtx) -> anyhow::Result<()> {
+    api.open_portal("gate")?;
+    api.drop_items()
+}
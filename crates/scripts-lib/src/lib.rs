@@ -3,9 +3,18 @@ use std::{
     time::Duration,
 };
 
-use shroom_meta::{id::{job_id::JobId, FieldId, ItemId, Money, NpcId}, npc::get_npc_script, QuestDataId};
+use shroom_meta::{
+    id::{job_id::JobId, FieldId, ItemId, Money, NpcId, QuestId},
+    item::EquipStat,
+    npc::get_npc_script,
+    QuestDataId,
+};
 use shroom_proto95::game::script::ScriptMessage;
-use shroom_script::{npc::NpcAction, BoxedNpcPlugin, BoxedSessionCtx, PluginBundle, SessionCtx};
+use shroom_script::{
+    field::FieldCtx, npc::NpcAction, portal::PortalCtx, reactor::ReactorCtx, BoxedFieldPlugin,
+    BoxedNpcPlugin, BoxedPortalPlugin, BoxedReactorPlugin, BoxedSessionCtx, PluginBundle,
+    SessionCtx,
+};
 
 //TODO block reloading as long handles are active
 
@@ -15,6 +24,8 @@ pub struct Shared;
 pub struct NpcHandle {
     plugin: BoxedNpcPlugin,
     id: NpcId,
+    quest: Option<QuestId>,
+    item: Option<ItemId>,
     _shared: Arc<Shared>,
 }
 
@@ -67,6 +78,10 @@ impl<T: SessionCtx> SessionCtx for RefCtx<T> {
         self.get_mut().set_job(job);
     }
 
+    fn derived_stat(&self, stat: EquipStat) -> u32 {
+        self.get_ref().derived_stat(stat)
+    }
+
     fn has_item(&self, id: ItemId) -> bool {
         self.get_ref().has_item(id)
     }
@@ -95,9 +110,17 @@ impl<T: SessionCtx> SessionCtx for RefCtx<T> {
         self.get_mut().transfer_field(field_id);
     }
 
+    fn transfer_field_portal(&mut self, field_id: FieldId, portal: &str) {
+        self.get_mut().transfer_field_portal(field_id, portal);
+    }
+
     fn say(&self, msg: &str) {
         self.get_ref().say(msg);
     }
+
+    fn notice(&mut self, msg: &str) {
+        self.get_mut().notice(msg);
+    }
     
     fn has_item_quantity(&self, id: ItemId, count: usize) -> bool {
         self.get_ref().has_item_quantity(id, count)
@@ -130,6 +153,38 @@ impl<T: SessionCtx> SessionCtx for RefCtx<T> {
     fn is_active_quest(&self, id: shroom_meta::id::QuestId) -> bool {
         self.get_ref().is_active_quest(id)
     }
+
+    fn set_quest_id(&mut self, id: Option<QuestId>) {
+        self.get_mut().set_quest_id(id);
+    }
+
+    fn current_quest_id(&self) -> Option<QuestId> {
+        self.get_ref().current_quest_id()
+    }
+
+    fn start_quest(&mut self, id: QuestId) -> anyhow::Result<bool> {
+        self.get_mut().start_quest(id)
+    }
+
+    fn forfeit_quest(&mut self, id: QuestId) -> anyhow::Result<()> {
+        self.get_mut().forfeit_quest(id)
+    }
+
+    fn complete_quest(&mut self, id: QuestId) -> anyhow::Result<bool> {
+        self.get_mut().complete_quest(id)
+    }
+
+    fn set_item_id(&mut self, id: Option<ItemId>) {
+        self.get_mut().set_item_id(id);
+    }
+
+    fn current_item_id(&self) -> Option<ItemId> {
+        self.get_ref().current_item_id()
+    }
+
+    fn consume_script_item(&mut self) -> anyhow::Result<bool> {
+        self.get_mut().consume_script_item()
+    }
 }
 
 unsafe impl<T: Send> Send for RefCtx<T> {}
@@ -141,6 +196,8 @@ impl NpcHandle {
         action: NpcAction,
     ) -> anyhow::Result<()> {
         ctx.set_npc_id(Some(self.id));
+        ctx.set_quest_id(self.quest);
+        ctx.set_item_id(self.item);
         // TODO: remove this hack
         // since poll-state needs a lifetime and asized parameter
         // we can't just pass &dyn 
@@ -149,6 +206,8 @@ impl NpcHandle {
         let mut ctx: BoxedSessionCtx = Box::new(ctx);
         let res = self.plugin.step(&mut ctx, action)?;
         ctx.set_npc_id(None);
+        ctx.set_quest_id(None);
+        ctx.set_item_id(None);
         Ok(res)
     }
 
@@ -159,6 +218,47 @@ impl NpcHandle {
     pub fn npc_id(&self) -> NpcId {
         self.id
     }
+
+    pub fn quest_id(&self) -> Option<QuestId> {
+        self.quest
+    }
+
+    pub fn item_id(&self) -> Option<ItemId> {
+        self.item
+    }
+}
+
+pub struct PortalHandle {
+    plugin: BoxedPortalPlugin,
+    _shared: Arc<Shared>,
+}
+
+impl PortalHandle {
+    pub fn enter(&mut self, ctx: &mut dyn SessionCtx, portal: &str) -> anyhow::Result<()> {
+        self.plugin.enter(&mut PortalCtx::new(ctx, portal))
+    }
+}
+
+pub struct ReactorHandle {
+    plugin: BoxedReactorPlugin,
+    _shared: Arc<Shared>,
+}
+
+impl ReactorHandle {
+    pub fn act(&mut self, ctx: &mut dyn ReactorCtx) -> anyhow::Result<()> {
+        self.plugin.act(ctx)
+    }
+}
+
+pub struct FieldHandle {
+    plugin: BoxedFieldPlugin,
+    _shared: Arc<Shared>,
+}
+
+impl FieldHandle {
+    pub fn enter(&mut self, ctx: &mut dyn FieldCtx) -> anyhow::Result<()> {
+        self.plugin.enter(ctx)
+    }
 }
 
 #[hot_lib_reloader::hot_module(
@@ -227,6 +327,8 @@ impl ScriptService {
         Some(NpcHandle {
             plugin,
             id: npc,
+            quest: None,
+            item: None,
             _shared: self.shared.clone(),
         })
     }
@@ -238,9 +340,63 @@ impl ScriptService {
                 NpcHandle {
                     plugin,
                     id: npc,
+                    quest: None,
+                    item: None,
                     _shared: self.shared.clone(),
                 }
             })
     }
 
+    /// Quest scripts run as dialog of the npc, which starts or completes the quest
+    pub fn get_quest_script(&self, script: &str, npc: NpcId, quest: QuestId) -> Option<NpcHandle> {
+        let plugin = self.get_bundle().as_ref().unwrap().get_quest_plugin(script)?;
+        Some(NpcHandle {
+            plugin,
+            id: npc,
+            quest: Some(quest),
+            item: None,
+            _shared: self.shared.clone(),
+        })
+    }
+
+    /// Item scripts run as dialog of the npc, which is summoned by the item
+    pub fn get_item_script(&self, script: &str, npc: NpcId, item: ItemId) -> Option<NpcHandle> {
+        let plugin = self.get_bundle().as_ref().unwrap().get_item_plugin(script)?;
+        Some(NpcHandle {
+            plugin,
+            id: npc,
+            quest: None,
+            item: Some(item),
+            _shared: self.shared.clone(),
+        })
+    }
+
+    pub fn get_portal_script(&self, script: &str) -> Option<PortalHandle> {
+        let plugin = self.get_bundle().as_ref().unwrap().get_portal_plugin(script)?;
+        Some(PortalHandle {
+            plugin,
+            _shared: self.shared.clone(),
+        })
+    }
+
+    pub fn get_reactor_script(&self, script: &str) -> Option<ReactorHandle> {
+        let plugin = self.get_bundle().as_ref().unwrap().get_reactor_plugin(script)?;
+        Some(ReactorHandle {
+            plugin,
+            _shared: self.shared.clone(),
+        })
+    }
+
+    /// Looks up the field script by the script name, falls back to the `field_<id>` script
+    pub fn get_field_script(&self, field: FieldId, script: Option<&str>) -> Option<FieldHandle> {
+        let bundle = self.get_bundle();
+        let bundle = bundle.as_ref().unwrap();
+        let plugin = script
+            .and_then(|script| bundle.get_field_plugin(script))
+            .or_else(|| bundle.get_field_plugin(&format!("field_{}", field.0)))?;
+        Some(FieldHandle {
+            plugin,
+            _shared: self.shared.clone(),
+        })
+    }
 }
@@ -3,12 +3,32 @@ pub mod helper;
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20240601_000001_create_buddy_table;
+mod m20240608_000001_create_merchant_table;
+mod m20240612_000001_create_trunk_table;
+mod m20240615_000001_create_guild_table;
+mod m20240620_000001_add_character_world;
+mod m20240625_000001_create_cash_shop_table;
+mod m20240630_000001_create_gm_log_table;
+mod m20240705_000001_add_ban_targets;
+mod m20240710_000001_add_character_whisper_blocked;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::<m20220101_000001_create_table::Migration>::default()]
+        vec![
+            Box::<m20220101_000001_create_table::Migration>::default(),
+            Box::<m20240601_000001_create_buddy_table::Migration>::default(),
+            Box::<m20240608_000001_create_merchant_table::Migration>::default(),
+            Box::<m20240612_000001_create_trunk_table::Migration>::default(),
+            Box::<m20240615_000001_create_guild_table::Migration>::default(),
+            Box::<m20240620_000001_add_character_world::Migration>::default(),
+            Box::<m20240625_000001_create_cash_shop_table::Migration>::default(),
+            Box::<m20240630_000001_create_gm_log_table::Migration>::default(),
+            Box::<m20240705_000001_add_ban_targets::Migration>::default(),
+            Box::<m20240710_000001_add_character_whisper_blocked::Migration>::default(),
+        ]
     }
 }
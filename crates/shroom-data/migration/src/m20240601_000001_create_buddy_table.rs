@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Character {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum Buddy {
+    Table,
+    Id,
+    CharId,
+    BuddyId,
+    BuddyName,
+    GroupName,
+    Status,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration {
+    buddy_table: ShroomTbl,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        // Only used as reference for the foreign key
+        let char_table = ShroomTbl::new(Character::Table, Character::Id, false, [], []);
+
+        let buddy_table = ShroomTbl::new(
+            Buddy::Table,
+            Buddy::Id,
+            false,
+            [
+                shroom_id(Buddy::BuddyId),
+                shroom_name(Buddy::BuddyName),
+                shroom_small_str(Buddy::GroupName).not_null().to_owned(),
+                shroom_int(Buddy::Status),
+            ],
+            [Ref::ownership(Buddy::CharId, &char_table)],
+        );
+
+        Self { buddy_table }
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.buddy_table.create_table(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.buddy_table.drop_fk(manager).await?;
+        self.buddy_table.drop_table(manager).await
+    }
+}
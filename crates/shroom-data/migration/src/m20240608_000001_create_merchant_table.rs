@@ -0,0 +1,113 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Character {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum EquipItem {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum Merchant {
+    Table,
+    Id,
+    CharId,
+    OwnerName,
+    FieldId,
+    X,
+    Y,
+    Fh,
+    TmplId,
+    Title,
+    Money,
+    Open,
+}
+
+#[derive(Iden)]
+enum MerchantItem {
+    Table,
+    Id,
+    MerchantId,
+    Pos,
+    ItemId,
+    Price,
+    Bundles,
+    PerBundle,
+    EquipItemId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration {
+    merchant_table: ShroomTbl,
+    merchant_item_table: ShroomTbl,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        // Only used as reference for the foreign keys
+        let char_table = ShroomTbl::new(Character::Table, Character::Id, false, [], []);
+        let equip_table = ShroomTbl::new(EquipItem::Table, EquipItem::Id, false, [], []);
+
+        let merchant_table = ShroomTbl::new(
+            Merchant::Table,
+            Merchant::Id,
+            false,
+            [
+                shroom_name(Merchant::OwnerName),
+                shroom_id(Merchant::FieldId),
+                shroom_int(Merchant::X),
+                shroom_int(Merchant::Y),
+                shroom_int(Merchant::Fh),
+                shroom_id(Merchant::TmplId),
+                shroom_str(Merchant::Title).not_null().to_owned(),
+                shroom_int(Merchant::Money),
+                shroom_bool(Merchant::Open),
+            ],
+            [Ref::ownership(Merchant::CharId, &char_table)],
+        );
+
+        let merchant_item_table = ShroomTbl::new(
+            MerchantItem::Table,
+            MerchantItem::Id,
+            false,
+            [
+                shroom_int(MerchantItem::Pos),
+                shroom_id(MerchantItem::ItemId),
+                shroom_int(MerchantItem::Price),
+                shroom_size(MerchantItem::Bundles),
+                shroom_size(MerchantItem::PerBundle),
+            ],
+            [
+                Ref::ownership(MerchantItem::MerchantId, &merchant_table),
+                Ref::opt(MerchantItem::EquipItemId, &equip_table),
+            ],
+        );
+
+        Self {
+            merchant_table,
+            merchant_item_table,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.merchant_table.create_table(manager).await?;
+        self.merchant_item_table.create_table(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.merchant_item_table.drop_fk(manager).await?;
+        self.merchant_table.drop_fk(manager).await?;
+        self.merchant_item_table.drop_table(manager).await?;
+        self.merchant_table.drop_table(manager).await
+    }
+}
@@ -0,0 +1,95 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Account {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum EquipItem {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum ItemStack {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum Trunk {
+    Table,
+    Id,
+    AccId,
+    Slots,
+    Money,
+}
+
+#[derive(Iden)]
+enum TrunkItem {
+    Table,
+    Id,
+    TrunkId,
+    Pos,
+    EquipItemId,
+    StackItemId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration {
+    trunk_table: ShroomTbl,
+    trunk_item_table: ShroomTbl,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        // Only used as reference for the foreign keys
+        let acc_table = ShroomTbl::new(Account::Table, Account::Id, false, [], []);
+        let equip_table = ShroomTbl::new(EquipItem::Table, EquipItem::Id, false, [], []);
+        let stack_table = ShroomTbl::new(ItemStack::Table, ItemStack::Id, false, [], []);
+
+        let trunk_table = ShroomTbl::new(
+            Trunk::Table,
+            Trunk::Id,
+            false,
+            [shroom_size(Trunk::Slots), shroom_int(Trunk::Money)],
+            [Ref::ownership(Trunk::AccId, &acc_table)],
+        );
+
+        let trunk_item_table = ShroomTbl::new(
+            TrunkItem::Table,
+            TrunkItem::Id,
+            false,
+            [shroom_int(TrunkItem::Pos)],
+            [
+                Ref::ownership(TrunkItem::TrunkId, &trunk_table),
+                Ref::opt(TrunkItem::EquipItemId, &equip_table),
+                Ref::opt(TrunkItem::StackItemId, &stack_table),
+            ],
+        );
+
+        Self {
+            trunk_table,
+            trunk_item_table,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.trunk_table.create_table(manager).await?;
+        self.trunk_item_table.create_table(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.trunk_item_table.drop_fk(manager).await?;
+        self.trunk_table.drop_fk(manager).await?;
+        self.trunk_item_table.drop_table(manager).await?;
+        self.trunk_table.drop_table(manager).await
+    }
+}
@@ -0,0 +1,106 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Character {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum Guild {
+    Table,
+    Id,
+    Name,
+    Notice,
+    Points,
+    Capacity,
+    MarkBg,
+    MarkBgColor,
+    Mark,
+    MarkColor,
+    Grade1,
+    Grade2,
+    Grade3,
+    Grade4,
+    Grade5,
+}
+
+#[derive(Iden)]
+enum GuildMember {
+    Table,
+    Id,
+    GuildId,
+    CharId,
+    Grade,
+    Commitment,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration {
+    guild_table: ShroomTbl,
+    guild_member_table: ShroomTbl,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        // Only used as reference for the foreign keys
+        let char_table = ShroomTbl::new(Character::Table, Character::Id, false, [], []);
+
+        let guild_table = ShroomTbl::new(
+            Guild::Table,
+            Guild::Id,
+            false,
+            [
+                shroom_name(Guild::Name).unique_key().to_owned(),
+                shroom_str(Guild::Notice).not_null().to_owned(),
+                shroom_int(Guild::Points),
+                shroom_size(Guild::Capacity),
+                shroom_int(Guild::MarkBg),
+                shroom_int(Guild::MarkBgColor),
+                shroom_int(Guild::Mark),
+                shroom_int(Guild::MarkColor),
+                shroom_small_str(Guild::Grade1).not_null().to_owned(),
+                shroom_small_str(Guild::Grade2).not_null().to_owned(),
+                shroom_small_str(Guild::Grade3).not_null().to_owned(),
+                shroom_small_str(Guild::Grade4).not_null().to_owned(),
+                shroom_small_str(Guild::Grade5).not_null().to_owned(),
+            ],
+            [],
+        );
+
+        let guild_member_table = ShroomTbl::new(
+            GuildMember::Table,
+            GuildMember::Id,
+            false,
+            [
+                shroom_size(GuildMember::Grade),
+                shroom_int(GuildMember::Commitment),
+            ],
+            [
+                Ref::ownership(GuildMember::GuildId, &guild_table),
+                Ref::ownership(GuildMember::CharId, &char_table),
+            ],
+        );
+
+        Self {
+            guild_table,
+            guild_member_table,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.guild_table.create_table(manager).await?;
+        self.guild_member_table.create_table(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.guild_member_table.drop_fk(manager).await?;
+        self.guild_member_table.drop_table(manager).await?;
+        self.guild_table.drop_table(manager).await
+    }
+}
@@ -0,0 +1,102 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Account {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum CashLockerItem {
+    Table,
+    Id,
+    AccId,
+    ItemId,
+    CommoditySn,
+    Quantity,
+    GiftFrom,
+    ExpiresAt,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum CashCoupon {
+    Table,
+    Id,
+    Code,
+    NxCredit,
+    ItemId,
+    Quantity,
+    UsedBy,
+    UsedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration {
+    cash_locker_item_table: ShroomTbl,
+    cash_coupon_table: ShroomTbl,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        // Only used as reference for the foreign keys
+        let acc_table = ShroomTbl::new(Account::Table, Account::Id, false, [], []);
+
+        let cash_locker_item_table = ShroomTbl::new(
+            CashLockerItem::Table,
+            CashLockerItem::Id,
+            false,
+            [
+                shroom_int(CashLockerItem::ItemId),
+                shroom_int(CashLockerItem::CommoditySn),
+                shroom_size(CashLockerItem::Quantity),
+                ColumnDef::new(CashLockerItem::GiftFrom)
+                    .string_len(13)
+                    .null()
+                    .to_owned(),
+                date_time(CashLockerItem::ExpiresAt).null().to_owned(),
+                created_at(CashLockerItem::CreatedAt),
+            ],
+            [Ref::ownership(CashLockerItem::AccId, &acc_table)],
+        );
+
+        let cash_coupon_table = ShroomTbl::new(
+            CashCoupon::Table,
+            CashCoupon::Id,
+            false,
+            [
+                shroom_str(CashCoupon::Code)
+                    .not_null()
+                    .unique_key()
+                    .to_owned(),
+                shroom_int(CashCoupon::NxCredit),
+                shroom_int(CashCoupon::ItemId),
+                shroom_size(CashCoupon::Quantity),
+                date_time(CashCoupon::UsedAt).null().to_owned(),
+            ],
+            [Ref::opt(CashCoupon::UsedBy, &acc_table)],
+        );
+
+        Self {
+            cash_locker_item_table,
+            cash_coupon_table,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.cash_locker_item_table.create_table(manager).await?;
+        self.cash_coupon_table.create_table(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.cash_coupon_table.drop_fk(manager).await?;
+        self.cash_locker_item_table.drop_fk(manager).await?;
+        self.cash_coupon_table.drop_table(manager).await?;
+        self.cash_locker_item_table.drop_table(manager).await
+    }
+}
@@ -0,0 +1,71 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Account {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum Character {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum GmLog {
+    Table,
+    Id,
+    AccId,
+    CharId,
+    Command,
+    GmLevel,
+    Allowed,
+    CreatedAt,
+}
+
+/// Audit log of the executed GM commands
+#[derive(DeriveMigrationName)]
+pub struct Migration {
+    gm_log_table: ShroomTbl,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        // Only used as reference for the foreign keys
+        let acc_table = ShroomTbl::new(Account::Table, Account::Id, false, [], []);
+        let char_table = ShroomTbl::new(Character::Table, Character::Id, false, [], []);
+
+        let gm_log_table = ShroomTbl::new(
+            GmLog::Table,
+            GmLog::Id,
+            false,
+            [
+                shroom_str(GmLog::Command).not_null().to_owned(),
+                shroom_int(GmLog::GmLevel),
+                shroom_bool(GmLog::Allowed),
+                created_at(GmLog::CreatedAt),
+            ],
+            [
+                Ref::ownership(GmLog::AccId, &acc_table),
+                Ref::ownership(GmLog::CharId, &char_table),
+            ],
+        );
+
+        Self { gm_log_table }
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.gm_log_table.create_table(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.gm_log_table.drop_fk(manager).await?;
+        self.gm_log_table.drop_table(manager).await
+    }
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Account {
+    Table,
+    LastIp,
+    LastMachineId,
+}
+
+#[derive(Iden)]
+enum Ban {
+    Table,
+    ReasonCode,
+    Ip,
+    MachineId,
+}
+
+/// Bans carry the reason code shown by the client and can also target the ip or machine
+/// of the account, which is why the last used ip and machine of an account are stored
+#[derive(DeriveMigrationName, Default)]
+pub struct Migration;
+
+fn add_column(table: impl IntoIden, col: ColumnDef) -> TableAlterStatement {
+    Table::alter().table(table).add_column(col).to_owned()
+}
+
+fn drop_column(table: impl IntoIden, col: impl IntoIden) -> TableAlterStatement {
+    Table::alter().table(table).drop_column(col).to_owned()
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Sqlite only supports a single column per alter statement
+        let stmts = [
+            add_column(Ban::Table, shroom_int(Ban::ReasonCode)),
+            add_column(Ban::Table, shroom_str(Ban::Ip).null().to_owned()),
+            add_column(Ban::Table, shroom_str(Ban::MachineId).null().to_owned()),
+            add_column(
+                Account::Table,
+                shroom_str(Account::LastIp).null().to_owned(),
+            ),
+            add_column(
+                Account::Table,
+                shroom_str(Account::LastMachineId).null().to_owned(),
+            ),
+        ];
+        for stmt in stmts {
+            manager.alter_table(stmt).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let stmts = [
+            drop_column(Account::Table, Account::LastMachineId),
+            drop_column(Account::Table, Account::LastIp),
+            drop_column(Ban::Table, Ban::MachineId),
+            drop_column(Ban::Table, Ban::Ip),
+            drop_column(Ban::Table, Ban::ReasonCode),
+        ];
+        for stmt in stmts {
+            manager.alter_table(stmt).await?;
+        }
+        Ok(())
+    }
+}
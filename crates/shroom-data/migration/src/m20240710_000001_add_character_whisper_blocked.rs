@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Character {
+    Table,
+    WhisperBlocked,
+}
+
+/// Characters can block incoming whispers, which is kept across sessions
+#[derive(DeriveMigrationName, Default)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Character::Table)
+                    .add_column(shroom_bool(Character::WhisperBlocked))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Character::Table)
+                    .drop_column(Character::WhisperBlocked)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -25,14 +25,22 @@ pub struct Model {
     pub nx_prepaid: i32,
     pub shroom_points: i32,
     pub tester: bool,
+    pub last_ip: Option<String>,
+    pub last_machine_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::ban::Entity")]
     Ban,
+    #[sea_orm(has_many = "super::cash_locker_item::Entity")]
+    CashLockerItem,
     #[sea_orm(has_many = "super::character::Entity")]
     Character,
+    #[sea_orm(has_many = "super::gm_log::Entity")]
+    GmLog,
+    #[sea_orm(has_many = "super::trunk::Entity")]
+    Trunk,
 }
 
 impl Related<super::ban::Entity> for Entity {
@@ -41,10 +49,28 @@ impl Related<super::ban::Entity> for Entity {
     }
 }
 
+impl Related<super::cash_locker_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CashLockerItem.def()
+    }
+}
+
 impl Related<super::character::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Character.def()
     }
 }
 
+impl Related<super::gm_log::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GmLog.def()
+    }
+}
+
+impl Related<super::trunk::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Trunk.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
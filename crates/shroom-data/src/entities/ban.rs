@@ -10,6 +10,9 @@ pub struct Model {
     pub reason: Option<String>,
     pub time: Option<DateTime>,
     pub acc_id: i32,
+    pub reason_code: i32,
+    pub ip: Option<String>,
+    pub machine_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
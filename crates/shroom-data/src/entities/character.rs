@@ -44,6 +44,8 @@ pub struct Model {
     pub field_id: i32,
     pub spawn_point: i32,
     pub acc_id: i32,
+    pub world_id: i32,
+    pub whisper_blocked: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -56,10 +58,18 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Account,
+    #[sea_orm(has_many = "super::buddy::Entity")]
+    Buddy,
     #[sea_orm(has_many = "super::func_key_map::Entity")]
     FuncKeyMap,
+    #[sea_orm(has_many = "super::gm_log::Entity")]
+    GmLog,
+    #[sea_orm(has_many = "super::guild_member::Entity")]
+    GuildMember,
     #[sea_orm(has_many = "super::inventory_slot::Entity")]
     InventorySlot,
+    #[sea_orm(has_many = "super::merchant::Entity")]
+    Merchant,
     #[sea_orm(has_many = "super::quest::Entity")]
     Quest,
     #[sea_orm(has_many = "super::skill::Entity")]
@@ -72,18 +82,42 @@ impl Related<super::account::Entity> for Entity {
     }
 }
 
+impl Related<super::buddy::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Buddy.def()
+    }
+}
+
 impl Related<super::func_key_map::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::FuncKeyMap.def()
     }
 }
 
+impl Related<super::gm_log::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GmLog.def()
+    }
+}
+
+impl Related<super::guild_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GuildMember.def()
+    }
+}
+
 impl Related<super::inventory_slot::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::InventorySlot.def()
     }
 }
 
+impl Related<super::merchant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Merchant.def()
+    }
+}
+
 impl Related<super::quest::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Quest.def()
@@ -51,6 +51,10 @@ pub struct Model {
 pub enum Relation {
     #[sea_orm(has_many = "super::inventory_slot::Entity")]
     InventorySlot,
+    #[sea_orm(has_many = "super::merchant_item::Entity")]
+    MerchantItem,
+    #[sea_orm(has_many = "super::trunk_item::Entity")]
+    TrunkItem,
 }
 
 impl Related<super::inventory_slot::Entity> for Entity {
@@ -59,4 +63,16 @@ impl Related<super::inventory_slot::Entity> for Entity {
     }
 }
 
+impl Related<super::merchant_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MerchantItem.def()
+    }
+}
+
+impl Related<super::trunk_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TrunkItem.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
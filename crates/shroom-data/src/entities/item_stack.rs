@@ -20,6 +20,8 @@ pub struct Model {
 pub enum Relation {
     #[sea_orm(has_many = "super::inventory_slot::Entity")]
     InventorySlot,
+    #[sea_orm(has_many = "super::trunk_item::Entity")]
+    TrunkItem,
 }
 
 impl Related<super::inventory_slot::Entity> for Entity {
@@ -28,4 +30,10 @@ impl Related<super::inventory_slot::Entity> for Entity {
     }
 }
 
+impl Related<super::trunk_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TrunkItem.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
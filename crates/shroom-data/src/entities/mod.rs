@@ -4,12 +4,22 @@ pub mod prelude;
 
 pub mod account;
 pub mod ban;
+pub mod buddy;
+pub mod cash_coupon;
+pub mod cash_locker_item;
 pub mod character;
 pub mod equip_item;
 pub mod func_key_map;
+pub mod gm_log;
+pub mod guild;
+pub mod guild_member;
 pub mod inventory_slot;
 pub mod item_stack;
+pub mod merchant;
+pub mod merchant_item;
 pub mod pet_item;
 pub mod quest;
 pub mod sea_orm_active_enums;
 pub mod skill;
+pub mod trunk;
+pub mod trunk_item;
@@ -2,11 +2,21 @@
 
 pub use super::account::Entity as Account;
 pub use super::ban::Entity as Ban;
+pub use super::buddy::Entity as Buddy;
+pub use super::cash_coupon::Entity as CashCoupon;
+pub use super::cash_locker_item::Entity as CashLockerItem;
 pub use super::character::Entity as Character;
 pub use super::equip_item::Entity as EquipItem;
 pub use super::func_key_map::Entity as FuncKeyMap;
+pub use super::gm_log::Entity as GmLog;
+pub use super::guild::Entity as Guild;
+pub use super::guild_member::Entity as GuildMember;
 pub use super::inventory_slot::Entity as InventorySlot;
 pub use super::item_stack::Entity as ItemStack;
+pub use super::merchant::Entity as Merchant;
+pub use super::merchant_item::Entity as MerchantItem;
 pub use super::pet_item::Entity as PetItem;
 pub use super::quest::Entity as Quest;
 pub use super::skill::Entity as Skill;
+pub use super::trunk::Entity as Trunk;
+pub use super::trunk_item::Entity as TrunkItem;
@@ -140,6 +140,16 @@ impl KeyMap {
         self.func.as_ref().map_or(false, |f| f.updated)
             || self.quick.as_ref().map_or(false, |q| q.updated)
     }
+
+    /// Marks the key map as saved
+    pub fn clear_changed(&mut self) {
+        if let Some(func) = self.func.as_mut() {
+            func.updated = false;
+        }
+        if let Some(quick) = self.quick.as_mut() {
+            quick.updated = false;
+        }
+    }
 }
 
 
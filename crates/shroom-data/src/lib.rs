@@ -15,7 +15,7 @@ pub mod model;
 use std::time::Duration;
 
 use chrono::{NaiveDateTime, Utc};
-use entities::{account, ban, character, equip_item, func_key_map, inventory_slot, item_stack, pet_item, quest, skill};
+use entities::{account, ban, buddy, cash_coupon, cash_locker_item, character, equip_item, func_key_map, guild, guild_member, inventory_slot, item_stack, merchant, merchant_item, pet_item, quest, skill, trunk, trunk_item};
 
 use sea_orm::{
     ActiveValue, ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbBackend, DbErr,
@@ -113,6 +113,60 @@ pub async fn gen_sqlite(opt: &str) -> Result<DatabaseConnection, DbErr> {
     )
     .await?;
 
+    db.execute(
+        db.get_database_backend()
+            .build(&schema.create_table_from_entity(buddy::Entity)),
+    )
+    .await?;
+
+    db.execute(
+        db.get_database_backend()
+            .build(&schema.create_table_from_entity(merchant::Entity)),
+    )
+    .await?;
+
+    db.execute(
+        db.get_database_backend()
+            .build(&schema.create_table_from_entity(merchant_item::Entity)),
+    )
+    .await?;
+
+    db.execute(
+        db.get_database_backend()
+            .build(&schema.create_table_from_entity(trunk::Entity)),
+    )
+    .await?;
+
+    db.execute(
+        db.get_database_backend()
+            .build(&schema.create_table_from_entity(trunk_item::Entity)),
+    )
+    .await?;
+
+    db.execute(
+        db.get_database_backend()
+            .build(&schema.create_table_from_entity(guild::Entity)),
+    )
+    .await?;
+
+    db.execute(
+        db.get_database_backend()
+            .build(&schema.create_table_from_entity(guild_member::Entity)),
+    )
+    .await?;
+
+    db.execute(
+        db.get_database_backend()
+            .build(&schema.create_table_from_entity(cash_locker_item::Entity)),
+    )
+    .await?;
+
+    db.execute(
+        db.get_database_backend()
+            .build(&schema.create_table_from_entity(cash_coupon::Entity)),
+    )
+    .await?;
+
     Ok(db)
 }
 
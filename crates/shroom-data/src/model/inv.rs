@@ -12,7 +12,11 @@ use shroom_srv::game::{
     stack_inv::{InvStackItem, StackInvEventHandler, StackInventory},
 };
 
-use crate::services::{character::ItemStarterSet, item::SharedItemSvc};
+use crate::services::{
+    char_save::{assign_db_id, SavedItemId, SavedItemKind, SnapshotItem, SnapshotSlot},
+    character::ItemStarterSet,
+    item::SharedItemSvc,
+};
 
 pub const EQUIPPED_CAP: usize = 96;
 pub const INV_ITEM_CAP: usize = 180;
@@ -504,4 +508,84 @@ impl<T: InvSetHandler> InventorySet<T> {
             self.get_equipped_inventory(ty).unwrap().capacity()
         }
     }
+
+    /// Copies all items with their slots, so they can be saved without
+    /// holding onto the inventory
+    pub fn snapshot(&self) -> Vec<SnapshotSlot> {
+        let equipped = [
+            (InventoryType::Equipped, &self.equipped),
+            (InventoryType::Special, &self.masked_equipped),
+        ]
+        .into_iter()
+        .flat_map(|(ty, inv)| {
+            inv.item_slots().map(move |(slot, item)| SnapshotSlot {
+                inv_type: ty,
+                slot: slot.to_ix() as i32,
+                item: SnapshotItem::Equip(item.0.item.clone()),
+            })
+        });
+        let equip = self.equip.item_slots().map(|(slot, item)| SnapshotSlot {
+            inv_type: InventoryType::Equip,
+            slot: slot as u8 as i32,
+            item: SnapshotItem::Equip(item.item.clone()),
+        });
+        let stacks = [
+            (InventoryType::Consume, &self.consume),
+            (InventoryType::Install, &self.misc),
+            (InventoryType::Etc, &self.etc),
+        ]
+        .into_iter()
+        .flat_map(|(ty, inv)| {
+            inv.item_slots().map(move |(slot, item)| SnapshotSlot {
+                inv_type: ty,
+                slot: slot as i32,
+                item: SnapshotItem::Stack(item.item.clone()),
+            })
+        });
+        let cash = self.cash.item_slots().map(|(slot, item)| SnapshotSlot {
+            inv_type: InventoryType::Cash,
+            slot: slot as i32,
+            item: match item {
+                CashItemSlot::Stack(stack) => SnapshotItem::Stack(stack.item.clone()),
+                CashItemSlot::Pet(pet) => SnapshotItem::Pet(pet.item.clone()),
+            },
+        });
+
+        equipped.chain(equip).chain(stacks).chain(cash).collect()
+    }
+
+    /// Assigns the db ids of items, which were inserted by a snapshot save
+    pub fn assign_db_ids(&mut self, ids: &[SavedItemId]) {
+        let equips = self
+            .equipped
+            .items_mut()
+            .map(|item| &mut item.0.item)
+            .chain(
+                self.masked_equipped
+                    .items_mut()
+                    .map(|item| &mut item.0.item),
+            )
+            .chain(self.equip.items_mut().map(|item| &mut item.item));
+        for item in equips {
+            assign_db_id(&mut item.info, SavedItemKind::Equip, ids);
+        }
+
+        let stacks = self
+            .consume
+            .items_mut()
+            .chain(self.misc.items_mut())
+            .chain(self.etc.items_mut());
+        for item in stacks {
+            assign_db_id(&mut item.info, SavedItemKind::Stack, ids);
+        }
+
+        for item in self.cash.items_mut() {
+            match item {
+                CashItemSlot::Stack(stack) => {
+                    assign_db_id(&mut stack.info, SavedItemKind::Stack, ids)
+                }
+                CashItemSlot::Pet(pet) => assign_db_id(&mut pet.info, SavedItemKind::Pet, ids),
+            }
+        }
+    }
 }
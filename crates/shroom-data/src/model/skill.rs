@@ -7,10 +7,16 @@ use shroom_meta::{id::SkillId, shared::EvalExpr, SkillMeta};
 use shroom_pkt::ShroomExpirationTime;
 use shroom_proto95::{
     game::user::{SkillCooldownSetResp, UpdatedSkillRecord},
-    shared::char::SkillInfo,
+    shared::char::{SkillCooltime, SkillInfo},
 };
 use shroom_srv::GameTime;
 
+/// The client counts cooldowns in whole seconds, partial seconds are rounded up
+fn cooldown_secs(d: Duration) -> u16 {
+    let secs = d.as_secs() + u64::from(d.subsec_nanos() > 0);
+    secs.min(u64::from(u16::MAX)) as u16
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Cooldown(pub GameTime);
 
@@ -30,7 +36,6 @@ pub struct SkillData {
     pub level: usize,
     pub mastery_level: Option<usize>,
     pub expires_at: Option<GameTime>,
-    pub cooldown: Option<GameTime>,
     pub meta: SkillMeta,
 }
 
@@ -109,7 +114,6 @@ impl From<(SkillId, SkillMeta)> for SkillData {
             level: 0,
             mastery_level: skill.master_level.map(|n| n as usize),
             expires_at: None,
-            cooldown: None,
             meta: skill,
         }
     }
@@ -233,10 +237,34 @@ impl SkillSet {
         self.updated_cooldowns.insert(skill_id);
     }
 
+    /// Restores a cooldown of a previous session, the client receives it with the character data
+    pub fn restore_cooldown(&mut self, skill_id: SkillId, t: GameTime, remaining: Duration) {
+        self.skill_cooldowns
+            .insert(skill_id, Cooldown::from(t + remaining));
+    }
+
+    /// Remaining cooldown of the skill, `None` if the skill is ready
+    pub fn remaining_cooldown(&self, skill_id: SkillId, t: GameTime) -> Option<Duration> {
+        self.skill_cooldowns
+            .get(&skill_id)
+            .and_then(|cd| cd.remaining_cooldown(t))
+            .filter(|d| !d.is_zero())
+    }
+
     pub fn get_cooldowns(&self, t: GameTime) -> impl Iterator<Item = (SkillId, Duration)> + '_ {
         self.skill_cooldowns
             .iter()
             .filter_map(move |(k, v)| v.remaining_cooldown(t).map(|d| (*k, d)))
+            .filter(|(_, d)| !d.is_zero())
+    }
+
+    pub fn get_cooltime_info(&self, t: GameTime) -> Vec<SkillCooltime> {
+        self.get_cooldowns(t)
+            .map(|(id, d)| SkillCooltime {
+                id,
+                time_left_s: cooldown_secs(d),
+            })
+            .collect()
     }
 
     pub fn get_skill_info(&self) -> Vec<SkillInfo> {
@@ -274,7 +302,7 @@ impl SkillSet {
                             .and_then(|cd| cd.remaining_cooldown(t));
                         dur.map(|d| SkillCooldownSetResp {
                             skill_id: id,
-                            cooldown_s: d.as_secs() as u16,
+                            cooldown_s: cooldown_secs(d),
                         })
                     })
                     .collect(),
@@ -284,3 +312,35 @@ impl SkillSet {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cooldowns() {
+        let t = GameTime::default();
+        let id = SkillId(1);
+        let mut skills = SkillSet::new();
+        assert_eq!(skills.remaining_cooldown(id, t), None);
+
+        skills.set_cooldown(id, t, Duration::from_millis(1_500));
+        assert_eq!(
+            skills.remaining_cooldown(id, t + Duration::from_secs(1)),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            skills.remaining_cooldown(id, t + Duration::from_secs(2)),
+            None
+        );
+
+        let updates = skills.get_cooldown_updates(t).unwrap();
+        assert_eq!(updates[0].cooldown_s, 2);
+        assert!(skills.get_cooldown_updates(t).is_none());
+
+        // Restored cooldowns are only sent with the character data
+        skills.restore_cooldown(SkillId(2), t, Duration::from_secs(30));
+        assert!(skills.get_cooldown_updates(t).is_none());
+        assert_eq!(skills.get_cooltime_info(t).len(), 2);
+    }
+}
@@ -12,6 +12,15 @@ pub fn db_to_shroom_time(dt: NaiveDateTime) -> ShroomTime {
     ShroomTime::try_from(chrono::Utc.from_utc_datetime(&dt)).unwrap()
 }
 
+/// Date used by the client for permanent restrictions
+pub fn permanent_shroom_time() -> ShroomTime {
+    let dt = chrono::NaiveDate::from_ymd_opt(2079, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    db_to_shroom_time(dt)
+}
+
 impl From<&GenderTy> for Gender {
     fn from(value: &GenderTy) -> Self {
         match value {
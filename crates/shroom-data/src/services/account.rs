@@ -1,20 +1,25 @@
 use std::net::IpAddr;
 
+use chrono::{Duration, NaiveDateTime, Utc};
 use constant_time_eq::constant_time_eq;
-use sea_orm::{ActiveModelTrait, DbErr, TryIntoModel};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ActiveModelTrait, Condition, DbErr, TryIntoModel};
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set};
+use shroom_meta::id::CharacterId;
+use shroom_proto95::login::MachineId;
 use thiserror::Error;
 
 use crate::created_at;
 use crate::entities::account::{self, ActiveModel, Column, Entity, Model};
 use crate::entities::ban;
+use crate::entities::gm_log;
 use crate::entities::sea_orm_active_enums::GenderTy;
 
 use super::password::PwService;
 use super::DbConn;
 
 pub type AccountId = i32;
-pub type HardwareInfo = u32;
+pub type HardwareInfo = MachineId;
 
 #[derive(Debug)]
 #[repr(u8)]
@@ -41,7 +46,7 @@ pub enum AccountServiceError {
     #[error("Password is only supposed to contain ASCII characters")]
     UsernameWrongChar,
     #[error("Account is banned")]
-    AccountBanned,
+    AccountBanned(BanInfo),
     #[error("Account already logged in")]
     AccountAlreadyLoggedIn,
     #[error("database")]
@@ -52,6 +57,49 @@ pub enum AccountServiceError {
 
 pub type AccResult<T> = std::result::Result<T, AccountServiceError>;
 
+/// Active ban, which prevents the login
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BanInfo {
+    /// Reason code, which is shown by the client
+    pub reason: u8,
+    /// End of the ban, permanent bans have none
+    pub until: Option<NaiveDateTime>,
+}
+
+impl From<ban::Model> for BanInfo {
+    fn from(ban: ban::Model) -> Self {
+        Self {
+            reason: ban.reason_code as u8,
+            until: ban.time,
+        }
+    }
+}
+
+impl BanInfo {
+    /// Picks the ban, which lasts the longest
+    pub fn longest(bans: impl IntoIterator<Item = Self>) -> Option<Self> {
+        bans.into_iter()
+            .max_by_key(|ban| (ban.until.is_none(), ban.until))
+    }
+}
+
+/// A new ban for an account
+#[derive(Debug, Clone)]
+pub struct BanRequest {
+    pub reason_code: u8,
+    pub reason: Option<String>,
+    /// Duration of the ban, permanent if none
+    pub duration: Option<Duration>,
+    /// Also ban the last ip of the account
+    pub ip: bool,
+    /// Also ban the last machine of the account
+    pub machine: bool,
+}
+
+fn machine_id_str(hw_info: &HardwareInfo) -> String {
+    hw_info.0.iter().map(|b| format!("{b:02x}")).collect()
+}
+
 #[derive(Debug)]
 pub struct AccountService {
     db: DbConn,
@@ -102,28 +150,103 @@ impl AccountService {
         Ok(res.last_insert_id)
     }
 
-    pub async fn try_login(&self, username: &str, password: &str) -> AccResult<Model> {
-        let res = Entity::find()
+    pub async fn try_login(
+        &self,
+        username: &str,
+        password: &str,
+        ip: IpAddr,
+        hw_info: &HardwareInfo,
+    ) -> AccResult<Model> {
+        let acc = Entity::find()
             .filter(Column::Username.eq(username))
-            //TODO find latest ban
-            .find_also_related(ban::Entity)
             .one(&self.db.0)
             .await?;
 
-        let Some((acc, last_ban)) = res else {
+        let Some(acc) = acc else {
             return Err(AccountServiceError::UsernameNotFound);
         };
 
-        if let Some(_last_ban) = last_ban {
-            return Err(AccountServiceError::AccountBanned);
-        }
-
         let verfiy_password = self.pw.verify_password(password, &acc.password_hash);
         if !verfiy_password {
             return Err(AccountServiceError::PasswordMismatch);
         }
 
-        Ok(acc)
+        self.check_hardware_info(&acc, hw_info, ip).await?;
+
+        // Remember the connection, so ip and machine bans can be issued later on
+        let mut acc: ActiveModel = acc.into();
+        acc.last_ip = Set(Some(ip.to_string()));
+        acc.last_machine_id = Set(Some(machine_id_str(hw_info)));
+        acc.last_login_at = Set(Some(Utc::now().naive_utc()));
+        Ok(acc.update(&self.db.0).await?)
+    }
+
+    /// Checks for an active ban of the account, the ip or the machine
+    pub async fn check_ban(
+        &self,
+        acc_id: AccountId,
+        ip: Option<IpAddr>,
+        hw_info: Option<&HardwareInfo>,
+    ) -> AccResult<()> {
+        let mut target = Condition::any().add(ban::Column::AccId.eq(acc_id));
+        if let Some(ip) = ip {
+            target = target.add(ban::Column::Ip.eq(ip.to_string()));
+        }
+        if let Some(hw_info) = hw_info {
+            target = target.add(ban::Column::MachineId.eq(machine_id_str(hw_info)));
+        }
+
+        let now = Utc::now().naive_utc();
+        let bans = ban::Entity::find()
+            .filter(target)
+            .filter(
+                Condition::any()
+                    .add(ban::Column::Time.is_null())
+                    .add(ban::Column::Time.gt(now)),
+            )
+            .all(&self.db.0)
+            .await?;
+
+        match BanInfo::longest(bans.into_iter().map(BanInfo::from)) {
+            Some(ban) => Err(AccountServiceError::AccountBanned(ban)),
+            None => Ok(()),
+        }
+    }
+
+    /// Bans the account, optionally also the last ip and machine of the account
+    pub async fn ban(&self, acc_id: AccountId, req: BanRequest) -> anyhow::Result<()> {
+        let Some(acc) = self.get(acc_id).await? else {
+            anyhow::bail!("Account {acc_id} not found");
+        };
+
+        let ban = ban::ActiveModel {
+            acc_id: Set(acc.id),
+            reason_code: Set(req.reason_code as i32),
+            reason: Set(req.reason),
+            time: Set(req.duration.map(|dur| Utc::now().naive_utc() + dur)),
+            ip: Set(acc.last_ip.filter(|_| req.ip)),
+            machine_id: Set(acc.last_machine_id.filter(|_| req.machine)),
+            ..Default::default()
+        };
+        ban::Entity::insert(ban).exec(&self.db.0).await?;
+        Ok(())
+    }
+
+    /// Lifts all active bans of the account, returns the number of lifted bans
+    pub async fn unban(&self, acc_id: AccountId) -> anyhow::Result<u64> {
+        let now = Utc::now().naive_utc();
+        // Bans are kept as history, so they just end now
+        let res = ban::Entity::update_many()
+            .col_expr(ban::Column::Time, Expr::value(now))
+            .filter(ban::Column::AccId.eq(acc_id))
+            .filter(
+                Condition::any()
+                    .add(ban::Column::Time.is_null())
+                    .add(ban::Column::Time.gt(now)),
+            )
+            .exec(&self.db.0)
+            .await?;
+        Ok(res.rows_affected)
     }
 
     pub async fn update(
@@ -171,6 +294,27 @@ impl AccountService {
         Ok(())
     }
 
+    /// Records the execution attempt of a GM command
+    pub async fn log_gm_command(
+        &self,
+        acc: &Model,
+        char_id: CharacterId,
+        command: &str,
+        allowed: bool,
+    ) -> anyhow::Result<()> {
+        let log = gm_log::ActiveModel {
+            acc_id: Set(acc.id),
+            char_id: Set(char_id.0 as i32),
+            command: Set(command.to_string()),
+            gm_level: Set(acc.gm_level),
+            allowed: Set(allowed),
+            created_at: created_at(&self.db.0),
+            ..Default::default()
+        };
+        gm_log::Entity::insert(log).exec(&self.db.0).await?;
+        Ok(())
+    }
+
     pub fn check_pin(&self, acc: &Model, pin: &str) -> anyhow::Result<bool> {
         let Some(acc_pin) = acc.pin.as_ref() else {
             anyhow::bail!("Pin not set")
@@ -187,13 +331,13 @@ impl AccountService {
         Ok(constant_time_eq(acc_pic.as_bytes(), pic.as_bytes()))
     }
 
-    pub fn check_hardware_info(
+    pub async fn check_hardware_info(
         &self,
-        _acc: &Model,
-        _hw_info: &HardwareInfo,
-        _ip: IpAddr,
-    ) -> anyhow::Result<()> {
-        Ok(())
+        acc: &Model,
+        hw_info: &HardwareInfo,
+        ip: IpAddr,
+    ) -> AccResult<()> {
+        self.check_ban(acc.id, Some(ip), Some(hw_info)).await
     }
 }
 
@@ -201,7 +345,7 @@ impl AccountService {
 mod tests {
     use crate::{entities::sea_orm_active_enums::GenderTy, services::account::Region};
 
-    use super::AccountService;
+    use super::{AccountService, BanInfo};
 
     async fn get_test_svc() -> anyhow::Result<AccountService> {
         /*  let acc_svc = AccountService::new(get_test_db().await?);
@@ -226,8 +370,33 @@ mod tests {
         assert!(acc.accepted_tos);
 
         //Login must work
-        svc.try_login(USERNAME, PW).await?;
+        svc.try_login(USERNAME, PW, [127, 0, 0, 1].into(), &Default::default())
+            .await?;
 
         Ok(())
     }
+
+    #[test]
+    fn longest_ban() {
+        let at = |h| {
+            Some(
+                chrono::NaiveDate::from_ymd_opt(2024, 1, 1)
+                    .unwrap()
+                    .and_hms_opt(h, 0, 0)
+                    .unwrap(),
+            )
+        };
+        let ban = |reason, until| BanInfo { reason, until };
+
+        assert_eq!(BanInfo::longest([]), None);
+        assert_eq!(
+            BanInfo::longest([ban(1, at(1)), ban(2, at(3)), ban(3, at(2))]),
+            Some(ban(2, at(3)))
+        );
+        // Permanent bans always win
+        assert_eq!(
+            BanInfo::longest([ban(1, at(1)), ban(2, None), ban(3, at(2))]),
+            Some(ban(2, None))
+        );
+    }
 }
@@ -0,0 +1,140 @@
+use sea_orm::{sea_query::Expr, ColumnTrait, EntityTrait, QueryFilter, Set, TransactionTrait};
+use shroom_meta::id::CharacterId;
+
+use crate::entities::buddy::{self, ActiveModel, Column, Entity};
+
+use super::DbConn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuddyStatus {
+    /// Both sides accepted
+    Accepted,
+    /// Request was sent to the buddy, but not accepted yet
+    Requested,
+    /// Request was received from the buddy, but not accepted yet
+    Pending,
+}
+
+impl From<BuddyStatus> for i32 {
+    fn from(value: BuddyStatus) -> Self {
+        match value {
+            BuddyStatus::Accepted => 0,
+            BuddyStatus::Requested => 1,
+            BuddyStatus::Pending => 2,
+        }
+    }
+}
+
+impl TryFrom<i32> for BuddyStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::Accepted,
+            1 => Self::Requested,
+            2 => Self::Pending,
+            _ => anyhow::bail!("Invalid buddy status: {value}"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuddyEntry {
+    pub id: CharacterId,
+    pub name: String,
+    pub group: String,
+    pub status: BuddyStatus,
+}
+
+impl BuddyEntry {
+    fn into_active_model(self, owner: CharacterId) -> ActiveModel {
+        ActiveModel {
+            char_id: Set(owner.0 as i32),
+            buddy_id: Set(self.id.0 as i32),
+            buddy_name: Set(self.name),
+            group_name: Set(self.group),
+            status: Set(self.status.into()),
+            ..Default::default()
+        }
+    }
+}
+
+impl TryFrom<buddy::Model> for BuddyEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(value: buddy::Model) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: CharacterId(value.buddy_id as u32),
+            name: value.buddy_name,
+            group: value.group_name,
+            status: value.status.try_into()?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct BuddyService {
+    db: DbConn,
+}
+
+impl BuddyService {
+    pub fn new(db: DbConn) -> Self {
+        Self { db }
+    }
+
+    pub async fn load(&self, char_id: CharacterId) -> anyhow::Result<Vec<BuddyEntry>> {
+        Entity::find()
+            .filter(Column::CharId.eq(char_id.0 as i32))
+            .all(&self.db.0)
+            .await?
+            .into_iter()
+            .map(BuddyEntry::try_from)
+            .collect()
+    }
+
+    /// Inserts or replaces a single entry of the buddy list,
+    /// used when the owner of the list is offline
+    pub async fn set(&self, char_id: CharacterId, buddy: BuddyEntry) -> anyhow::Result<()> {
+        // There's no unique key on (char_id, buddy_id), so the entry is
+        // replaced within a transaction, to never lose or duplicate it
+        let txn = self.db.0.begin().await?;
+        Entity::delete_many()
+            .filter(Column::CharId.eq(char_id.0 as i32))
+            .filter(Column::BuddyId.eq(buddy.id.0 as i32))
+            .exec(&txn)
+            .await?;
+        Entity::insert(buddy.into_active_model(char_id))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Updates the status of a single entry while keeping the group,
+    /// used when the owner of the list is offline
+    pub async fn set_status(
+        &self,
+        char_id: CharacterId,
+        buddy_id: CharacterId,
+        status: BuddyStatus,
+    ) -> anyhow::Result<()> {
+        Entity::update_many()
+            .col_expr(Column::Status, Expr::value(i32::from(status)))
+            .filter(Column::CharId.eq(char_id.0 as i32))
+            .filter(Column::BuddyId.eq(buddy_id.0 as i32))
+            .exec(&self.db.0)
+            .await?;
+        Ok(())
+    }
+
+    /// Removes a single entry of the buddy list,
+    /// used when the owner of the list is offline
+    pub async fn remove(&self, char_id: CharacterId, buddy_id: CharacterId) -> anyhow::Result<()> {
+        Entity::delete_many()
+            .filter(Column::CharId.eq(char_id.0 as i32))
+            .filter(Column::BuddyId.eq(buddy_id.0 as i32))
+            .exec(&self.db.0)
+            .await?;
+        Ok(())
+    }
+}
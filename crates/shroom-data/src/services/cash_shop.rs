@@ -0,0 +1,430 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+use shroom_meta::{cash::Commodity, id::ItemId};
+use thiserror::Error;
+
+use crate::entities::{account, cash_coupon, cash_locker_item, character, trunk};
+
+use super::{account::AccountId, DbConn};
+
+/// Serial number of an item in the cash locker
+pub type CashItemSn = i32;
+
+#[derive(Debug, Error)]
+pub enum CashShopError {
+    #[error("Not enough cash")]
+    NoCash,
+    #[error("Cash limit exceeded")]
+    CashLimit,
+    #[error("No item with the serial number in the locker")]
+    InvalidSn,
+    #[error("Invalid coupon")]
+    InvalidCoupon,
+    #[error("Coupon was already used")]
+    CouponUsed,
+    #[error("No character with the name exists")]
+    UnknownReceiver,
+    #[error("Account not found")]
+    UnknownAccount,
+    #[error("database")]
+    Disconnect(#[from] DbErr),
+}
+
+pub type CashResult<T> = std::result::Result<T, CashShopError>;
+
+/// Change to the cash locker, which is saved together with the inventory
+#[derive(Debug, Clone)]
+pub enum LockerChange {
+    /// Item was moved from the locker into the inventory
+    Take(AccountId, CashItemSn),
+    /// Item was moved from the inventory into the locker
+    Put(AccountId, CashLockerItemDTO),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CashType {
+    NxCredit,
+    ShroomPoints,
+    NxPrepaid,
+}
+
+impl CashType {
+    fn column(self) -> account::Column {
+        match self {
+            CashType::NxCredit => account::Column::NxCredit,
+            CashType::ShroomPoints => account::Column::ShroomPoints,
+            CashType::NxPrepaid => account::Column::NxPrepaid,
+        }
+    }
+}
+
+/// Cash balances of an account
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CashBalance {
+    pub nx_credit: u32,
+    pub nx_prepaid: u32,
+    pub shroom_points: u32,
+}
+
+impl From<&account::Model> for CashBalance {
+    fn from(acc: &account::Model) -> Self {
+        Self {
+            nx_credit: acc.nx_credit.max(0) as u32,
+            nx_prepaid: acc.nx_prepaid.max(0) as u32,
+            shroom_points: acc.shroom_points.max(0) as u32,
+        }
+    }
+}
+
+impl CashBalance {
+    fn get_mut(&mut self, ty: CashType) -> &mut u32 {
+        match ty {
+            CashType::NxCredit => &mut self.nx_credit,
+            CashType::ShroomPoints => &mut self.shroom_points,
+            CashType::NxPrepaid => &mut self.nx_prepaid,
+        }
+    }
+
+    pub fn pay(&mut self, ty: CashType, price: u32) -> CashResult<()> {
+        let cash = self.get_mut(ty);
+        *cash = cash.checked_sub(price).ok_or(CashShopError::NoCash)?;
+        Ok(())
+    }
+
+    pub fn charge(&mut self, ty: CashType, amount: u32) -> CashResult<()> {
+        let cash = self.get_mut(ty);
+        *cash = cash
+            .checked_add(amount)
+            .filter(|c| *c <= i32::MAX as u32)
+            .ok_or(CashShopError::CashLimit)?;
+        Ok(())
+    }
+
+    /// Copies the balance onto the account
+    pub fn apply(&self, acc: &mut account::Model) {
+        acc.nx_credit = self.nx_credit as i32;
+        acc.nx_prepaid = self.nx_prepaid as i32;
+        acc.shroom_points = self.shroom_points as i32;
+    }
+}
+
+/// Item, which is put into the cash locker
+#[derive(Debug, Clone)]
+pub struct CashLockerItemDTO {
+    pub item_id: ItemId,
+    pub commodity_sn: u32,
+    pub quantity: u16,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl From<&Commodity> for CashLockerItemDTO {
+    fn from(commodity: &Commodity) -> Self {
+        Self {
+            item_id: commodity.item_id,
+            commodity_sn: commodity.sn,
+            quantity: commodity.count.max(1),
+            expires_at: (!commodity.is_permanent())
+                .then(|| Utc::now().naive_utc() + Duration::days(i64::from(commodity.period))),
+        }
+    }
+}
+
+/// Sender of a gifted item
+#[derive(Debug, Clone)]
+pub struct CashGift {
+    pub receiver: AccountId,
+    pub sender_name: String,
+}
+
+/// Rewards of a redeemed coupon
+#[derive(Debug)]
+pub struct CouponReward {
+    pub balance: CashBalance,
+    pub nx_credit: u32,
+    pub item: Option<cash_locker_item::Model>,
+}
+
+/// Handles the cash balances and the account-wide cash locker,
+/// every operation is committed to the database right away
+#[derive(Debug)]
+pub struct CashShopService {
+    db: DbConn,
+}
+
+impl CashShopService {
+    pub fn new(db: DbConn) -> Self {
+        Self { db }
+    }
+
+    async fn get_balance_in<C: ConnectionTrait>(
+        db: &C,
+        acc_id: AccountId,
+    ) -> CashResult<CashBalance> {
+        account::Entity::find_by_id(acc_id)
+            .one(db)
+            .await?
+            .map(|acc| CashBalance::from(&acc))
+            .ok_or(CashShopError::UnknownAccount)
+    }
+
+    /// Takes the price with a conditional update, so concurrent purchases
+    /// can never spend the same cash twice, returns the new balance
+    async fn pay_in<C: ConnectionTrait>(
+        db: &C,
+        acc_id: AccountId,
+        ty: CashType,
+        price: u32,
+    ) -> CashResult<CashBalance> {
+        let price = i32::try_from(price).map_err(|_| CashShopError::NoCash)?;
+        let col = ty.column();
+        let res = account::Entity::update_many()
+            .col_expr(col, Expr::col(col).sub(price))
+            .filter(account::Column::Id.eq(acc_id))
+            .filter(col.gte(price))
+            .exec(db)
+            .await?;
+        let balance = Self::get_balance_in(db, acc_id).await?;
+        if res.rows_affected != 1 {
+            return Err(CashShopError::NoCash);
+        }
+        Ok(balance)
+    }
+
+    /// Adds the amount with a conditional update, which keeps the cash below the limit
+    async fn charge_in<C: ConnectionTrait>(
+        db: &C,
+        acc_id: AccountId,
+        ty: CashType,
+        amount: u32,
+    ) -> CashResult<CashBalance> {
+        let amount = i32::try_from(amount).map_err(|_| CashShopError::CashLimit)?;
+        let col = ty.column();
+        let res = account::Entity::update_many()
+            .col_expr(col, Expr::col(col).add(amount))
+            .filter(account::Column::Id.eq(acc_id))
+            .filter(col.lte(i32::MAX - amount))
+            .exec(db)
+            .await?;
+        let balance = Self::get_balance_in(db, acc_id).await?;
+        if res.rows_affected != 1 {
+            return Err(CashShopError::CashLimit);
+        }
+        Ok(balance)
+    }
+
+    pub(crate) async fn insert_item_in<C: ConnectionTrait>(
+        db: &C,
+        acc_id: AccountId,
+        item: CashLockerItemDTO,
+        gift_from: Option<String>,
+    ) -> CashResult<cash_locker_item::Model> {
+        Ok(cash_locker_item::ActiveModel {
+            acc_id: Set(acc_id),
+            item_id: Set(item.item_id.0 as i32),
+            commodity_sn: Set(item.commodity_sn as i32),
+            quantity: Set(i32::from(item.quantity)),
+            gift_from: Set(gift_from),
+            expires_at: Set(item.expires_at),
+            created_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+
+    pub async fn get_balance(&self, acc_id: AccountId) -> CashResult<CashBalance> {
+        Self::get_balance_in(&self.db.0, acc_id).await
+    }
+
+    pub async fn load_locker(&self, acc_id: AccountId) -> CashResult<Vec<cash_locker_item::Model>> {
+        Ok(cash_locker_item::Entity::find()
+            .filter(cash_locker_item::Column::AccId.eq(acc_id))
+            .order_by_asc(cash_locker_item::Column::Id)
+            .all(&self.db.0)
+            .await?)
+    }
+
+    /// Looks up the account of the character, which receives a gift
+    pub async fn find_gift_receiver(&self, name: &str) -> CashResult<AccountId> {
+        character::Entity::find()
+            .filter(character::Column::Name.eq(name))
+            .one(&self.db.0)
+            .await?
+            .map(|chr| chr.acc_id)
+            .ok_or(CashShopError::UnknownReceiver)
+    }
+
+    /// Charges the price from the account and puts the item into the locker,
+    /// gifted items end up in the locker of the receiver
+    pub async fn buy(
+        &self,
+        acc_id: AccountId,
+        ty: CashType,
+        price: u32,
+        item: CashLockerItemDTO,
+        gift: Option<CashGift>,
+    ) -> CashResult<(CashBalance, cash_locker_item::Model)> {
+        let txn = self.db.0.begin().await?;
+        let balance = Self::pay_in(&txn, acc_id, ty, price).await?;
+
+        let (owner, gift_from) = match gift {
+            Some(gift) => (gift.receiver, Some(gift.sender_name)),
+            None => (acc_id, None),
+        };
+        let item = Self::insert_item_in(&txn, owner, item, gift_from).await?;
+        txn.commit().await?;
+
+        Ok((balance, item))
+    }
+
+    /// Charges the price from the account and stores the new slot count of the trunk
+    pub async fn buy_trunk_slots(
+        &self,
+        acc_id: AccountId,
+        ty: CashType,
+        price: u32,
+        slots: u8,
+    ) -> CashResult<CashBalance> {
+        let txn = self.db.0.begin().await?;
+        let balance = Self::pay_in(&txn, acc_id, ty, price).await?;
+
+        trunk::Entity::update_many()
+            .col_expr(trunk::Column::Slots, Expr::value(i32::from(slots)))
+            .filter(trunk::Column::AccId.eq(acc_id))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+
+        Ok(balance)
+    }
+
+    /// Looks up the item in the locker of the account
+    pub async fn get_locker_item(
+        &self,
+        acc_id: AccountId,
+        sn: CashItemSn,
+    ) -> CashResult<cash_locker_item::Model> {
+        cash_locker_item::Entity::find_by_id(sn)
+            .filter(cash_locker_item::Column::AccId.eq(acc_id))
+            .one(&self.db.0)
+            .await?
+            .ok_or(CashShopError::InvalidSn)
+    }
+
+    /// Removes the item from the locker, fails if It was already taken out
+    pub(crate) async fn remove_locker_item_in<C: ConnectionTrait>(
+        db: &C,
+        acc_id: AccountId,
+        sn: CashItemSn,
+    ) -> CashResult<()> {
+        let res = cash_locker_item::Entity::delete_many()
+            .filter(cash_locker_item::Column::Id.eq(sn))
+            .filter(cash_locker_item::Column::AccId.eq(acc_id))
+            .exec(db)
+            .await?;
+        if res.rows_affected != 1 {
+            return Err(CashShopError::InvalidSn);
+        }
+        Ok(())
+    }
+
+    /// Marks the coupon as used and hands out the rewards
+    pub async fn redeem_coupon(&self, acc_id: AccountId, code: &str) -> CashResult<CouponReward> {
+        let txn = self.db.0.begin().await?;
+        let coupon = cash_coupon::Entity::find()
+            .filter(cash_coupon::Column::Code.eq(code))
+            .one(&txn)
+            .await?
+            .ok_or(CashShopError::InvalidCoupon)?;
+
+        // Only claims the coupon if nobody else did in the meantime
+        let res = cash_coupon::Entity::update_many()
+            .col_expr(cash_coupon::Column::UsedBy, Expr::value(acc_id))
+            .col_expr(
+                cash_coupon::Column::UsedAt,
+                Expr::value(Utc::now().naive_utc()),
+            )
+            .filter(cash_coupon::Column::Code.eq(code))
+            .filter(cash_coupon::Column::UsedBy.is_null())
+            .exec(&txn)
+            .await?;
+        if res.rows_affected != 1 {
+            return Err(CashShopError::CouponUsed);
+        }
+
+        let nx_credit = coupon.nx_credit.max(0) as u32;
+        let balance = if nx_credit > 0 {
+            Self::charge_in(&txn, acc_id, CashType::NxCredit, nx_credit).await?
+        } else {
+            Self::get_balance_in(&txn, acc_id).await?
+        };
+
+        let item = if coupon.item_id != 0 {
+            let item = CashLockerItemDTO {
+                item_id: ItemId(coupon.item_id as u32),
+                commodity_sn: 0,
+                quantity: coupon.quantity.max(1) as u16,
+                expires_at: None,
+            };
+            Some(Self::insert_item_in(&txn, acc_id, item, None).await?)
+        } else {
+            None
+        };
+        txn.commit().await?;
+
+        Ok(CouponReward {
+            balance,
+            nx_credit,
+            item,
+        })
+    }
+
+    /// Creates a new coupon, which can be redeemed once
+    pub async fn create_coupon(
+        &self,
+        code: &str,
+        nx_credit: u32,
+        item: Option<(ItemId, u16)>,
+    ) -> CashResult<()> {
+        let (item_id, quantity) = item.map_or((0, 0), |(id, n)| (id.0 as i32, i32::from(n)));
+        cash_coupon::Entity::insert(cash_coupon::ActiveModel {
+            code: Set(code.to_string()),
+            nx_credit: Set(nx_credit as i32),
+            item_id: Set(item_id),
+            quantity: Set(quantity),
+            ..Default::default()
+        })
+        .exec(&self.db.0)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cash_balance() {
+        let mut balance = CashBalance {
+            nx_credit: 100,
+            ..Default::default()
+        };
+        assert!(matches!(
+            balance.pay(CashType::NxPrepaid, 1),
+            Err(CashShopError::NoCash)
+        ));
+        balance.pay(CashType::NxCredit, 100).unwrap();
+        assert_eq!(balance.nx_credit, 0);
+
+        balance.charge(CashType::ShroomPoints, 50).unwrap();
+        assert_eq!(balance.shroom_points, 50);
+        assert!(matches!(
+            balance.charge(CashType::ShroomPoints, i32::MAX as u32),
+            Err(CashShopError::CashLimit)
+        ));
+    }
+}
@@ -0,0 +1,424 @@
+use sea_orm::{
+    ActiveValue::NotSet, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, Set,
+    TransactionTrait,
+};
+use shroom_meta::{
+    id::{item_id::InventoryType, CharacterId},
+    item::it::{EquipItem, ItemInfo, PetItem, StackItem},
+};
+use shroom_srv::GameTime;
+
+use crate::{
+    blob::BinaryBlob,
+    entities::{cash_locker_item, character, func_key_map, inventory_slot, quest, skill},
+    entity_ext::KeyMap,
+    model::skill::SkillSet,
+};
+
+use super::{
+    account::AccountId,
+    cash_shop::{CashShopService, LockerChange},
+    character::{quest_models, skill_models, QuestSet},
+    item::ItemService,
+    merchant::{Merchant, MerchantService},
+    trunk::{Trunk, TrunkItem, TrunkService},
+    DbConn,
+};
+
+/// Parts of a character, which changed since the last save
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CharSaveParts {
+    pub char: bool,
+    pub inventory: bool,
+    pub skills: bool,
+    pub key_map: bool,
+    pub quests: bool,
+    pub trunk: bool,
+}
+
+impl CharSaveParts {
+    pub const ALL: Self = Self {
+        char: true,
+        inventory: true,
+        skills: true,
+        key_map: true,
+        quests: true,
+        trunk: true,
+    };
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn merge(&mut self, other: Self) {
+        self.char |= other.char;
+        self.inventory |= other.inventory;
+        self.skills |= other.skills;
+        self.key_map |= other.key_map;
+        self.quests |= other.quests;
+        self.trunk |= other.trunk;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SavedItemKind {
+    Equip,
+    Stack,
+    Pet,
+}
+
+/// Db id of an item, which was inserted by a save,
+/// game ids are only unique per kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SavedItemId {
+    pub kind: SavedItemKind,
+    pub game_id: i64,
+    pub db_id: i32,
+}
+
+/// Assigns the db id of a saved item, if the item has no id yet
+pub fn assign_db_id(info: &mut ItemInfo, kind: SavedItemKind, ids: &[SavedItemId]) {
+    if info.db_id.is_some() {
+        return;
+    }
+    info.db_id = ids
+        .iter()
+        .find(|id| id.kind == kind && id.game_id == info.game_id)
+        .map(|id| id.db_id);
+}
+
+#[derive(Debug, Clone)]
+pub enum SnapshotItem {
+    Equip(Box<EquipItem>),
+    Stack(Box<StackItem>),
+    Pet(Box<PetItem>),
+}
+
+#[derive(Debug, Clone)]
+pub struct SnapshotSlot {
+    pub inv_type: InventoryType,
+    pub slot: i32,
+    pub item: SnapshotItem,
+}
+
+/// Copy of the changed parts of a character, parts which are `None` are not written
+#[derive(Debug)]
+pub struct CharSnapshot {
+    pub id: CharacterId,
+    pub acc_id: AccountId,
+    pub char: Option<character::ActiveModel>,
+    pub inventory: Option<Vec<SnapshotSlot>>,
+    pub skills: Option<Vec<skill::ActiveModel>>,
+    pub key_map: Option<KeyMap>,
+    pub quests: Option<QuestSet>,
+    pub trunk: Option<Trunk>,
+    /// Merchants changed together with the character, a merchant
+    /// which is `None` is removed
+    pub merchants: Vec<(CharacterId, Option<Merchant>)>,
+}
+
+impl CharSnapshot {
+    pub fn new(id: CharacterId, acc_id: AccountId) -> Self {
+        Self {
+            id,
+            acc_id,
+            char: None,
+            inventory: None,
+            skills: None,
+            key_map: None,
+            quests: None,
+            trunk: None,
+            merchants: Vec::new(),
+        }
+    }
+
+    pub fn with_char(mut self, char: character::ActiveModel) -> Self {
+        self.char = Some(char);
+        self
+    }
+
+    pub fn with_inventory(mut self, slots: Vec<SnapshotSlot>) -> Self {
+        self.inventory = Some(slots);
+        self
+    }
+
+    pub fn with_skills(mut self, t: GameTime, skills: &SkillSet) -> Self {
+        self.skills = Some(skill_models(t, self.id, skills));
+        self
+    }
+
+    pub fn with_key_map(mut self, key_map: KeyMap) -> Self {
+        self.key_map = Some(key_map);
+        self
+    }
+
+    pub fn with_quests(mut self, quests: QuestSet) -> Self {
+        self.quests = Some(quests);
+        self
+    }
+
+    pub fn with_trunk(mut self, trunk: Trunk) -> Self {
+        self.trunk = Some(trunk);
+        self
+    }
+
+    pub fn with_merchant(mut self, owner: CharacterId, merchant: Option<Merchant>) -> Self {
+        self.merchants.push((owner, merchant));
+        self
+    }
+
+    /// Parts contained in this snapshot
+    pub fn parts(&self) -> CharSaveParts {
+        CharSaveParts {
+            char: self.char.is_some(),
+            inventory: self.inventory.is_some(),
+            skills: self.skills.is_some(),
+            key_map: self.key_map.is_some(),
+            quests: self.quests.is_some(),
+            trunk: self.trunk.is_some(),
+        }
+    }
+}
+
+/// Writes character snapshots within a single transaction, so a failed save
+/// never leaves the character and its items in an inconsistent state
+#[derive(Debug)]
+pub struct CharSaveService<'svc> {
+    db: DbConn,
+    item: &'svc ItemService,
+}
+
+impl<'svc> CharSaveService<'svc> {
+    pub fn new(db: DbConn, item: &'svc ItemService) -> Self {
+        Self { db, item }
+    }
+
+    /// Saves the snapshot, returns the ids of all newly inserted items
+    pub async fn save(&self, snapshot: &mut CharSnapshot) -> anyhow::Result<Vec<SavedItemId>> {
+        let txn = self.db.0.begin().await?;
+        let ids = self.save_in(&txn, snapshot).await?;
+        txn.commit().await?;
+        Ok(ids)
+    }
+
+    /// Saves the snapshot and applies the change to the cash locker in the same transaction,
+    /// so an item moved between the locker and the inventory is neither lost nor duplicated
+    pub async fn save_with_locker(
+        &self,
+        snapshot: &mut CharSnapshot,
+        change: LockerChange,
+    ) -> anyhow::Result<(Vec<SavedItemId>, Option<cash_locker_item::Model>)> {
+        let txn = self.db.0.begin().await?;
+        let ids = self.save_in(&txn, snapshot).await?;
+        let item = match change {
+            LockerChange::Take(acc_id, sn) => {
+                CashShopService::remove_locker_item_in(&txn, acc_id, sn).await?;
+                None
+            }
+            LockerChange::Put(acc_id, item) => {
+                Some(CashShopService::insert_item_in(&txn, acc_id, item, None).await?)
+            }
+        };
+        txn.commit().await?;
+        Ok((ids, item))
+    }
+
+    async fn save_in<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        snapshot: &mut CharSnapshot,
+    ) -> anyhow::Result<Vec<SavedItemId>> {
+        let char_id = snapshot.id.0 as i32;
+        let mut ids = Vec::new();
+
+        if let Some(char) = snapshot.char.clone() {
+            character::Entity::update(char).exec(db).await?;
+        }
+
+        if let Some(slots) = snapshot.inventory.as_mut() {
+            self.save_inventory(db, char_id, slots, &mut ids).await?;
+        }
+
+        if let Some(skills) = snapshot.skills.clone() {
+            skill::Entity::delete_many()
+                .filter(skill::Column::CharId.eq(char_id))
+                .exec(db)
+                .await?;
+            if !skills.is_empty() {
+                skill::Entity::insert_many(skills).exec(db).await?;
+            }
+        }
+
+        if let Some(key_map) = snapshot.key_map.as_ref() {
+            func_key_map::Entity::delete_many()
+                .filter(func_key_map::Column::CharId.eq(char_id))
+                .exec(db)
+                .await?;
+            func_key_map::Entity::insert(func_key_map::ActiveModel {
+                id: NotSet,
+                char_id: Set(char_id),
+                data: Set(key_map.to_blob()?),
+            })
+            .exec(db)
+            .await?;
+        }
+
+        if let Some(quests) = snapshot.quests.clone() {
+            let quests = quest_models(snapshot.id, quests)?;
+            quest::Entity::delete_many()
+                .filter(quest::Column::CharId.eq(char_id))
+                .exec(db)
+                .await?;
+            if !quests.is_empty() {
+                quest::Entity::insert_many(quests).exec(db).await?;
+            }
+        }
+
+        if let Some(trunk) = snapshot.trunk.as_mut() {
+            let new: Vec<_> = trunk
+                .items
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| trunk_item_info(item).db_id.is_none())
+                .map(|(ix, _)| ix)
+                .collect();
+            TrunkService::new(self.db.clone(), self.item)
+                .save_in(db, snapshot.acc_id, trunk)
+                .await?;
+            for ix in new {
+                let item = &trunk.items[ix];
+                let kind = match item {
+                    TrunkItem::Equip(_) => SavedItemKind::Equip,
+                    TrunkItem::Stack(_) => SavedItemKind::Stack,
+                };
+                let info = trunk_item_info(item);
+                ids.push(SavedItemId {
+                    kind,
+                    game_id: info.game_id,
+                    db_id: info.db_id.expect("saved item"),
+                });
+            }
+        }
+
+        let merchant_svc = MerchantService::new(self.db.clone(), self.item);
+        for (owner, merchant) in snapshot.merchants.iter_mut() {
+            match merchant {
+                Some(merchant) => merchant_svc.save_in(db, merchant).await?,
+                None => merchant_svc.remove_in(db, *owner).await?,
+            }
+        }
+
+        Ok(ids)
+    }
+
+    async fn save_inventory<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        char_id: i32,
+        slots: &mut [SnapshotSlot],
+        ids: &mut Vec<SavedItemId>,
+    ) -> anyhow::Result<()> {
+        inventory_slot::Entity::delete_many()
+            .filter(inventory_slot::Column::CharId.eq(char_id))
+            .exec(db)
+            .await?;
+
+        let mut rows = Vec::with_capacity(slots.len());
+        for slot in slots.iter_mut() {
+            let (kind, info) = match &mut slot.item {
+                SnapshotItem::Equip(item) => {
+                    let new = item.db_id.is_none();
+                    self.item.save_equip_in(db, item).await?;
+                    (new.then_some(SavedItemKind::Equip), &item.info)
+                }
+                SnapshotItem::Stack(item) => {
+                    let new = item.db_id.is_none();
+                    self.item.save_stack_in(db, item).await?;
+                    (new.then_some(SavedItemKind::Stack), &item.info)
+                }
+                SnapshotItem::Pet(item) => {
+                    let new = item.db_id.is_none();
+                    self.item.save_pet_in(db, item).await?;
+                    (new.then_some(SavedItemKind::Pet), &item.info)
+                }
+            };
+            let db_id = info.db_id.expect("saved item");
+            if let Some(kind) = kind {
+                ids.push(SavedItemId {
+                    kind,
+                    game_id: info.game_id,
+                    db_id,
+                });
+            }
+
+            let (equip_item_id, stack_item_id, pet_item_id) = match slot.item {
+                SnapshotItem::Equip(_) => (Some(db_id), None, None),
+                SnapshotItem::Stack(_) => (None, Some(db_id), None),
+                SnapshotItem::Pet(_) => (None, None, Some(db_id)),
+            };
+            rows.push(inventory_slot::ActiveModel {
+                id: NotSet,
+                equip_item_id: Set(equip_item_id),
+                stack_item_id: Set(stack_item_id),
+                pet_item_id: Set(pet_item_id),
+                char_id: Set(char_id),
+                slot: Set(slot.slot),
+                inv_type: Set(slot.inv_type as i32),
+            });
+        }
+
+        if !rows.is_empty() {
+            inventory_slot::Entity::insert_many(rows).exec(db).await?;
+        }
+        Ok(())
+    }
+}
+
+fn trunk_item_info(item: &TrunkItem) -> &ItemInfo {
+    match item {
+        TrunkItem::Equip(item) => &item.info,
+        TrunkItem::Stack(item) => &item.info,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shroom_meta::id::ItemId;
+
+    use super::*;
+
+    #[test]
+    fn assign_saved_ids() {
+        let ids = [
+            SavedItemId {
+                kind: SavedItemKind::Stack,
+                game_id: 1,
+                db_id: 10,
+            },
+            SavedItemId {
+                kind: SavedItemKind::Equip,
+                game_id: 2,
+                db_id: 20,
+            },
+        ];
+
+        // Game ids are only unique per kind
+        let mut info = ItemInfo::from_id(ItemId(2000000), 2, false);
+        assign_db_id(&mut info, SavedItemKind::Stack, &ids);
+        assert_eq!(info.db_id, None);
+        assign_db_id(&mut info, SavedItemKind::Equip, &ids);
+        assert_eq!(info.db_id, Some(20));
+
+        // Existing ids are kept
+        assign_db_id(&mut info, SavedItemKind::Equip, &ids[..1]);
+        assert_eq!(info.db_id, Some(20));
+
+        let mut parts = CharSaveParts::default();
+        assert!(parts.is_empty());
+        parts.merge(CharSaveParts {
+            inventory: true,
+            ..Default::default()
+        });
+        assert!(!parts.is_empty());
+    }
+}
@@ -15,7 +15,10 @@ use shroom_meta::{
     }, CharLevel, MetaService, QuestDataId
 };
 use shroom_proto95::{
-    login::char::{DeleteCharResult, SelectCharResultCode},
+    login::{
+        char::{DeleteCharResult, SelectCharResultCode},
+        WorldId,
+    },
     shared::Gender,
 };
 use shroom_srv::GameTime;
@@ -189,7 +192,8 @@ pub struct CharacterCreateDTO {
     pub starter_set: ItemStarterSet,
     pub gender: Gender,
     pub max_skills: bool,
-    pub level: Option<CharLevel>
+    pub level: Option<CharLevel>,
+    pub world_id: WorldId,
 }
 
 impl CharacterCreateDTO {
@@ -284,12 +288,25 @@ impl<'svc> CharacterService<'svc> {
             .await?)
     }
 
+    pub async fn get_characters_for_world(
+        &self,
+        acc_id: AccountId,
+        world_id: WorldId,
+    ) -> anyhow::Result<Vec<Model>> {
+        Ok(Entity::find()
+            .filter(Column::AccId.eq(acc_id))
+            .filter(Column::WorldId.eq(world_id as i32))
+            .all(&self.db.0)
+            .await?)
+    }
+
     pub async fn get_characters_with_equips(
         &self,
         acc_id: AccountId,
+        world_id: WorldId,
     ) -> anyhow::Result<Vec<CharWithEquips>> {
         // TODO should be a single query + caching
-        let chars = self.get_characters_for_account(acc_id).await?;
+        let chars = self.get_characters_for_world(acc_id, world_id).await?;
         let mut res = Vec::with_capacity(chars.len());
         for char in chars {
             let equips = self
@@ -305,6 +322,13 @@ impl<'svc> CharacterService<'svc> {
         Ok(Entity::find_by_id(char_id.0 as i32).one(&self.db.0).await?)
     }
 
+    pub async fn get_by_name(&self, name: &str) -> anyhow::Result<Option<Model>> {
+        Ok(Entity::find()
+            .filter(Column::Name.eq(name))
+            .one(&self.db.0)
+            .await?)
+    }
+
     pub async fn must_get(&self, char_id: CharacterId) -> anyhow::Result<Model> {
         self.get(char_id)
             .await?
@@ -352,6 +376,7 @@ impl<'svc> CharacterService<'svc> {
         let field_id = job_group.get_start_field().0 as i32;
         let char = ActiveModel {
             acc_id: Set(acc_id),
+            world_id: Set(create.world_id as i32),
             created_at: created_at(&self.db.0),
             gender: Set((create.gender).into()),
             name: Set(create.name),
@@ -468,8 +493,10 @@ impl<'svc> CharacterService<'svc> {
             .await?;
 
         let now = Utc::now();
+        // Times are stored as UTC, passed times have no time left
+        let remaining = |at: chrono::NaiveDateTime| (at.and_utc() - now).to_std().ok();
 
-        SkillSet::from_skills(skills.iter().map(|skill| {
+        let mut set = SkillSet::from_skills(skills.iter().map(|skill| {
             let id = SkillId(skill.skill_id as u32);
             let meta = self.meta.get_skill(id).unwrap();
             SkillData {
@@ -478,13 +505,19 @@ impl<'svc> CharacterService<'svc> {
                 mastery_level: (skill.master_level != 0).then_some(skill.master_level as usize),
                 expires_at: skill
                     .expires_at
-                    .map(|exp_at| t + (now - exp_at.and_utc()).to_std().unwrap()),
-                cooldown: skill
-                    .expires_at
-                    .map(|exp_at| t + (now - exp_at.and_utc()).to_std().unwrap()),
+                    .map(|exp_at| t + remaining(exp_at).unwrap_or_default()),
                 meta,
             }
-        }))
+        }))?;
+
+        // Cooldowns keep running while the character is offline
+        for skill in skills.iter() {
+            if let Some(cd) = skill.cooldown.and_then(remaining) {
+                set.restore_cooldown(SkillId(skill.skill_id as u32), t, cd);
+            }
+        }
+
+        Ok(set)
     }
 
     pub async fn save_skills(
@@ -499,23 +532,8 @@ impl<'svc> CharacterService<'svc> {
             .exec(&self.db.0)
             .await?;
 
-        let now = Utc::now();
-        let get_utc_time =
-            |tt: GameTime| now + chrono::Duration::from_std(tt.delta_ticks(t).into()).unwrap();
-
         // Insert new skills
-        let skills: Vec<_> = skills
-            .skills()
-            .map(|skill| skill::ActiveModel {
-                id: NotSet,
-                skill_id: Set(skill.id.0 as i32),
-                level: Set(skill.level as i32),
-                master_level: Set(skill.mastery_level.unwrap_or(0) as i32),
-                expires_at: Set(skill.expires_at.map(|t| get_utc_time(t).naive_utc())),
-                cooldown: Set(skill.cooldown.map(|t| get_utc_time(t).naive_utc())),
-                char_id: Set(char_id.0 as i32),
-            })
-            .collect();
+        let skills = skill_models(t, char_id, skills);
         skill::Entity::insert_many(skills).exec(&self.db.0).await?;
 
         Ok(())
@@ -592,39 +610,7 @@ impl<'svc> CharacterService<'svc> {
         char_id: CharacterId,
         q: QuestSet
     ) -> anyhow::Result<()> {
-        let mut quests = Vec::new();
-        for quest in q.active {
-            quests.push(quest::ActiveModel {
-                char_id: Set(char_id.0 as i32),
-                id: Set(quest.quest.0 as i32),
-                data: Set(quest.data.to_blob()?),
-                started_at: Set(Some(quest.started_at.naive_utc())),
-                completed_at: Set(None),
-                status: Set(1),
-            });
-        }
-        for quest in q.finished {
-            quests.push(quest::ActiveModel {
-                char_id: Set(char_id.0 as i32),
-                id: Set(quest.quest.0 as i32),
-                data: Set(Vec::new()),
-                started_at: Set(Some(quest.finished_at.naive_utc())),
-                completed_at: Set(Some(quest.finished_at.naive_utc())),
-                status: Set(2),
-            });
-        }
-
-        for quest in q.data {
-            quests.push(quest::ActiveModel {
-                char_id: Set(char_id.0 as i32),
-                id: Set(quest.quest.0 as i32),
-                data: Set(quest.data.clone()),
-                started_at: Set(None),
-                completed_at: Set(None),
-                status: Set(0),
-            });
-        }
-
+        let quests = quest_models(char_id, q)?;
         if quests.is_empty() {
             return Ok(());
         }
@@ -640,3 +626,70 @@ impl<'svc> CharacterService<'svc> {
         Ok(())
     }
 }
+
+/// Rows of the skills, the times are relative to the game time `t`
+pub(crate) fn skill_models(
+    t: GameTime,
+    char_id: CharacterId,
+    skills: &SkillSet,
+) -> Vec<skill::ActiveModel> {
+    let now = Utc::now();
+    let get_utc_time = |tt: GameTime| {
+        let remaining = tt.checked_duration_since(t).unwrap_or_default();
+        now + chrono::Duration::from_std(remaining).unwrap()
+    };
+
+    skills
+        .skills()
+        .map(|skill| skill::ActiveModel {
+            id: NotSet,
+            skill_id: Set(skill.id.0 as i32),
+            level: Set(skill.level as i32),
+            master_level: Set(skill.mastery_level.unwrap_or(0) as i32),
+            expires_at: Set(skill.expires_at.map(|t| get_utc_time(t).naive_utc())),
+            cooldown: Set(skills
+                .remaining_cooldown(skill.id, t)
+                .map(|cd| get_utc_time(t + cd).naive_utc())),
+            char_id: Set(char_id.0 as i32),
+        })
+        .collect()
+}
+
+pub(crate) fn quest_models(
+    char_id: CharacterId,
+    q: QuestSet,
+) -> anyhow::Result<Vec<quest::ActiveModel>> {
+    let mut quests = Vec::new();
+    for quest in q.active {
+        quests.push(quest::ActiveModel {
+            char_id: Set(char_id.0 as i32),
+            id: Set(quest.quest.0 as i32),
+            data: Set(quest.data.to_blob()?),
+            started_at: Set(Some(quest.started_at.naive_utc())),
+            completed_at: Set(None),
+            status: Set(1),
+        });
+    }
+    for quest in q.finished {
+        quests.push(quest::ActiveModel {
+            char_id: Set(char_id.0 as i32),
+            id: Set(quest.quest.0 as i32),
+            data: Set(Vec::new()),
+            started_at: Set(Some(quest.finished_at.naive_utc())),
+            completed_at: Set(Some(quest.finished_at.naive_utc())),
+            status: Set(2),
+        });
+    }
+
+    for quest in q.data {
+        quests.push(quest::ActiveModel {
+            char_id: Set(char_id.0 as i32),
+            id: Set(quest.quest.0 as i32),
+            data: Set(quest.data),
+            started_at: Set(None),
+            completed_at: Set(None),
+            status: Set(0),
+        });
+    }
+    Ok(quests)
+}
@@ -0,0 +1,188 @@
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set};
+use shroom_meta::id::{job_id::JobId, CharacterId};
+
+use crate::entities::{character, guild, guild_member};
+
+use super::DbConn;
+
+pub type GuildId = u32;
+
+pub const GUILD_GRADES: usize = 5;
+pub const GUILD_DEFAULT_CAPACITY: u8 = 10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GuildMark {
+    pub bg: u16,
+    pub bg_color: u8,
+    pub mark: u16,
+    pub mark_color: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct GuildMemberData {
+    pub id: CharacterId,
+    pub name: String,
+    pub job: JobId,
+    pub level: u8,
+    /// Grade starting at 1 for the master
+    pub grade: u8,
+    pub commitment: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct GuildData {
+    pub id: GuildId,
+    pub name: String,
+    pub notice: String,
+    pub points: u32,
+    pub capacity: u8,
+    pub mark: GuildMark,
+    pub grades: [String; GUILD_GRADES],
+    pub members: Vec<GuildMemberData>,
+}
+
+impl GuildData {
+    pub fn new(id: GuildId, name: String, master: GuildMemberData) -> Self {
+        Self {
+            id,
+            name,
+            notice: String::new(),
+            points: 0,
+            capacity: GUILD_DEFAULT_CAPACITY,
+            mark: GuildMark::default(),
+            grades: ["Master", "Jr. Master", "Member", "Member", "Member"].map(String::from),
+            members: vec![GuildMemberData { grade: 1, ..master }],
+        }
+    }
+
+    fn to_active_model(&self) -> guild::ActiveModel {
+        let [grade1, grade2, grade3, grade4, grade5] = self.grades.clone();
+        guild::ActiveModel {
+            id: Set(self.id as i32),
+            name: Set(self.name.clone()),
+            notice: Set(self.notice.clone()),
+            points: Set(self.points as i32),
+            capacity: Set(self.capacity as i32),
+            mark_bg: Set(self.mark.bg as i32),
+            mark_bg_color: Set(self.mark.bg_color as i32),
+            mark: Set(self.mark.mark as i32),
+            mark_color: Set(self.mark.mark_color as i32),
+            grade1: Set(grade1),
+            grade2: Set(grade2),
+            grade3: Set(grade3),
+            grade4: Set(grade4),
+            grade5: Set(grade5),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GuildService {
+    db: DbConn,
+}
+
+impl GuildService {
+    pub fn new(db: DbConn) -> Self {
+        Self { db }
+    }
+
+    pub async fn load_all(&self) -> anyhow::Result<Vec<GuildData>> {
+        let mut guilds: Vec<GuildData> = guild::Entity::find()
+            .all(&self.db.0)
+            .await?
+            .into_iter()
+            .map(|g| GuildData {
+                id: g.id as GuildId,
+                name: g.name,
+                notice: g.notice,
+                points: g.points as u32,
+                capacity: g.capacity as u8,
+                mark: GuildMark {
+                    bg: g.mark_bg as u16,
+                    bg_color: g.mark_bg_color as u8,
+                    mark: g.mark as u16,
+                    mark_color: g.mark_color as u8,
+                },
+                grades: [g.grade1, g.grade2, g.grade3, g.grade4, g.grade5],
+                members: Vec::new(),
+            })
+            .collect();
+
+        let members = guild_member::Entity::find()
+            .find_also_related(character::Entity)
+            .all(&self.db.0)
+            .await?;
+
+        for (member, chr) in members {
+            let Some(chr) = chr else {
+                anyhow::bail!("Invalid guild member: {}", member.char_id);
+            };
+            let Some(guild) = guilds
+                .iter_mut()
+                .find(|g| g.id == member.guild_id as GuildId)
+            else {
+                continue;
+            };
+            guild.members.push(GuildMemberData {
+                id: CharacterId(chr.id as u32),
+                name: chr.name,
+                job: JobId::try_from(chr.job as u16)?,
+                level: chr.level as u8,
+                grade: member.grade as u8,
+                commitment: member.commitment as u32,
+            });
+        }
+
+        Ok(guilds)
+    }
+
+    /// Stores the guild with all members, the id is assigned by the caller
+    pub async fn save(&self, guild: &GuildData) -> anyhow::Result<()> {
+        // TODO use a transaction
+        let exists = guild::Entity::find_by_id(guild.id as i32)
+            .one(&self.db.0)
+            .await?
+            .is_some();
+        if exists {
+            guild::Entity::update(guild.to_active_model())
+                .exec(&self.db.0)
+                .await?;
+        } else {
+            guild::Entity::insert(guild.to_active_model())
+                .exec(&self.db.0)
+                .await?;
+        }
+
+        guild_member::Entity::delete_many()
+            .filter(guild_member::Column::GuildId.eq(guild.id as i32))
+            .exec(&self.db.0)
+            .await?;
+
+        if !guild.members.is_empty() {
+            guild_member::Entity::insert_many(guild.members.iter().map(|m| {
+                guild_member::ActiveModel {
+                    guild_id: Set(guild.id as i32),
+                    char_id: Set(m.id.0 as i32),
+                    grade: Set(m.grade as i32),
+                    commitment: Set(m.commitment as i32),
+                    ..Default::default()
+                }
+            }))
+            .exec(&self.db.0)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn remove(&self, id: GuildId) -> anyhow::Result<()> {
+        guild_member::Entity::delete_many()
+            .filter(guild_member::Column::GuildId.eq(id as i32))
+            .exec(&self.db.0)
+            .await?;
+        guild::Entity::delete_by_id(id as i32)
+            .exec(&self.db.0)
+            .await?;
+        Ok(())
+    }
+}
@@ -11,8 +11,8 @@ use anyhow::anyhow;
 use itertools::Itertools;
 use num_enum::TryFromPrimitive;
 use sea_orm::{
-    sea_query::Expr, ActiveValue::NotSet, ColumnTrait, DeriveColumn, EntityTrait, EnumIter,
-    QueryFilter, QuerySelect, Set,
+    sea_query::Expr, ActiveValue::NotSet, ColumnTrait, ConnectionTrait, DeriveColumn, EntityTrait,
+    EnumIter, QueryFilter, QuerySelect, Set,
 };
 use shroom_meta::{
     id::{
@@ -139,6 +139,11 @@ impl SharedItemSvc {
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
     }
 
+    /// Maximum quantity of a single stack of the item
+    pub fn slot_max(&self, id: ItemId) -> usize {
+        self.meta.get_slot_max(id).unwrap_or(1).max(1) as usize
+    }
+
     pub fn new_stack_item(&self, id: ItemId, quantity: usize) -> Box<StackItem> {
         Box::new(StackItem {
             info: ItemInfo {
@@ -295,16 +300,24 @@ impl ItemService {
     }
 
     pub async fn save_equip(&self, item: &mut EquipItem) -> anyhow::Result<()> {
+        self.save_equip_in(&self.db.0, item).await
+    }
+
+    pub async fn save_equip_in<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        item: &mut EquipItem,
+    ) -> anyhow::Result<()> {
         if let Some(db_id) = item.db_id {
             if item.last_update > 0 {
                 equip_item::Entity::update(map_equip_to_active_model(item))
                     .filter(equip_item::Column::Id.eq(db_id))
-                    .exec(&self.db.0)
+                    .exec(db)
                     .await?;
             }
         } else {
             let id = equip_item::Entity::insert(map_equip_to_active_model(item))
-                .exec(&self.db.0)
+                .exec(db)
                 .await?
                 .last_insert_id;
             item.db_id = Some(id);
@@ -315,16 +328,24 @@ impl ItemService {
     }
 
     pub async fn save_stack(&self, item: &mut StackItem) -> anyhow::Result<()> {
+        self.save_stack_in(&self.db.0, item).await
+    }
+
+    pub async fn save_stack_in<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        item: &mut StackItem,
+    ) -> anyhow::Result<()> {
         if let Some(db_id) = item.db_id {
             if item.last_update > 0 {
                 item_stack::Entity::update(map_stack_to_active_model(item))
                     .filter(item_stack::Column::Id.eq(db_id))
-                    .exec(&self.db.0)
+                    .exec(db)
                     .await?;
             }
         } else {
             let id = item_stack::Entity::insert(map_stack_to_active_model(item))
-                .exec(&self.db.0)
+                .exec(db)
                 .await?
                 .last_insert_id;
             item.db_id = Some(id);
@@ -335,16 +356,24 @@ impl ItemService {
     }
 
     pub async fn save_pet(&self, item: &mut PetItem) -> anyhow::Result<()> {
+        self.save_pet_in(&self.db.0, item).await
+    }
+
+    pub async fn save_pet_in<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        item: &mut PetItem,
+    ) -> anyhow::Result<()> {
         if let Some(db_id) = item.db_id {
             if item.last_update > 0 {
                 pet_item::Entity::update(map_pet_to_active_model(item))
                     .filter(pet_item::Column::Id.eq(db_id))
-                    .exec(&self.db.0)
+                    .exec(db)
                     .await?;
             }
         } else {
             let id = pet_item::Entity::insert(map_pet_to_active_model(item))
-                .exec(&self.db.0)
+                .exec(db)
                 .await?
                 .last_insert_id;
             item.db_id = Some(id);
@@ -756,7 +785,8 @@ mod tests {
                     },
                     gender: Gender::Male,
                     max_skills: false,
-                    level: None
+                    level: None,
+                    world_id: 0,
                 },
                 &item_svc,
             )
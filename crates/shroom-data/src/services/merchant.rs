@@ -0,0 +1,279 @@
+use std::collections::{HashMap, HashSet};
+
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, Set, TransactionTrait};
+use shroom_meta::{
+    id::{CharacterId, FieldId, FootholdId, ItemId},
+    item::it::EquipItem,
+    twod::Vec2,
+};
+
+use crate::entities::{equip_item, inventory_slot, merchant, merchant_item, trunk_item};
+
+use super::{item::ItemService, DbConn};
+
+/// Item, which is sold by a merchant
+#[derive(Debug, Clone)]
+pub enum MerchantStock {
+    Equip(Box<EquipItem>),
+    Stack(ItemId),
+}
+
+#[derive(Debug, Clone)]
+pub struct MerchantItem {
+    pub stock: MerchantStock,
+    pub price: u32,
+    /// Remaining bundles
+    pub bundles: u16,
+    /// Quantity of a single bundle
+    pub per_bundle: u16,
+}
+
+impl MerchantItem {
+    pub fn id(&self) -> ItemId {
+        match &self.stock {
+            MerchantStock::Equip(item) => item.item_id,
+            MerchantStock::Stack(id) => *id,
+        }
+    }
+
+    pub fn quantity(&self) -> usize {
+        self.bundles as usize * self.per_bundle as usize
+    }
+
+    pub fn is_sold_out(&self) -> bool {
+        self.bundles == 0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Merchant {
+    pub owner: CharacterId,
+    pub owner_name: String,
+    pub field_id: FieldId,
+    pub pos: Vec2,
+    pub fh: FootholdId,
+    pub tmpl_id: ItemId,
+    pub title: String,
+    pub money: u32,
+    /// Closed merchants keep their items until the owner retrieves them
+    pub open: bool,
+    pub items: Vec<MerchantItem>,
+}
+
+impl Merchant {
+    fn to_active_model(&self) -> merchant::ActiveModel {
+        merchant::ActiveModel {
+            char_id: Set(self.owner.0 as i32),
+            owner_name: Set(self.owner_name.clone()),
+            field_id: Set(self.field_id.0 as i32),
+            x: Set(self.pos.x as i32),
+            y: Set(self.pos.y as i32),
+            fh: Set(self.fh.0 as i32),
+            tmpl_id: Set(self.tmpl_id.0 as i32),
+            title: Set(self.title.clone()),
+            money: Set(self.money as i32),
+            open: Set(self.open),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MerchantService<'svc> {
+    db: DbConn,
+    item: &'svc ItemService,
+}
+
+impl<'svc> MerchantService<'svc> {
+    pub fn new(db: DbConn, item: &'svc ItemService) -> Self {
+        Self { db, item }
+    }
+
+    pub async fn load_all(&self) -> anyhow::Result<Vec<Merchant>> {
+        let mut items: HashMap<i32, Vec<MerchantItem>> = HashMap::new();
+        let rows = merchant_item::Entity::find()
+            .find_also_related(equip_item::Entity)
+            .all(&self.db.0)
+            .await?;
+
+        for (row, equip) in rows {
+            let stock = match (row.equip_item_id, equip) {
+                (Some(_), Some(equip)) => MerchantStock::Equip(Box::new(equip.into())),
+                (None, _) => MerchantStock::Stack(ItemId(row.item_id as u32)),
+                (Some(id), None) => anyhow::bail!("Invalid merchant equip item: {id}"),
+            };
+
+            items
+                .entry(row.merchant_id)
+                .or_default()
+                .push(MerchantItem {
+                    stock,
+                    price: row.price as u32,
+                    bundles: row.bundles as u16,
+                    per_bundle: row.per_bundle as u16,
+                });
+        }
+
+        Ok(merchant::Entity::find()
+            .all(&self.db.0)
+            .await?
+            .into_iter()
+            .map(|m| Merchant {
+                owner: CharacterId(m.char_id as u32),
+                owner_name: m.owner_name,
+                field_id: FieldId(m.field_id as u32),
+                pos: Vec2::new(m.x as i16, m.y as i16),
+                fh: FootholdId(m.fh as u16),
+                tmpl_id: ItemId(m.tmpl_id as u32),
+                title: m.title,
+                money: m.money as u32,
+                open: m.open,
+                items: items.remove(&m.id).unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    /// Replaces the stored merchant of the owner, equips without a db id
+    /// are inserted and get their id assigned
+    pub async fn save(&self, merchant: &mut Merchant) -> anyhow::Result<()> {
+        let txn = self.db.0.begin().await?;
+        self.save_in(&txn, merchant).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Replaces the stored merchant with the given connection, used to save
+    /// the merchant within the transaction of a character save
+    pub async fn save_in<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        merchant: &mut Merchant,
+    ) -> anyhow::Result<()> {
+        let keep: HashSet<i32> = merchant
+            .items
+            .iter()
+            .filter_map(|item| match &item.stock {
+                MerchantStock::Equip(equip) => equip.db_id,
+                MerchantStock::Stack(_) => None,
+            })
+            .collect();
+        self.remove_stock_in(db, merchant.owner, &keep).await?;
+
+        let id = merchant::Entity::insert(merchant.to_active_model())
+            .exec(db)
+            .await?
+            .last_insert_id;
+
+        let mut rows = Vec::with_capacity(merchant.items.len());
+        for (pos, item) in merchant.items.iter_mut().enumerate() {
+            let item_id = item.id();
+            let equip_item_id = match &mut item.stock {
+                MerchantStock::Equip(equip) => {
+                    self.item.save_equip_in(db, equip).await?;
+                    equip.db_id
+                }
+                MerchantStock::Stack(_) => None,
+            };
+
+            rows.push(merchant_item::ActiveModel {
+                merchant_id: Set(id),
+                pos: Set(pos as i32),
+                item_id: Set(item_id.0 as i32),
+                price: Set(item.price as i32),
+                bundles: Set(item.bundles as i32),
+                per_bundle: Set(item.per_bundle as i32),
+                equip_item_id: Set(equip_item_id),
+                ..Default::default()
+            });
+        }
+
+        if !rows.is_empty() {
+            merchant_item::Entity::insert_many(rows).exec(db).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn remove(&self, owner: CharacterId) -> anyhow::Result<()> {
+        let txn = self.db.0.begin().await?;
+        self.remove_in(&txn, owner).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    pub async fn remove_in<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        owner: CharacterId,
+    ) -> anyhow::Result<()> {
+        self.remove_stock_in(db, owner, &HashSet::new()).await
+    }
+
+    /// Removes the stored merchant and the equips of its stock, except the
+    /// ones in `keep` and the ones which were moved into an inventory or trunk
+    async fn remove_stock_in<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        owner: CharacterId,
+        keep: &HashSet<i32>,
+    ) -> anyhow::Result<()> {
+        let ids: Vec<i32> = merchant::Entity::find()
+            .filter(merchant::Column::CharId.eq(owner.0 as i32))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut equip_ids: HashSet<i32> = merchant_item::Entity::find()
+            .filter(merchant_item::Column::MerchantId.is_in(ids.clone()))
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|item| item.equip_item_id)
+            .filter(|id| !keep.contains(id))
+            .collect();
+
+        merchant_item::Entity::delete_many()
+            .filter(merchant_item::Column::MerchantId.is_in(ids.clone()))
+            .exec(db)
+            .await?;
+        merchant::Entity::delete_many()
+            .filter(merchant::Column::Id.is_in(ids))
+            .exec(db)
+            .await?;
+
+        if equip_ids.is_empty() {
+            return Ok(());
+        }
+
+        // Sold or retrieved equips are already stored in the new inventory
+        let in_inv = inventory_slot::Entity::find()
+            .filter(inventory_slot::Column::EquipItemId.is_in(equip_ids.clone()))
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|slot| slot.equip_item_id);
+        let in_trunk = trunk_item::Entity::find()
+            .filter(trunk_item::Column::EquipItemId.is_in(equip_ids.clone()))
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|item| item.equip_item_id);
+        for id in in_inv.chain(in_trunk) {
+            equip_ids.remove(&id);
+        }
+
+        if !equip_ids.is_empty() {
+            equip_item::Entity::delete_many()
+                .filter(equip_item::Column::Id.is_in(equip_ids))
+                .exec(db)
+                .await?;
+        }
+        Ok(())
+    }
+}
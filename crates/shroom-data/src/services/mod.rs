@@ -12,15 +12,27 @@ use crate::entities::sea_orm_active_enums::GenderTy;
 
 use self::{
     account::{AccountId, AccountService, Region},
+    buddy::BuddyService,
+    cash_shop::CashShopService,
+    char_save::CharSaveService,
     character::{CharacterCreateDTO, CharacterService, ItemStarterSet},
+    guild::GuildService,
     item::ItemService,
+    merchant::MerchantService,
+    trunk::TrunkService,
 };
 
 pub mod account;
+pub mod buddy;
+pub mod cash_shop;
+pub mod char_save;
 pub mod character;
+pub mod guild;
 pub mod item;
+pub mod merchant;
 pub mod password;
 pub mod server_service;
+pub mod trunk;
 //pub mod shared;
 
 #[derive(Debug, Clone)]
@@ -35,6 +47,9 @@ pub struct DataProvider {
     meta: &'static MetaService,
     pub account: AccountService,
     pub item: ItemService,
+    pub buddy: BuddyService,
+    pub guild: GuildService,
+    pub cash_shop: CashShopService,
     //pub char: CharacterService,
 }
 
@@ -48,6 +63,9 @@ impl DataProvider {
             db: db.clone(),
             meta,
             account: AccountService::new(db.clone()),
+            buddy: BuddyService::new(db.clone()),
+            guild: GuildService::new(db.clone()),
+            cash_shop: CashShopService::new(db.clone()),
             item: ItemService::new(db, meta).await?,
             //char: CharacterService::new(db.clone(), meta),
         })
@@ -57,6 +75,18 @@ impl DataProvider {
         CharacterService::new(self.db.clone(), self.meta, &self.account, &self.item)
     }
 
+    pub fn char_save(&self) -> CharSaveService {
+        CharSaveService::new(self.db.clone(), &self.item)
+    }
+
+    pub fn merchant(&self) -> MerchantService {
+        MerchantService::new(self.db.clone(), &self.item)
+    }
+
+    pub fn trunk(&self) -> TrunkService {
+        TrunkService::new(self.db.clone(), &self.item)
+    }
+
     /*pub fn new(
         db: DatabaseConnection,
         servers: impl IntoIterator<Item = ServerInfo>,
@@ -121,7 +151,8 @@ impl DataProvider {
                 },
                 gender: Gender::Male,
                 max_skills: false,
-                level: None
+                level: None,
+                world_id: 0,
             },
             &self.item,
         ))
@@ -145,7 +176,8 @@ impl DataProvider {
                 },
                 gender: Gender::Male,
                 max_skills: false,
-                level: None
+                level: None,
+                world_id: 0,
             },
             &self.item,
         ))
@@ -194,7 +226,8 @@ impl DataProvider {
                     starter_set: ItemStarterSet::from_job_group(job_group),
                     gender: Gender::Male,
                     max_skills: true,
-                    level
+                    level,
+                    world_id: 0,
                     
                 },
                 &self.item,
@@ -1,14 +1,24 @@
 use anyhow::anyhow;
 use shroom_pkt::ShroomList16;
-use std::net::{IpAddr, SocketAddr};
-
-use shroom_proto95::login::{ChannelId, ChannelItem, WorldId, WorldInfoResp, WorldItem};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use shroom_proto95::login::{
+    ChannelId, ChannelItem, WorldId, WorldInfoResp, WorldItem, WorldState,
+};
 
 #[derive(Debug, Clone)]
 pub struct ChannelInfo {
     pub ip: IpAddr,
     pub port: u16,
     pub name: String,
+    /// Number of users, which are currently connected to the channel
+    users: Arc<AtomicU32>,
 }
 
 #[derive(Debug, Clone)]
@@ -17,6 +27,13 @@ pub struct ServerInfo {
     pub port: u16,
     pub channels: Vec<ChannelInfo>,
     pub name: String,
+    pub state: WorldState,
+    pub event_desc: String,
+    /// Exp rate in percent
+    pub exp_rate: u16,
+    /// Drop rate in percent
+    pub drop_rate: u16,
+    pub block_char_creation: bool,
 }
 
 impl ChannelInfo {
@@ -26,33 +43,75 @@ impl ChannelInfo {
             ip,
             port,
             name: format!("{server_name}-{print_id}"),
+            users: Arc::default(),
         }
     }
 
     pub fn socket_addr(&self) -> SocketAddr {
         SocketAddr::new(self.ip, self.port)
     }
+
+    pub fn users(&self) -> u32 {
+        self.users.load(Ordering::Relaxed)
+    }
+
+    pub fn user_enter(&self) {
+        self.users.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn user_leave(&self) {
+        let _ = self
+            .users
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    }
 }
 
 impl ServerInfo {
     pub fn new(ip: IpAddr, port: u16, name: String, channels: usize) -> Self {
+        Self::with_channel_port(ip, port, port + 1, name, channels)
+    }
+
+    /// Creates a world, which channels listen on the ports starting from `channel_port`
+    pub fn with_channel_port(
+        ip: IpAddr,
+        port: u16,
+        channel_port: u16,
+        name: String,
+        channels: usize,
+    ) -> Self {
         Self {
             ip,
             port,
             channels: (0..channels)
-                .map(|id| ChannelInfo::new(ip, port + 1 + id as u16, &name, id as ChannelId))
+                .map(|id| ChannelInfo::new(ip, channel_port + id as u16, &name, id as ChannelId))
                 .collect(),
             name,
+            state: WorldState::Normal,
+            event_desc: String::new(),
+            exp_rate: 100,
+            drop_rate: 100,
+            block_char_creation: false,
         }
     }
 
-    pub fn get_channel_addr(&self, ch: ChannelId) -> anyhow::Result<SocketAddr> {
+    pub fn exp_multiplier(&self) -> f32 {
+        f32::from(self.exp_rate) / 100.
+    }
+
+    pub fn drop_multiplier(&self) -> f32 {
+        f32::from(self.drop_rate) / 100.
+    }
+
+    pub fn get_channel(&self, ch: ChannelId) -> anyhow::Result<&ChannelInfo> {
         self.channels
             .get(ch as usize)
-            .map(|ch| ch.socket_addr())
             .ok_or_else(|| anyhow!("Invalid channel: {ch}"))
     }
 
+    pub fn get_channel_addr(&self, ch: ChannelId) -> anyhow::Result<SocketAddr> {
+        self.get_channel(ch).map(|ch| ch.socket_addr())
+    }
+
     pub fn get_login_addr(&self) -> SocketAddr {
         SocketAddr::new(self.ip, self.port)
     }
@@ -71,17 +130,17 @@ impl ServerInfo {
                 id: id as u8,
                 adult_channel: false,
                 world_id: world_id as u8,
-                user_number: 0,
+                user_number: ch.users(),
             })
             .collect();
 
         WorldItem {
             name: self.name.clone(),
-            state:shroom_proto95::login::WorldState::Normal,
-            event_desc: "Event!!".to_string(),
-            event_exp: 100,
-            event_drop_rate: 100,
-            block_char_creation: false,
+            state: self.state,
+            event_desc: self.event_desc.clone(),
+            event_exp: self.exp_rate,
+            event_drop_rate: self.drop_rate,
+            block_char_creation: self.block_char_creation,
             channels,
             balloons: ShroomList16::default(),
         }
@@ -100,12 +159,20 @@ impl ServerService {
         }
     }
 
+    pub fn servers(&self) -> &[ServerInfo] {
+        &self.servers
+    }
+
     pub fn get_server(&self, world: WorldId) -> anyhow::Result<&ServerInfo> {
         self.servers
             .get(world as usize)
             .ok_or_else(|| anyhow!("Invalid world: {world}"))
     }
 
+    pub fn get_channel(&self, world: WorldId, ch: ChannelId) -> anyhow::Result<&ChannelInfo> {
+        self.get_server(world)?.get_channel(ch)
+    }
+
     pub fn get_channel_addr(&self, world: WorldId, ch: ChannelId) -> anyhow::Result<SocketAddr> {
         self.get_server(world)?.get_channel_addr(ch)
     }
@@ -121,3 +188,32 @@ impl ServerService {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn channel_users() {
+        let svc = ServerService::new([ServerInfo::new(
+            Ipv4Addr::LOCALHOST.into(),
+            8484,
+            "test".to_string(),
+            2,
+        )]);
+        assert_eq!(svc.get_channel_addr(0, 1).unwrap().port(), 8486);
+        assert!(svc.get_channel(0, 2).is_err());
+
+        let ch = svc.get_channel(0, 1).unwrap();
+        ch.user_enter();
+        ch.user_enter();
+        ch.user_leave();
+        svc.get_channel(0, 0).unwrap().user_leave();
+
+        // Leaving an empty channel must not underflow
+        assert_eq!(svc.get_channel(0, 0).unwrap().users(), 0);
+        assert_eq!(ch.users(), 1);
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+use shroom_meta::{
+    id::{item_id::InventoryType, ItemId},
+    item::it::{EquipItem, StackItem},
+};
+use thiserror::Error;
+
+use crate::entities::{equip_item, item_stack, trunk, trunk_item};
+
+use super::{
+    account::AccountId,
+    char_save::{assign_db_id, SavedItemId, SavedItemKind},
+    item::ItemService,
+    DbConn,
+};
+
+pub const TRUNK_DEFAULT_SLOTS: u8 = 4;
+pub const TRUNK_MAX_SLOTS: u8 = 48;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TrunkError {
+    #[error("Trunk is full")]
+    Full,
+    #[error("No item at the given trunk index")]
+    InvalidIndex,
+    #[error("Not enough money")]
+    NoMoney,
+    #[error("Money limit exceeded")]
+    MoneyLimit,
+}
+
+#[derive(Debug, Clone)]
+pub enum TrunkItem {
+    Equip(Box<EquipItem>),
+    Stack(Box<StackItem>),
+}
+
+impl TrunkItem {
+    pub fn id(&self) -> ItemId {
+        match self {
+            Self::Equip(item) => item.item_id,
+            Self::Stack(item) => item.item_id,
+        }
+    }
+
+    pub fn inv_type(&self) -> InventoryType {
+        match self {
+            Self::Equip(_) => InventoryType::Equip,
+            // Trunk only accepts items with a valid id
+            Self::Stack(item) => item.item_id.get_inv_type().unwrap_or(InventoryType::Etc),
+        }
+    }
+}
+
+/// Account-wide item storage, shared by all characters of an account
+#[derive(Debug, Clone)]
+pub struct Trunk {
+    pub slots: u8,
+    pub money: u32,
+    pub items: Vec<TrunkItem>,
+}
+
+impl Default for Trunk {
+    fn default() -> Self {
+        Self {
+            slots: TRUNK_DEFAULT_SLOTS,
+            money: 0,
+            items: Vec::new(),
+        }
+    }
+}
+
+impl Trunk {
+    pub fn is_full(&self) -> bool {
+        self.items.len() >= self.slots as usize
+    }
+
+    /// Items of the inventory type in the order the client displays them
+    pub fn items_by_type(&self, ty: InventoryType) -> impl Iterator<Item = &TrunkItem> + '_ {
+        self.items.iter().filter(move |item| item.inv_type() == ty)
+    }
+
+    pub fn get(&self, ty: InventoryType, index: usize) -> Option<&TrunkItem> {
+        self.items_by_type(ty).nth(index)
+    }
+
+    pub fn put(&mut self, item: TrunkItem) -> Result<(), TrunkError> {
+        if self.is_full() {
+            return Err(TrunkError::Full);
+        }
+        self.items.push(item);
+        Ok(())
+    }
+
+    /// Takes the item at the index of the inventory type list
+    pub fn take(&mut self, ty: InventoryType, index: usize) -> Result<TrunkItem, TrunkError> {
+        let ix = self
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.inv_type() == ty)
+            .nth(index)
+            .map(|(ix, _)| ix)
+            .ok_or(TrunkError::InvalidIndex)?;
+        Ok(self.items.remove(ix))
+    }
+
+    pub fn sort(&mut self) {
+        self.items
+            .sort_by_key(|item| (item.inv_type() as u8, item.id().0));
+    }
+
+    pub fn deposit(&mut self, money: u32) -> Result<(), TrunkError> {
+        self.money = self
+            .money
+            .checked_add(money)
+            .filter(|m| *m <= i32::MAX as u32)
+            .ok_or(TrunkError::MoneyLimit)?;
+        Ok(())
+    }
+
+    pub fn withdraw(&mut self, money: u32) -> Result<(), TrunkError> {
+        self.money = self.money.checked_sub(money).ok_or(TrunkError::NoMoney)?;
+        Ok(())
+    }
+
+    /// Assigns the db ids of items, which were inserted by a snapshot save
+    pub fn assign_db_ids(&mut self, ids: &[SavedItemId]) {
+        for item in self.items.iter_mut() {
+            match item {
+                TrunkItem::Equip(item) => assign_db_id(&mut item.info, SavedItemKind::Equip, ids),
+                TrunkItem::Stack(item) => assign_db_id(&mut item.info, SavedItemKind::Stack, ids),
+            }
+        }
+    }
+
+    /// Adds slots up to the max, returns false if the trunk can't be expanded
+    pub fn expand(&mut self, slots: u8) -> bool {
+        match self.slots.checked_add(slots) {
+            Some(n) if n <= TRUNK_MAX_SLOTS => {
+                self.slots = n;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TrunkService<'svc> {
+    db: DbConn,
+    item: &'svc ItemService,
+}
+
+impl<'svc> TrunkService<'svc> {
+    pub fn new(db: DbConn, item: &'svc ItemService) -> Self {
+        Self { db, item }
+    }
+
+    async fn find(&self, acc_id: AccountId) -> anyhow::Result<Option<trunk::Model>> {
+        Ok(trunk::Entity::find()
+            .filter(trunk::Column::AccId.eq(acc_id))
+            .one(&self.db.0)
+            .await?)
+    }
+
+    /// Loads the trunk of the account, accounts without a stored trunk get an empty one
+    pub async fn load(&self, acc_id: AccountId) -> anyhow::Result<Trunk> {
+        let Some(model) = self.find(acc_id).await? else {
+            return Ok(Trunk::default());
+        };
+
+        let rows = trunk_item::Entity::find()
+            .filter(trunk_item::Column::TrunkId.eq(model.id))
+            .order_by_asc(trunk_item::Column::Pos)
+            .all(&self.db.0)
+            .await?;
+
+        let mut equips: HashMap<i32, equip_item::Model> = equip_item::Entity::find()
+            .filter(equip_item::Column::Id.is_in(rows.iter().filter_map(|r| r.equip_item_id)))
+            .all(&self.db.0)
+            .await?
+            .into_iter()
+            .map(|item| (item.id, item))
+            .collect();
+
+        let mut stacks: HashMap<i32, item_stack::Model> = item_stack::Entity::find()
+            .filter(item_stack::Column::Id.is_in(rows.iter().filter_map(|r| r.stack_item_id)))
+            .all(&self.db.0)
+            .await?
+            .into_iter()
+            .map(|item| (item.id, item))
+            .collect();
+
+        let items = rows
+            .into_iter()
+            .map(|row| {
+                Ok(match (row.equip_item_id, row.stack_item_id) {
+                    (Some(id), None) => TrunkItem::Equip(Box::new(
+                        equips
+                            .remove(&id)
+                            .ok_or_else(|| anyhow::anyhow!("Invalid trunk equip item: {id}"))?
+                            .into(),
+                    )),
+                    (None, Some(id)) => TrunkItem::Stack(Box::new(
+                        stacks
+                            .remove(&id)
+                            .ok_or_else(|| anyhow::anyhow!("Invalid trunk stack item: {id}"))?
+                            .into(),
+                    )),
+                    _ => anyhow::bail!("Invalid trunk item: {}", row.id),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Trunk {
+            slots: model.slots as u8,
+            money: model.money as u32,
+            items,
+        })
+    }
+
+    /// Stores the trunk of the account, items without a db id
+    /// are inserted and get their id assigned
+    pub async fn save(&self, acc_id: AccountId, trunk: &mut Trunk) -> anyhow::Result<()> {
+        let txn = self.db.0.begin().await?;
+        self.save_in(&txn, acc_id, trunk).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Stores the trunk with the given connection, used to save
+    /// the trunk within the transaction of a character save
+    pub async fn save_in<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        acc_id: AccountId,
+        trunk: &mut Trunk,
+    ) -> anyhow::Result<()> {
+        let model = trunk::Entity::find()
+            .filter(trunk::Column::AccId.eq(acc_id))
+            .one(db)
+            .await?;
+        let id = match model {
+            Some(model) => {
+                trunk::Entity::update(trunk::ActiveModel {
+                    id: Set(model.id),
+                    slots: Set(trunk.slots as i32),
+                    money: Set(trunk.money as i32),
+                    acc_id: Set(acc_id),
+                })
+                .exec(db)
+                .await?;
+                trunk_item::Entity::delete_many()
+                    .filter(trunk_item::Column::TrunkId.eq(model.id))
+                    .exec(db)
+                    .await?;
+                model.id
+            }
+            None => {
+                trunk::Entity::insert(trunk::ActiveModel {
+                    slots: Set(trunk.slots as i32),
+                    money: Set(trunk.money as i32),
+                    acc_id: Set(acc_id),
+                    ..Default::default()
+                })
+                .exec(db)
+                .await?
+                .last_insert_id
+            }
+        };
+
+        let mut rows = Vec::with_capacity(trunk.items.len());
+        for (pos, item) in trunk.items.iter_mut().enumerate() {
+            let (equip_item_id, stack_item_id) = match item {
+                TrunkItem::Equip(equip) => {
+                    self.item.save_equip_in(db, equip).await?;
+                    (equip.db_id, None)
+                }
+                TrunkItem::Stack(stack) => {
+                    self.item.save_stack_in(db, stack).await?;
+                    (None, stack.db_id)
+                }
+            };
+
+            rows.push(trunk_item::ActiveModel {
+                trunk_id: Set(id),
+                pos: Set(pos as i32),
+                equip_item_id: Set(equip_item_id),
+                stack_item_id: Set(stack_item_id),
+                ..Default::default()
+            });
+        }
+
+        if !rows.is_empty() {
+            trunk_item::Entity::insert_many(rows).exec(db).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shroom_meta::item::it::ItemInfo;
+
+    use super::*;
+
+    fn stack(id: u32) -> TrunkItem {
+        TrunkItem::Stack(Box::new(StackItem {
+            info: ItemInfo::from_id(ItemId(id), 0, false),
+            quantity: 1,
+        }))
+    }
+
+    #[test]
+    fn trunk_slots_and_money() {
+        let mut trunk = Trunk::default();
+        for _ in 0..TRUNK_DEFAULT_SLOTS {
+            trunk.put(stack(4000000)).unwrap();
+        }
+        assert_eq!(trunk.put(stack(2000000)), Err(TrunkError::Full));
+        assert!(trunk.expand(4));
+        trunk.put(stack(2000000)).unwrap();
+        assert!(!trunk.expand(TRUNK_MAX_SLOTS));
+
+        // Consume items are listed separately from etc items
+        assert_eq!(
+            trunk.get(InventoryType::Consume, 0).unwrap().id(),
+            ItemId(2000000)
+        );
+        assert!(trunk.take(InventoryType::Consume, 1).is_err());
+        trunk.take(InventoryType::Consume, 0).unwrap();
+        assert_eq!(trunk.items_by_type(InventoryType::Etc).count(), 4);
+
+        trunk.deposit(100).unwrap();
+        assert_eq!(trunk.withdraw(101), Err(TrunkError::NoMoney));
+        trunk.withdraw(100).unwrap();
+        assert_eq!(trunk.money, 0);
+    }
+}
@@ -0,0 +1,80 @@
+use shroom_data::services::char_save::{CharSaveParts, SavedItemId};
+use shroom_meta::id::CharacterId;
+use shroom_srv::GameTime;
+
+use crate::game::{GameMessage, GameSession};
+
+/// State of the periodic save of the character
+#[derive(Debug, Default)]
+pub struct AutosaveState {
+    next: Option<GameTime>,
+    /// A save is running, the next one has to wait for the ids of the inserted items
+    pending: bool,
+}
+
+impl GameSession {
+    /// Writes the changed parts of the character in the background once the
+    /// interval elapsed, the tick only pays for copying the changed parts
+    pub(crate) fn autosave(&mut self, t: GameTime) {
+        let interval = self.services.game.config.autosave_interval;
+        let next = *self.autosave.next.get_or_insert(t + interval);
+        if self.autosave.pending || t < next {
+            return;
+        }
+        self.autosave.next = Some(t + interval);
+
+        let Some(mut snapshot) = self.session.dirty_snapshot() else {
+            return;
+        };
+        let shops = std::mem::take(&mut self.session.dirty_shops);
+        self.autosave.pending = true;
+
+        let (svc, id) = (self.services.game.clone(), self.char_id());
+        let lock = svc.char_saves.get(id);
+        tokio::spawn(async move {
+            let mut saved = lock.lock().await;
+            let res = svc
+                .employee
+                .save_char(&svc.data, &mut snapshot, &shops)
+                .await;
+            let msg = match res {
+                Ok(ids) => {
+                    // Kept until the session applied them, in case the session
+                    // is saved and closed before the message is handled
+                    saved.extend_from_slice(&ids);
+                    GameMessage::AutosaveDone(ids)
+                }
+                Err(err) => {
+                    log::error!("Unable to autosave character {id:?}: {err:?}");
+                    GameMessage::AutosaveFailed(snapshot.parts(), shops)
+                }
+            };
+            drop(saved);
+            svc.sessions.send_to(id, msg);
+        });
+    }
+
+    /// Runs the autosave with the next tick, used when changes must
+    /// be written soon, like changes of a merchant
+    pub(crate) fn request_autosave(&mut self) {
+        self.autosave.next = Some(self.services.game.current_time.load());
+    }
+
+    pub(crate) fn handle_autosave_done(&mut self, ids: Vec<SavedItemId>) {
+        self.autosave.pending = false;
+        self.session.assign_db_ids(&ids);
+        // No other save runs, because the autosave is not pending anymore
+        if let Ok(mut saved) = self.services.game.char_saves.get(self.char_id()).try_lock() {
+            saved.clear();
+        }
+    }
+
+    /// Marks the parts of the failed save as dirty again, so the next save retries them
+    pub(crate) fn handle_autosave_failed(&mut self, parts: CharSaveParts, shops: Vec<CharacterId>) {
+        self.autosave.pending = false;
+        self.session.char.dirty.merge(parts);
+        for owner in shops {
+            self.session.mark_shop_dirty(owner);
+        }
+    }
+}
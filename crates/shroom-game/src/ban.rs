@@ -0,0 +1,74 @@
+use shroom_data::services::account::BanRequest;
+use shroom_meta::id::CharacterId;
+use shroom_proto95::game::chat::UserChatMsgResp;
+
+use crate::{
+    game::{GameMessage, GameSession},
+    services::shared::SharedGameServices,
+};
+
+/// Bans the account of the character and disconnects its online characters
+async fn ban_account(
+    svc: &SharedGameServices,
+    name: &str,
+    req: BanRequest,
+) -> anyhow::Result<String> {
+    let Some(chr) = svc.data.char().get_by_name(name).await? else {
+        return Ok(format!("No character named {name}"));
+    };
+
+    svc.data.account.ban(chr.acc_id, req).await?;
+    for id in svc.data.char().char_ids_for_account(chr.acc_id).await? {
+        svc.sessions.send_to(id, GameMessage::Banned);
+    }
+    Ok(format!("Banned the account of {name}"))
+}
+
+async fn unban_account(svc: &SharedGameServices, name: &str) -> anyhow::Result<String> {
+    let Some(chr) = svc.data.char().get_by_name(name).await? else {
+        return Ok(format!("No character named {name}"));
+    };
+
+    let lifted = svc.data.account.unban(chr.acc_id).await?;
+    Ok(format!("Lifted {lifted} bans of the account of {name}"))
+}
+
+/// Sends the result of a ban command to the gm
+fn notify_gm(svc: &SharedGameServices, gm: CharacterId, res: anyhow::Result<String>) {
+    let msg = res.unwrap_or_else(|err| {
+        log::error!("Ban command of {gm:?} failed: {err:?}");
+        format!("Ban command failed: {err}")
+    });
+
+    let resp = UserChatMsgResp {
+        char: gm,
+        is_admin: true,
+        msg,
+        only_balloon: false,
+    };
+    if let Err(err) = svc.sessions.send_to_encode(gm, resp) {
+        log::error!("Unable to notify {gm:?}: {err:?}");
+    }
+}
+
+impl GameSession {
+    /// Bans the account of the character in the background
+    pub(crate) fn ban_char(&self, name: String, req: BanRequest) {
+        let svc = self.services.game.clone();
+        let gm = self.char_id();
+        tokio::spawn(async move {
+            let res = ban_account(&svc, &name, req).await;
+            notify_gm(&svc, gm, res);
+        });
+    }
+
+    /// Lifts the active bans of the account of the character in the background
+    pub(crate) fn unban_char(&self, name: String) {
+        let svc = self.services.game.clone();
+        let gm = self.char_id();
+        tokio::spawn(async move {
+            let res = unban_account(&svc, &name).await;
+            notify_gm(&svc, gm, res);
+        });
+    }
+}
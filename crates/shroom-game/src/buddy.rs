@@ -0,0 +1,381 @@
+use std::future::Future;
+
+use shroom_data::services::buddy::{BuddyEntry, BuddyStatus};
+use shroom_meta::id::CharacterId;
+use shroom_proto95::{
+    game::friend::{
+        FriendChangeChannel, FriendList, FriendRecord, FriendReq, FriendResultResp, FriendSetReq,
+        FriendUserReq, FRIEND_CHANNEL_OFFLINE,
+    },
+    login::ChannelId,
+};
+
+use crate::{
+    game::{GameContext, GameMessage, GameSession},
+    life::char::buddy::BuddyError,
+    services::{online::OnlineChar, shared::SharedGameServices},
+};
+
+pub const DEFAULT_BUDDY_GROUP: &str = "Default Group";
+
+/// Messages between the sessions of two buddies
+#[derive(Debug, Clone)]
+pub enum BuddyMsg {
+    /// A character wants to add the receiver as buddy
+    Request {
+        id: CharacterId,
+        name: String,
+        level: u8,
+        job: u32,
+    },
+    /// The receiver's request was accepted by the buddy
+    Accepted(CharacterId),
+    /// The buddy removed the receiver from the list
+    Removed(CharacterId),
+}
+
+impl BuddyError {
+    fn resp(&self) -> FriendResultResp {
+        match self {
+            BuddyError::Full => FriendResultResp::FullMe(()),
+            BuddyError::AlreadySet => FriendResultResp::AlreadySet(()),
+            BuddyError::Unknown => FriendResultResp::UnknownUser(()),
+        }
+    }
+}
+
+fn channel_to_proto(channel: Option<ChannelId>) -> u32 {
+    channel.map_or(FRIEND_CHANNEL_OFFLINE, |ch| ch as u32)
+}
+
+impl GameSession {
+    fn buddy_record(&self, entry: &BuddyEntry) -> anyhow::Result<FriendRecord> {
+        // Only accepted buddies can see each other
+        let channel = match entry.status {
+            BuddyStatus::Accepted => self
+                .services
+                .game
+                .online
+                .get(self.world_id, entry.id)
+                .map(|c| c.channel),
+            _ => None,
+        };
+
+        Ok(FriendRecord {
+            id: entry.id,
+            name: entry.name.as_str().try_into()?,
+            flag: (entry.status == BuddyStatus::Requested) as u8,
+            channel_id: channel_to_proto(channel),
+            friend_group: entry.group.as_str().try_into()?,
+        })
+    }
+
+    pub fn buddy_list(&self) -> anyhow::Result<FriendList> {
+        let friends = self
+            .session
+            .char
+            .buddies
+            .listed()
+            .map(|entry| self.buddy_record(entry))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(FriendList {
+            len: friends.len() as u8,
+            in_shop: vec![0; friends.len()],
+            friends,
+        })
+    }
+
+    fn send_buddy_list(&self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        ctx.socket.reply(FriendResultResp::Reset(self.buddy_list()?))?;
+        Ok(())
+    }
+
+    fn send_buddy_req(
+        &self,
+        ctx: &mut GameContext,
+        entry: &BuddyEntry,
+        level: u8,
+        job: u32,
+    ) -> anyhow::Result<()> {
+        ctx.socket.reply(FriendResultResp::Req(FriendReq {
+            friend_id: entry.id,
+            friend_name: entry.name.clone(),
+            level: level as u32,
+            job_code: job,
+            record: self.buddy_record(entry)?,
+            in_shop: false,
+        }))?;
+        Ok(())
+    }
+
+    /// Notifies all online buddies about the channel of this character
+    fn send_buddy_channel(&self, channel: Option<ChannelId>) -> anyhow::Result<()> {
+        self.services.game.sessions.send_all_encode(
+            self.session.char.buddies.accepted_ids(),
+            FriendResultResp::ChangeChannel(FriendChangeChannel {
+                friend_id: self.char_id(),
+                in_shop: false,
+                channel: channel_to_proto(channel),
+            }),
+        )?;
+        Ok(())
+    }
+
+    /// Runs a write to the buddy list of the owner in the background,
+    /// writes to the same list are applied in order
+    fn spawn_buddy_write<F>(&self, owner: CharacterId, f: impl FnOnce(SharedGameServices) -> F)
+    where
+        F: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        let task = f(self.services.game.clone());
+        self.services.game.buddy_writes.spawn(owner, task);
+    }
+
+    fn save_buddy(&self, id: CharacterId) {
+        let owner = self.char_id();
+        let Some(entry) = self.session.char.buddies.get(id).cloned() else {
+            return;
+        };
+        self.spawn_buddy_write(owner, move |svc| async move {
+            svc.data.buddy.set(owner, entry).await
+        });
+    }
+
+    fn delete_buddy(&self, id: CharacterId) {
+        let owner = self.char_id();
+        self.spawn_buddy_write(owner, move |svc| async move {
+            svc.data.buddy.remove(owner, id).await
+        });
+    }
+
+    fn send_buddy_msg(&self, id: CharacterId, msg: BuddyMsg) -> bool {
+        self.services
+            .game
+            .sessions
+            .send_to(id, GameMessage::Buddy(msg))
+    }
+
+    pub(crate) fn on_buddy_world_enter(
+        &mut self,
+        ctx: &mut GameContext,
+        prev: Option<OnlineChar>,
+    ) -> anyhow::Result<()> {
+        let prev_channel = prev.as_ref().map(|chr| chr.channel);
+        if prev_channel != Some(self.channel_id) {
+            self.send_buddy_channel(Some(self.channel_id))?;
+        }
+
+        // Requests received while being offline, level and job are not stored
+        if prev.is_none() {
+            let pending: Vec<_> = self.session.char.buddies.pending().cloned().collect();
+            for entry in pending.iter() {
+                self.send_buddy_req(ctx, entry, 0, 0)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn on_buddy_world_leave(&mut self) -> anyhow::Result<()> {
+        self.send_buddy_channel(None)
+    }
+
+    pub fn handle_friend_req(
+        &mut self,
+        ctx: &mut GameContext,
+        req: FriendUserReq,
+    ) -> anyhow::Result<()> {
+        match req {
+            FriendUserReq::Load(()) => self.send_buddy_list(ctx),
+            FriendUserReq::Set(req) => self.handle_buddy_set(ctx, req),
+            FriendUserReq::Accept(id) => self.handle_buddy_accept(ctx, id),
+            FriendUserReq::Delete(id) => self.handle_buddy_delete(ctx, id),
+        }
+    }
+
+    fn handle_buddy_set(&mut self, ctx: &mut GameContext, req: FriendSetReq) -> anyhow::Result<()> {
+        let group = if req.group.is_empty() {
+            DEFAULT_BUDDY_GROUP.to_string()
+        } else {
+            req.group
+        };
+        if group.len() >= 0x11 {
+            ctx.socket.reply(FriendResultResp::SetUnknown(None.into()))?;
+            return Ok(());
+        }
+
+        let me = self.char_id();
+        let existing = self
+            .session
+            .char
+            .buddies
+            .get_by_name(&req.name)
+            .map(|e| (e.id, e.status));
+        let res = match existing {
+            // Adding a character, which sent a request is the same as accepting It
+            Some((id, BuddyStatus::Pending)) => {
+                self.session.char.buddies.set_group(id, group)?;
+                return self.handle_buddy_accept(ctx, id);
+            }
+            // Existing entries only change the group
+            Some((id, _)) => self.session.char.buddies.set_group(id, group).map(|e| e.id),
+            None => {
+                let Some(target) = self
+                    .services
+                    .game
+                    .online
+                    .get_by_name(self.world_id, &req.name)
+                    .filter(|chr| chr.id != me)
+                else {
+                    ctx.socket.reply(FriendResultResp::UnknownUser(()))?;
+                    return Ok(());
+                };
+
+                let res = self
+                    .session
+                    .char
+                    .buddies
+                    .add(BuddyEntry {
+                        id: target.id,
+                        name: target.name.clone(),
+                        group,
+                        status: BuddyStatus::Requested,
+                    })
+                    .map(|e| e.id);
+
+                if res.is_ok() {
+                    let chr = &self.session.char;
+                    let delivered = self.send_buddy_msg(
+                        target.id,
+                        BuddyMsg::Request {
+                            id: me,
+                            name: chr.name.clone(),
+                            level: chr.stats.level,
+                            job: chr.stats.job as u32,
+                        },
+                    );
+
+                    // The target went offline in the meantime
+                    if !delivered {
+                        let entry = BuddyEntry {
+                            id: me,
+                            name: chr.name.clone(),
+                            group: DEFAULT_BUDDY_GROUP.to_string(),
+                            status: BuddyStatus::Pending,
+                        };
+                        self.spawn_buddy_write(target.id, move |svc| async move {
+                            svc.data.buddy.set(target.id, entry).await
+                        });
+                    }
+                }
+                res
+            }
+        };
+
+        match res {
+            Ok(id) => {
+                self.save_buddy(id);
+                self.send_buddy_list(ctx)
+            }
+            Err(err) => {
+                ctx.socket.reply(err.resp())?;
+                Ok(())
+            }
+        }
+    }
+
+    fn handle_buddy_accept(&mut self, ctx: &mut GameContext, id: CharacterId) -> anyhow::Result<()> {
+        let buddies = &mut self.session.char.buddies;
+        if buddies.get(id).map(|e| e.status) != Some(BuddyStatus::Pending) {
+            ctx.socket.reply(FriendResultResp::AcceptUnknown(None.into()))?;
+            return Ok(());
+        }
+
+        if let Err(err) = buddies.set_status(id, BuddyStatus::Accepted) {
+            ctx.socket.reply(err.resp())?;
+            return Ok(());
+        }
+        self.save_buddy(id);
+
+        let me = self.char_id();
+        if !self.send_buddy_msg(id, BuddyMsg::Accepted(me)) {
+            self.spawn_buddy_write(id, move |svc| async move {
+                svc.data
+                    .buddy
+                    .set_status(id, me, BuddyStatus::Accepted)
+                    .await
+            });
+        }
+
+        self.send_buddy_list(ctx)
+    }
+
+    fn handle_buddy_delete(&mut self, ctx: &mut GameContext, id: CharacterId) -> anyhow::Result<()> {
+        if self.session.char.buddies.remove(id).is_none() {
+            ctx.socket.reply(FriendResultResp::DeleteUnknown(None.into()))?;
+            return Ok(());
+        }
+        self.delete_buddy(id);
+
+        let me = self.char_id();
+        if !self.send_buddy_msg(id, BuddyMsg::Removed(me)) {
+            self.spawn_buddy_write(
+                id,
+                move |svc| async move { svc.data.buddy.remove(id, me).await },
+            );
+        }
+
+        self.send_buddy_list(ctx)
+    }
+
+    pub(crate) fn handle_buddy_msg(
+        &mut self,
+        ctx: &mut GameContext,
+        msg: BuddyMsg,
+    ) -> anyhow::Result<()> {
+        let buddies = &mut self.session.char.buddies;
+        match msg {
+            BuddyMsg::Request {
+                id,
+                name,
+                level,
+                job,
+            } => match buddies.get(id).map(|e| e.status) {
+                // Both sides requested each other
+                Some(BuddyStatus::Requested) => {
+                    buddies.set_status(id, BuddyStatus::Accepted)?;
+                    self.save_buddy(id);
+                    self.send_buddy_msg(id, BuddyMsg::Accepted(self.char_id()));
+                    self.send_buddy_list(ctx)?;
+                }
+                Some(_) => {}
+                None => {
+                    let entry = buddies
+                        .add(BuddyEntry {
+                            id,
+                            name,
+                            group: DEFAULT_BUDDY_GROUP.to_string(),
+                            status: BuddyStatus::Pending,
+                        })?
+                        .clone();
+                    self.save_buddy(id);
+                    self.send_buddy_req(ctx, &entry, level, job)?;
+                }
+            },
+            BuddyMsg::Accepted(id) => {
+                if buddies.get(id).map(|e| e.status) == Some(BuddyStatus::Requested) {
+                    buddies.set_status(id, BuddyStatus::Accepted)?;
+                    self.save_buddy(id);
+                    self.send_buddy_list(ctx)?;
+                }
+            }
+            BuddyMsg::Removed(id) => {
+                if buddies.remove(id).is_some() {
+                    self.delete_buddy(id);
+                    self.send_buddy_list(ctx)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,731 @@
+use std::{marker::PhantomData, net::SocketAddr};
+
+use shroom_data::{
+    entities::cash_locker_item,
+    proto_mapper::db_to_shroom_time,
+    services::{
+        cash_shop::{
+            CashBalance, CashGift, CashLockerItemDTO, CashShopError, CashType, LockerChange,
+        },
+        char_save::CharSaveParts,
+    },
+};
+use shroom_meta::{
+    cash::Commodity,
+    id::{CharacterId, ItemId},
+};
+use shroom_net::codec::ShroomCodec;
+use shroom_pkt::pkt::Message;
+use shroom_proto95::{
+    game::{
+        cash_shop::{
+            CashCouponDone, CashGiftDone, CashItemBuyReq, CashItemFailReason, CashItemGiftReq,
+            CashItemIncTrunkCountReq, CashItemInfo, CashItemMoveLtoSReq, CashItemMoveStoLReq,
+            CashItemReq, CashItemResultResp, CashLockerData, CashMoveLtoSDone, CashShopBestItem,
+            CashShopCheckCouponReq, CashShopLeaveReq, CashShopPayment, CashShopQueryCashReq,
+            CashShopQueryCashResp, MigrateToCashShopReq, SetCashShopResp, CASH_SHOP_BEST_ITEMS,
+        },
+        user::char::CharDataFlags,
+        MigrateCommandResp, MigrateInGameReq, TransferChannelIgnoredReason,
+        TransferChannelReqIgnoredResp,
+    },
+    recv_opcodes::RecvOpcodes,
+    shared::{char::CharDataHeader, PingResp, PongReq},
+};
+use shroom_srv::rpc::{RpcCtx, RpcResponse, RpcService};
+
+use crate::{
+    game::{char_data_all, GameContext, GameSession},
+    services::shared::SharedServices,
+    session::{ClientKey, OwnedShroomGameSession, ShroomMigrationKey},
+};
+
+impl GameSession {
+    /// Sends the client to the cash shop server, the session is saved and put into
+    /// migration once the client disconnects from this channel
+    pub fn handle_migrate_to_cash_shop(
+        &mut self,
+        ctx: &mut GameContext,
+        _req: MigrateToCashShopReq,
+    ) -> anyhow::Result<()> {
+        let addr = match self.services.game.server_info.get_server(self.world_id) {
+            Ok(world) => SocketAddr::new(world.ip, self.services.game.config.cash_shop_port),
+            Err(_) => {
+                ctx.socket.reply(TransferChannelReqIgnoredResp {
+                    reason: TransferChannelIgnoredReason::CashShopUnavailable,
+                })?;
+                return Ok(());
+            }
+        };
+
+        log::info!("Character {:?} enters the cash shop", self.char_id());
+        self.session.cash_shop_return = Some((self.world_id, self.channel_id));
+        self.services.session_manager.migrate_on_drop(
+            *self.session.key(),
+            ShroomMigrationKey::new(self.client_key, self.addr),
+        );
+        ctx.socket.reply(MigrateCommandResp {
+            unknown: true,
+            addr: addr.try_into()?,
+        })?;
+        Ok(())
+    }
+}
+
+/// Storage expansion item, which is bought to expand the trunk
+const TRUNK_EXPAND_ITEM: ItemId = ItemId(9110000);
+/// Slots added by a storage expansion item
+const TRUNK_EXPAND_ITEM_SLOTS: u8 = 8;
+/// Slots and price of expanding the trunk without the item
+const TRUNK_EXPAND_SLOTS: u8 = 4;
+const TRUNK_EXPAND_PRICE: u32 = 4000;
+
+fn cash_type(payment: CashShopPayment) -> CashType {
+    match payment {
+        CashShopPayment::NxCredit => CashType::NxCredit,
+        CashShopPayment::ShroomPoints => CashType::ShroomPoints,
+        CashShopPayment::NxPrepaid => CashType::NxPrepaid,
+    }
+}
+
+/// Maps a failed cash shop operation onto the reason shown by the client,
+/// database errors are passed on
+fn fail_reason(err: CashShopError) -> anyhow::Result<CashItemFailReason> {
+    Ok(match err {
+        CashShopError::NoCash | CashShopError::CashLimit => CashItemFailReason::NoCash,
+        CashShopError::InvalidCoupon => CashItemFailReason::InvalidCoupon,
+        CashShopError::CouponUsed => CashItemFailReason::CouponUsed,
+        CashShopError::UnknownReceiver => CashItemFailReason::InvalidReceiver,
+        CashShopError::InvalidSn | CashShopError::UnknownAccount => CashItemFailReason::Unknown,
+        CashShopError::Disconnect(err) => return Err(err.into()),
+    })
+}
+
+fn locker_item_info(
+    item: &cash_locker_item::Model,
+    char_id: CharacterId,
+) -> anyhow::Result<CashItemInfo> {
+    Ok(CashItemInfo {
+        sn: item.id as u64,
+        acc_id: item.acc_id as u32,
+        char_id,
+        item_id: ItemId(item.item_id as u32),
+        commodity_sn: item.commodity_sn as u32,
+        quantity: item.quantity as u16,
+        gift_from: item.gift_from.as_deref().unwrap_or_default().try_into()?,
+        expiration: item.expires_at.map(db_to_shroom_time).into(),
+        payback_rate: 0,
+        discount_rate: 0,
+    })
+}
+
+fn balance_resp(balance: &CashBalance) -> CashShopQueryCashResp {
+    CashShopQueryCashResp {
+        nx_credit: balance.nx_credit,
+        shroom_points: balance.shroom_points,
+        nx_prepaid: balance.nx_prepaid,
+    }
+}
+
+/// Saves the inventory together with the change to the locker, so the item
+/// is either in the locker or in the inventory
+async fn save_locker_move(
+    svc: &SharedServices,
+    sess: &mut OwnedShroomGameSession,
+    change: LockerChange,
+) -> anyhow::Result<Option<cash_locker_item::Model>> {
+    // Wait for a running autosave of the channel and apply the ids It inserted
+    let lock = svc.game.char_saves.get(sess.char.id);
+    let mut saved = lock.lock().await;
+    sess.assign_db_ids(&saved);
+    saved.clear();
+
+    let mut snapshot = sess.snapshot(CharSaveParts {
+        inventory: true,
+        ..Default::default()
+    });
+    let (ids, item) = svc
+        .data
+        .char_save()
+        .save_with_locker(&mut snapshot, change)
+        .await?;
+    sess.assign_db_ids(&ids);
+    sess.char.dirty.inventory = false;
+    Ok(item)
+}
+
+fn claimed(
+    session: &mut Option<OwnedShroomGameSession>,
+) -> anyhow::Result<&mut OwnedShroomGameSession> {
+    session
+        .as_mut()
+        .ok_or_else(|| anyhow::anyhow!("No session claimed for the cash shop"))
+}
+
+pub type CashShopResponse = anyhow::Result<RpcResponse>;
+
+/// Connection to the cash shop, the session is claimed from the channel
+/// and handed back once the client leaves
+pub struct CashShopSession<C> {
+    services: SharedServices,
+    session: Option<OwnedShroomGameSession>,
+    client_key: ClientKey,
+    _c: PhantomData<C>,
+}
+
+impl<C: ShroomCodec> RpcService for CashShopSession<C> {
+    type Ctx = SharedServices;
+    type Codec = C;
+    type PingPacket = PingResp;
+
+    fn create(ctx: &Self::Ctx) -> anyhow::Result<Self> {
+        Ok(Self {
+            services: ctx.clone(),
+            session: None,
+            client_key: ClientKey::default(),
+            _c: PhantomData,
+        })
+    }
+
+    fn ping_packet(&self) -> Self::PingPacket {
+        PingResp
+    }
+
+    async fn on_packet(&mut self, msg: Message, ctx: &mut RpcCtx<C>) -> CashShopResponse {
+        let op = msg.opcode_value();
+        let res = self.handle_packet(msg, ctx).await;
+        if let Err(err) = &res {
+            log::error!("Error handling cash shop packet: {:?} - op: {:?}", err, op);
+        }
+
+        res
+    }
+
+    async fn finish(self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl<C: ShroomCodec> CashShopSession<C> {
+    async fn handle_packet(&mut self, msg: Message, ctx: &mut RpcCtx<C>) -> CashShopResponse {
+        macro_rules! handler {
+            ($ctx:ident, $msg:ident, $this:ident, $default:ident, $($req:ty => $handler:ident),*) => {
+                match $msg.opcode().unwrap() {
+                    $(
+                        <$req as shroom_pkt::HasOpCode>::OPCODE => $this.$handler($ctx, $msg.decode::<$req>()?).await,
+                    )*
+                    _ => $this.$default($ctx, $msg).await
+                }
+            };
+        }
+
+        handler!(
+            ctx,
+            msg,
+            self,
+            handle_default,
+            MigrateInGameReq => handle_migrate_in,
+            PongReq => handle_pong,
+            CashShopQueryCashReq => handle_query_cash,
+            CashItemReq => handle_cash_item_req,
+            CashShopCheckCouponReq => handle_check_coupon,
+            CashShopLeaveReq => handle_leave
+        )
+    }
+
+    async fn handle_default(&mut self, _ctx: &mut RpcCtx<C>, msg: Message) -> CashShopResponse {
+        log::info!(
+            "Unhandled cash shop packet: {:?}",
+            msg.opcode::<RecvOpcodes>()
+        );
+        Ok(RpcResponse::Ok)
+    }
+
+    async fn handle_pong(&mut self, _ctx: &mut RpcCtx<C>, _req: PongReq) -> CashShopResponse {
+        Ok(RpcResponse::Pong)
+    }
+
+    async fn handle_migrate_in(
+        &mut self,
+        ctx: &mut RpcCtx<C>,
+        req: MigrateInGameReq,
+    ) -> CashShopResponse {
+        let migrate_key = ShroomMigrationKey::new(req.client_key, ctx.peer_addr());
+        let session = self
+            .services
+            .session_manager
+            .claim_migrating_session(migrate_key)
+            .await?;
+        let session: OwnedShroomGameSession = session.try_map(|sess| sess.as_mut().try_into())?;
+        log::info!(
+            "Cash shop session for acc: {} - char: {}",
+            session.acc.username,
+            session.char.name
+        );
+
+        ctx.send(SetCashShopResp {
+            char_data_flags: CharDataFlags::all(),
+            char_data_hdr: CharDataHeader {
+                combat_orders: 0,
+                extra_data: None.into(),
+            },
+            char_data: char_data_all(&session.char),
+            authorized: true,
+            account_name: session.acc.username.clone(),
+            not_sale: Default::default(),
+            modified_commodities: 0,
+            discount_rates: 0,
+            best: [CashShopBestItem::default(); CASH_SHOP_BEST_ITEMS],
+            stock: 0,
+            limit_goods: 0,
+            zero_goods: 0,
+            event_on: false,
+            highest_char_level: u32::from(session.char.stats.level),
+        })
+        .await?;
+
+        self.client_key = req.client_key;
+        self.session = Some(session);
+        self.send_locker(ctx).await?;
+        self.handle_query_cash(ctx, CashShopQueryCashReq).await
+    }
+
+    async fn handle_query_cash(
+        &mut self,
+        ctx: &mut RpcCtx<C>,
+        _req: CashShopQueryCashReq,
+    ) -> CashShopResponse {
+        let sess = claimed(&mut self.session)?;
+        let balance = CashBalance::from(&sess.acc);
+        ctx.send(balance_resp(&balance)).await?;
+        Ok(RpcResponse::Ok)
+    }
+
+    async fn send_locker(&mut self, ctx: &mut RpcCtx<C>) -> anyhow::Result<()> {
+        let sess = claimed(&mut self.session)?;
+        let items = self
+            .services
+            .data
+            .cash_shop
+            .load_locker(sess.acc.id)
+            .await?;
+        let items = items
+            .iter()
+            .map(|item| locker_item_info(item, sess.char.id))
+            .collect::<anyhow::Result<_>>()?;
+
+        ctx.send(CashItemResultResp::LoadLockerDone(CashLockerData {
+            items,
+            trunk_slots: u16::from(sess.trunk.slots),
+            char_slots: sess.acc.character_slots as u16,
+            buy_char_count: 0,
+            char_count: 0,
+        }))
+        .await?;
+        Ok(())
+    }
+
+    /// Looks up a commodity, which can be bought by the character
+    fn get_commodity(
+        &self,
+        sn: u32,
+        sess: &OwnedShroomGameSession,
+    ) -> Result<&'static Commodity, CashItemFailReason> {
+        let commodity = self
+            .services
+            .game
+            .meta
+            .get_commodity(sn)
+            .filter(|commodity| commodity.on_sale)
+            .ok_or(CashItemFailReason::NotForSale)?;
+        if !commodity.allows_gender(sess.char.gender as u8) {
+            return Err(CashItemFailReason::GenderMismatch);
+        }
+        Ok(commodity)
+    }
+
+    async fn handle_cash_item_req(
+        &mut self,
+        ctx: &mut RpcCtx<C>,
+        req: CashItemReq,
+    ) -> CashShopResponse {
+        match req {
+            CashItemReq::LoadLocker(()) => self.send_locker(ctx).await?,
+            CashItemReq::Buy(req) => self.handle_buy(ctx, req).await?,
+            CashItemReq::Gift(req) => self.handle_gift(ctx, req).await?,
+            CashItemReq::IncTrunkCount(req) => self.handle_inc_trunk_count(ctx, req).await?,
+            CashItemReq::MoveLtoS(req) => self.handle_move_l_to_s(ctx, req).await?,
+            CashItemReq::MoveStoL(req) => self.handle_move_s_to_l(ctx, req).await?,
+        }
+        Ok(RpcResponse::Ok)
+    }
+
+    async fn handle_buy(&mut self, ctx: &mut RpcCtx<C>, req: CashItemBuyReq) -> anyhow::Result<()> {
+        let sess = self
+            .session
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No session"))?;
+        let commodity = match self.get_commodity(req.commodity_sn, sess) {
+            Ok(commodity) => commodity,
+            Err(reason) => {
+                ctx.send(CashItemResultResp::BuyFailed(reason)).await?;
+                return Ok(());
+            }
+        };
+
+        let sess = claimed(&mut self.session)?;
+        let res = self
+            .services
+            .data
+            .cash_shop
+            .buy(
+                sess.acc.id,
+                cash_type(req.payment),
+                commodity.price,
+                commodity.into(),
+                None,
+            )
+            .await;
+        match res {
+            Ok((balance, item)) => {
+                balance.apply(&mut sess.acc);
+                ctx.send(CashItemResultResp::BuyDone(locker_item_info(
+                    &item,
+                    sess.char.id,
+                )?))
+                .await?;
+                ctx.send(balance_resp(&balance)).await?;
+            }
+            Err(err) => {
+                ctx.send(CashItemResultResp::BuyFailed(fail_reason(err)?))
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_gift(
+        &mut self,
+        ctx: &mut RpcCtx<C>,
+        req: CashItemGiftReq,
+    ) -> anyhow::Result<()> {
+        let sess = self
+            .session
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No session"))?;
+        let commodity = match self.get_commodity(req.commodity_sn, sess) {
+            Ok(commodity) => commodity,
+            Err(reason) => {
+                ctx.send(CashItemResultResp::GiftFailed(reason)).await?;
+                return Ok(());
+            }
+        };
+
+        let svc = &self.services.data;
+        let sess = claimed(&mut self.session)?;
+        // The second password is only checked, if the account has one
+        if sess.acc.pic.is_some() && !svc.account.check_pic(&sess.acc, &req.spw)? {
+            ctx.send(CashItemResultResp::GiftFailed(CashItemFailReason::Unknown))
+                .await?;
+            return Ok(());
+        }
+
+        let res = match svc.cash_shop.find_gift_receiver(&req.receiver).await {
+            Ok(receiver) if receiver == sess.acc.id => {
+                ctx.send(CashItemResultResp::GiftFailed(
+                    CashItemFailReason::GiftToSelf,
+                ))
+                .await?;
+                return Ok(());
+            }
+            Ok(receiver) => {
+                let gift = CashGift {
+                    receiver,
+                    sender_name: sess.char.name.clone(),
+                };
+                svc.cash_shop
+                    .buy(
+                        sess.acc.id,
+                        CashType::NxCredit,
+                        commodity.price,
+                        commodity.into(),
+                        Some(gift),
+                    )
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+
+        match res {
+            Ok((balance, _)) => {
+                balance.apply(&mut sess.acc);
+                ctx.send(CashItemResultResp::GiftDone(CashGiftDone {
+                    receiver: req.receiver,
+                    item_id: commodity.item_id,
+                    count: commodity.count,
+                    price: commodity.price,
+                }))
+                .await?;
+                ctx.send(balance_resp(&balance)).await?;
+            }
+            Err(err) => {
+                ctx.send(CashItemResultResp::GiftFailed(fail_reason(err)?))
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
+    /// Expands the trunk, a storage expansion item adds more slots
+    /// than the direct expansion
+    async fn handle_inc_trunk_count(
+        &mut self,
+        ctx: &mut RpcCtx<C>,
+        req: CashItemIncTrunkCountReq,
+    ) -> anyhow::Result<()> {
+        let sess = self
+            .session
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No session"))?;
+        let res = match req.commodity_sn.0 {
+            Some(sn) => self.get_commodity(sn, sess).and_then(|commodity| {
+                if commodity.item_id != TRUNK_EXPAND_ITEM {
+                    return Err(CashItemFailReason::NotForSale);
+                }
+                Ok((commodity.price, TRUNK_EXPAND_ITEM_SLOTS))
+            }),
+            None => Ok((TRUNK_EXPAND_PRICE, TRUNK_EXPAND_SLOTS)),
+        };
+        let (price, slots) = match res {
+            Ok(res) => res,
+            Err(reason) => {
+                ctx.send(CashItemResultResp::IncTrunkCountFailed(reason))
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        let sess = claimed(&mut self.session)?;
+        let mut trunk = sess.trunk.clone();
+        if !trunk.expand(slots) {
+            ctx.send(CashItemResultResp::IncTrunkCountFailed(
+                CashItemFailReason::Unknown,
+            ))
+            .await?;
+            return Ok(());
+        }
+
+        let res = self
+            .services
+            .data
+            .cash_shop
+            .buy_trunk_slots(sess.acc.id, cash_type(req.payment), price, trunk.slots)
+            .await;
+        match res {
+            Ok(balance) => {
+                balance.apply(&mut sess.acc);
+                sess.expand_trunk(slots);
+                ctx.send(CashItemResultResp::IncTrunkCountDone(u16::from(
+                    sess.trunk.slots,
+                )))
+                .await?;
+                ctx.send(balance_resp(&balance)).await?;
+            }
+            Err(err) => {
+                ctx.send(CashItemResultResp::IncTrunkCountFailed(fail_reason(err)?))
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_move_l_to_s(
+        &mut self,
+        ctx: &mut RpcCtx<C>,
+        req: CashItemMoveLtoSReq,
+    ) -> anyhow::Result<()> {
+        let svc = &self.services;
+        let sess = claimed(&mut self.session)?;
+        if sess.char.inventory.free_slots(req.inv_type)? == 0 {
+            ctx.send(CashItemResultResp::MoveLtoSFailed(
+                CashItemFailReason::InventoryFull,
+            ))
+            .await?;
+            return Ok(());
+        }
+
+        let sn = req.sn as i32;
+        let item = match svc.data.cash_shop.get_locker_item(sess.acc.id, sn).await {
+            Ok(item) => item,
+            Err(err) => {
+                ctx.send(CashItemResultResp::MoveLtoSFailed(fail_reason(err)?))
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        let id = ItemId(item.item_id as u32);
+        let (slot, item) = match sess.char.inventory.add_cash_item(
+            &svc.data.item,
+            id,
+            item.quantity as u16,
+            item.expires_at,
+        ) {
+            Ok(item) => item,
+            Err(err) => {
+                log::error!("Unable to move locker item {id:?} into the inventory: {err:?}");
+                sess.char.inventory.get_updates();
+                ctx.send(CashItemResultResp::MoveLtoSFailed(
+                    CashItemFailReason::Unknown,
+                ))
+                .await?;
+                return Ok(());
+            }
+        };
+        // The client moves the item on It's own, so the pending updates are dropped
+        sess.char.inventory.get_updates();
+
+        let change = LockerChange::Take(sess.acc.id, sn);
+        if let Err(err) = save_locker_move(svc, sess, change).await {
+            log::error!("Unable to save the move of locker item {id:?}: {err:?}");
+            // Undo the move, the item stays in the locker
+            sess.char.inventory.remove_cash_slot(id, slot)?;
+            sess.char.inventory.get_updates();
+            ctx.send(CashItemResultResp::MoveLtoSFailed(
+                CashItemFailReason::Unknown,
+            ))
+            .await?;
+            return Ok(());
+        }
+
+        ctx.send(CashItemResultResp::MoveLtoSDone(CashMoveLtoSDone {
+            slot,
+            item,
+        }))
+        .await?;
+        Ok(())
+    }
+
+    async fn handle_move_s_to_l(
+        &mut self,
+        ctx: &mut RpcCtx<C>,
+        req: CashItemMoveStoLReq,
+    ) -> anyhow::Result<()> {
+        let svc = &self.services;
+        let sess = claimed(&mut self.session)?;
+        let (id, quantity, expires_at) =
+            match sess.char.inventory.take_cash_item(req.inv_type, req.sn) {
+                Ok(item) => item,
+                Err(err) => {
+                    log::info!("Unable to move cash item into the locker: {err:?}");
+                    ctx.send(CashItemResultResp::MoveStoLFailed(
+                        CashItemFailReason::Unknown,
+                    ))
+                    .await?;
+                    return Ok(());
+                }
+            };
+        sess.char.inventory.get_updates();
+
+        let item = CashLockerItemDTO {
+            item_id: id,
+            commodity_sn: 0,
+            quantity,
+            expires_at,
+        };
+        let change = LockerChange::Put(sess.acc.id, item);
+        match save_locker_move(svc, sess, change).await {
+            Ok(Some(item)) => {
+                ctx.send(CashItemResultResp::MoveStoLDone(locker_item_info(
+                    &item,
+                    sess.char.id,
+                )?))
+                .await?;
+            }
+            res => {
+                // Keep the item in the inventory, so It's not lost
+                sess.char
+                    .inventory
+                    .add_cash_item(&svc.data.item, id, quantity, expires_at)?;
+                sess.char.inventory.get_updates();
+                log::error!("Unable to save the move of cash item {id:?}: {res:?}");
+                ctx.send(CashItemResultResp::MoveStoLFailed(
+                    CashItemFailReason::Unknown,
+                ))
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_check_coupon(
+        &mut self,
+        ctx: &mut RpcCtx<C>,
+        req: CashShopCheckCouponReq,
+    ) -> CashShopResponse {
+        let sess = claimed(&mut self.session)?;
+        match self
+            .services
+            .data
+            .cash_shop
+            .redeem_coupon(sess.acc.id, &req.code)
+            .await
+        {
+            Ok(reward) => {
+                reward.balance.apply(&mut sess.acc);
+                let items = reward
+                    .item
+                    .iter()
+                    .map(|item| locker_item_info(item, sess.char.id))
+                    .collect::<anyhow::Result<_>>()?;
+                ctx.send(CashItemResultResp::UseCouponDone(CashCouponDone {
+                    items,
+                    cash: reward.nx_credit,
+                    normal_items: Default::default(),
+                    money: 0,
+                }))
+                .await?;
+                ctx.send(balance_resp(&reward.balance)).await?;
+            }
+            Err(err) => {
+                ctx.send(CashItemResultResp::UseCouponFailed(fail_reason(err)?))
+                    .await?
+            }
+        }
+        Ok(RpcResponse::Ok)
+    }
+
+    /// Sends the client back to the channel It came from, the session is saved
+    /// and put into migration once It's dropped
+    async fn handle_leave(
+        &mut self,
+        ctx: &mut RpcCtx<C>,
+        _req: CashShopLeaveReq,
+    ) -> CashShopResponse {
+        let mut sess = self
+            .session
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("No session claimed for the cash shop"))?;
+        let (world_id, channel_id) = sess.cash_shop_return.take().unwrap_or_default();
+        let addr = self
+            .services
+            .game
+            .server_info
+            .get_channel_addr(world_id, channel_id)?;
+
+        log::info!("Character {:?} leaves the cash shop", sess.char.id);
+        self.services.session_manager.migrate_on_drop(
+            *sess.key(),
+            ShroomMigrationKey::new(self.client_key, ctx.peer_addr()),
+        );
+        drop(sess);
+
+        ctx.send(MigrateCommandResp {
+            unknown: true,
+            addr: addr.try_into()?,
+        })
+        .await?;
+        // The client closes the connection on It's own, once It connects to the channel
+        Ok(RpcResponse::Ok)
+    }
+}
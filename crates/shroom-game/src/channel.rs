@@ -0,0 +1,117 @@
+use shroom_proto95::{
+    game::{
+        MigrateCommandResp, TransferChannelIgnoredReason, TransferChannelReq,
+        TransferChannelReqIgnoredResp,
+    },
+    login::ChannelId,
+};
+
+use crate::{
+    game::{GameContext, GameSession},
+    services::online::OnlineChar,
+    session::ShroomMigrationKey,
+};
+
+impl GameSession {
+    /// Sends the client to the new channel, the session is saved and put into
+    /// migration once the client disconnects from this channel
+    pub fn handle_transfer_channel(
+        &mut self,
+        ctx: &mut GameContext,
+        req: TransferChannelReq,
+    ) -> anyhow::Result<()> {
+        let channel = req.channel_id as ChannelId;
+        let addr = match self
+            .services
+            .game
+            .server_info
+            .get_channel_addr(self.world_id, channel)
+        {
+            Ok(addr) if channel != self.channel_id => addr,
+            _ => {
+                ctx.socket.reply(TransferChannelReqIgnoredResp {
+                    reason: TransferChannelIgnoredReason::ChannelOffline,
+                })?;
+                return Ok(());
+            }
+        };
+
+        log::info!(
+            "Character {:?} changes channel {} -> {channel}",
+            self.char_id(),
+            self.channel_id
+        );
+        self.services.session_manager.migrate_on_drop(
+            *self.session.key(),
+            ShroomMigrationKey::new(self.client_key, self.addr),
+        );
+        ctx.socket.reply(MigrateCommandResp {
+            unknown: true,
+            addr: addr.try_into()?,
+        })?;
+        Ok(())
+    }
+
+    /// Applies the exp rate of the world to exp gained from mobs
+    pub(crate) fn world_exp(&self, exp: u32) -> u32 {
+        self.services
+            .game
+            .server_info
+            .get_server(self.world_id)
+            .map_or(exp, |world| (exp as f32 * world.exp_multiplier()) as u32)
+    }
+
+    /// Frees the slot of the session in the user count of the channel
+    pub(crate) fn leave_channel(&self) {
+        match self
+            .services
+            .game
+            .server_info
+            .get_channel(self.world_id, self.channel_id)
+        {
+            Ok(channel) => channel.user_leave(),
+            Err(err) => log::error!("Unable to leave channel: {err:?}"),
+        }
+    }
+
+    pub(crate) fn online_char(&self) -> OnlineChar {
+        let chr = &self.session.char;
+        OnlineChar {
+            id: chr.id,
+            name: chr.name.clone(),
+            world: self.world_id,
+            channel: self.channel_id,
+            field: self.field_id,
+            whisper_blocked: chr.whisper_blocked,
+        }
+    }
+
+    /// Registers the character as online and updates the party members and buddies,
+    /// called every time the session enters a field
+    pub fn on_world_enter(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        let prev = self.services.game.online.update(self.online_char());
+        self.on_guild_world_enter(ctx, prev.is_none())?;
+        self.on_buddy_world_enter(ctx, prev)?;
+        // Deliveries of trades, which were closed during the migration
+        self.claim_trade()?;
+        self.on_party_world_enter(ctx)
+    }
+
+    /// Marks the character as offline for the rest of the world
+    pub fn on_world_leave(&mut self) -> anyhow::Result<()> {
+        self.services.game.online.remove(self.char_id());
+        self.on_buddy_world_leave()?;
+        self.on_guild_world_leave()?;
+        self.cancel_trade()?;
+        self.on_party_world_leave()
+    }
+}
+
+impl Drop for GameSession {
+    fn drop(&mut self) {
+        self.leave_channel();
+        if let Err(err) = self.on_world_leave() {
+            log::error!("Error during leaving the world: {err:?}");
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use shroom_meta::id::CharacterId;
+use shroom_proto95::game::chat::{GroupMessageResp, MultiChatPacket, MultiChatPacketType};
+
+use crate::game::{GameContext, GameSession};
+
+impl GameSession {
+    /// Members of the group, the recipients sent by the client are not trusted,
+    /// so buddy messages only reach the selected buddies, which accepted the request
+    fn group_chat_recipients(&self, req: &MultiChatPacket) -> Vec<CharacterId> {
+        let id = self.char_id();
+        match req.ty {
+            MultiChatPacketType::Buddy => {
+                let buddies = &self.session.char.buddies;
+                let mut recipients: Vec<_> = req
+                    .recipients
+                    .items
+                    .iter()
+                    .copied()
+                    .filter(|id| buddies.accepted_ids().any(|buddy| buddy == *id))
+                    .collect();
+                recipients.sort_unstable();
+                recipients.dedup();
+                recipients
+            }
+            MultiChatPacketType::Party => self
+                .services
+                .game
+                .party
+                .get_party_of(id)
+                .map(|party| party.online_ids().filter(|m| *m != id).collect())
+                .unwrap_or_default(),
+            MultiChatPacketType::Guild => self
+                .services
+                .game
+                .guild
+                .get_guild_of(id)
+                .map(|guild| guild.online_ids().filter(|m| *m != id).collect())
+                .unwrap_or_default(),
+            // No alliances yet
+            MultiChatPacketType::Alliance => Vec::new(),
+        }
+    }
+
+    pub fn handle_group_chat(
+        &mut self,
+        _ctx: &mut GameContext,
+        req: MultiChatPacket,
+    ) -> anyhow::Result<()> {
+        let recipients = self.group_chat_recipients(&req);
+        self.services.game.sessions.send_all_encode(
+            recipients,
+            GroupMessageResp {
+                ty: req.ty,
+                name: self.session.char.name.clone(),
+                message: req.message,
+            },
+        )?;
+        Ok(())
+    }
+}
@@ -1,9 +1,12 @@
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc, time::Duration};
 
 use shroom_meta::{
     drops::QuestDropFlags,
     field::{FhTree, FieldLife},
-    id::{CharacterId, FieldId, ItemId, MobId, Money, NpcId, ObjectId, ReactorId, SkillId},
+    id::{
+        CharacterId, FieldId, FootholdId, ItemId, MobId, Money, NpcId, ObjectId, ReactorId, SkillId,
+    },
+    reactor::ReactorInput,
     twod::{Box2, Range2, Vec2},
     FieldMeta, MetaService,
 };
@@ -11,14 +14,22 @@ use shroom_pkt::util::packet_buf::PacketBuf;
 use shroom_proto95::{
     game::{
         drop::DropOwner,
+        field::{ClockResp, FieldEffectResp},
         life::{
+            employee::EmployeeBalloon,
             mob::{MobLeaveType, MobMoveReq},
             npc::NpcMoveReq,
+            summon::{SummonEnterType, SummonHitReq, SummonLeaveType, SummonMoveReq},
         },
-        user::remote::UserMoveResp,
+        user::remote::{UserEnterFieldResp, UserLeaveFieldResp, UserMoveResp},
+        BroadcastMessageResp,
     },
     shared::movement::MovePath,
 };
+use shroom_script::{
+    field::{FieldCtx, FieldScriptEvent},
+    reactor::ReactorCtx,
+};
 use shroom_srv::{
     act::{
         room::{ControlMessage, RoomActor, RoomId},
@@ -37,9 +48,12 @@ use shroom_srv::{
 use crate::{
     game::{GameMessage, GameSession},
     life::{
-        char::Character,
+        char::{
+            summon::{Summon, SummonPool},
+            Character,
+        },
         drop_item::{DropItem, DropItemPool, DropLeaveParam, DropTypeValue},
-        employee::EmployeePool,
+        employee::{Employee, EmployeePool},
         minor::{
             AffectedArea, AffectedAreaPool, MessageBoxPool, OpenGatePool, TownPortal,
             TownPortalPool,
@@ -49,6 +63,7 @@ use crate::{
         reactor::{Reactor, ReactorPool},
         Obj,
     },
+    services::shared::SharedGameServices,
     system::GameSystem,
 };
 
@@ -81,17 +96,24 @@ type Tx = BroadcastSet<CharacterId, GameMessage>;
 pub enum FieldEvent {
     DropTimeout(ObjectId),
     AffectedAreaTimeout(ObjectId),
+    /// Reactor finished the animation of its final state
+    ReactorDestroy(ObjectId),
+    /// Reactor with the key in the field meta is spawned again
+    ReactorRespawn(u32),
 }
 
 #[derive(Debug)]
 pub struct SharedFieldState {
     pub field_meta: FieldMeta,
     pub field_fh: &'static FhTree,
+    /// Drop rate multiplier of the world
+    pub drop_rate: f32,
 }
 
 #[derive(Debug)]
 pub struct FieldHandler {
     meta: &'static MetaService,
+    game: SharedGameServices,
     field_id: FieldId,
 
     shared: Arc<SharedFieldState>,
@@ -107,10 +129,16 @@ pub struct FieldHandler {
     message_box_pool: MessageBoxPool,
     open_gate_pool: OpenGatePool,
     town_portal_pool: TownPortalPool,
+    summon_pool: SummonPool,
+    /// Portals, which were opened by a reactor script
+    open_portals: HashSet<String>,
+    /// End of the clock, which was started by a field script
+    clock_end: Option<GameTime>,
 }
 
 impl FieldHandler {
-    pub fn new(meta_svc: &'static MetaService, t: GameTime, shared: Arc<SharedFieldState>) -> Self {
+    pub fn new(game: SharedGameServices, t: GameTime, shared: Arc<SharedFieldState>) -> Self {
+        let meta_svc = game.meta;
         let meta = shared.field_meta;
         let npcs = meta
             .life
@@ -146,8 +174,16 @@ impl FieldHandler {
 
         let reactors = meta
             .reactors
-            .values()
-            .map(|r| Reactor::new(r.id, r))
+            .iter()
+            .map(|(key, r)| Reactor::new(*key, r, meta_svc.get_reactor(r.id)))
+            .map(Obj::next);
+
+        // Hired merchants, which were opened in this field
+        let employees = game
+            .employee
+            .shops_in_field(shared.field_meta.id)
+            .into_iter()
+            .map(|(merchant, balloon)| Employee::from_merchant(&merchant, balloon))
             .map(Obj::next);
 
         Self {
@@ -159,11 +195,15 @@ impl FieldHandler {
             reactor_pool: ReactorPool::from_elems(reactors),
             affected_area_pool: AffectedAreaPool::default(),
             message_box_pool: Default::default(),
-            employee_pool: Default::default(),
+            employee_pool: EmployeePool::from_elems(employees),
             open_gate_pool: Default::default(),
             town_portal_pool: Default::default(),
+            summon_pool: Default::default(),
+            open_portals: HashSet::new(),
+            clock_end: None,
             events: DelayQueue::new(),
             meta: meta_svc,
+            game,
             controller: None,
         }
     }
@@ -227,6 +267,49 @@ impl RoomActor for FieldHandler {
                         (),
                     );
                 }
+                FieldEvent::ReactorDestroy(id) => {
+                    let room = &mut ctx.ctx.room;
+                    let reactor = room.reactor_pool.remove(
+                        &mut FieldPoolCtx {
+                            tx: &mut ctx.ctx.tx,
+                            t,
+                            ctrl: room.controller,
+                        },
+                        &id,
+                        (),
+                    )?;
+                    if let Some(reactor) = reactor {
+                        if let Some(delay) = reactor.respawn_delay {
+                            room.events
+                                .push(FieldEvent::ReactorRespawn(reactor.key), t + delay);
+                        }
+                    }
+                }
+                FieldEvent::ReactorRespawn(key) => {
+                    let room = &mut ctx.ctx.room;
+                    let Some(reactor) = room.shared.field_meta.reactors.get(&key) else {
+                        continue;
+                    };
+                    // Portals opened by the reactor close again
+                    if let Some(name) = reactor.name.as_deref() {
+                        let field_meta = room.shared.field_meta;
+                        room.open_portals.retain(|portal| {
+                            field_meta
+                                .get_portal_by_name(portal)
+                                .and_then(|(_, portal)| portal.reactor_name.as_deref())
+                                != Some(name)
+                        });
+                    }
+                    let reactor = Reactor::new(key, reactor, room.meta.get_reactor(reactor.id));
+                    room.reactor_pool.insert(
+                        &mut FieldPoolCtx {
+                            tx: &mut ctx.ctx.tx,
+                            t,
+                            ctrl: room.controller,
+                        },
+                        Obj::next(reactor),
+                    )?;
+                }
             }
         }
 
@@ -250,7 +333,8 @@ impl RoomActor for FieldHandler {
         let char = &session.handler.session.char;
 
         // Assign a new controller if there's none
-        if ctx.ctx.room.controller.is_none() {
+        let first_user = ctx.ctx.room.controller.is_none();
+        if first_user {
             ctx.ctx.room.update_controller(
                 &mut FieldPoolCtx {
                     tx: &mut ctx.ctx.tx,
@@ -263,11 +347,19 @@ impl RoomActor for FieldHandler {
             )?;
         }
 
-        let field = &mut ctx.ctx.room;
-
-        // Send spawn packets
+        // Send spawn packets, hidden users stay invisible
         let mut buf = PacketBuf::default();
-        //TODO self.field.user_pool.on_enter(char.id, &mut buf)?;
+        for sess in ctx.sessions() {
+            let user = &sess.inner().handler.session.char;
+            if !user.hidden {
+                buf.encode(UserEnterFieldResp {
+                    char_id: user.id,
+                    user_init_data: user.get_remote_init_data(),
+                })?;
+            }
+        }
+
+        let field = &mut ctx.ctx.room;
         field.drop_pool.on_enter(&mut buf, t)?;
         field.npc_pool.on_enter(char.id, &mut buf, t)?;
         field.mob_pool.on_enter(char.id, &mut buf, t)?;
@@ -277,11 +369,60 @@ impl RoomActor for FieldHandler {
         field.message_box_pool.on_enter(&mut buf, t)?;
         field.open_gate_pool.on_enter(&mut buf, t)?;
         field.town_portal_pool.on_enter(&mut buf, t)?;
+        field.summon_pool.on_enter(&mut buf, t)?;
+        for portal in field.open_portals.iter() {
+            buf.encode(FieldEffectResp::Object(portal.clone()))?;
+        }
+        // Late joiners see the remaining time of a running clock
+        let clock_left = field
+            .clock_end
+            .and_then(|end| end.checked_duration_since(t));
+        if let Some(left) = clock_left {
+            buf.encode(ClockResp::Timer(left.as_secs() as u32))?;
+        }
         session.socket.send_buf(buf)?;
 
+        // Summons following the character are spawned next to them
+        let char = &mut session.handler.session.char;
+        for mut summon in char.summons.take_carried() {
+            summon.pos = char.pos;
+            summon.fh = char.fh;
+            summon.enter_type = SummonEnterType::ReregisterSummon;
+            let skill_id = summon.skill_id;
+            let id = ctx.ctx.room.summon_pool.insert(
+                &mut FieldPoolCtx {
+                    tx: &mut ctx.ctx.tx,
+                    t,
+                    ctrl: ctx.ctx.room.controller,
+                },
+                summon,
+            )?;
+            char.summons.insert(skill_id, id);
+        }
+
         // Do the post init
         session.handler.init_char(&mut session.socket)?;
 
+        let char_id = session.handler.session.char.id;
+        let mut field = FieldContext {
+            field: &mut ctx.ctx.room,
+            tx: &mut ctx.ctx.tx,
+            t,
+        };
+        let char = &session.handler.session.char;
+        if !char.hidden {
+            field.show_user(char)?;
+        }
+        // A failing script must not keep the user from entering the field
+        if first_user {
+            if let Err(err) = field.run_field_script(char_id, FieldScriptEvent::FirstUserEnter) {
+                log::error!("First user enter script failed: {err:?}");
+            }
+        }
+        if let Err(err) = field.run_field_script(char_id, FieldScriptEvent::UserEnter) {
+            log::error!("User enter script failed: {err:?}");
+        }
+
         Ok(())
     }
 
@@ -292,6 +433,11 @@ impl RoomActor for FieldHandler {
         let field = &mut ctx.ctx.room;
         let char = &session.handler.session.char;
         let char_id = char.id;
+        if !char.hidden {
+            ctx.ctx
+                .tx
+                .broadcast_encode(UserLeaveFieldResp { char_id })?;
+        }
 
         if field.controller == Some(char_id) {
             let next_controller = ctx
@@ -312,6 +458,19 @@ impl RoomActor for FieldHandler {
             )?;
         }
 
+        // Summons are bound to the field, following ones are spawned again in the next field
+        let t = ctx.ctx.time();
+        let summons = ctx.ctx.room.summon_pool.remove_owned(
+            &mut FieldPoolCtx {
+                tx: &mut ctx.ctx.tx,
+                t,
+                ctrl: ctx.ctx.room.controller,
+            },
+            char_id,
+            SummonLeaveType::LeaveField,
+        )?;
+        session.handler.session.char.summons.leave_field(summons);
+
         Ok(())
     }
 }
@@ -362,6 +521,36 @@ impl<'a> FieldContext<'a> {
         Ok(())
     }
 
+    /// Portals linked to a reactor stay closed until a reactor script opens them
+    pub fn is_portal_open(&self, name: &str) -> bool {
+        let linked = self
+            .field
+            .shared
+            .field_meta
+            .get_portal_by_name(name)
+            .is_some_and(|(_, portal)| portal.reactor_name.is_some());
+        !linked || self.field.open_portals.contains(name)
+    }
+
+    /// Spawns the user for the other users in the field
+    pub fn show_user(&mut self, chr: &Character) -> anyhow::Result<()> {
+        self.tx.broadcast_filter_encode(
+            UserEnterFieldResp {
+                char_id: chr.id,
+                user_init_data: chr.get_remote_init_data(),
+            },
+            chr.id,
+        )?;
+        Ok(())
+    }
+
+    /// Removes the user for the other users in the field
+    pub fn hide_user(&mut self, char_id: CharacterId) -> anyhow::Result<()> {
+        self.tx
+            .broadcast_filter_encode(UserLeaveFieldResp { char_id }, char_id)?;
+        Ok(())
+    }
+
     pub fn handle_user_move(
         &mut self,
         char_id: CharacterId,
@@ -450,7 +639,7 @@ impl<'a> FieldContext<'a> {
         let Some(mob) =
             self.field
                 .mob_pool
-                .attack(pool_ctx!(self), &attacker, id, dmg)?
+                .attack(pool_ctx!(self), &self.field.game.party, &attacker, id, dmg)?
         else {
             if let Some(debuff) = debuff {
                 self.field.mob_pool.debuff(
@@ -465,15 +654,25 @@ impl<'a> FieldContext<'a> {
             return Ok(());
         };
 
-        let (items, money) = self
-            .field
-            .meta
-            .get_drops_and_money_for_mob(mob.tmpl_id, dbg!(&mob.quest_drop_flags));
+        let (items, money) = self.field.meta.get_drops_and_money_for_mob(
+            mob.tmpl_id,
+            dbg!(&mob.quest_drop_flags),
+            self.field.shared.drop_rate,
+        );
         self.spread_drops(mob.pos, DropOwner::User(attacker.attacker()), &items, money)?;
 
         Ok(())
     }
 
+    /// Kills all mobs of the field, returns the number of killed mobs
+    pub fn kill_all_mobs(&mut self, attacker: &Character) -> anyhow::Result<usize> {
+        let mobs = self.field.mob_pool.mobs_hp();
+        for (id, hp) in mobs.iter() {
+            self.attack_mob(*id, *hp, attacker, &None, SkillId(0))?;
+        }
+        Ok(mobs.len())
+    }
+
     pub fn drop_money(&mut self, money: Money, pos: Vec2) -> anyhow::Result<()> {
         self.add_drop(DropItem {
             owner: DropOwner::None,
@@ -579,22 +778,122 @@ impl<'a> FieldContext<'a> {
         Ok(())
     }
 
+    pub fn add_employee(&mut self, employee: Employee) -> anyhow::Result<()> {
+        self.field
+            .employee_pool
+            .insert(pool_ctx!(self), Obj::next(employee))?;
+        Ok(())
+    }
+
+    pub fn remove_employee(&mut self, owner: CharacterId) -> anyhow::Result<()> {
+        if let Some(id) = self.field.employee_pool.find_by_owner(owner) {
+            self.field.employee_pool.remove(pool_ctx!(self), &id, ())?;
+        }
+        Ok(())
+    }
+
+    pub fn update_employee_balloon(
+        &mut self,
+        owner: CharacterId,
+        balloon: EmployeeBalloon,
+    ) -> anyhow::Result<()> {
+        if let Some(id) = self.field.employee_pool.find_by_owner(owner) {
+            self.field
+                .employee_pool
+                .update_balloon(pool_ctx!(self), id, balloon)?;
+        }
+        Ok(())
+    }
+
     pub fn get_town_portal_target(&self, id: ObjectId) -> anyhow::Result<FieldId> {
         Ok(self.field.town_portal_pool.must_get(&id)?.target_map)
     }
 
-    pub fn attack_reactor(
+    /// Triggers the reactor with the input, returns false if the input had no effect
+    pub fn trigger_reactor(
         &mut self,
         id: ObjectId,
+        input: ReactorInput,
         atk: impl AttackerContext,
-    ) -> anyhow::Result<()> {
-        let meta = self.field.meta;
-        if let Some(reactor) = self.field.reactor_pool.attack(pool_ctx!(self), &atk, id)? {
-            let drops = meta.get_reactor_drops(reactor.tmpl_id, &reactor.quest_drop_flags);
-            self.spread_drops(reactor.pos, DropOwner::User(atk.attacker()), &drops, 10)?;
+    ) -> anyhow::Result<bool> {
+        let Some(change) = self
+            .field
+            .reactor_pool
+            .trigger(pool_ctx!(self), &atk, id, &input)?
+        else {
+            return Ok(false);
+        };
+
+        // The reactor is removed after the animation of the final state
+        if change.is_final {
+            self.field
+                .events
+                .push(FieldEvent::ReactorDestroy(id), self.t + change.anim_delay);
+            self.run_reactor_action(id, atk.attacker())?;
         }
+        Ok(true)
+    }
 
-        Ok(())
+    /// Dropping an item on a reactor, which is triggered by it consumes the item
+    pub fn drop_item_on_reactor(
+        &mut self,
+        input: ReactorInput,
+        atk: impl AttackerContext,
+    ) -> anyhow::Result<bool> {
+        let Some(id) = self.field.reactor_pool.find_item_drop_target(&input) else {
+            return Ok(false);
+        };
+        self.trigger_reactor(id, input, atk)
+    }
+
+    fn run_reactor_action(&mut self, id: ObjectId, attacker: CharacterId) -> anyhow::Result<()> {
+        let reactor = self.field.reactor_pool.must_get(&id)?;
+        let (tmpl_id, pos, action) = (reactor.tmpl_id, reactor.pos, reactor.action());
+        let script = action.and_then(|action| {
+            let script = self.field.game.scripts.get_reactor_script(action);
+            if script.is_none() {
+                log::info!("Reactor script not implemented: {action}");
+            }
+            script
+        });
+
+        let mut ctx = ReactorScriptCtx {
+            field: self,
+            id,
+            tmpl_id,
+            pos,
+            attacker,
+        };
+        match script {
+            Some(mut script) => script.act(&mut ctx),
+            // Reactors without a script just drop their items
+            None => ctx.drop_items(),
+        }
+    }
+
+    fn run_field_script(
+        &mut self,
+        char_id: CharacterId,
+        event: FieldScriptEvent,
+    ) -> anyhow::Result<()> {
+        let field_id = self.field.field_id;
+        let scripts = self.field.meta.get_field_scripts(field_id);
+        let script = scripts.and_then(|scripts| match event {
+            FieldScriptEvent::FirstUserEnter => scripts.on_first_user_enter.as_deref(),
+            FieldScriptEvent::UserEnter => scripts.on_user_enter.as_deref(),
+        });
+        let Some(mut handle) = self.field.game.scripts.get_field_script(field_id, script) else {
+            if let Some(script) = script {
+                log::info!("Field script not implemented: {script}");
+            }
+            return Ok(());
+        };
+
+        handle.enter(&mut FieldScriptCtx {
+            field: self,
+            event,
+            char_id,
+        })
     }
 
     pub fn update_controller(
@@ -623,7 +922,137 @@ impl<'a> FieldContext<'a> {
         Ok(())
     }
 
+    pub fn add_summon(&mut self, summon: Summon) -> anyhow::Result<ObjectId> {
+        self.field
+            .summon_pool
+            .insert(pool_ctx!(self), Obj::next(summon))
+    }
+
+    pub fn remove_summon(&mut self, id: ObjectId, leave: SummonLeaveType) -> anyhow::Result<()> {
+        self.field.summon_pool.remove(pool_ctx!(self), &id, leave)?;
+        Ok(())
+    }
+
+    pub fn move_summon(&mut self, owner: CharacterId, req: SummonMoveReq) -> anyhow::Result<()> {
+        self.field
+            .summon_pool
+            .handle_move(pool_ctx!(self), owner, req)
+    }
+
+    /// Returns true if the summon died
+    pub fn hit_summon(&mut self, owner: CharacterId, req: SummonHitReq) -> anyhow::Result<bool> {
+        self.field
+            .summon_pool
+            .handle_hit(pool_ctx!(self), owner, req)
+    }
+
     pub fn get_npc_tmpl_id(&self, id: ObjectId) -> Option<NpcId> {
         self.field.npc_pool.get(&id).map(|n| n.tmpl_id)
     }
 }
+
+/// Field access of a reactor script
+struct ReactorScriptCtx<'a, 'b> {
+    field: &'a mut FieldContext<'b>,
+    id: ObjectId,
+    tmpl_id: ReactorId,
+    pos: Vec2,
+    attacker: CharacterId,
+}
+
+impl ReactorCtx for ReactorScriptCtx<'_, '_> {
+    fn reactor_id(&self) -> ReactorId {
+        self.tmpl_id
+    }
+
+    fn reactor_pos(&self) -> Vec2 {
+        self.pos
+    }
+
+    fn spawn_mob(&mut self, id: MobId, pos: Vec2) -> anyhow::Result<()> {
+        let meta = self.field.field.meta;
+        if meta.get_mob_data(id).is_none() {
+            anyhow::bail!("Reactor {:?} spawns invalid mob: {id:?}", self.tmpl_id);
+        }
+        self.field
+            .add_mob(Mob::new_at(meta, id, pos, FootholdId::none(), None))
+    }
+
+    fn drop_items(&mut self) -> anyhow::Result<()> {
+        let field = &self.field.field;
+        let reactor = field.reactor_pool.must_get(&self.id)?;
+        let drops = field.meta.get_reactor_drops(
+            reactor.tmpl_id,
+            &reactor.quest_drop_flags,
+            field.shared.drop_rate,
+        );
+        self.field
+            .spread_drops(self.pos, DropOwner::User(self.attacker), &drops, 10)
+    }
+
+    fn open_portal(&mut self, name: &str) -> anyhow::Result<()> {
+        self.field.field.open_portals.insert(name.to_string());
+        self.field
+            .tx
+            .broadcast_encode(FieldEffectResp::Object(name.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Field access of a field script
+struct FieldScriptCtx<'a, 'b> {
+    field: &'a mut FieldContext<'b>,
+    event: FieldScriptEvent,
+    char_id: CharacterId,
+}
+
+impl FieldCtx for FieldScriptCtx<'_, '_> {
+    fn field_id(&self) -> FieldId {
+        self.field.field.field_id
+    }
+
+    fn event(&self) -> FieldScriptEvent {
+        self.event
+    }
+
+    fn char_id(&self) -> CharacterId {
+        self.char_id
+    }
+
+    fn show_effect(&mut self, effect: FieldEffectResp) -> anyhow::Result<()> {
+        self.field.tx.broadcast_encode(effect)?;
+        Ok(())
+    }
+
+    fn spawn_mob(&mut self, id: MobId, pos: Vec2) -> anyhow::Result<()> {
+        let meta = self.field.field.meta;
+        if meta.get_mob_data(id).is_none() {
+            anyhow::bail!("Field {:?} spawns invalid mob: {id:?}", self.field_id());
+        }
+        self.field
+            .add_mob(Mob::new_at(meta, id, pos, FootholdId::none(), None))
+    }
+
+    fn start_clock(&mut self, duration: Duration) -> anyhow::Result<()> {
+        self.field.field.clock_end = Some(self.field.t + duration);
+        self.field
+            .tx
+            .broadcast_encode(ClockResp::Timer(duration.as_secs() as u32))?;
+        Ok(())
+    }
+
+    fn message(&mut self, msg: &str) -> anyhow::Result<()> {
+        self.field.tx.send_to_encode(
+            self.char_id,
+            BroadcastMessageResp::PinkMessage(msg.to_string()),
+        )?;
+        Ok(())
+    }
+
+    fn broadcast_message(&mut self, msg: &str) -> anyhow::Result<()> {
+        self.field
+            .tx
+            .broadcast_encode(BroadcastMessageResp::PinkMessage(msg.to_string()))?;
+        Ok(())
+    }
+}
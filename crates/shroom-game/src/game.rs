@@ -2,13 +2,18 @@ use std::{net::IpAddr, num::Wrapping, ops::Neg, time::Duration};
 
 use either::Either;
 use scripts_lib::NpcHandle;
-use shroom_data::entity_ext::FuncKey;
+use shroom_data::{
+    entity_ext::FuncKey,
+    model::inv::InventorySlot,
+    services::char_save::{CharSaveParts, SavedItemId},
+};
 use shroom_meta::{
     buffs::char::{CharBuffMad, CharBuffPad},
     id::{
-        item_id::InventoryType, BuffId, CharacterId, FieldId, MobId, Money, NpcId, ObjectId,
-        QuestId, SkillId,
+        item_id::InventoryType, BuffId, CharacterId, FieldId, ItemId, MobId, Money, NpcId,
+        ObjectId, QuestId, SkillId,
     },
+    reactor::ReactorInput,
     tmpl::item::BundleItemValue,
     FieldMeta, MetaService, QuestDataId,
 };
@@ -18,39 +23,49 @@ use shroom_pkt::{
 };
 use shroom_proto95::{
     game::{
-        chat::{ChatMsgReq, UserChatMsgResp},
+        cash_shop::MigrateToCashShopReq,
+        chat::{ChatMsgReq, MultiChatPacket, UserChatMsgResp, WhiperMsgReq},
         field::{
             CrcSeed, FieldCharData, FieldTransferData, LogoutGiftConfig, NotificationList,
             SetFieldResp,
         },
-        friend::{FriendList, FriendResultResp},
+        friend::{FriendResultResp, FriendUserReq},
+        guild::{GuildReq, GuildResultReq},
         key_map::{
             FuncKeyMapChangeReq, FuncKeyMapInitResp, QuickSlotInitResp, QuickslotKeyMapChangedReq,
         },
         life::{
             mob::{MobApplyCtrlReq, MobMoveReq},
             npc::{NpcMoveReq, UserSelectNpcReq},
-            reactor::ReactorHitReq,
-            summon::{SummonAttackReq, SummonSkillReq},
+            reactor::{ReactorHitReq, ReactorTouchReq},
+            summon::{
+                SummonAttackReq, SummonEndReq, SummonHitReq, SummonLeaveType, SummonMoveReq,
+                SummonRemoveReq, SummonSkillReq,
+            },
             town_portal::TownPortalEnterReq,
         },
         quest::{
-            ConstantU8, QuestRecordMessageResp, QuestState, UserQuestReq, UserQuestResultResp,
-            UserQuestSuccessResult,
+            ConstantU8, QuestRecordMessageResp, QuestScript, QuestState, UserQuestReq,
+            UserQuestResultResp, UserQuestSuccessResult,
         },
+        mini_room::{MiniRoomLeave, MiniRoomLeaveReason, MiniRoomReq, MiniRoomResp, UserEntrustedShopReq},
+        party::{PartyReq, PartyResultReq},
         script::{ScriptAnswerReq, ScriptMessageResp},
+        shop::ShopUserReq,
+        trunk::UserTrunkReq,
         user::{
             char::{CharDataAll, CharDataFlags},
             effect::{
                 ItemHyperUpgradeEffectResp, ItemUpgradeEffectResp, LocalUserEffectResp, UserEffect,
             },
             secondary_stats::{LocalSecondaryStatResetResp, LocalSecondaryStatSetResp2},
-            AttackTargetInfo, ChangeSkillRecordResp, Hits, UserBodyAttackReq, UserDropMoneyReq,
-            UserDropPickUpReq, UserHitReq, UserMagicAttackReq, UserMeleeAttackReq, UserMoveReq,
-            UserShotAttackReq, UserSkillCancelReq, UserSkillUpReq, UserSkillUseReq,
-            UserStatChangeReq, UserTransferFieldReq,
+            AttackTargetInfo, ChangeSkillRecordResp, Hits, SkillCooldownSetResp, UserBodyAttackReq,
+            UserDropMoneyReq, UserDropPickUpReq, UserHitReq, UserMagicAttackReq,
+            UserMeleeAttackReq, UserMoveReq, UserShotAttackReq, UserSkillCancelReq,
+            UserSkillUpReq, UserSkillUseReq, UserStatChangeReq, UserTransferFieldReq,
         },
-        BroadcastMessageResp, ClaimSvrStatusChangedResp, CtxSetGenderResp, UserPortalScriptReq,
+        BroadcastMessageResp, ClaimSvrStatusChangedResp, CtxSetGenderResp, TransferChannelReq,
+        UserPortalScriptReq,
     },
     login::{ChannelId, ClientKey, WorldId},
     recv_opcodes::RecvOpcodes,
@@ -60,8 +75,8 @@ use shroom_proto95::{
             QuestInfo, SkillInfo, SocialRecords, TeleportRockInfo,
         },
         inventory::{
-            InvChangeSlotPosReq, InventoryOperationsResp, ItemHyperUpgradeReq,
-            ItemStatChangeItemUseReq, ItemUpgradeReq,
+            InvChangeSlotPosReq, InventoryOperationsResp, ItemHyperUpgradeReq, ItemScriptUseReq,
+            ItemSelectNpcUseReq, ItemStatChangeItemUseReq, ItemUpgradeReq,
         },
         item::Item,
     },
@@ -76,26 +91,52 @@ use shroom_srv::{
 };
 
 use crate::{
+    autosave::AutosaveState,
+    buddy::BuddyMsg,
     life::{
         char::{buffs::CharBuffPacket, class::UseSkillData, quest::QuestCheckError, Character},
         drop_item::{DropItem, DropTypeValue},
     },
-    repl::GameRepl,
+    repl::{GameRepl, GmLevel},
     services::shared::SharedServices,
     session::{
         shroom_session_backend::SessionIngameData, shroom_session_manager::OwnedShroomGameSession,
     },
+    shop::OpenedShop,
 };
 
 use super::field::FieldHandler;
 
 pub type SessionId = CharacterId;
 
+/// Speaker of item scripts, which don't summon a npc
+const ITEM_SCRIPT_NPC: NpcId = NpcId(9010000);
+
+/// Max distance between the character and a portal to run its script
+const PORTAL_SCRIPT_RANGE: i64 = 200;
+
 #[derive(Debug, Clone)]
 pub enum GameMessage {
     Pkt(PktMsg),
     MobExp(MobId, u32, u8),
     ExpGain(u32),
+    /// Party member in the same field requests the hp
+    PartyHpRequest(CharacterId),
+    Buddy(BuddyMsg),
+    /// The trade was closed, the position of the receiver in the trade is passed
+    TradeClosed(u8, MiniRoomLeaveReason),
+    /// The receiver was removed from a shop at the position
+    MiniRoomLeave(u8, MiniRoomLeaveReason),
+    /// Items of the visited shop changed
+    ShopRefresh,
+    /// Guild name or mark of the receiver changed
+    GuildChanged,
+    /// Autosave finished with the ids of the inserted items
+    AutosaveDone(Vec<SavedItemId>),
+    /// Autosave failed, the parts and merchants must be saved again
+    AutosaveFailed(CharSaveParts, Vec<CharacterId>),
+    /// The account of the receiver was banned
+    Banned,
 }
 
 impl From<PktMsg> for GameMessage {
@@ -135,6 +176,12 @@ pub struct GameSession {
     pub field_meta: FieldMeta,
     pub repl: GameRepl,
     pub current_script: Option<NpcHandle>,
+    pub shop: Option<OpenedShop>,
+    /// Npc of the currently opened trunk
+    pub trunk: Option<NpcId>,
+    /// Npc of the currently opened guild dialog
+    pub guild_npc: Option<NpcId>,
+    pub autosave: AutosaveState,
     pub field_key: Wrapping<u8>,
 }
 
@@ -182,36 +229,72 @@ impl Handler for GameSession {
 
     fn on_enter_room(
         &mut self,
-        _ctx: &mut shroom_srv::net::session::NetSessionContext<Self>,
+        ctx: &mut shroom_srv::net::session::NetSessionContext<Self>,
     ) -> Result<(), Self::Error> {
-        Ok(())
+        self.on_world_enter(ctx)
     }
 
     fn on_leave_room(
         &mut self,
-        _ctx: &mut shroom_srv::net::session::NetSessionContext<Self>,
+        ctx: &mut shroom_srv::net::session::NetSessionContext<Self>,
     ) -> Result<(), Self::Error> {
+        // Trades and shop visits are bound to the field
+        self.cancel_trade()?;
+        self.leave_shop(ctx)?;
         Ok(())
     }
 
-    fn on_msg(&mut self, _ctx: &mut GameContext, msg: Self::Msg) -> anyhow::Result<()> {
+    fn on_msg(&mut self, ctx: &mut GameContext, msg: Self::Msg) -> anyhow::Result<()> {
         match msg {
             // Will be handled earlier
             GameMessage::Pkt(_) => {}
             GameMessage::ExpGain(exp) => {
+                let exp = self.world_exp(exp);
                 self.session.char.add_exp(exp);
             }
             GameMessage::MobExp(mob_id, exp, _perc) => {
+                let exp = self.world_exp(exp);
                 self.session.char.add_exp(exp);
                 self.session.char.quests.on_mob_killed(mob_id, 1);
             }
+            GameMessage::PartyHpRequest(member) => {
+                ctx.room.tx.send_to_encode(member, self.hp_resp())?;
+            }
+            GameMessage::Buddy(msg) => {
+                self.handle_buddy_msg(ctx, msg)?;
+            }
+            GameMessage::TradeClosed(pos, reason) => {
+                self.handle_trade_closed(ctx, pos, reason)?;
+            }
+            GameMessage::MiniRoomLeave(pos, reason) => {
+                ctx.socket
+                    .reply(MiniRoomResp::Leave(MiniRoomLeave { pos, reason }))?;
+            }
+            GameMessage::ShopRefresh => {
+                self.send_shop_refresh(ctx)?;
+            }
+            GameMessage::GuildChanged => {
+                self.broadcast_guild_look(ctx)?;
+            }
+            GameMessage::AutosaveDone(ids) => {
+                self.handle_autosave_done(ids);
+            }
+            GameMessage::AutosaveFailed(parts, shops) => {
+                self.handle_autosave_failed(parts, shops);
+            }
+            GameMessage::Banned => {
+                // Closing the session disconnects the client
+                anyhow::bail!("Account of {} was banned", self.session.char.name);
+            }
         }
         Ok(())
     }
 
     fn on_tick(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        self.session.char.handle_update(ctx)?;
         self.update_char_stats(ctx)?;
         self.session.char.last_update = ctx.time();
+        self.autosave(ctx.time());
 
         Ok(())
     }
@@ -227,6 +310,7 @@ impl Handler for GameSession {
             handle_default,
             UserSkillUseReq =>  handle_use_skill,
             ReactorHitReq => handle_reactor_hit,
+            ReactorTouchReq => handle_reactor_touch,
             MobMoveReq => handle_mob_move,
             NpcMoveReq => handle_npc_move,
             UserPortalScriptReq => handle_portal_script,
@@ -247,6 +331,10 @@ impl Handler for GameSession {
             UserSkillCancelReq => handle_skill_cancel,
             SummonSkillReq => handle_summon_use_skill,
             SummonAttackReq => handle_summon_attack,
+            SummonMoveReq => handle_summon_move,
+            SummonHitReq => handle_summon_hit,
+            SummonRemoveReq => handle_summon_remove,
+            SummonEndReq => handle_summon_end,
             TownPortalEnterReq => handle_town_portal_enter,
             FuncKeyMapChangeReq => handle_func_key_map_change,
             QuickslotKeyMapChangedReq => handle_quick_slot_changed,
@@ -255,7 +343,22 @@ impl Handler for GameSession {
             ItemUpgradeReq => handle_item_upgrade,
             ItemHyperUpgradeReq => handle_item_hyper_upgrade,
             ItemStatChangeItemUseReq => handle_item_stat_change_use,
-            UserSelectNpcReq => handle_select_npc
+            ItemScriptUseReq => handle_item_script_use,
+            ItemSelectNpcUseReq => handle_item_select_npc_use,
+            UserSelectNpcReq => handle_select_npc,
+            ShopUserReq => handle_shop_req,
+            UserTrunkReq => handle_trunk_req,
+            PartyReq => handle_party_req,
+            PartyResultReq => handle_party_result_req,
+            GuildReq => handle_guild_req,
+            GuildResultReq => handle_guild_result_req,
+            FriendUserReq => handle_friend_req,
+            WhiperMsgReq => handle_whisper,
+            MultiChatPacket => handle_group_chat,
+            MiniRoomReq => handle_mini_room_req,
+            UserEntrustedShopReq => handle_entrusted_shop_req,
+            TransferChannelReq => handle_transfer_channel,
+            MigrateToCashShopReq => handle_migrate_to_cash_shop
         );
         if res.is_err() {
             log::error!("Error handling op: {op:?}");
@@ -305,6 +408,10 @@ impl shroom_script::SessionCtx for Character {
         *self.stats.level()
     }
 
+    fn derived_stat(&self, stat: shroom_meta::item::EquipStat) -> u32 {
+        self.derived.get(stat)
+    }
+
     fn set_level(&mut self, level: u8) {
         *self.stats.level_mut() = level;
     }
@@ -346,8 +453,24 @@ impl shroom_script::SessionCtx for Character {
         log::info!("Saying: {}", msg);
     }
 
+    fn notice(&mut self, msg: &str) {
+        self.script_notices.push_back(msg.to_string());
+    }
+
     fn transfer_field(&mut self, field_id: FieldId) {
-        self.do_script_transfer = Some(field_id);
+        self.do_script_transfer = Some((field_id, None));
+    }
+
+    fn transfer_field_portal(&mut self, field_id: FieldId, portal: &str) {
+        let has_portal = self
+            .game
+            .meta
+            .get_field(field_id)
+            .is_some_and(|field| field.get_portal_by_name(portal).is_some());
+        if !has_portal {
+            log::warn!("Script transfer to unknown portal {portal} in {field_id:?}");
+        }
+        self.do_script_transfer = Some((field_id, has_portal.then(|| portal.to_string())));
     }
 
     fn has_item_quantity(&self, id: shroom_meta::id::ItemId, count: usize) -> bool {
@@ -383,6 +506,7 @@ impl shroom_script::SessionCtx for Character {
 
     fn set_quest_state_data(&mut self, id: QuestDataId, data: Vec<u8>) -> anyhow::Result<()> {
         self.quests.quest_data.insert(id, data);
+        self.dirty.quests = true;
         Ok(())
     }
 
@@ -401,6 +525,69 @@ impl shroom_script::SessionCtx for Character {
     fn is_active_quest(&self, id: QuestId) -> bool {
         self.quests.is_active(id)
     }
+
+    fn set_quest_id(&mut self, id: Option<QuestId>) {
+        self.quest_id = id;
+    }
+
+    fn current_quest_id(&self) -> Option<QuestId> {
+        self.quest_id
+    }
+
+    fn set_item_id(&mut self, id: Option<ItemId>) {
+        self.item_id = id;
+    }
+
+    fn current_item_id(&self) -> Option<ItemId> {
+        self.item_id
+    }
+
+    fn consume_script_item(&mut self) -> anyhow::Result<bool> {
+        let Some((id, slot)) = self.script_item.take() else {
+            return Ok(false);
+        };
+        // The item might have been moved or used up while the script was running
+        if self.inventory.get_stack_item_id(slot).ok() != Some(id) {
+            return Ok(false);
+        }
+        self.inventory
+            .drop_stack_item(InventoryType::Consume, slot, Some(1))?;
+        Ok(true)
+    }
+
+    fn start_quest(&mut self, id: QuestId) -> anyhow::Result<bool> {
+        if self.meta().get_quest(id).is_none() {
+            anyhow::bail!("Invalid quest: {id:?}");
+        }
+
+        if let Err(err) = self.try_accept_quest(id) {
+            log::info!("Script failed to start quest {id:?}: {err:?}");
+            return Ok(false);
+        }
+        self.quest_updates
+            .push_back((id, QuestState::Accept(String::new())));
+        Ok(true)
+    }
+
+    fn forfeit_quest(&mut self, id: QuestId) -> anyhow::Result<()> {
+        let meta = self.meta();
+        self.quests.forfeit_quest(id, meta)?;
+        self.dirty.quests = true;
+        self.quest_updates
+            .push_back((id, QuestState::NotStarted(0)));
+        Ok(())
+    }
+
+    fn complete_quest(&mut self, id: QuestId) -> anyhow::Result<bool> {
+        if !self.quests.is_active(id) {
+            return Ok(false);
+        }
+
+        self.try_complete_quest(id)?;
+        self.quest_updates
+            .push_back((id, QuestState::Complete(ShroomTime::now())));
+        Ok(true)
+    }
 }
 
 impl GameSession {
@@ -426,6 +613,13 @@ impl GameSession {
         };*/
 
         let npc = field!(ctx).get_npc_tmpl_id(ObjectId(req.id.0)).unwrap();
+        if self.open_store_bank(ctx, npc)?
+            || self.open_trunk(ctx, npc)?
+            || self.open_guild_npc(ctx, npc)?
+            || self.open_shop(ctx, npc)? {
+            return Ok(());
+        }
+
         let script = self.services.game.scripts.get_npc_script_or_fallback(npc);
         self.start_script(ctx, script)?;
 
@@ -480,6 +674,69 @@ impl GameSession {
         Ok(())
     }
 
+    fn handle_item_script_use(
+        &mut self,
+        ctx: &mut GameContext,
+        req: ItemScriptUseReq,
+    ) -> anyhow::Result<()> {
+        self.use_script_item(ctx, req.slot, req.item_id)
+    }
+
+    fn handle_item_select_npc_use(
+        &mut self,
+        ctx: &mut GameContext,
+        req: ItemSelectNpcUseReq,
+    ) -> anyhow::Result<()> {
+        self.use_script_item(ctx, req.slot, req.item_id)
+    }
+
+    /// Launches the script or the npc of the item,
+    /// the item is consumed from the used slot once the script succeeds
+    fn use_script_item(
+        &mut self,
+        ctx: &mut GameContext,
+        slot: u16,
+        id: ItemId,
+    ) -> anyhow::Result<()> {
+        if id.get_inv_type()? != InventoryType::Consume {
+            anyhow::bail!("Not a consumable script item: {id:?}");
+        }
+        let slot = (InventoryType::Consume, slot as i16).try_into()?;
+        if self.session.char.inventory.get_stack_item_id(slot)? != id {
+            anyhow::bail!("Invalid script item: {id:?}");
+        }
+
+        let tmpl = self
+            .services
+            .game
+            .meta
+            .items()
+            .consume
+            .get(&id)
+            .ok_or_else(|| anyhow::format_err!("Invalid item: {id:?}"))?;
+        let npc = self.services.game.meta.get_item_npc(id);
+        if tmpl.script().is_none() && npc.is_none() {
+            anyhow::bail!("Not a script item: {id:?}");
+        }
+
+        let scripts = &self.services.game.scripts;
+        let handle = match (tmpl.script(), npc) {
+            (Some(script), _) => {
+                scripts.get_item_script(script, npc.unwrap_or(ITEM_SCRIPT_NPC), id)
+            }
+            (None, Some(npc)) => scripts.get_npc_script(npc),
+            (None, None) => None,
+        };
+
+        let Some(handle) = handle else {
+            log::info!("Item script not implemented: {id:?} {:?}", tmpl.script());
+            self.enable_char();
+            return Ok(());
+        };
+
+        self.start_item_script(ctx, handle, Some((id, slot)))
+    }
+
     fn user_effect(&mut self, ctx: &mut GameContext, eff: UserEffect) -> anyhow::Result<()> {
         ctx.socket.reply(LocalUserEffectResp(eff))?;
         Ok(())
@@ -574,7 +831,9 @@ impl GameSession {
                         ctx.socket
                             .reply(UserQuestResultResp::FailedInventory(q.id))?;
                     }
-                    Err(_) => todo!(),
+                    Err(QuestCheckError::PreQuest) => {
+                        ctx.socket.reply(UserQuestResultResp::FailedUnknown(()))?;
+                    }
                 }
             }
             UserQuestReq::CompleteQuest(q) => {
@@ -594,6 +853,31 @@ impl GameSession {
                     state: QuestState::Complete(ShroomTime::now()),
                 })?;
             }
+            UserQuestReq::ResignQuest(id) => {
+                let meta = self.meta();
+                let chr = &mut self.session.char;
+                chr.quests.forfeit_quest(id, meta)?;
+                chr.dirty.quests = true;
+                ctx.socket.reply(QuestRecordMessageResp {
+                    marker: ConstantU8,
+                    id,
+                    state: QuestState::NotStarted(0),
+                })?;
+            }
+            UserQuestReq::OpenScript(q) => {
+                let quest = self
+                    .meta()
+                    .get_quest(q.id)
+                    .ok_or_else(|| anyhow::format_err!("Invalid quest"))?;
+                self.start_quest_script(ctx, quest.start_req.script.as_deref(), q)?;
+            }
+            UserQuestReq::CompleteScript(q) => {
+                let quest = self
+                    .meta()
+                    .get_quest(q.id)
+                    .ok_or_else(|| anyhow::format_err!("Invalid quest"))?;
+                self.start_quest_script(ctx, quest.end_req.script.as_deref(), q)?;
+            }
             _ => {
                 log::info!("Quest msg: {req:?}");
             }
@@ -602,6 +886,40 @@ impl GameSession {
         Ok(())
     }
 
+    fn start_quest_script(
+        &mut self,
+        ctx: &mut GameContext,
+        script: Option<&str>,
+        q: QuestScript,
+    ) -> anyhow::Result<()> {
+        let handle = script.and_then(|script| {
+            self.services
+                .game
+                .scripts
+                .get_quest_script(script, q.npc_tmpl_id, q.id)
+        });
+
+        let Some(handle) = handle else {
+            log::info!("Quest script not implemented: {:?} {script:?}", q.id);
+            self.enable_char();
+            return Ok(());
+        };
+
+        self.start_script(ctx, handle)
+    }
+
+    /// Sends the quest state changes done by scripts
+    fn send_quest_updates(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        while let Some((id, state)) = self.session.char.quest_updates.pop_front() {
+            ctx.socket.reply(QuestRecordMessageResp {
+                marker: ConstantU8,
+                id,
+                state,
+            })?;
+        }
+        Ok(())
+    }
+
     pub fn send_set_field(&mut self, sck: &mut NetSocket) -> anyhow::Result<()> {
         sck.reply(self.set_field(true))?;
         self.field_key += 1;
@@ -609,7 +927,7 @@ impl GameSession {
     }
 
     pub fn init_char(&mut self, sck: &mut NetSocket) -> anyhow::Result<()> {
-        sck.reply(FriendResultResp::Reset3(FriendList::empty()))?;
+        sck.reply(FriendResultResp::Reset3(self.buddy_list()?))?;
         sck.reply(FuncKeyMapInitResp::from(
             self.session.char.key_map.func().map(|map| map.to_proto()),
         ))?;
@@ -620,6 +938,14 @@ impl GameSession {
                 .quick_slots()
                 .map(|map| map.to_proto()),
         ))?;
+        // Running cooldowns, so the client keeps them after entering the field
+        let chr = &self.session.char;
+        for cd in chr.skills.get_cooltime_info(chr.last_update) {
+            sck.reply(SkillCooldownSetResp {
+                skill_id: cd.id,
+                cooldown_s: cd.time_left_s,
+            })?;
+        }
         sck.reply(ClaimSvrStatusChangedResp { connected: true })?;
         sck.reply(CtxSetGenderResp {
             gender: self.session.char.gender,
@@ -658,7 +984,7 @@ impl GameSession {
         Ok(())
     }
 
-    fn char_id(&self) -> CharacterId {
+    pub(crate) fn char_id(&self) -> CharacterId {
         self.session.char.id
     }
 
@@ -669,8 +995,19 @@ impl GameSession {
     }
 
     pub fn start_script(&mut self, ctx: &mut GameContext, script: NpcHandle) -> anyhow::Result<()> {
+        self.start_item_script(ctx, script, None)
+    }
+
+    /// Starts the script, which was launched by the item in the slot
+    fn start_item_script(
+        &mut self,
+        ctx: &mut GameContext,
+        script: NpcHandle,
+        item: Option<(ItemId, InventorySlot)>,
+    ) -> anyhow::Result<()> {
         log::info!("About to start script");
         self.current_script = Some(script);
+        self.session.char.script_item = item;
         self.poll_npc(ctx, NpcAction::Start)?;
         log::info!("Waiting for next poll");
         Ok(())
@@ -683,9 +1020,14 @@ impl GameSession {
             .ok_or_else(|| anyhow::format_err!("No script"))?;
         let is_end = matches!(input, NpcAction::End);
         let res = self.run_script(&mut script, input);
+        let failed = res.is_err();
+        if failed {
+            self.session.char.script_item = None;
+        }
         if !is_end {
             res?;
         }
+        self.send_quest_updates(ctx)?;
 
         if let Some(msg) = self.session.char.npc_msg.pop_front() {
             ctx.socket.reply(ScriptMessageResp {
@@ -696,12 +1038,14 @@ impl GameSession {
         }
 
         if script.is_finished() {
+            // Npc scripts don't know about the item, so it's used up once the npc is done
+            if !failed && script.item_id().is_none() {
+                self.session.char.consume_script_item()?;
+            }
+            self.session.char.script_item = None;
             self.session.char.npc_msg.clear();
             self.session.char.unlock_char();
-            if let Some(transfer) = self.session.char.do_script_transfer.take() {
-                self.do_field_transfer(ctx, transfer, None)?;
-            }
-
+            self.apply_script_effects(ctx)?;
             return Ok(());
         }
 
@@ -710,6 +1054,18 @@ impl GameSession {
         Ok(())
     }
 
+    /// Sends the pending script messages and does the script's field transfer
+    fn apply_script_effects(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        while let Some(msg) = self.session.char.script_notices.pop_front() {
+            ctx.socket.reply(BroadcastMessageResp::PinkMessage(msg))?;
+        }
+
+        if let Some((field, portal)) = self.session.char.do_script_transfer.take() {
+            self.do_field_transfer(ctx, field, portal.as_deref())?;
+        }
+        Ok(())
+    }
+
     pub fn enable_char(&mut self) {
         self.session.char.unlock_char()
     }
@@ -719,7 +1075,11 @@ impl GameSession {
     }
 
     fn update_char_stats(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        self.update_party_stats(ctx)?;
+        self.update_guild_stats()?;
+        let mut stats_changed = self.session.char.inventory.take_eq_stats_changed();
         if let Some(partial) = self.session.char.get_stats_update() {
+            stats_changed = true;
             ctx.socket.reply(CharStatChangedResp {
                 excl: true, //TODO handle this
                 stats: PartialFlag {
@@ -740,6 +1100,8 @@ impl GameSession {
         }
 
         if let Some(skills) = self.session.char.skills.get_updates() {
+            self.session.char.dirty.skills = true;
+            stats_changed = true;
             ctx.socket.reply(ChangeSkillRecordResp {
                 reset_excl: true,
                 skill_records: skills.into(),
@@ -748,6 +1110,7 @@ impl GameSession {
         }
 
         if let Some(skill_cd) = self.session.char.skills.get_cooldown_updates(ctx.time()) {
+            self.session.char.dirty.skills = true;
             for cd in skill_cd {
                 ctx.socket.reply(cd)?;
             }
@@ -755,6 +1118,7 @@ impl GameSession {
 
         let removals = self.session.char.buffs.update_expirations(ctx.time());
         if !removals.is_empty() {
+            stats_changed = true;
             log::info!("Removing buffs: {:?}", removals);
             ctx.socket.reply(LocalSecondaryStatResetResp {
                 flags: removals,
@@ -764,6 +1128,7 @@ impl GameSession {
 
         let updated_stats = self.session.char.buffs.take_updated();
         if !updated_stats.is_empty() {
+            stats_changed = true;
             log::info!("Updated stats: {:?}", updated_stats);
             let stats = CharBuffPacket {
                 buffs: &self.session.char.buffs,
@@ -777,7 +1142,13 @@ impl GameSession {
             })?;
         }
 
-        for (qid, qr) in self.session.char.quests.updates_states() {
+        let chr = &mut self.session.char;
+        if stats_changed {
+            chr.update_derived_stats();
+        }
+
+        for (qid, qr) in chr.quests.updates_states() {
+            chr.dirty.quests = true;
             log::info!("Quest update: {:?} with {qr}", qid);
             ctx.socket.reply(QuestRecordMessageResp {
                 marker: ConstantU8,
@@ -863,7 +1234,7 @@ impl GameSession {
         ctx: &mut GameContext,
         req: SummonAttackReq,
     ) -> anyhow::Result<()> {
-        let Some(summon) = self.session.char.get_summon(req.summon_id) else {
+        let Some(skill_id) = self.session.char.summons.skill_id(req.summon_id) else {
             return Ok(());
         };
 
@@ -883,16 +1254,61 @@ impl GameSession {
                 hits: Hits::single(t.hit),
             })
             .collect();
-        self.handle_attack(ctx, atk, summon.skill_id)?;
+        self.handle_attack(ctx, atk, skill_id)?;
+
+        Ok(())
+    }
+
+    fn handle_summon_move(
+        &mut self,
+        ctx: &mut GameContext,
+        req: SummonMoveReq,
+    ) -> anyhow::Result<()> {
+        field!(ctx).move_summon(self.session.char.id, req)
+    }
 
+    fn handle_summon_hit(
+        &mut self,
+        ctx: &mut GameContext,
+        req: SummonHitReq,
+    ) -> anyhow::Result<()> {
+        let id = req.summon_id;
+        if field!(ctx).hit_summon(self.session.char.id, req)? {
+            self.session.char.summons.remove(id);
+        }
         Ok(())
     }
 
+    fn handle_summon_remove(
+        &mut self,
+        ctx: &mut GameContext,
+        req: SummonRemoveReq,
+    ) -> anyhow::Result<()> {
+        self.session
+            .char
+            .remove_summon(ctx, req.summon_id, SummonLeaveType::OnRemove)
+    }
+
+    fn handle_summon_end(
+        &mut self,
+        ctx: &mut GameContext,
+        req: SummonEndReq,
+    ) -> anyhow::Result<()> {
+        self.session
+            .char
+            .remove_summon(ctx, req.summon_id, SummonLeaveType::Default)
+    }
+
     fn handle_script_answer_req(
         &mut self,
         ctx: &mut GameContext,
         req: ScriptAnswerReq,
     ) -> anyhow::Result<()> {
+        // Answers to plain npc dialogs without a script
+        if self.current_script.is_none() {
+            self.enable_char();
+            return Ok(());
+        }
         self.poll_npc(ctx, req.into())
     }
 
@@ -901,7 +1317,20 @@ impl GameSession {
         ctx: &mut GameContext,
         req: ReactorHitReq,
     ) -> anyhow::Result<()> {
-        field!(ctx).attack_reactor(req.id, &self.session.char)?;
+        let input = ReactorInput::Hit(SkillId(req.skill_id));
+        field!(ctx).trigger_reactor(req.id, input, &self.session.char)?;
+        Ok(())
+    }
+
+    fn handle_reactor_touch(
+        &mut self,
+        ctx: &mut GameContext,
+        req: ReactorTouchReq,
+    ) -> anyhow::Result<()> {
+        // Only entering the reactor triggers it
+        if req.has_reactor {
+            field!(ctx).trigger_reactor(req.id, ReactorInput::Touch, &self.session.char)?;
+        }
         Ok(())
     }
 
@@ -921,10 +1350,52 @@ impl GameSession {
 
     fn handle_portal_script(
         &mut self,
-        _ctx: &mut GameContext,
-        _req: UserPortalScriptReq,
+        ctx: &mut GameContext,
+        req: UserPortalScriptReq,
     ) -> anyhow::Result<()> {
+        let res = self.run_portal_script(ctx, &req.portal);
+        // The client stays locked until the script ran, so It's always unlocked
         self.enable_char();
+        if let Err(err) = res {
+            log::error!("Portal script of {} failed: {err:?}", req.portal);
+        }
+        Ok(())
+    }
+
+    fn run_portal_script(&mut self, ctx: &mut GameContext, portal: &str) -> anyhow::Result<()> {
+        let Some((_, portal_meta)) = self.field_meta.get_portal_by_name(portal) else {
+            log::warn!("Unknown portal {portal}");
+            return Ok(());
+        };
+        let Some(script) = portal_meta.script.as_deref() else {
+            log::warn!("Portal {portal} has no script");
+            return Ok(());
+        };
+        if !field!(ctx).is_portal_open(portal) {
+            log::info!("Portal {portal} is not opened yet");
+            return Ok(());
+        }
+
+        let chr = &self.session.char;
+        let dist = (chr.pos.cast::<i64>() - portal_meta.pos.cast::<i64>()).square_length();
+        if dist > PORTAL_SCRIPT_RANGE * PORTAL_SCRIPT_RANGE {
+            log::warn!(
+                target: "audit",
+                "Character {} ({:?}) used portal {portal} from {:?}",
+                chr.name,
+                chr.id,
+                chr.pos
+            );
+            return Ok(());
+        }
+
+        match self.services.game.scripts.get_portal_script(script) {
+            Some(mut handle) => {
+                handle.enter(&mut self.session.char, portal)?;
+                self.apply_script_effects(ctx)?;
+            }
+            None => log::info!("Portal script not implemented: {script}"),
+        }
         Ok(())
     }
 
@@ -937,7 +1408,10 @@ impl GameSession {
             chr.pos = pos;
             chr.fh = fh.unwrap_or(chr.fh);
         }
-        field!(ctx).handle_user_move(self.char_id(), req.move_path)?;
+        // Hidden GMs don't show their movement
+        if !chr.hidden {
+            field!(ctx).handle_user_move(self.char_id(), req.move_path)?;
+        }
         Ok(())
     }
 
@@ -1045,9 +1519,15 @@ impl GameSession {
     fn handle_user_hit_req(
         &mut self,
         _ctx: &mut GameContext,
-        _req: UserHitReq,
+        req: UserHitReq,
     ) -> anyhow::Result<()> {
-        //dbg!(&req);
+        let char = &mut self.session.char;
+        // Invincible GMs don't take any damage
+        if !char.invincible {
+            // Damage above i32::MAX must not wrap around and heal the character
+            let dmg = i32::try_from(char.hit_damage(&req.hit)).unwrap_or(i32::MAX);
+            char.stats.update_hp(dmg.saturating_neg());
+        }
         Ok(())
     }
 
@@ -1062,10 +1542,16 @@ impl GameSession {
 
     fn handle_skill_cancel(
         &mut self,
-        _ctx: &mut GameContext,
+        ctx: &mut GameContext,
         req: UserSkillCancelReq,
     ) -> anyhow::Result<()> {
         self.session.char.buffs.cancel_by_id(req.skill_id.into());
+        // Cancelling a summon skill removes the summon
+        if let Some(id) = self.session.char.summons.get_by_skill(req.skill_id) {
+            self.session
+                .char
+                .remove_summon(ctx, id, SummonLeaveType::Default)?;
+        }
         Ok(())
     }
 
@@ -1099,6 +1585,19 @@ impl GameSession {
                 },
             };
 
+            // Items dropped on a reactor are consumed by it
+            if let DropTypeValue::Item(item) = drop.value {
+                let input = ReactorInput::ItemDrop {
+                    item,
+                    quantity: drop.quantity,
+                    pos: drop.pos,
+                };
+                if field!(ctx).drop_item_on_reactor(input, &self.session.char)? {
+                    self.enable_char();
+                    return Ok(());
+                }
+            }
+
             field!(ctx).add_drop(drop)?;
         } else {
             let to = (req.inv_type, req.to).try_into()?;
@@ -1113,7 +1612,7 @@ impl GameSession {
         &mut self,
         ctx: &mut GameContext,
         field: FieldId,
-        spawn_portal: Option<&'static str>,
+        spawn_portal: Option<&str>,
     ) -> anyhow::Result<()> {
         let field_meta = self.meta().get_field(field).unwrap();
         let spawn = match spawn_portal {
@@ -1122,6 +1621,7 @@ impl GameSession {
         };
 
         ctx.room.change_room(field)?;
+        self.shop = None;
         self.session.char.transfer_map(field, spawn);
         self.field_id = field;
         self.field_meta = field_meta;
@@ -1142,6 +1642,11 @@ impl GameSession {
                 .field_meta
                 .get_target_field(&req.portal)
                 .ok_or_else(|| anyhow::format_err!("Invalid portal"))?;
+            if !field!(ctx).is_portal_open(&req.portal) {
+                log::info!("Portal {} is not opened yet", req.portal);
+                self.enable_char();
+                return Ok(());
+            }
             (target, portal.tn.as_deref())
         };
 
@@ -1191,6 +1696,7 @@ impl GameSession {
                     )?;
                 }
 
+                self.session.char.dirty.key_map = true;
                 log::info!("Updated func key");
             }
             _ => {
@@ -1206,11 +1712,12 @@ impl GameSession {
         req: QuickslotKeyMapChangedReq,
     ) -> anyhow::Result<()> {
         self.session.char.key_map.set_quick_slots(req.0);
+        self.session.char.dirty.key_map = true;
         Ok(())
     }
 
     fn handle_chat_msg(&mut self, ctx: &mut GameContext, req: ChatMsgReq) -> anyhow::Result<()> {
-        let admin = false;
+        let admin = self.gm_level() > GmLevel::Player;
         if let Some(s) = req.msg.strip_prefix('@') {
             log::info!("repl: {}", s);
             let repl_resp = self.handle_repl(ctx, s)?;
@@ -1235,84 +1742,7 @@ impl GameSession {
 
     fn set_field(&self, char_data: bool) -> SetFieldResp {
         let field_data = if char_data {
-            let char = &self.session.char;
-            let inv = &char.inventory;
-
-            let equipped: ShroomIndexListZ16<Item> = self
-                .session
-                .char
-                .inventory
-                .invs
-                .equipped
-                .item_slots()
-                .map(|(slot, item)| (slot.0 as u16, Item::Equip(item.0.item.as_ref().into())))
-                .collect();
-
-            let equip: ShroomIndexListZ16<Item> = self
-                .session
-                .char
-                .inventory
-                .invs
-                .equip
-                .item_slots()
-                .map(|(slot, item)| (slot as u16 + 1, Item::Equip(item.item.as_ref().into())))
-                .collect();
-
-            let char_equipped = CharDataEquipped {
-                equipped,
-                equip,
-                ..Default::default()
-            };
-
-            let skillrecords: ShroomList16<SkillInfo> =
-                self.session.char.skills.get_skill_info().into();
-
-            let quests = self
-                .session
-                .char
-                .quests
-                .active_quest_records()
-                .map(|q| QuestInfo {
-                    id: q.0,
-                    value: q.1,
-                });
-
-            let completed =
-                self.session
-                    .char
-                    .quests
-                    .completed_records()
-                    .map(|q| QuestCompleteInfo {
-                        id: q.0,
-                        time: q.1.try_into().unwrap(),
-                    });
-
-            let char_data = CharDataAll {
-                stat: CharDataStat {
-                    stat: char.get_all_stats(),
-                    friend_max: 30,
-                    linked_character: None.into(),
-                },
-                money: char.money(),
-                invsize: char.inventory.inv_size(),
-                equipextslotexpiration: ShroomExpirationTime::never(),
-                equipped: char_equipped,
-                consumeinv: inv.get_stack_inv_list(InventoryType::Consume),
-                setupinv: inv.get_stack_inv_list(InventoryType::Install),
-                etcinv: inv.get_stack_inv_list(InventoryType::Etc),
-                cashinv: inv.get_cash_inv_list(),
-                skillrecords,
-                skllcooltime: ShroomList16::default(),
-                quests: quests.collect(),
-                questscompleted: completed.collect(),
-                minigamerecords: ShroomList16::default(),
-                socialrecords: SocialRecords::default(),
-                teleportrockinfo: TeleportRockInfo::default(),
-                newyearcards: ShroomList16::default(),
-                questrecordsexpired: ShroomList16::default(),
-                questcompleteold: ShroomList16::default(),
-                visitorquestloginfo: ShroomList16::default(),
-            };
+            let char_data = char_data_all(&self.session.char);
 
             Either::Left(FieldCharData {
                 seed: CrcSeed {
@@ -1353,3 +1783,67 @@ impl GameSession {
         }
     }
 }
+
+/// Encodes all data of the character, which is sent when the client enters the game
+pub(crate) fn char_data_all(char: &Character) -> CharDataAll {
+    let inv = &char.inventory;
+
+    let equipped: ShroomIndexListZ16<Item> = inv
+        .invs
+        .equipped
+        .item_slots()
+        .map(|(slot, item)| (slot.0 as u16, Item::Equip(item.0.item.as_ref().into())))
+        .collect();
+
+    let equip: ShroomIndexListZ16<Item> = inv
+        .invs
+        .equip
+        .item_slots()
+        .map(|(slot, item)| (slot as u16 + 1, Item::Equip(item.item.as_ref().into())))
+        .collect();
+
+    let char_equipped = CharDataEquipped {
+        equipped,
+        equip,
+        ..Default::default()
+    };
+
+    let skillrecords: ShroomList16<SkillInfo> = char.skills.get_skill_info().into();
+
+    let quests = char.quests.active_quest_records().map(|q| QuestInfo {
+        id: q.0,
+        value: q.1,
+    });
+
+    let completed = char.quests.completed_records().map(|q| QuestCompleteInfo {
+        id: q.0,
+        time: q.1.try_into().unwrap(),
+    });
+
+    CharDataAll {
+        stat: CharDataStat {
+            stat: char.get_all_stats(),
+            friend_max: char.buddies.capacity(),
+            linked_character: None.into(),
+        },
+        money: char.money(),
+        invsize: inv.inv_size(),
+        equipextslotexpiration: ShroomExpirationTime::never(),
+        equipped: char_equipped,
+        consumeinv: inv.get_stack_inv_list(InventoryType::Consume),
+        setupinv: inv.get_stack_inv_list(InventoryType::Install),
+        etcinv: inv.get_stack_inv_list(InventoryType::Etc),
+        cashinv: inv.get_cash_inv_list(),
+        skillrecords,
+        skllcooltime: char.skills.get_cooltime_info(char.last_update).into(),
+        quests: quests.collect(),
+        questscompleted: completed.collect(),
+        minigamerecords: ShroomList16::default(),
+        socialrecords: SocialRecords::default(),
+        teleportrockinfo: TeleportRockInfo::default(),
+        newyearcards: ShroomList16::default(),
+        questrecordsexpired: ShroomList16::default(),
+        questcompleteold: ShroomList16::default(),
+        visitorquestloginfo: ShroomList16::default(),
+    }
+}
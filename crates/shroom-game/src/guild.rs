@@ -0,0 +1,499 @@
+use shroom_data::services::guild::{GuildId, GuildMemberData};
+use shroom_meta::id::NpcId;
+use shroom_proto95::game::{
+    guild::{
+        GuildGradeNames, GuildInvite, GuildJoin, GuildMarkChanged, GuildMemberGrade,
+        GuildMemberLeave, GuildMemberLevelJob, GuildMemberLogin, GuildNotice, GuildPoints,
+        GuildReq, GuildResultReq, GuildResultResp,
+    },
+    user::remote::{GuildMarkData, UserGuildMarkChangedResp, UserGuildNameChangedResp},
+};
+
+use crate::{
+    game::{GameContext, GameMessage, GameSession},
+    services::guild::{guild_mark_from_proto, Guild, GuildError},
+};
+
+/// Npc, which creates new guilds
+pub const GUILD_NPC: NpcId = NpcId(2010007);
+/// Npc, which changes the guild mark
+pub const GUILD_MARK_NPC: NpcId = NpcId(2010008);
+
+pub const GUILD_CREATE_FEE: u32 = 1_500_000;
+pub const GUILD_MARK_FEE: u32 = 5_000_000;
+pub const GUILD_MIN_LEVEL: u8 = 10;
+
+impl GameSession {
+    fn guild_member_data(&self) -> GuildMemberData {
+        let chr = &self.session.char;
+        GuildMemberData {
+            id: chr.id,
+            name: chr.name.clone(),
+            job: chr.stats.job,
+            level: chr.stats.level,
+            grade: 0,
+            commitment: 0,
+        }
+    }
+
+    fn send_guild(&self, guild: &Guild, msg: GuildResultResp) -> anyhow::Result<()> {
+        self.services
+            .game
+            .sessions
+            .send_all_encode(guild.online_ids(), msg)?;
+        Ok(())
+    }
+
+    /// Persists the guild in the background
+    fn persist_guild(&self, id: GuildId) {
+        let svc = self.services.game.clone();
+        tokio::spawn(async move {
+            if let Err(err) = svc.guild.persist(&svc.data, id).await {
+                log::error!("Unable to persist guild {id}: {err:?}");
+            }
+        });
+    }
+
+    /// Notifies the online members, that the name or the mark of the guild changed
+    fn notify_guild_changed(&self, guild: &Guild) {
+        for id in guild.online_ids() {
+            self.services
+                .game
+                .sessions
+                .send_to(id, GameMessage::GuildChanged);
+        }
+    }
+
+    /// Shows the current guild name and mark of the character to the field
+    pub(crate) fn broadcast_guild_look(&self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        let char_id = self.char_id();
+        let (guild_name, guild_mark) = self
+            .services
+            .game
+            .guild
+            .get_guild_of(char_id)
+            .map(|guild| (guild.data.name.clone(), guild.mark_data()))
+            .unwrap_or_default();
+
+        ctx.room.tx.broadcast_filter_encode(
+            UserGuildNameChangedResp {
+                char_id,
+                guild_name,
+            },
+            char_id,
+        )?;
+        ctx.room.tx.broadcast_filter_encode(
+            UserGuildMarkChangedResp {
+                char_id,
+                guild_mark,
+            },
+            char_id,
+        )?;
+        Ok(())
+    }
+
+    fn load_guild_resp(&self) -> GuildResultResp {
+        GuildResultResp::LoadGuildDone(
+            self.services
+                .game
+                .guild
+                .get_guild_of(self.char_id())
+                .map(|guild| guild.to_proto())
+                .into(),
+        )
+    }
+
+    /// Marks the character as online in the guild, the members are only
+    /// notified about the login and not about field changes
+    pub(crate) fn on_guild_world_enter(
+        &mut self,
+        ctx: &mut GameContext,
+        login: bool,
+    ) -> anyhow::Result<()> {
+        let id = self.char_id();
+        let Some(guild) = self.services.game.guild.set_online(id, true) else {
+            return Ok(());
+        };
+
+        ctx.socket.reply(self.load_guild_resp())?;
+        if login {
+            self.services.game.sessions.send_all_encode(
+                guild.online_ids().filter(|m| *m != id),
+                GuildResultResp::NotifyLoginOrLogout(GuildMemberLogin {
+                    guild_id: guild.id(),
+                    char_id: id,
+                    online: true,
+                }),
+            )?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn on_guild_world_leave(&mut self) -> anyhow::Result<()> {
+        let id = self.char_id();
+        let Some(guild) = self.services.game.guild.set_online(id, false) else {
+            return Ok(());
+        };
+
+        self.send_guild(
+            &guild,
+            GuildResultResp::NotifyLoginOrLogout(GuildMemberLogin {
+                guild_id: guild.id(),
+                char_id: id,
+                online: false,
+            }),
+        )
+    }
+
+    /// Updates the guild about a changed level or job, must be called before the stats are flushed
+    pub fn update_guild_stats(&mut self) -> anyhow::Result<()> {
+        let stats = &self.session.char.stats;
+        if !stats.is_level_job_changed() {
+            return Ok(());
+        }
+
+        let (id, level, job) = (self.char_id(), stats.level, stats.job);
+        let Some(guild) = self.services.game.guild.update_member(id, |m| {
+            m.level = level;
+            m.job = job;
+        }) else {
+            return Ok(());
+        };
+
+        self.send_guild(
+            &guild,
+            GuildResultResp::ChangeLevelOrJob(GuildMemberLevelJob {
+                guild_id: guild.id(),
+                char_id: id,
+                level: level as u32,
+                job: job as u32,
+            }),
+        )?;
+        self.persist_guild(guild.id());
+        Ok(())
+    }
+
+    /// Opens the dialogs of the guild npcs, returns false for other npcs
+    pub fn open_guild_npc(&mut self, ctx: &mut GameContext, npc_id: NpcId) -> anyhow::Result<bool> {
+        let id = self.char_id();
+        let guild = self.services.game.guild.get_guild_of(id);
+        let resp = match npc_id {
+            GUILD_NPC if guild.is_some() => GuildResultResp::CreateNewGuildAlreadyJoined(()),
+            GUILD_NPC if self.session.char.stats.level < GUILD_MIN_LEVEL => {
+                GuildResultResp::CreateNewGuildBeginner(())
+            }
+            GUILD_NPC => GuildResultResp::InputGuildName(()),
+            // Only the master can change the mark, everyone else talks to the npc script
+            GUILD_MARK_NPC
+                if guild
+                    .as_ref()
+                    .and_then(|guild| guild.get_member(id))
+                    .is_some_and(|m| m.grade == 1) =>
+            {
+                GuildResultResp::InputMark(())
+            }
+            _ => return Ok(false),
+        };
+
+        if matches!(
+            resp,
+            GuildResultResp::InputGuildName(()) | GuildResultResp::InputMark(())
+        ) {
+            self.guild_npc = Some(npc_id);
+        }
+        ctx.socket.reply(resp)?;
+        Ok(true)
+    }
+
+    pub fn handle_guild_req(&mut self, ctx: &mut GameContext, req: GuildReq) -> anyhow::Result<()> {
+        let id = self.char_id();
+        let guild_svc = &self.services.game.guild;
+        match req {
+            GuildReq::LoadGuild(()) => {
+                ctx.socket.reply(self.load_guild_resp())?;
+            }
+            GuildReq::CheckGuildName(name) => self.create_guild(ctx, name)?,
+            GuildReq::InviteGuild(name) => {
+                let Some(target) = self.services.game.online.get_by_name(self.world_id, &name)
+                else {
+                    ctx.socket
+                        .reply(GuildResultResp::JoinGuildUnknownUser(()))?;
+                    return Ok(());
+                };
+
+                let resp = match guild_svc.invite(id, target.id) {
+                    Ok(guild) => {
+                        let chr = &self.session.char;
+                        self.services.game.sessions.send_to_encode(
+                            target.id,
+                            GuildResultResp::Invite(GuildInvite {
+                                guild_id: guild.id(),
+                                inviter: chr.name.clone(),
+                                level: chr.stats.level as u32,
+                                job: chr.stats.job as u32,
+                                u1: 0,
+                            }),
+                        )?;
+                        return Ok(());
+                    }
+                    Err(GuildError::AlreadyJoined) => GuildResultResp::JoinGuildAlreadyJoined(()),
+                    Err(GuildError::AlreadyInvited) => {
+                        GuildResultResp::InviteGuildAlreadyInvited(target.name)
+                    }
+                    Err(GuildError::Full) => GuildResultResp::JoinGuildAlreadyFull(()),
+                    Err(_) => GuildResultResp::JoinGuildUnknown(()),
+                };
+                ctx.socket.reply(resp)?;
+            }
+            GuildReq::JoinGuild(req) => {
+                if req.char_id != id {
+                    anyhow::bail!("Invalid guild join for: {:?}", req.char_id);
+                }
+
+                match guild_svc.join(self.guild_member_data(), req.guild_id) {
+                    Ok(guild) => {
+                        let member = guild.get_member(id).expect("member");
+                        self.send_guild(
+                            &guild,
+                            GuildResultResp::JoinGuildDone(GuildJoin {
+                                guild_id: guild.id(),
+                                char_id: id,
+                                member: guild.member_to_proto(member),
+                            }),
+                        )?;
+                        ctx.socket.reply(self.load_guild_resp())?;
+                        self.broadcast_guild_look(ctx)?;
+                        self.persist_guild(guild.id());
+                    }
+                    Err(GuildError::AlreadyJoined) => {
+                        ctx.socket
+                            .reply(GuildResultResp::JoinGuildAlreadyJoined(()))?;
+                    }
+                    Err(GuildError::Full) => {
+                        ctx.socket
+                            .reply(GuildResultResp::JoinGuildAlreadyFull(()))?;
+                    }
+                    Err(_) => {
+                        ctx.socket.reply(GuildResultResp::JoinGuildUnknown(()))?;
+                    }
+                }
+            }
+            GuildReq::WithdrawGuild(req) => {
+                if req.char_id != id {
+                    anyhow::bail!("Invalid guild withdraw for: {:?}", req.char_id);
+                }
+
+                match guild_svc.withdraw(id) {
+                    Ok((guild, member)) => {
+                        let msg = || {
+                            GuildResultResp::WithdrawGuildDone(GuildMemberLeave {
+                                guild_id: guild.id(),
+                                char_id: id,
+                                name: member.name.clone(),
+                            })
+                        };
+                        ctx.socket.reply(msg())?;
+                        self.send_guild(&guild, msg())?;
+                        self.broadcast_guild_look(ctx)?;
+                        self.persist_guild(guild.id());
+                    }
+                    Err(_) => {
+                        ctx.socket
+                            .reply(GuildResultResp::WithdrawGuildNotJoined(()))?;
+                    }
+                }
+            }
+            GuildReq::KickGuild(req) => match guild_svc.kick(id, req.char_id) {
+                Ok((guild, member)) => {
+                    let msg = || {
+                        GuildResultResp::KickGuildDone(GuildMemberLeave {
+                            guild_id: guild.id(),
+                            char_id: member.id,
+                            name: member.name.clone(),
+                        })
+                    };
+                    let sessions = &self.services.game.sessions;
+                    sessions.send_to_encode(member.id, msg())?;
+                    sessions.send_to(member.id, GameMessage::GuildChanged);
+                    self.send_guild(&guild, msg())?;
+                    self.persist_guild(guild.id());
+                }
+                Err(_) => {
+                    ctx.socket.reply(GuildResultResp::KickGuildNotJoined(()))?;
+                }
+            },
+            GuildReq::RemoveGuild(()) => match guild_svc.disband(id) {
+                Ok(guild) => {
+                    self.send_guild(&guild, GuildResultResp::RemoveGuildDone(guild.id()))?;
+                    self.notify_guild_changed(&guild);
+                    self.persist_guild(guild.id());
+                }
+                Err(err) => {
+                    log::info!("Unable to disband guild: {err}");
+                }
+            },
+            GuildReq::SetGradeName(grades) => match guild_svc.set_grade_names(id, grades) {
+                Ok(guild) => {
+                    self.send_guild(
+                        &guild,
+                        GuildResultResp::SetGradeNameDone(GuildGradeNames {
+                            guild_id: guild.id(),
+                            grades: guild.data.grades.clone(),
+                        }),
+                    )?;
+                    self.persist_guild(guild.id());
+                }
+                Err(err) => {
+                    log::info!("Unable to set guild grade names: {err}");
+                }
+            },
+            GuildReq::SetMemberGrade(req) => {
+                match guild_svc.set_member_grade(id, req.char_id, req.grade) {
+                    Ok(guild) => {
+                        self.send_guild(
+                            &guild,
+                            GuildResultResp::SetMemberGradeDone(GuildMemberGrade {
+                                guild_id: guild.id(),
+                                char_id: req.char_id,
+                                grade: req.grade,
+                            }),
+                        )?;
+                        self.persist_guild(guild.id());
+                    }
+                    Err(err) => {
+                        log::info!("Unable to set guild member grade: {err}");
+                    }
+                }
+            }
+            GuildReq::SetMark(mark) => self.set_guild_mark(mark)?,
+            GuildReq::SetNotice(notice) => match guild_svc.set_notice(id, notice) {
+                Ok(guild) => {
+                    self.send_guild(
+                        &guild,
+                        GuildResultResp::SetNoticeDone(GuildNotice {
+                            guild_id: guild.id(),
+                            notice: guild.data.notice.clone(),
+                        }),
+                    )?;
+                    self.persist_guild(guild.id());
+                }
+                Err(err) => {
+                    log::info!("Unable to set guild notice: {err}");
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    fn create_guild(&mut self, ctx: &mut GameContext, name: String) -> anyhow::Result<()> {
+        if self.guild_npc.take() != Some(GUILD_NPC) {
+            anyhow::bail!("Guild creation without the guild npc");
+        }
+        if self.session.char.money() < GUILD_CREATE_FEE {
+            ctx.socket
+                .reply(GuildResultResp::CreateNewGuildUnknown(()))?;
+            return Ok(());
+        }
+
+        let resp = match self
+            .services
+            .game
+            .guild
+            .create(&name, self.guild_member_data())
+        {
+            Ok(guild) => {
+                self.session.char.update_mesos(-(GUILD_CREATE_FEE as i32));
+                self.broadcast_guild_look(ctx)?;
+                self.persist_guild(guild.id());
+                self.load_guild_resp()
+            }
+            Err(GuildError::AlreadyJoined) => GuildResultResp::CreateNewGuildAlreadyJoined(()),
+            Err(GuildError::NameTaken) => GuildResultResp::CheckGuildNameAlreadyUsed(()),
+            Err(_) => GuildResultResp::CreateNewGuildUnknown(()),
+        };
+        ctx.socket.reply(resp)?;
+        Ok(())
+    }
+
+    fn set_guild_mark(&mut self, mark: GuildMarkData) -> anyhow::Result<()> {
+        if self.guild_npc.take() != Some(GUILD_MARK_NPC) {
+            anyhow::bail!("Guild mark change without the mark npc");
+        }
+        if self.session.char.money() < GUILD_MARK_FEE {
+            return Ok(());
+        }
+
+        let guild = match self
+            .services
+            .game
+            .guild
+            .set_mark(self.char_id(), guild_mark_from_proto(&mark))
+        {
+            Ok(guild) => guild,
+            Err(err) => {
+                log::info!("Unable to set guild mark: {err}");
+                return Ok(());
+            }
+        };
+
+        self.session.char.update_mesos(-(GUILD_MARK_FEE as i32));
+        self.send_guild(
+            &guild,
+            GuildResultResp::SetMarkDone(GuildMarkChanged {
+                guild_id: guild.id(),
+                mark,
+            }),
+        )?;
+        self.notify_guild_changed(&guild);
+        self.persist_guild(guild.id());
+        Ok(())
+    }
+
+    /// Adds guild points for the character, used by quests and commands
+    pub fn add_guild_points(&mut self, points: u32) -> anyhow::Result<()> {
+        let guild = self
+            .services
+            .game
+            .guild
+            .add_points(self.char_id(), points)?;
+        self.send_guild(
+            &guild,
+            GuildResultResp::IncPointDone(GuildPoints {
+                guild_id: guild.id(),
+                points: guild.data.points,
+            }),
+        )?;
+        self.persist_guild(guild.id());
+        Ok(())
+    }
+
+    pub fn handle_guild_result_req(
+        &mut self,
+        _ctx: &mut GameContext,
+        req: GuildResultReq,
+    ) -> anyhow::Result<()> {
+        let Some(guild) = self.services.game.guild.decline(self.char_id()) else {
+            return Ok(());
+        };
+        log::info!("Guild invite declined({}): {}", req.ty, guild.data.name);
+
+        let Some(inviter) = self
+            .services
+            .game
+            .online
+            .get_by_name(self.world_id, &req.inviter)
+        else {
+            return Ok(());
+        };
+        if guild.get_member(inviter.id).is_none() {
+            return Ok(());
+        }
+
+        self.services.game.sessions.send_to_encode(
+            inviter.id,
+            GuildResultResp::InviteGuildRejected(self.session.char.name.clone()),
+        )?;
+        Ok(())
+    }
+}
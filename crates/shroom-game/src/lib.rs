@@ -1,7 +1,20 @@
+pub mod autosave;
+pub mod ban;
+pub mod buddy;
+pub mod cash_shop;
+pub mod channel;
+pub mod chat;
 pub mod field;
 pub mod game;
+pub mod guild;
+pub mod merchant;
+pub mod party;
 pub mod repl;
 pub mod services;
 pub mod session;
+pub mod shop;
 pub mod system;
+pub mod trade;
+pub mod trunk;
+pub mod whisper;
 pub mod life;
@@ -0,0 +1,156 @@
+use shroom_data::services::buddy::{BuddyEntry, BuddyStatus};
+use shroom_meta::id::CharacterId;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum BuddyError {
+    #[error("Buddy list is full")]
+    Full,
+    #[error("Already in the buddy list")]
+    AlreadySet,
+    #[error("Unknown buddy")]
+    Unknown,
+}
+
+/// Buddy list of a character, pending requests
+/// don't count towards the capacity
+#[derive(Debug)]
+pub struct CharBuddies {
+    capacity: u8,
+    entries: Vec<BuddyEntry>,
+}
+
+impl CharBuddies {
+    pub fn new(capacity: u8, entries: Vec<BuddyEntry>) -> Self {
+        Self { capacity, entries }
+    }
+
+    pub fn capacity(&self) -> u8 {
+        self.capacity
+    }
+
+    pub fn get(&self, id: CharacterId) -> Option<&BuddyEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&BuddyEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Entries which are shown in the list
+    pub fn listed(&self) -> impl Iterator<Item = &BuddyEntry> {
+        self.entries
+            .iter()
+            .filter(|e| e.status != BuddyStatus::Pending)
+    }
+
+    /// Requests from other characters, which are not answered yet
+    pub fn pending(&self) -> impl Iterator<Item = &BuddyEntry> {
+        self.entries
+            .iter()
+            .filter(|e| e.status == BuddyStatus::Pending)
+    }
+
+    pub fn accepted_ids(&self) -> impl Iterator<Item = CharacterId> + '_ {
+        self.entries
+            .iter()
+            .filter(|e| e.status == BuddyStatus::Accepted)
+            .map(|e| e.id)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.listed().count() >= self.capacity as usize
+    }
+
+    /// Adds a new entry, which is not pending
+    pub fn add(&mut self, entry: BuddyEntry) -> Result<&BuddyEntry, BuddyError> {
+        if self.get(entry.id).is_some_and(|e| e.status != BuddyStatus::Pending) {
+            return Err(BuddyError::AlreadySet);
+        }
+        if entry.status != BuddyStatus::Pending && self.is_full() {
+            return Err(BuddyError::Full);
+        }
+
+        self.remove(entry.id);
+        self.entries.push(entry);
+        Ok(self.entries.last().expect("entry"))
+    }
+
+    pub fn set_group(&mut self, id: CharacterId, group: String) -> Result<&BuddyEntry, BuddyError> {
+        let entry = self.get_mut(id)?;
+        entry.group = group;
+        Ok(entry)
+    }
+
+    pub fn set_status(
+        &mut self,
+        id: CharacterId,
+        status: BuddyStatus,
+    ) -> Result<&BuddyEntry, BuddyError> {
+        if status != BuddyStatus::Pending
+            && self.get(id).is_some_and(|e| e.status == BuddyStatus::Pending)
+            && self.is_full()
+        {
+            return Err(BuddyError::Full);
+        }
+
+        let entry = self.get_mut(id)?;
+        entry.status = status;
+        Ok(entry)
+    }
+
+    pub fn remove(&mut self, id: CharacterId) -> Option<BuddyEntry> {
+        let ix = self.entries.iter().position(|e| e.id == id)?;
+        Some(self.entries.remove(ix))
+    }
+
+    fn get_mut(&mut self, id: CharacterId) -> Result<&mut BuddyEntry, BuddyError> {
+        self.entries
+            .iter_mut()
+            .find(|e| e.id == id)
+            .ok_or(BuddyError::Unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u32, status: BuddyStatus) -> BuddyEntry {
+        BuddyEntry {
+            id: CharacterId(id),
+            name: format!("buddy{id}"),
+            group: "Default Group".to_string(),
+            status,
+        }
+    }
+
+    #[test]
+    fn buddy_capacity() {
+        let mut buddies = CharBuddies::new(2, vec![]);
+        buddies.add(entry(1, BuddyStatus::Accepted)).unwrap();
+        buddies.add(entry(2, BuddyStatus::Requested)).unwrap();
+        assert_eq!(
+            buddies.add(entry(1, BuddyStatus::Requested)).unwrap_err(),
+            BuddyError::AlreadySet
+        );
+
+        // Pending requests are not counted
+        buddies.add(entry(3, BuddyStatus::Pending)).unwrap();
+        assert_eq!(buddies.pending().count(), 1);
+        assert_eq!(
+            buddies
+                .set_status(CharacterId(3), BuddyStatus::Accepted)
+                .unwrap_err(),
+            BuddyError::Full
+        );
+
+        buddies.remove(CharacterId(2));
+        buddies
+            .set_status(CharacterId(3), BuddyStatus::Accepted)
+            .unwrap();
+        assert_eq!(buddies.accepted_ids().count(), 2);
+        assert!(buddies.get_by_name("BUDDY3").is_some());
+    }
+}
@@ -53,21 +53,8 @@ impl ArcherHandler {
                 log::info!("hawk skill: {skill_id:?} {d:?}");
 
                 d.buff.apply_buff(req.t, buffs);
-                let chr = &ctx.chr;
-                
-                ctx.spawn_summon(
-                    Summon {
-                        pos: chr.pos,
-                        fh: chr.fh,
-                        skill_id,
-                        skill_level: slvl,
-                        char_level: chr.stats.level,
-                        char_id: chr.id,
-                        move_ability: d.summon.move_ability,
-                        assist_type: d.summon.assist_type,
-                        expiration: req.t + d.summon.dur,
-                    },
-                )?;
+                let summ = Summon::new(ctx.chr, skill_id, slvl, &d.summon, req.t);
+                ctx.spawn_summon(summ)?;
             }
         }
 
@@ -54,21 +54,8 @@ impl MageHandler {
             | MageSkillData::Elquines(d)
             | MageSkillData::Ifrit(d) => {
                 d.buff.apply_buff(req.t, buffs);
-                let chr = &ctx.chr;
-                let summ = Summon {
-                    pos: chr.pos,
-                    fh: chr.fh,
-                    skill_id,
-                    skill_level: slvl,
-                    char_level: chr.stats.level,
-                    char_id: chr.id,
-                    move_ability: d.summon.move_ability,
-                    assist_type: d.summon.assist_type,
-                    expiration: req.t + d.summon.dur,
-                };
-                ctx.spawn_summon(
-                    summ
-                )?;
+                let summ = Summon::new(ctx.chr, skill_id, slvl, &d.summon, req.t);
+                ctx.spawn_summon(summ)?;
             }
             MageSkillData::MysticDoor(d) => {
                 log::info!("Mystic door: {:?}", d);
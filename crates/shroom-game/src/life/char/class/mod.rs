@@ -41,17 +41,62 @@ impl<'a, 'b> ClassContext<'a, 'b> {
         targets: &[AttackTargetInfo],
         debuff: &Option<Box<dyn MobApplyDebuff>>,
     ) -> anyhow::Result<()> {
+        if let Some(skill_id) = skill {
+            if !self.start_cooldown(skill_id) {
+                return Ok(());
+            }
+        }
+
+        let (limits, skill_violation) = self.chr.attack_limits(skill);
+        let mut atk = limits.check(targets);
+        atk.violations.extend(skill_violation);
+        for violation in atk.violations.iter() {
+            log::warn!(
+                target: "audit",
+                "Character {} ({:?}) attack with skill {skill:?} violates {limits:?}: {violation:?}",
+                self.chr.name,
+                self.chr.id
+            );
+        }
+
         let ctx = &mut self.ctx;
         let mut field = crate::field!(ctx);
         let debuff = Box::new(debuff);
-        for target in targets {
-            let dmg = target.hits.iter().sum::<u32>();
+        for (mob_id, dmg) in atk.damage {
             let attacker: &Character = self.chr;
-            field.attack_mob(target.mob_id, dmg, attacker, &debuff, skill.unwrap_or(SkillId(0)))?;
+            field.attack_mob(mob_id, dmg, attacker, &debuff, skill.unwrap_or(SkillId(0)))?;
         }
 
         Ok(())
     }
+
+    /// Starts the cooldown of an attack skill,
+    /// returns false if the skill is still on cooldown
+    fn start_cooldown(&mut self, skill_id: SkillId) -> bool {
+        let t = self.ctx.time();
+        if let Some(cd) = self.chr.skills.remaining_cooldown(skill_id, t) {
+            log::warn!(
+                target: "audit",
+                "Character {} ({:?}) attacked with skill {:?} with {cd:?} cooldown left",
+                self.chr.name,
+                self.chr.id,
+                skill_id
+            );
+            return false;
+        }
+
+        let cooltime = self
+            .chr
+            .skills
+            .get(skill_id)
+            .ok()
+            .and_then(|skill| skill.meta.cooltime_dur(skill.level as u8));
+        if let Some(cd) = cooltime {
+            self.chr.skills.set_cooldown(skill_id, t, cd);
+            self.chr.dirty.skills = true;
+        }
+        true
+    }
 }
 
 pub struct UseSkillData {
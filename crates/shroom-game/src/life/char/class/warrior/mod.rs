@@ -73,18 +73,7 @@ impl WarriorHandler {
             }
             WarriorSkillData::Beholder(beholder) => {
                 beholder.buff.apply_buff(req.t, buffs);
-                let chr = &ctx.chr;
-                let summ = Summon {
-                    pos: chr.pos,
-                    fh: chr.fh,
-                    skill_id: look_up_id,
-                    skill_level: slvl,
-                    char_level: chr.stats.level,
-                    char_id: chr.id,
-                    move_ability: beholder.summon.move_ability,
-                    assist_type: beholder.summon.assist_type,
-                    expiration: req.t + beholder.summon.dur,
-                };
+                let summ = Summon::new(ctx.chr, look_up_id, slvl, &beholder.summon, req.t);
                 ctx.spawn_summon(summ)?;
                 log::info!("Applying beholder...");
             }
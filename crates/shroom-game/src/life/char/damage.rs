@@ -0,0 +1,342 @@
+use shroom_data::model::inv::EquipSlot;
+use shroom_meta::{
+    buffs::char::{ComboCounter, ShadowPartner},
+    id::{item_id::WeaponType, ObjectId, SkillId},
+};
+use shroom_proto95::{
+    game::user::{AttackTargetInfo, UserHit},
+    shared::inventory::CharEquipSlot,
+};
+
+use super::Character;
+
+/// Damage cap of a single hit in the client
+pub const MAX_HIT_DAMAGE: u32 = 199_999;
+
+/// Slack on top of the calculated ceiling in percent, covers bonuses which are
+/// not tracked by the server like elemental weaknesses
+const DAMAGE_TOLERANCE: u32 = 250;
+
+/// Limits of a single attack, derived from the stats of the attacker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttackLimits {
+    pub max_targets: usize,
+    pub max_hits: usize,
+    /// Lowest damage of a regular hit before the defense of the mob, raised by the mastery
+    pub min_damage: u32,
+    pub max_damage: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DamageViolation {
+    UnknownSkill(SkillId),
+    TooManyTargets {
+        count: usize,
+        max: usize,
+    },
+    TooManyHits {
+        mob: ObjectId,
+        count: usize,
+        max: usize,
+    },
+    DamageExceeded {
+        mob: ObjectId,
+        damage: u32,
+        max: u32,
+    },
+}
+
+/// Damage of an attack after clamping it to the limits
+#[derive(Debug, Default)]
+pub struct CheckedAttack {
+    pub damage: Vec<(ObjectId, u32)>,
+    pub violations: Vec<DamageViolation>,
+}
+
+impl AttackLimits {
+    /// Sums up the hits on a single target, hits over the limits are clamped
+    pub fn clamp_hits(
+        &self,
+        mob: ObjectId,
+        hits: &[u32],
+        violations: &mut Vec<DamageViolation>,
+    ) -> u32 {
+        if hits.len() > self.max_hits {
+            violations.push(DamageViolation::TooManyHits {
+                mob,
+                count: hits.len(),
+                max: self.max_hits,
+            });
+        }
+
+        hits.iter()
+            .take(self.max_hits)
+            .map(|&hit| {
+                if hit > self.max_damage {
+                    violations.push(DamageViolation::DamageExceeded {
+                        mob,
+                        damage: hit,
+                        max: self.max_damage,
+                    });
+                }
+                hit.min(self.max_damage)
+            })
+            .sum()
+    }
+
+    /// Clamps the damage of all targets, surplus targets are dropped
+    pub fn check(&self, targets: &[AttackTargetInfo]) -> CheckedAttack {
+        let mut violations = Vec::new();
+        if targets.len() > self.max_targets {
+            violations.push(DamageViolation::TooManyTargets {
+                count: targets.len(),
+                max: self.max_targets,
+            });
+        }
+
+        let damage = targets
+            .iter()
+            .take(self.max_targets)
+            .map(|target| {
+                let hits = target.hits.0.as_slice();
+                (
+                    target.mob_id,
+                    self.clamp_hits(target.mob_id, hits, &mut violations),
+                )
+            })
+            .collect();
+
+        CheckedAttack { damage, violations }
+    }
+}
+
+/// Stat multiplier of the weapon, the primary stat is multiplied
+/// and the secondary stat is added on top
+fn weapon_multiplier(weapon: WeaponType) -> u32 {
+    match weapon {
+        WeaponType::OneHandSword => 40,
+        WeaponType::OneHandAxe | WeaponType::OneHandMace => 44,
+        WeaponType::TwoHandSword => 46,
+        WeaponType::TwoHandAxe | WeaponType::TwoHandMace | WeaponType::Knuckle => 48,
+        WeaponType::Spear | WeaponType::PoleArm => 50,
+        WeaponType::Bow => 34,
+        WeaponType::BareHand | WeaponType::None => 42,
+        _ => 36,
+    }
+}
+
+/// Max physical damage of a hit without any skill or buff
+pub fn max_physical_damage(weapon: WeaponType, primary: u32, secondary: u32, pad: u32) -> u32 {
+    (primary * weapon_multiplier(weapon) / 10 + secondary) * pad / 100
+}
+
+/// Min physical damage of a hit without any skill or buff with the mastery in percent
+pub fn min_physical_damage(
+    weapon: WeaponType,
+    primary: u32,
+    secondary: u32,
+    pad: u32,
+    mastery: u32,
+) -> u32 {
+    (primary * weapon_multiplier(weapon) * 9 * mastery / 10_000 + secondary) * pad / 100
+}
+
+/// Max magical damage of a hit with a spell of the given attack
+pub fn max_magic_damage(mad: u32, int: u32, spell_attack: u32) -> u32 {
+    ((mad * mad / 1000 + mad) / 30 + int / 200) * spell_attack
+}
+
+/// Min magical damage of a hit with a spell of the given attack with the mastery in percent
+pub fn min_magic_damage(mad: u32, int: u32, mastery: u32, spell_attack: u32) -> u32 {
+    ((mad * mad / 1000 + mad * mastery * 9 / 1000) / 30 + int / 200) * spell_attack
+}
+
+impl Character {
+    /// Clamps the damage of a hit by a mob to the most the mob can deal to the character,
+    /// hits by unknown mobs don't deal any damage
+    pub fn hit_damage(&self, hit: &UserHit) -> u32 {
+        let (info, magic) = match hit {
+            UserHit::MobPhysical(info) => (info, false),
+            UserHit::MobMagic(info) => (info, true),
+            _ => return hit.dmg(),
+        };
+        let Some(mob) = self.game.meta.get_mob_data(info.mob_tmpl_id) else {
+            return 0;
+        };
+        let max = if mob.fixed_damage > 0 {
+            u32::try_from(mob.fixed_damage).unwrap_or(u32::MAX)
+        } else {
+            let atk = if magic { mob.ma_dmg } else { mob.pa_dmg };
+            self.derived
+                .max_mob_damage(u32::try_from(atk).unwrap_or_default(), magic)
+        };
+        info.dmg.min(max)
+    }
+
+    pub(crate) fn weapon_type(&self) -> WeaponType {
+        self.inventory
+            .invs
+            .equipped
+            .get(EquipSlot(CharEquipSlot::Weapon))
+            .map(|item| item.0.item_id)
+            .filter(|id| self.game.meta.get_weapon(*id).is_some())
+            .and_then(|id| id.weapon_type())
+            .unwrap_or(WeaponType::BareHand)
+    }
+
+    /// Returns the primary and the secondary stat, which are used with the weapon
+    fn weapon_stats(&self, weapon: WeaponType) -> (u32, u32) {
+        let stats = &self.derived;
+        match weapon {
+            WeaponType::Bow | WeaponType::Crossbow | WeaponType::Gun => (stats.dex, stats.str),
+            WeaponType::Claw | WeaponType::Dagger | WeaponType::SubDagger => {
+                (stats.luk, stats.str + stats.dex)
+            }
+            _ => (stats.str, stats.dex),
+        }
+    }
+
+    /// Calculates the limits for an attack with the given skill,
+    /// attacks with an unknown skill fall back to the limits of a regular attack
+    pub fn attack_limits(
+        &self,
+        skill_id: Option<SkillId>,
+    ) -> (AttackLimits, Option<DamageViolation>) {
+        let skill = match skill_id {
+            Some(id) => match self.skills.get_leveled(id) {
+                Ok(skill) => Some(skill),
+                Err(_) => {
+                    return (
+                        self.attack_limits(None).0,
+                        Some(DamageViolation::UnknownSkill(id)),
+                    )
+                }
+            },
+            None => None,
+        };
+
+        let buff = |v: Option<i16>| v.unwrap_or(0).max(0) as u32;
+        let lvl = skill.map(|skill| skill.level as u8).unwrap_or(0);
+
+        let mastery = self.derived.mastery;
+        let (min_dmg, max_dmg) = match skill.filter(|skill| skill.meta.stats.mad.is_some()) {
+            Some(spell) => {
+                let int = self.derived.int;
+                let mad = self.derived.mad + int;
+                let spell_attack = spell.meta.mad(lvl).max(0) as u32;
+                let amp = 100 + self.passives.magic_damage_r;
+                (
+                    min_magic_damage(mad, int, mastery, spell_attack) * amp / 100,
+                    max_magic_damage(mad, int, spell_attack) * amp / 100,
+                )
+            }
+            None => {
+                let weapon = self.weapon_type();
+                let (primary, secondary) = self.weapon_stats(weapon);
+                let pad = self.derived.pad;
+                let skill_dmg = skill
+                    .filter(|skill| skill.meta.stats.damage.is_some())
+                    .map_or(100, |skill| skill.meta.damage(lvl).max(0) as u32);
+                (
+                    min_physical_damage(weapon, primary, secondary, pad, mastery) * skill_dmg / 100,
+                    max_physical_damage(weapon, primary, secondary, pad) * skill_dmg / 100,
+                )
+            }
+        };
+
+        // Percent bonuses of active buffs
+        let combo = self
+            .buffs
+            .get::<ComboCounter>()
+            .map_or(0, |b| buff(Some(b.data.orbs * b.data.damage_per_orb)));
+        // Critical hits are only possible with a critical rate
+        let crit = if self.derived.crit_rate > 0 {
+            self.derived.crit_damage
+        } else {
+            0
+        };
+        let max_damage = max_dmg * (100 + combo + crit) / 100 * DAMAGE_TOLERANCE / 100;
+
+        let shadow_partner = if self.buffs.get::<ShadowPartner>().is_some() {
+            2
+        } else {
+            1
+        };
+        let (max_targets, max_hits) = skill.map_or((1, 1), |skill| {
+            (skill.meta.mob_count(lvl), skill.meta.attack_count(lvl))
+        });
+
+        (
+            AttackLimits {
+                max_targets,
+                max_hits: max_hits * shadow_partner,
+                min_damage: min_dmg * (100 + combo) / 100,
+                max_damage: max_damage.clamp(1, MAX_HIT_DAMAGE),
+            },
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_hits() {
+        let limits = AttackLimits {
+            max_targets: 1,
+            max_hits: 2,
+            min_damage: 10,
+            max_damage: 100,
+        };
+        let mob = ObjectId(1);
+
+        let mut violations = Vec::new();
+        assert_eq!(limits.clamp_hits(mob, &[50, 100], &mut violations), 150);
+        assert!(violations.is_empty());
+
+        assert_eq!(limits.clamp_hits(mob, &[500, 50, 50], &mut violations), 150);
+        assert_eq!(
+            violations,
+            vec![
+                DamageViolation::TooManyHits {
+                    mob,
+                    count: 3,
+                    max: 2
+                },
+                DamageViolation::DamageExceeded {
+                    mob,
+                    damage: 500,
+                    max: 100
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn damage_formulas() {
+        // 100 str with a one handed sword and 100 attack
+        assert_eq!(
+            max_physical_damage(WeaponType::OneHandSword, 100, 0, 100),
+            400
+        );
+        assert!(
+            max_physical_damage(WeaponType::Spear, 100, 20, 100)
+                > max_physical_damage(WeaponType::OneHandSword, 100, 20, 100)
+        );
+        assert_eq!(max_magic_damage(0, 0, 100), 0);
+        assert!(max_magic_damage(400, 400, 100) > max_magic_damage(200, 400, 100));
+
+        // The mastery raises the min damage up to 90% of the max damage
+        assert_eq!(
+            min_physical_damage(WeaponType::OneHandSword, 100, 0, 100, 0),
+            0
+        );
+        assert_eq!(
+            min_physical_damage(WeaponType::OneHandSword, 100, 0, 100, 100),
+            360
+        );
+        assert!(min_magic_damage(400, 400, 60, 100) < max_magic_damage(400, 400, 100));
+    }
+}
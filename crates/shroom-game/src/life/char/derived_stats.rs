@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+
+use shroom_meta::{
+    buffs::char::{
+        Acc, BasicStatUp, Booster, CriticalRate, Evasion, ExtraMaxHp, ExtraMaxMp, ExtraMdd,
+        ExtraPad, ExtraPdd, Jump, Mad, MaxHp, MaxMp, Mdd, Pad, Pdd, SharpEyes, Speed,
+    },
+    id::item_id::WeaponType,
+    item::{EquipBaseStats, EquipStat},
+    tmpl::equip::SetId,
+};
+
+use super::{buffs::CharBuffs, passive::PassiveSkills, stats::CharStats, Character};
+
+/// Cap of max HP and max MP
+pub const MAX_HP_MP: u32 = 30_000;
+pub const BASE_SPEED: u32 = 100;
+pub const MAX_SPEED: u32 = 140;
+pub const BASE_JUMP: u32 = 100;
+pub const MAX_JUMP: u32 = 123;
+
+/// Part of the attack of a mob, which can be mitigated by the defense in percent
+const MOB_DAMAGE_MITIGATION: u32 = 50;
+
+/// Stats of a character after applying equips, set bonuses, passive skills and buffs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivedStats {
+    pub str: u32,
+    pub dex: u32,
+    pub int: u32,
+    pub luk: u32,
+    pub max_hp: u32,
+    pub max_mp: u32,
+    pub pad: u32,
+    pub mad: u32,
+    pub pdd: u32,
+    pub mdd: u32,
+    pub acc: u32,
+    pub eva: u32,
+    pub speed: u32,
+    pub jump: u32,
+    /// Mastery of the equipped weapon in percent
+    pub mastery: u32,
+    /// Critical rate and the extra damage of a critical hit in percent
+    pub crit_rate: u32,
+    pub crit_damage: u32,
+    /// Attack speed change of boosters
+    pub booster: i32,
+}
+
+impl Default for DerivedStats {
+    fn default() -> Self {
+        Self {
+            str: 0,
+            dex: 0,
+            int: 0,
+            luk: 0,
+            max_hp: 0,
+            max_mp: 0,
+            pad: 0,
+            mad: 0,
+            pdd: 0,
+            mdd: 0,
+            acc: 0,
+            eva: 0,
+            speed: BASE_SPEED,
+            jump: BASE_JUMP,
+            mastery: 0,
+            crit_rate: 0,
+            crit_damage: 0,
+            booster: 0,
+        }
+    }
+}
+
+impl DerivedStats {
+    /// Starts with the ability points and the base max HP/MP of the character
+    pub fn from_base(stats: &CharStats) -> Self {
+        Self {
+            str: u32::from(stats.str),
+            dex: u32::from(stats.dex),
+            int: u32::from(stats.int),
+            luk: u32::from(stats.luk),
+            max_hp: stats.hp.max,
+            max_mp: stats.mp.max,
+            ..Default::default()
+        }
+    }
+
+    pub fn get(&self, stat: EquipStat) -> u32 {
+        match stat {
+            EquipStat::Str => self.str,
+            EquipStat::Dex => self.dex,
+            EquipStat::Int => self.int,
+            EquipStat::Luk => self.luk,
+            EquipStat::Hp => self.max_hp,
+            EquipStat::Mp => self.max_mp,
+            EquipStat::Pad => self.pad,
+            EquipStat::Mad => self.mad,
+            EquipStat::Pdd => self.pdd,
+            EquipStat::Mdd => self.mdd,
+            EquipStat::Acc => self.acc,
+            EquipStat::Eva => self.eva,
+            EquipStat::Speed => self.speed,
+            EquipStat::Jump => self.jump,
+            EquipStat::Craft => 0,
+        }
+    }
+
+    fn get_mut(&mut self, stat: EquipStat) -> Option<&mut u32> {
+        Some(match stat {
+            EquipStat::Str => &mut self.str,
+            EquipStat::Dex => &mut self.dex,
+            EquipStat::Int => &mut self.int,
+            EquipStat::Luk => &mut self.luk,
+            EquipStat::Hp => &mut self.max_hp,
+            EquipStat::Mp => &mut self.max_mp,
+            EquipStat::Pad => &mut self.pad,
+            EquipStat::Mad => &mut self.mad,
+            EquipStat::Pdd => &mut self.pdd,
+            EquipStat::Mdd => &mut self.mdd,
+            EquipStat::Acc => &mut self.acc,
+            EquipStat::Eva => &mut self.eva,
+            EquipStat::Speed => &mut self.speed,
+            EquipStat::Jump => &mut self.jump,
+            EquipStat::Craft => return None,
+        })
+    }
+
+    /// Adds flat bonuses like the stats of equipped items or set effects
+    pub fn add_stats(&mut self, stats: &EquipBaseStats) {
+        for (stat, value) in stats.0.iter() {
+            if let Some(v) = self.get_mut(stat) {
+                *v += u32::from(value.0);
+            }
+        }
+    }
+
+    /// Adds the flat bonuses of passive skills and scales the max HP/MP by their boosts,
+    /// the mastery is the one of the equipped weapon
+    pub fn add_passives(&mut self, passives: &PassiveSkills, weapon: WeaponType) {
+        self.add_stats(&passives.stats);
+        self.max_hp = self.max_hp * (100 + passives.max_hp_r) / 100;
+        self.max_mp = self.max_mp * (100 + passives.max_mp_r) / 100;
+        self.mastery = passives.mastery(weapon);
+        self.crit_rate += passives.crit_rate;
+        self.crit_damage = self.crit_damage.max(passives.crit_damage);
+        self.booster += passives.booster;
+    }
+
+    /// Adds the active buffs, the percent based buffs like Maple Warrior
+    /// scale the ability points and Hyper Body scales the max HP/MP
+    pub fn add_buffs(&mut self, base: &CharStats, buffs: &CharBuffs) {
+        let value = |v: Option<i16>| v.unwrap_or(0).max(0) as u32;
+
+        let basic = value(buffs.get::<BasicStatUp>().map(|b| b.data.0));
+        self.str += u32::from(base.str) * basic / 100;
+        self.dex += u32::from(base.dex) * basic / 100;
+        self.int += u32::from(base.int) * basic / 100;
+        self.luk += u32::from(base.luk) * basic / 100;
+
+        self.pad += value(buffs.get::<Pad>().map(|b| b.data.0))
+            + value(buffs.get::<ExtraPad>().map(|b| b.data.0));
+        self.mad += value(buffs.get::<Mad>().map(|b| b.data.0));
+        self.pdd += value(buffs.get::<Pdd>().map(|b| b.data.0))
+            + value(buffs.get::<ExtraPdd>().map(|b| b.data.0));
+        self.mdd += value(buffs.get::<Mdd>().map(|b| b.data.0))
+            + value(buffs.get::<ExtraMdd>().map(|b| b.data.0));
+        self.acc += value(buffs.get::<Acc>().map(|b| b.data.0));
+        self.eva += value(buffs.get::<Evasion>().map(|b| b.data.0));
+        self.speed += value(buffs.get::<Speed>().map(|b| b.data.0));
+        self.jump += value(buffs.get::<Jump>().map(|b| b.data.0));
+
+        let sharp_eyes = buffs.get::<SharpEyes>().map(|b| &b.data);
+        self.crit_rate += value(buffs.get::<CriticalRate>().map(|b| b.data.0))
+            + sharp_eyes.map_or(0, |b| u32::from(b.crit_rate));
+        self.crit_damage += sharp_eyes.map_or(0, |b| u32::from(b.crit_dmg_max));
+        self.booster += buffs.get::<Booster>().map_or(0, |b| i32::from(b.data.0));
+
+        self.max_hp = self.max_hp * (100 + value(buffs.get::<MaxHp>().map(|b| b.data.0))) / 100
+            + value(buffs.get::<ExtraMaxHp>().map(|b| b.data.0));
+        self.max_mp = self.max_mp * (100 + value(buffs.get::<MaxMp>().map(|b| b.data.0))) / 100
+            + value(buffs.get::<ExtraMaxMp>().map(|b| b.data.0));
+    }
+
+    /// Adds the accuracy and avoidability of the primary stats and applies the caps of the client
+    pub fn finish(mut self) -> Self {
+        self.acc += self.dex * 4 / 5 + self.luk / 2;
+        self.eva += self.luk / 2 + self.dex / 4;
+        self.max_hp = self.max_hp.min(MAX_HP_MP);
+        self.max_mp = self.max_mp.min(MAX_HP_MP);
+        self.speed = self.speed.min(MAX_SPEED);
+        self.jump = self.jump.min(MAX_JUMP);
+        self
+    }
+
+    /// Highest damage a single mob attack can deal to the character,
+    /// the defense mitigates up to half of the attack of the mob
+    pub fn max_mob_damage(&self, mob_atk: u32, magic: bool) -> u32 {
+        let def = if magic { self.mdd } else { self.pdd };
+        let mitigated = (def / 2).min(mob_atk * MOB_DAMAGE_MITIGATION / 100);
+        (mob_atk - mitigated).max(1)
+    }
+}
+
+impl Character {
+    /// Stats of the active set effects of the equipped items
+    fn set_item_stats(&self) -> EquipBaseStats {
+        let meta = self.game.meta;
+        let mut sets: BTreeMap<SetId, usize> = BTreeMap::new();
+        for item in self.inventory.invs.equipped.items() {
+            if let Some(set_id) = meta
+                .items()
+                .get_equip(item.0.item_id)
+                .and_then(|tmpl| tmpl.set_id)
+            {
+                *sets.entry(set_id).or_default() += 1;
+            }
+        }
+
+        sets.into_iter()
+            .filter_map(|(id, count)| meta.get_set_item(id).map(|set| set.active_stats(count)))
+            .fold(EquipBaseStats::default(), |acc, stats| acc + stats)
+    }
+
+    /// Calculates the derived stats from scratch
+    pub fn calc_derived_stats(&self) -> DerivedStats {
+        let mut stats = DerivedStats::from_base(&self.stats);
+        stats.add_stats(&self.inventory.get_equipped_stats());
+        stats.add_stats(&self.set_item_stats());
+        stats.add_passives(&self.passives, self.weapon_type());
+        stats.add_buffs(&self.stats, &self.buffs);
+        stats.finish()
+    }
+
+    /// Recalculates the cached passive skills and derived stats,
+    /// must be called after the stats, equips, skills or buffs changed
+    pub fn update_derived_stats(&mut self) {
+        self.passives = PassiveSkills::from_skills(self.stats.job, &self.skills);
+        self.derived = self.calc_derived_stats();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shroom_meta::item::ItemStat;
+
+    use super::*;
+
+    #[test]
+    fn derive_stats() {
+        let mut stats = DerivedStats {
+            dex: 40,
+            luk: 20,
+            max_hp: 29_000,
+            ..Default::default()
+        };
+        stats.add_stats(&EquipBaseStats::from_fn(|stat| match stat {
+            EquipStat::Pad => ItemStat(50),
+            EquipStat::Hp => ItemStat(2_000),
+            EquipStat::Speed => ItemStat(60),
+            _ => ItemStat(0),
+        }));
+        let stats = stats.finish();
+
+        assert_eq!(stats.pad, 50);
+        assert_eq!(stats.acc, 42);
+        assert_eq!(stats.eva, 20);
+        assert_eq!(stats.max_hp, MAX_HP_MP);
+        assert_eq!(stats.speed, MAX_SPEED);
+        assert_eq!(stats.jump, BASE_JUMP);
+        assert_eq!(stats.get(EquipStat::Pad), 50);
+    }
+
+    #[test]
+    fn mob_damage() {
+        let stats = DerivedStats {
+            pdd: 100,
+            ..Default::default()
+        };
+        assert_eq!(stats.max_mob_damage(500, false), 450);
+        // Defense never mitigates more than half
+        assert_eq!(stats.max_mob_damage(60, false), 30);
+        assert_eq!(stats.max_mob_damage(500, true), 500);
+    }
+}
@@ -1,6 +1,7 @@
 use std::{collections::VecDeque, sync::Arc};
 
 use anyhow::Context;
+use chrono::NaiveDateTime;
 use either::Either;
 
 use rand::thread_rng;
@@ -195,6 +196,7 @@ impl InvSetHandler for CharInvHandler {
 pub struct CharInventory {
     pub invs: InventorySet<CharInvHandler>,
     pub inv_size: InventorySize,
+    item_svc: Arc<SharedItemSvc>,
     recalc_eq_stats: bool,
     eq_ops: PendingOperations,
 }
@@ -204,7 +206,8 @@ impl CharInventory {
         let svc = inv.consume.handler().0.clone();
         Self {
             inv_size,
-            invs: inv.with_handler(svc),
+            invs: inv.with_handler(svc.clone()),
+            item_svc: svc,
             eq_ops: PendingOperations::default(),
             recalc_eq_stats: false,
         }
@@ -313,10 +316,57 @@ impl CharInventory {
         })
     }
 
+    /// Checks if the given quantity of an item would fit into the inventory
+    pub fn can_add(&self, id: ItemId, quantity: usize) -> anyhow::Result<bool> {
+        let ty = id.get_inv_type()?;
+        let slot_max = self.item_svc.slot_max(id);
+        Ok(match ty {
+            InventoryType::Cash => self.invs.cash.can_add_stack(id, quantity, slot_max),
+            ty if ty.is_stack() => self
+                .invs
+                .get_stack_inventory(ty)?
+                .can_add_stack(id, quantity, slot_max),
+            _ => self.invs.equip.capacity() - self.invs.equip.len() >= quantity,
+        })
+    }
+
+    pub fn free_slots(&self, ty: InventoryType) -> anyhow::Result<usize> {
+        Ok(match ty {
+            InventoryType::Equip => self.invs.equip.capacity() - self.invs.equip.len(),
+            InventoryType::Cash => self.invs.cash.capacity() - self.invs.cash.len(),
+            ty => {
+                let inv = self.invs.get_stack_inventory(ty)?;
+                inv.capacity() - inv.len()
+            }
+        })
+    }
+
+    /// Checks if all items would fit into the inventory at once,
+    /// every item is assumed to require its own free slot
+    pub fn can_add_all(&self, items: &[(ItemId, usize)]) -> anyhow::Result<bool> {
+        let types = items
+            .iter()
+            .map(|(id, _)| id.get_inv_type())
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        for ty in types.iter() {
+            let required = types.iter().filter(|t| *t == ty).count();
+            if required > self.free_slots(*ty)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     pub fn add_equip_by_id(&mut self, id: ItemId, data: &ItemService) -> anyhow::Result<usize> {
         self.try_add_equip(data.create_equip(id)?)
     }
 
+    /// Returns whether the equipped items changed since the last call
+    pub fn take_eq_stats_changed(&mut self) -> bool {
+        std::mem::take(&mut self.recalc_eq_stats)
+    }
+
     pub fn get_equipped_stats(&self) -> EquipBaseStats {
         self.invs
             .equipped
@@ -344,8 +394,9 @@ impl CharInventory {
         quantity: usize,
         inv_type: InventoryType,
     ) -> anyhow::Result<()> {
+        let slot_max = self.item_svc.slot_max(id);
         let inv = self.invs.get_stack_inventory_mut(inv_type)?;
-        inv.try_add_stack(id, quantity)?;
+        inv.try_add_stack(id, quantity, slot_max)?;
         Ok(())
     }
 
@@ -437,6 +488,19 @@ impl CharInventory {
         Ok(DropStackItem(id, q))
     }
 
+    /// Id of the item in the stack inventory slot
+    pub fn get_stack_item_id(&self, slot: InventorySlot) -> anyhow::Result<ItemId> {
+        let InventorySlot::Slot(ty, _) = slot else {
+            anyhow::bail!("Not a stack slot: {slot:?}");
+        };
+        let item = self
+            .invs
+            .get_stack_inventory(ty)?
+            .get(slot.as_slot())
+            .context("No item in slot")?;
+        Ok(item.item_id)
+    }
+
     pub fn drop_equip_item(&mut self, slot: InventorySlot) -> anyhow::Result<EquipItemSlot> {
         Ok(match slot {
             InventorySlot::Slot(_, _) => {
@@ -513,6 +577,102 @@ impl CharInventory {
         Ok(())
     }
 
+    /// Adds an item, which was taken out of the cash locker,
+    /// returns the slot index and the encoded item
+    pub fn add_cash_item(
+        &mut self,
+        data: &ItemService,
+        id: ItemId,
+        quantity: u16,
+        expiration: Option<NaiveDateTime>,
+    ) -> anyhow::Result<(u16, Item)> {
+        if id.get_inv_type()? == InventoryType::Equip {
+            let mut item = data.create_equip(id)?;
+            item.info.is_cash = true;
+            item.info.expiration = expiration;
+            let slot = self.try_add_equip(item)?;
+            let item = self.invs.equip.get(slot).unwrap().item.as_ref().into();
+            return Ok((slot as u16 + 1, Item::Equip(item)));
+        }
+
+        let item = if id.is_pet() {
+            let mut pet = data.create_pet(id)?;
+            pet.info.expiration = expiration;
+            CashItemSlot::Pet(pet.into())
+        } else {
+            let mut stack = data.create_stack(id, quantity)?;
+            stack.info.is_cash = true;
+            stack.info.expiration = expiration;
+            CashItemSlot::Stack(stack.into())
+        };
+        let slot = self.invs.cash.try_add(item)?;
+        let item = match self.invs.cash.get(slot).unwrap() {
+            CashItemSlot::Stack(item) => Item::Stack(item.as_ref().into()),
+            CashItemSlot::Pet(item) => Item::Pet(item.as_ref().into()),
+        };
+        Ok((slot as u16 + 1, item))
+    }
+
+    /// Removes the cash item from the slot returned by `add_cash_item`,
+    /// used to undo the move of a locker item
+    pub fn remove_cash_slot(&mut self, id: ItemId, slot: u16) -> anyhow::Result<()> {
+        let slot = (slot as usize)
+            .checked_sub(1)
+            .ok_or_else(|| anyhow::anyhow!("Invalid cash slot: {slot}"))?;
+        if id.get_inv_type()? == InventoryType::Equip {
+            self.invs.equip.try_remove(slot)?;
+            self.eq_ops.remove(InventoryType::Equip, slot as u16 + 1);
+            return Ok(());
+        }
+
+        let quantity = match self.invs.cash.get(slot) {
+            Some(CashItemSlot::Stack(stack)) => stack.quantity(),
+            Some(CashItemSlot::Pet(_)) => 1,
+            None => anyhow::bail!("No cash item in slot: {slot}"),
+        };
+        self.invs.cash.take_quantity(slot, quantity)?;
+        Ok(())
+    }
+
+    /// Removes the cash item with the serial number,
+    /// returns the id, quantity and expiration of the item
+    pub fn take_cash_item(
+        &mut self,
+        inv_type: InventoryType,
+        sn: u64,
+    ) -> anyhow::Result<(ItemId, u16, Option<NaiveDateTime>)> {
+        match inv_type {
+            InventoryType::Equip => {
+                let slot = self
+                    .invs
+                    .equip
+                    .item_slots()
+                    .find(|(_, item)| item.item.info.cash_id() == Some(sn))
+                    .map(|(slot, _)| slot)
+                    .ok_or_else(|| anyhow::anyhow!("No cash equip with sn: {sn}"))?;
+                let item = self.invs.equip.try_remove(slot)?;
+                self.eq_ops.remove(InventoryType::Equip, slot as u16 + 1);
+                Ok((item.item_id, 1, item.item.info.expiration))
+            }
+            InventoryType::Cash => {
+                let (slot, info, quantity) = self
+                    .invs
+                    .cash
+                    .item_slots()
+                    .map(|(slot, item)| match item {
+                        CashItemSlot::Stack(stack) => (slot, &stack.info, stack.quantity()),
+                        CashItemSlot::Pet(pet) => (slot, &pet.info, 1),
+                    })
+                    .find(|(_, info, _)| info.cash_id() == Some(sn))
+                    .ok_or_else(|| anyhow::anyhow!("No cash item with sn: {sn}"))?;
+                let expiration = info.expiration;
+                let (id, _) = self.invs.cash.take_quantity(slot, quantity)?;
+                Ok((id, quantity as u16, expiration))
+            }
+            _ => anyhow::bail!("Invalid cash inventory: {inv_type:?}"),
+        }
+    }
+
     pub fn get_pet(&self, slot: usize) -> Option<&PetItem> {
         self.invs
             .get_cash_inventory()
@@ -1,6 +1,10 @@
+pub mod buddy;
 pub mod buffs;
 pub mod class;
+pub mod damage;
+pub mod derived_stats;
 pub mod inv;
+pub mod passive;
 pub mod pet;
 pub mod quest;
 pub mod stats;
@@ -19,10 +23,12 @@ use shroom_data::{
     entities::character::{self, Model},
     entity_ext::KeyMap,
     model::{
-        inv::{InventorySet, NoopInvSetHandler},
+        inv::{InventorySet, InventorySlot, NoopInvSetHandler},
         skill::{SkillData, SkillSet},
     },
-    services::{character::QuestSet, item::ItemService},
+    services::{
+        buddy::BuddyEntry, char_save::CharSaveParts, character::QuestSet, item::ItemService,
+    },
 };
 use shroom_meta::{
     class::HealBuff,
@@ -36,9 +42,11 @@ use shroom_pkt::ShroomIndexList8;
 
 use shroom_proto95::{
     game::{
+        life::summon::SummonLeaveType,
+        quest::QuestState,
         script::ScriptMessage,
         user::{
-            remote::{GuildMarkData, TamingMobData, UserRemoteInitData},
+            remote::{TamingMobData, UserRemoteInitData},
             secondary_stats::RemoteCharSecondaryStatPartial,
         },
     },
@@ -49,26 +57,29 @@ use shroom_proto95::{
     },
 };
 use shroom_script::SessionCtx;
-use shroom_srv::{act::Context, game::pool::PoolItem, util::DelayQueue, GameTime};
+use shroom_srv::{act::Context, util::DelayQueue, GameTime};
 
 use crate::{
-    field::AttackerContext, game::GameContext, life::char::summon::Summon,
+    field::AttackerContext, game::GameContext,
     services::shared::SharedGameServices,
 };
 
 use self::{
+    buddy::CharBuddies,
     buffs::CharBuffs,
     class::{AttackData, ClassContext, ClassHandler, UseSkillData},
+    derived_stats::DerivedStats,
     inv::CharInventory,
+    passive::PassiveSkills,
     pet::{CharPets, Pet},
     quest::{CharQuests, QuestCheckError},
     stats::CharStats,
+    summon::CharSummons,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CharEvents {
-    SummonSpawned(ObjectId),
-    SummonRemoved(ObjectId),
+    SummonExpired(ObjectId),
 }
 
 #[derive(Debug)]
@@ -78,6 +89,8 @@ pub struct Character {
     pub name: String,
     pub gender: Gender,
     pub stats: CharStats,
+    /// Stats after equips, set bonuses, passive skills and buffs
+    pub derived: DerivedStats,
     pub inventory: CharInventory,
     pub field: FieldId,
     pub spawn_point: SpawnPoint,
@@ -85,21 +98,42 @@ pub struct Character {
     pub hair: HairId,
     pub face: FaceId,
     pub skills: SkillSet,
+    /// Modifiers of the learned passive skills
+    pub passives: PassiveSkills,
     pub pos: Vec2,
     pub fh: FootholdId,
     pub buffs: CharBuffs,
     pub npc_msg: VecDeque<ScriptMessage>,
-    pub do_script_transfer: Option<FieldId>,
+    /// Pending field transfer of a script with an optional target portal
+    pub do_script_transfer: Option<(FieldId, Option<String>)>,
+    /// System messages of scripts, which still have to be sent
+    pub script_notices: VecDeque<String>,
     pub key_map: KeyMap,
     pub pets: CharPets,
-    pub summons: slab::Slab<Summon>,
+    pub summons: CharSummons,
     pub quests: CharQuests,
+    pub buddies: CharBuddies,
+    pub whisper_blocked: bool,
+    /// GM hide mode, the character is not shown to other players
+    pub hidden: bool,
+    /// GM invincibility, hits don't reduce the HP
+    pub invincible: bool,
     pub last_update: GameTime,
+    /// Parts, which changed since the last save
+    pub dirty: CharSaveParts,
 
     pub last_id: u32,
 
     pub pending: DelayQueue<CharEvents>,
     pub npc_id: Option<NpcId>,
+    /// Quest of the running quest script
+    pub quest_id: Option<QuestId>,
+    /// Quest state changes of scripts, which still have to be sent
+    pub quest_updates: VecDeque<(QuestId, QuestState)>,
+    /// Item of the running item script
+    pub item_id: Option<ItemId>,
+    /// Item and slot of the item, which launched the running script
+    pub script_item: Option<(ItemId, InventorySlot)>,
 
     pub playtime: std::time::Duration,
     pub game_start: std::time::Instant,
@@ -134,16 +168,19 @@ impl Character {
         skills: SkillSet,
         key_map: KeyMap,
         q: QuestSet,
+        buddies: Vec<BuddyEntry>,
     ) -> Self {
         let meta = game.meta;
+        let buddy_capacity = game.config.buddy_capacity;
         let field = FieldId(model.field_id as u32);
         let field_meta = game.meta.get_field(field).unwrap();
         let spawn_point = field_meta.get_spawn_point(model.spawn_point as u8).unwrap();
 
-        Self {
+        let mut chr = Self {
             game,
             id: CharacterId(model.id as u32),
             stats: (&model).into(),
+            derived: DerivedStats::default(),
             inventory: CharInventory::from_inv_set(model.get_inventory_size(), inventory),
             gender: (&model.gender).into(),
             name: model.name.clone(),
@@ -155,24 +192,38 @@ impl Character {
             fh: FootholdId::none(),
             spawn_point,
             skills,
+            passives: PassiveSkills::default(),
             npc_msg: VecDeque::default(),
             buffs: CharBuffs::new(),
             do_script_transfer: None,
+            script_notices: VecDeque::default(),
             summons: Default::default(),
             last_id: 1,
             pending: DelayQueue::new(),
             game_start: Instant::now(),
             playtime: std::time::Duration::from_secs(model.play_time as u64),
             npc_id: None,
+            quest_id: None,
+            quest_updates: VecDeque::default(),
+            item_id: None,
+            script_item: None,
             last_update: t,
             key_map,
             pets: CharPets::default(),
             quests: CharQuests::from_data(q, meta),
-        }
+            buddies: CharBuddies::new(buddy_capacity, buddies),
+            whisper_blocked: model.whisper_blocked,
+            hidden: false,
+            invincible: false,
+            dirty: CharSaveParts::default(),
+        };
+        chr.update_derived_stats();
+        chr
     }
 
     pub fn try_accept_quest(&mut self, qid: QuestId) -> Result<(), QuestCheckError> {
         let meta = self.meta();
+        self.dirty.quests = true;
         self.quests
             .try_start_quest(qid, meta, &self.field, &self.stats, &mut self.inventory)
     }
@@ -191,25 +242,15 @@ impl Character {
 
         CharQuests::reward_quest(qid, mq, self)?;
         self.quests.complete_quest(qid, meta)?;
+        self.dirty.quests = true;
         Ok(())
     }
 
     pub fn handle_update(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
         while let Some(event) = self.pending.pop(ctx.time()) {
             match event {
-                CharEvents::SummonSpawned(id) => {
-                    let summ = self.summons.get(id.0 as usize).unwrap();
-                    ctx.room
-                        .tx
-                        .broadcast_encode(summ.enter_msg(id, ctx.time()))?;
-                    self.pending.push(
-                        CharEvents::SummonRemoved(id),
-                        ctx.time() + std::time::Duration::from_secs(10),
-                    );
-                }
-                CharEvents::SummonRemoved(id) => {
-                    let summ = self.summons.remove(id.0 as usize);
-                    ctx.room.tx.broadcast_encode(summ.leave_msg(id, ()))?;
+                CharEvents::SummonExpired(id) => {
+                    self.remove_summon(ctx, id, SummonLeaveType::Default)?;
                 }
             }
         }
@@ -233,30 +274,6 @@ impl Character {
         Ok(())
     }
 
-    pub fn get_summon(&self, id: ObjectId) -> Option<&Summon> {
-        self.summons.get(id.0 as usize)
-    }
-
-    pub fn get_summon_mut(&mut self, id: ObjectId) -> Option<&mut Summon> {
-        self.summons.get_mut(id.0 as usize)
-    }
-
-    pub fn add_summon(
-        &mut self,
-        ctx: &mut GameContext,
-        summon: Summon,
-    ) -> anyhow::Result<ObjectId> {
-        let exp = summon.expiration;
-        let id = ObjectId(self.summons.insert(summon) as u32);
-
-        let t = ctx.time();
-
-        let summ = self.summons.get(id.0 as usize).unwrap();
-        ctx.room.tx.broadcast_encode(summ.enter_msg(id, t))?;
-        self.pending.push(CharEvents::SummonRemoved(id), exp);
-        Ok(id)
-    }
-
     pub fn apply_heal(&mut self, heal: HealBuff) -> anyhow::Result<()> {
         match heal {
             HealBuff::Flat(flat) => {
@@ -327,12 +344,15 @@ impl Character {
     }
 
     pub fn get_stats_update(&mut self) -> Option<CharStatPartial> {
-        self.stats.get_stats_partial()
+        let partial = self.stats.get_stats_partial();
+        self.dirty.char |= partial.is_some();
+        partial
     }
 
     pub fn transfer_map(&mut self, map: FieldId, sp: SpawnPoint) {
         self.field = map;
         self.spawn_point = sp;
+        self.dirty.char = true;
         // Reset the updates, since we use set field anyway
         self.stats.reset();
 
@@ -413,6 +433,9 @@ impl Character {
                 );
             }
         }
+
+        // Passives of the previous job line no longer apply
+        self.update_derived_stats();
         Ok(())
     }
 
@@ -501,13 +524,27 @@ impl Character {
     }
 
     pub fn get_inv_op_updates(&mut self) -> Option<Vec<InventoryOperation>> {
-        self.inventory.get_updates()
+        let ops = self.inventory.get_updates();
+        self.dirty.inventory |= ops.is_some();
+        ops
     }
 
     pub fn use_skill(&mut self, req: &UseSkillData, ctx: &mut GameContext) -> anyhow::Result<()> {
         let skill = self.skills.get(req.skill_id)?;
         let mp_cost = skill.mp_cost();
-        //let cd = Duration::from_secs(15);
+        let cooltime = skill.meta.cooltime_dur(skill.level as u8);
+        if let Some(cd) = self.skills.remaining_cooldown(req.skill_id, req.t) {
+            log::warn!(
+                target: "audit",
+                "Character {} ({:?}) used skill {:?} with {cd:?} cooldown left",
+                self.name,
+                self.id,
+                req.skill_id
+            );
+            *self.stats.action_locked_mut() = false;
+            return Ok(());
+        }
+
         if let Some(cost) = mp_cost {
             if !self.stats.try_update_mp(-(cost as i32)) {
                 return Ok(());
@@ -517,7 +554,10 @@ impl Character {
         // Give buff
         ClassHandler::handle_skill(ClassContext::new(self, ctx), req)?;
         *self.stats.action_locked_mut() = false;
-        //self.skills.set_cooldown(skill_id, cd);
+        if let Some(cd) = cooltime {
+            self.skills.set_cooldown(req.skill_id, req.t, cd);
+            self.dirty.skills = true;
+        }
 
         Ok(())
     }
@@ -528,24 +568,27 @@ impl Character {
         }
 
         self.skills.skill_up(skill_id, 1)?;
+        self.update_derived_stats();
         Ok(())
     }
 
     pub fn get_remote_init_data(&self) -> UserRemoteInitData {
         let job = self.stats.job;
-        // TODO
-        let secondary_stat = RemoteCharSecondaryStatPartial {
-            //shadowpartner: Some(4111002).into(),
-            darksight: Some(()).into(),
-            curse: Some(1000).into(),
-            ..Default::default()
-        };
+        // TODO send the active buffs
+        let secondary_stat = RemoteCharSecondaryStatPartial::default();
+
+        let (guild_name, guild_mark) = self
+            .game
+            .guild
+            .get_guild_of(self.id)
+            .map(|guild| (guild.data.name.clone(), guild.mark_data()))
+            .unwrap_or_default();
 
         UserRemoteInitData {
             level: self.stats.level,
             name: self.name.clone(),
-            guild_name: "".to_string(),
-            guild_mark: GuildMarkData::default(),
+            guild_name,
+            guild_mark,
             secondary_stat: secondary_stat.into(),
             defense_att: 0,
             defense_state: 0,
@@ -605,6 +648,7 @@ impl Character {
             hair: Set(self.hair.0 as i32),
             field_id: Set(self.field.0 as i32),
             spawn_point: Set(self.spawn_point.id as i32),
+            whisper_blocked: Set(self.whisper_blocked),
             ..Default::default()
         }
     }
@@ -0,0 +1,139 @@
+use shroom_data::model::skill::SkillSet;
+use shroom_meta::{
+    id::{item_id::WeaponType, job_id::JobId},
+    item::{EquipBaseStats, EquipStat, ItemStat},
+    shared::EvalExpr,
+    skill::{Skill, SkillLevel},
+};
+
+/// Permanent modifiers of the learned passive skills of the current job line
+#[derive(Debug, Clone, Default)]
+pub struct PassiveSkills {
+    /// Flat stat bonuses
+    pub stats: EquipBaseStats,
+    /// Max HP and MP increase in percent
+    pub max_hp_r: u32,
+    pub max_mp_r: u32,
+    /// Critical rate and the extra damage of a critical hit in percent
+    pub crit_rate: u32,
+    pub crit_damage: u32,
+    /// Mastery in percent per weapon type, `None` applies to every weapon
+    pub masteries: Vec<(Option<WeaponType>, u32)>,
+    /// Attack speed change of passive boosters
+    pub booster: i32,
+    /// Extra magic damage of amplifications in percent
+    pub magic_damage_r: u32,
+}
+
+impl PassiveSkills {
+    /// Collects the modifiers of the skills, which belong to the job or one of the previous jobs,
+    /// so skills of a former job line stop applying after a job change
+    pub fn from_skills(job: JobId, skills: &SkillSet) -> Self {
+        let mut jobs = job.prev_jobs();
+        jobs.push(job);
+
+        let mut passives = Self::default();
+        for skill in skills.skills() {
+            if skill.level > 0 && jobs.iter().any(|job| job.skill_range().contains(&skill.id)) {
+                passives.add_skill(skill.meta, skill.level as SkillLevel);
+            }
+        }
+        passives
+    }
+
+    pub fn add_skill(&mut self, skill: &Skill, lvl: SkillLevel) {
+        let id = skill.id;
+        let stats = &skill.stats;
+        let eval = |e: &Option<EvalExpr>| e.as_ref().map(|e| e.eval(i32::from(lvl)));
+        let positive = |v: Option<i32>| v.unwrap_or(0).max(0) as u32;
+
+        if id.is_hp_boost() {
+            self.max_hp_r += positive(eval(&stats.max_hp_ratio).or_else(|| eval(&stats.x)));
+            return;
+        }
+
+        if id.is_mp_boost() {
+            self.max_mp_r += positive(eval(&stats.max_mp_ratio).or_else(|| eval(&stats.x)));
+            return;
+        }
+
+        if id.is_critical_passive() {
+            self.crit_rate += positive(eval(&stats.critical_ratio).or_else(|| eval(&stats.prop)));
+            let dmg = eval(&stats.critical_damage_max)
+                .or_else(|| eval(&stats.damage).map(|dmg| dmg - 100));
+            self.crit_damage = self.crit_damage.max(positive(dmg));
+            return;
+        }
+
+        if id.is_element_amplification() {
+            self.magic_damage_r += positive(eval(&stats.y).map(|y| y - 100));
+            return;
+        }
+
+        if skill.skill_type.is_mastery() {
+            let weapon = skill
+                .weapon
+                .and_then(|w| u8::try_from(w).ok())
+                .and_then(|w| WeaponType::try_from(w).ok());
+            self.masteries
+                .push((weapon, positive(eval(&stats.mastery))));
+        }
+
+        // Boosters are active skills, but some of them only have a passive part
+        if skill.skill_type.is_booster() {
+            if stats.time.is_none() {
+                self.booster += eval(&stats.x).unwrap_or(0);
+            }
+            return;
+        }
+
+        // Skills with a duration, damage or a cost are active ones
+        if stats.time.is_some() || stats.damage.is_some() || skill.cost.mp.is_some() {
+            return;
+        }
+
+        let flat = |e: &Option<EvalExpr>| ItemStat(positive(eval(e)) as u16);
+        self.stats += &EquipBaseStats::from_fn(|stat| match stat {
+            EquipStat::Pad => flat(&stats.pad),
+            EquipStat::Mad => flat(&stats.mad),
+            EquipStat::Pdd => flat(&stats.pdd),
+            EquipStat::Mdd => flat(&stats.mdd),
+            EquipStat::Acc => flat(&stats.accuracy),
+            EquipStat::Eva => flat(&stats.evasion),
+            EquipStat::Speed => flat(&stats.speed),
+            EquipStat::Jump => flat(&stats.jump),
+            _ => ItemStat(0),
+        });
+    }
+
+    /// Mastery with the weapon, the best matching mastery wins
+    pub fn mastery(&self, weapon: WeaponType) -> u32 {
+        self.masteries
+            .iter()
+            .filter(|(w, _)| w.map_or(true, |w| w == weapon))
+            .map(|(_, mastery)| *mastery)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mastery() {
+        let passives = PassiveSkills {
+            masteries: vec![
+                (Some(WeaponType::OneHandSword), 60),
+                (Some(WeaponType::Bow), 50),
+                (None, 10),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(passives.mastery(WeaponType::OneHandSword), 60);
+        assert_eq!(passives.mastery(WeaponType::Bow), 50);
+        assert_eq!(passives.mastery(WeaponType::Claw), 10);
+        assert_eq!(PassiveSkills::default().mastery(WeaponType::Bow), 0);
+    }
+}
@@ -300,6 +300,18 @@ impl CharQuests {
         Ok(())
     }
 
+    pub fn forfeit_quest(
+        &mut self,
+        qid: QuestId,
+        meta: &'static MetaService,
+    ) -> anyhow::Result<()> {
+        if !self.is_active(qid) {
+            anyhow::bail!("Quest {qid:?} is not active");
+        }
+        self.remove_active_quest(qid, meta);
+        Ok(())
+    }
+
     pub fn on_mob_killed(&mut self, mob: MobId, n: usize) {
         for active in self.active.iter_mut() {
             if active.1.update_mobs(mob, n) {
@@ -180,6 +180,15 @@ impl CharStats {
         self.action_locked = true;
     }
 
+    pub fn is_hp_changed(&self) -> bool {
+        self.flags.contains(CharStatsFlags::hp)
+    }
+
+    pub fn is_level_job_changed(&self) -> bool {
+        self.flags
+            .intersects(CharStatsFlags::level | CharStatsFlags::job)
+    }
+
     pub fn process_level_up(&mut self) {
         let mut r = thread_rng();
         *self.ap_mut() += 5;
@@ -1,13 +1,24 @@
+use std::collections::BTreeMap;
+
 use shroom_meta::{
+    class::SummonSkill,
     id::{CharacterId, FootholdId, ObjectId, SkillId},
     twod::Vec2,
 };
 use shroom_proto95::game::life::summon::{
-        SummonAssistType, SummonCreateResp, SummonDeleteResp, SummonEnterType, SummonInitData,
-        SummonLeaveType, SummonMoveAbility, SummonMoveAction,
-    };
-use shroom_srv::{game::pool::PoolItem, GameTime};
+    SummonAssistType, SummonCreateResp, SummonDeleteResp, SummonEnterType, SummonHitReq,
+    SummonHitResp, SummonInitData, SummonLeaveType, SummonMoveAbility, SummonMoveAction,
+    SummonMoveReq, SummonMoveResp,
+};
+use shroom_srv::{
+    act::Context,
+    game::pool::{Pool, PoolCtx, PoolItem},
+    GameTime,
+};
+
+use crate::{game::GameContext, life::Obj};
 
+use super::{CharEvents, Character};
 
 #[derive(Debug, Clone)]
 pub struct Summon {
@@ -19,7 +30,35 @@ pub struct Summon {
     pub char_id: CharacterId,
     pub move_ability: shroom_meta::class::SummonMoveAbility,
     pub assist_type: shroom_meta::class::SummonAssistType,
-    pub expiration: GameTime
+    pub expiration: GameTime,
+    /// Remaining HP, summons without HP can't be killed by mobs
+    pub hp: Option<u32>,
+    pub enter_type: SummonEnterType,
+}
+
+impl Summon {
+    /// Creates a summon of the skill at the position of the character
+    pub fn new(
+        chr: &Character,
+        skill_id: SkillId,
+        skill_level: u8,
+        summon: &SummonSkill,
+        t: GameTime,
+    ) -> Self {
+        Self {
+            pos: chr.pos,
+            fh: chr.fh,
+            skill_id,
+            skill_level,
+            char_level: chr.stats.level,
+            char_id: chr.id,
+            move_ability: summon.move_ability.clone(),
+            assist_type: summon.assist_type.clone(),
+            expiration: t + summon.dur,
+            hp: summon.hp,
+            enter_type: SummonEnterType::CreateSummon,
+        }
+    }
 }
 
 fn map_summon_move(s: &shroom_meta::class::SummonMoveAbility) -> SummonMoveAbility {
@@ -30,7 +69,7 @@ fn map_summon_move(s: &shroom_meta::class::SummonMoveAbility) -> SummonMoveAbili
         shroom_meta::class::SummonMoveAbility::CircleFollow => SummonMoveAbility::CircleFollow,
         shroom_meta::class::SummonMoveAbility::Escort => SummonMoveAbility::Escort,
         shroom_meta::class::SummonMoveAbility::Jump => SummonMoveAbility::Jump,
-        shroom_meta::class::SummonMoveAbility::None => SummonMoveAbility::NoMove, 
+        shroom_meta::class::SummonMoveAbility::None => SummonMoveAbility::NoMove,
     }
 }
 
@@ -50,7 +89,7 @@ impl PoolItem for Summon {
     type EnterMsg = SummonCreateResp;
     type LeaveMsg = SummonDeleteResp;
 
-    type LeaveParam = ();
+    type LeaveParam = SummonLeaveType;
 
     fn enter_msg(&self, id: Self::Id, _t: GameTime) -> Self::EnterMsg {
         SummonCreateResp {
@@ -61,21 +100,257 @@ impl PoolItem for Summon {
             skill_level: self.skill_level,
             init: SummonInitData {
                 pos: self.pos,
-                move_action: SummonMoveAction::Walk, // TODO
+                move_action: if self.move_ability.is_stationary() {
+                    SummonMoveAction::Stand
+                } else {
+                    SummonMoveAction::Walk
+                },
                 cur_fh: self.fh,
                 move_ability: map_summon_move(&self.move_ability),
                 assist_type: map_summon_assists(&self.assist_type),
-                enter_type: SummonEnterType::CreateSummon,
+                enter_type: self.enter_type,
                 avatar: None.into(),
             },
         }
     }
 
-    fn leave_msg(&self, id: Self::Id, _param: Self::LeaveParam) -> Self::LeaveMsg {
+    fn leave_msg(&self, id: Self::Id, param: Self::LeaveParam) -> Self::LeaveMsg {
         SummonDeleteResp {
             char: self.char_id,
             summon_id: id,
-            leave: SummonLeaveType::Default,
+            leave: param,
+        }
+    }
+}
+
+#[derive(Debug, Default, derive_more::Deref, derive_more::DerefMut)]
+pub struct SummonPool(pub Pool<Obj<Summon>>);
+
+impl SummonPool {
+    fn get_owned_mut(&mut self, owner: CharacterId, id: ObjectId) -> Option<&mut Obj<Summon>> {
+        self.get_mut(&id).filter(|summon| summon.char_id == owner)
+    }
+
+    /// Updates the position of a moving summon and forwards the movement to the other players
+    pub fn handle_move(
+        &mut self,
+        ctx: &mut impl PoolCtx<Id = CharacterId>,
+        owner: CharacterId,
+        req: SummonMoveReq,
+    ) -> anyhow::Result<()> {
+        let Some(summon) = self.get_owned_mut(owner, req.summon_id) else {
+            return Ok(());
+        };
+
+        // Stationary summons keep their position
+        if summon.move_ability.is_stationary() {
+            return Ok(());
+        }
+
+        if let Some((pos, fh)) = req.path.get_last_pos_fh() {
+            summon.pos = pos;
+            summon.fh = fh.unwrap_or(summon.fh);
+        }
+
+        ctx.tx().broadcast_filter_encode(
+            SummonMoveResp {
+                char: owner,
+                summon_id: req.summon_id,
+                path: req.path,
+            },
+            owner,
+        )?;
+        Ok(())
+    }
+
+    /// Applies the damage of a mob to the summon, returns true if the summon died
+    pub fn handle_hit(
+        &mut self,
+        ctx: &mut impl PoolCtx<Id = CharacterId>,
+        owner: CharacterId,
+        req: SummonHitReq,
+    ) -> anyhow::Result<bool> {
+        let Some(summon) = self.get_owned_mut(owner, req.summon_id) else {
+            return Ok(false);
+        };
+
+        let dead = match summon.hp.as_mut() {
+            Some(hp) => {
+                *hp = hp.saturating_sub(req.damage);
+                *hp == 0
+            }
+            None => false,
+        };
+
+        ctx.tx().broadcast_filter_encode(
+            SummonHitResp {
+                char: owner,
+                summon_id: req.summon_id,
+                atk_index: req.atk_index,
+                damage: req.damage,
+                mob_tmpl_id: req.mob.as_ref().map_or(0, |mob| mob.tmpl_id.0),
+                left: req.mob.as_ref().map_or(false, |mob| mob.left),
+            },
+            owner,
+        )?;
+
+        if dead {
+            self.remove(ctx, &req.summon_id, SummonLeaveType::Die)?;
+        }
+        Ok(dead)
+    }
+
+    /// Removes all summons of the owner
+    pub fn remove_owned(
+        &mut self,
+        ctx: &mut impl PoolCtx<Id = CharacterId>,
+        owner: CharacterId,
+        leave: SummonLeaveType,
+    ) -> anyhow::Result<Vec<Obj<Summon>>> {
+        let ids: Vec<_> = self
+            .0
+             .0
+            .values()
+            .filter(|summon| summon.char_id == owner)
+            .map(|summon| summon.id)
+            .collect();
+
+        let mut summons = Vec::with_capacity(ids.len());
+        for id in ids {
+            summons.extend(self.remove(ctx, &id, leave)?);
+        }
+        Ok(summons)
+    }
+}
+
+/// Summons of a character, at most one summon per skill
+#[derive(Debug, Default)]
+pub struct CharSummons {
+    /// Summons in the current field
+    active: BTreeMap<SkillId, ObjectId>,
+    /// Summons, which follow the character into the next field
+    carried: Vec<Obj<Summon>>,
+}
+
+impl CharSummons {
+    /// Adds the summon, returns the previous summon of the skill
+    pub fn insert(&mut self, skill_id: SkillId, id: ObjectId) -> Option<ObjectId> {
+        self.active.insert(skill_id, id)
+    }
+
+    pub fn remove(&mut self, id: ObjectId) -> Option<SkillId> {
+        self.carried.retain(|summon| summon.id != id);
+        let skill_id = self.skill_id(id)?;
+        self.active.remove(&skill_id);
+        Some(skill_id)
+    }
+
+    pub fn skill_id(&self, id: ObjectId) -> Option<SkillId> {
+        self.active
+            .iter()
+            .find(|(_, summon)| **summon == id)
+            .map(|(skill_id, _)| *skill_id)
+    }
+
+    pub fn get_by_skill(&self, skill_id: SkillId) -> Option<ObjectId> {
+        self.active.get(&skill_id).copied()
+    }
+
+    /// Called after the summons were removed from the old field,
+    /// only the summons which follow the character are kept
+    pub fn leave_field(&mut self, summons: impl IntoIterator<Item = Obj<Summon>>) {
+        self.active.clear();
+        self.carried.extend(
+            summons
+                .into_iter()
+                .filter(|summon| summon.move_ability.follows_owner()),
+        );
+    }
+
+    pub fn take_carried(&mut self) -> Vec<Obj<Summon>> {
+        std::mem::take(&mut self.carried)
+    }
+}
+
+impl Character {
+    /// Spawns the summon in the field, an existing summon of the same skill is replaced
+    pub fn add_summon(
+        &mut self,
+        ctx: &mut GameContext,
+        summon: Summon,
+    ) -> anyhow::Result<ObjectId> {
+        if let Some(old) = self.summons.get_by_skill(summon.skill_id) {
+            self.remove_summon(ctx, old, SummonLeaveType::Default)?;
+        }
+
+        let (skill_id, exp) = (summon.skill_id, summon.expiration);
+        let id = crate::field!(ctx).add_summon(summon)?;
+        self.summons.insert(skill_id, id);
+        self.pending.push(CharEvents::SummonExpired(id), exp);
+        Ok(id)
+    }
+
+    /// Removes the summon, summons which were already removed are ignored
+    pub fn remove_summon(
+        &mut self,
+        ctx: &mut GameContext,
+        id: ObjectId,
+        leave: SummonLeaveType,
+    ) -> anyhow::Result<()> {
+        if self.summons.remove(id).is_some() {
+            crate::field!(ctx).remove_summon(id, leave)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shroom_meta::class::SummonAssistType;
+
+    use super::*;
+
+    fn summon(move_ability: shroom_meta::class::SummonMoveAbility) -> Summon {
+        Summon {
+            pos: Vec2::default(),
+            fh: FootholdId::none(),
+            skill_id: SkillId(1),
+            skill_level: 1,
+            char_level: 1,
+            char_id: CharacterId(1),
+            move_ability,
+            assist_type: SummonAssistType::Attack,
+            expiration: GameTime::default(),
+            hp: None,
+            enter_type: SummonEnterType::CreateSummon,
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn char_summons() {
+        let mut summons = CharSummons::default();
+        assert_eq!(summons.insert(SkillId(1), ObjectId(1)), None);
+        assert_eq!(summons.insert(SkillId(1), ObjectId(2)), Some(ObjectId(1)));
+        assert_eq!(summons.insert(SkillId(2), ObjectId(3)), None);
+        assert_eq!(summons.skill_id(ObjectId(2)), Some(SkillId(1)));
+        assert_eq!(summons.remove(ObjectId(3)), Some(SkillId(2)));
+        assert_eq!(summons.remove(ObjectId(3)), None);
+
+        // Only following summons are carried into the next field
+        summons.leave_field([
+            Obj::new(
+                ObjectId(2),
+                summon(shroom_meta::class::SummonMoveAbility::Follow),
+            ),
+            Obj::new(
+                ObjectId(4),
+                summon(shroom_meta::class::SummonMoveAbility::None),
+            ),
+        ]);
+        assert_eq!(summons.get_by_skill(SkillId(1)), None);
+        let carried = summons.take_carried();
+        assert_eq!(carried.len(), 1);
+        assert_eq!(carried[0].id, ObjectId(2));
+        assert!(summons.take_carried().is_empty());
+    }
+}
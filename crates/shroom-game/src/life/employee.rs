@@ -1,9 +1,10 @@
+use shroom_data::services::merchant::Merchant;
 use shroom_meta::{
-    id::{FootholdId, ObjectId},
+    id::{CharacterId, FootholdId, ObjectId},
     twod::Vec2,
 };
 use shroom_proto95::game::life::employee::{
-    EmployeeCreateResp, EmployeeMiniRoomBalloon, EmployeeMiniRoomBalloonResp, EmployeeRemoveResp,
+    EmployeeBalloon, EmployeeCreateResp, EmployeeMiniRoomBalloonResp, EmployeeRemoveResp,
 };
 use shroom_srv::{
     game::pool::{Pool, PoolCtx, PoolItem},
@@ -14,12 +15,25 @@ use super::Obj;
 
 #[derive(Debug)]
 pub struct Employee {
+    pub owner: CharacterId,
     pub tmpl_id: u32,
     pub pos: Vec2,
     pub fh: FootholdId,
     pub char_name: String,
-    pub room_type: u8,
-    pub balloon: Option<EmployeeMiniRoomBalloon>,
+    pub balloon: EmployeeBalloon,
+}
+
+impl Employee {
+    pub fn from_merchant(merchant: &Merchant, balloon: EmployeeBalloon) -> Self {
+        Self {
+            owner: merchant.owner,
+            tmpl_id: merchant.tmpl_id.0,
+            pos: merchant.pos,
+            fh: merchant.fh,
+            char_name: merchant.owner_name.clone(),
+            balloon,
+        }
+    }
 }
 
 impl PoolItem for Employee {
@@ -35,7 +49,7 @@ impl PoolItem for Employee {
             pos: self.pos,
             fh: self.fh,
             char_name: self.char_name.clone(),
-            balloon: self.balloon.clone().into(),
+            balloon: self.balloon.clone(),
         }
     }
 
@@ -48,18 +62,30 @@ impl PoolItem for Employee {
 pub struct EmployeePool(pub Pool<Obj<Employee>>);
 
 impl EmployeePool {
+    pub fn from_elems(elems: impl Iterator<Item = Obj<Employee>>) -> Self {
+        Self(elems.collect())
+    }
+
+    pub fn find_by_owner(&self, owner: CharacterId) -> Option<ObjectId> {
+        self.0
+             .0
+            .values()
+            .find(|employee| employee.owner == owner)
+            .map(|employee| employee.id)
+    }
+
     pub fn update_balloon(
         &mut self,
         ctx: &mut impl PoolCtx,
         id: ObjectId,
-        balloon: Option<EmployeeMiniRoomBalloon>,
+        balloon: EmployeeBalloon,
     ) -> anyhow::Result<()> {
         let employee = self.must_get_mut(&id)?;
         employee.balloon = balloon.clone();
 
         ctx.tx().broadcast_encode(EmployeeMiniRoomBalloonResp {
             employee_id: id,
-            balloon: balloon.into(),
+            balloon,
         })?;
         Ok(())
     }
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{collections::HashSet, time::Duration};
 
 use rand::thread_rng;
 use shroom_meta::{
@@ -25,6 +25,7 @@ use shroom_srv::{
 use crate::{
     field::{AttackerContext, CharSetRef, FieldHandler},
     game::GameMessage,
+    services::party::{party_exp_share, PartyService},
 };
 
 use super::Obj;
@@ -454,6 +455,7 @@ impl MobPool {
     pub fn attack(
         &mut self,
         ctx: &mut impl PoolCtx<Id = CharacterId, Msg = GameMessage>,
+        parties: &PartyService,
         attacker: &impl AttackerContext,
         id: ObjectId,
         dmg: u32,
@@ -483,15 +485,26 @@ impl MobPool {
         )?;
 
         if mob.is_dead() {
-            Ok(Some(self.kill(ctx, id)?))
+            Ok(Some(self.kill(ctx, parties, id)?))
         } else {
             Ok(None)
         }
     }
 
+    /// Ids and the remaining HP of all mobs
+    pub fn mobs_hp(&self) -> Vec<(ObjectId, u32)> {
+        self.pool
+            .pool
+            .0
+            .values()
+            .map(|mob| (mob.id, mob.hp.value))
+            .collect()
+    }
+
     pub fn kill(
         &mut self,
         ctx: &mut impl PoolCtx<Id = CharacterId, Msg = GameMessage>,
+        parties: &PartyService,
         id: ObjectId,
     ) -> anyhow::Result<Mob> {
         let mob = self.remove(ctx, id, MobLeaveType::Etc(())).unwrap();
@@ -508,10 +521,33 @@ impl MobPool {
         }
 
         let exp = mob.meta.exp as u32;
+        let mut rewarded_parties = HashSet::new();
         // TODO do something with the damage
         for (atk, _) in mob.attackers.iter() {
-            ctx.tx()
-                .send_to(*atk, GameMessage::MobExp(mob.meta.id, exp, 100))
+            let Some(party) = parties.get_party_of(*atk) else {
+                ctx.tx()
+                    .send_to(*atk, GameMessage::MobExp(mob.meta.id, exp, 100));
+                continue;
+            };
+
+            if !rewarded_parties.insert(party.id) {
+                continue;
+            }
+
+            // The exp is shared with all party members in this field
+            let members: Vec<_> = party
+                .online_ids()
+                .filter(|id| ctx.tx().contains(id))
+                .collect();
+            let share = party_exp_share(exp, members.len());
+            for member in members {
+                let msg = if mob.attackers.contains_key(&member) {
+                    GameMessage::MobExp(mob.meta.id, share, 100)
+                } else {
+                    GameMessage::ExpGain(share)
+                };
+                ctx.tx().send_to(member, msg);
+            }
         }
 
         Ok(mob)
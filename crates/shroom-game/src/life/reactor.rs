@@ -1,9 +1,16 @@
-use std::num::Saturating;
+use std::time::Duration;
 
 use shroom_meta::{
-    drops::QuestDropFlags, field::FieldReactor, id::{ObjectId, ReactorId}, twod::Vec2
+    drops::QuestDropFlags,
+    field::FieldReactor,
+    id::{ObjectId, ReactorId},
+    reactor::ReactorInput,
+    twod::Vec2,
+    ReactorMeta,
+};
+use shroom_proto95::game::life::reactor::{
+    ReactorChangeStateResp, ReactorEnterFieldResp, ReactorLeaveFieldResp,
 };
-use shroom_proto95::game::life::reactor::{ReactorEnterFieldResp, ReactorLeaveFieldResp};
 use shroom_srv::{
     game::pool::{Pool, PoolCtx, PoolItem},
     GameTime,
@@ -17,31 +24,86 @@ use super::Obj;
 pub struct Reactor {
     pub pos: Vec2,
     pub tmpl_id: ReactorId,
-    pub state: Saturating<u8>,
+    pub state: u8,
     pub name: Option<String>,
     pub quest_drop_flags: QuestDropFlags,
+    pub meta: Option<ReactorMeta>,
+    /// Key of the reactor in the field meta, used to respawn it
+    pub key: u32,
+    /// Respawn delay after the reactor was destroyed
+    pub respawn_delay: Option<Duration>,
+}
+
+/// State change of a reactor, which is broadcasted to the field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReactorStateChange {
+    pub event_ix: u8,
+    pub state: u8,
+    pub anim_delay: Duration,
+    pub is_final: bool,
 }
 
 impl Reactor {
-    pub fn new(id: ReactorId, reactor: &FieldReactor) -> Self {
+    pub fn new(key: u32, reactor: &FieldReactor, meta: Option<ReactorMeta>) -> Self {
         Self {
             pos: reactor.pos,
-            tmpl_id: id,
-            state: Saturating(0),
+            tmpl_id: reactor.id,
+            state: 0,
             name: reactor.name.clone(), // TODO remove that allocation
-            quest_drop_flags: Default::default()
+            quest_drop_flags: Default::default(),
+            meta,
+            key,
+            // Only a positive time respawns the reactor
+            respawn_delay: reactor
+                .time
+                .filter(|time| (*time as i32) > 0)
+                .map(|time| Duration::from_secs(time as u64)),
         }
     }
 
-    pub fn attack(&mut self, flags: Option<&QuestDropFlags>) {
-        self.state -= 1;
-        if let Some(flags) = flags {
+    /// Advances the state with the input, returns the change if there was an event for the input
+    pub fn trigger(
+        &mut self,
+        input: &ReactorInput,
+        flags: Option<&QuestDropFlags>,
+    ) -> Option<ReactorStateChange> {
+        if self.is_dead() {
+            return None;
+        }
+
+        let (event_ix, next) = match self.meta {
+            Some(meta) => meta.next_state(self.state, input, self.pos)?,
+            // Without any states the first hit destroys the reactor
+            None => matches!(input, ReactorInput::Hit(_)).then_some((0, self.state + 1))?,
+        };
+
+        if let (ReactorInput::Hit(_), Some(flags)) = (input, flags) {
             self.quest_drop_flags.union(flags);
         }
+
+        self.state = next;
+        let anim_delay = self
+            .meta
+            .and_then(|meta| meta.get_state(next))
+            .map_or(0, |state| state.anim_delay_ms);
+        Some(ReactorStateChange {
+            event_ix,
+            state: next,
+            anim_delay: Duration::from_millis(anim_delay as u64),
+            is_final: self.is_dead(),
+        })
+    }
+
+    /// Action script, which runs when the final state is reached
+    pub fn action(&self) -> Option<&'static str> {
+        self.meta.and_then(|meta| meta.action.as_deref())
     }
 
     pub fn is_dead(&self) -> bool {
-        self.state.0 == 0
+        match self.meta {
+            Some(meta) => meta.is_final_state(self.state),
+            None => self.state > 0,
+        }
     }
 }
 
@@ -56,7 +118,7 @@ impl PoolItem for Reactor {
         ReactorEnterFieldResp {
             id,
             tmpl_id: self.tmpl_id,
-            state: self.state.0,
+            state: self.state,
             pos: self.pos,
             flipped: false,
             name: self.name.clone().unwrap_or_default(),
@@ -66,7 +128,7 @@ impl PoolItem for Reactor {
     fn leave_msg(&self, id: Self::Id, _param: Self::LeaveParam) -> Self::LeaveMsg {
         ReactorLeaveFieldResp {
             id,
-            state: self.state.0,
+            state: self.state,
             pos: self.pos,
         }
     }
@@ -80,19 +142,42 @@ impl ReactorPool {
         Self(elems.collect())
     }
 
-    pub fn attack(
+    /// Triggers the reactor and broadcasts the state change
+    pub fn trigger(
         &mut self,
         ctx: &mut impl PoolCtx,
         atk: &impl AttackerContext,
-        id: ObjectId
-    ) -> anyhow::Result<Option<Reactor>> {
+        id: ObjectId,
+        input: &ReactorInput,
+    ) -> anyhow::Result<Option<ReactorStateChange>> {
         let reactor = self.must_get_mut(&id)?;
         let quest_flags = atk.get_reactor_quest_flag(reactor.tmpl_id);
-        reactor.attack(quest_flags);
-        Ok(if reactor.is_dead() {
-            self.remove(ctx, &id, ())?.map(|r| r.item)
-        } else {
-            None
-        })
+        let Some(change) = reactor.trigger(input, quest_flags) else {
+            return Ok(None);
+        };
+
+        ctx.tx().broadcast_encode(ReactorChangeStateResp {
+            id,
+            state: change.state,
+            pos: reactor.pos,
+            animation_delay: change.anim_delay.into(),
+            proper_event_id: change.event_ix,
+            end_state: 0,
+        })?;
+        Ok(Some(change))
+    }
+
+    /// Finds a reactor, which is triggered by the dropped item
+    pub fn find_item_drop_target(&self, input: &ReactorInput) -> Option<ObjectId> {
+        self.0
+             .0
+            .values()
+            .find(|r| {
+                !r.is_dead()
+                    && r.meta
+                        .and_then(|meta| meta.next_state(r.state, input, r.pos))
+                        .is_some()
+            })
+            .map(|r| r.id)
     }
 }
@@ -0,0 +1,580 @@
+use shroom_data::{
+    model::inv::InventorySlot,
+    services::merchant::{Merchant, MerchantItem, MerchantStock},
+};
+use shroom_meta::{
+    id::{item_id::InventoryType, CharacterId, NpcId},
+    item::it::{ItemInfo, StackItem},
+};
+use shroom_proto95::{
+    game::{
+        mini_room::{
+            EntrustedShopAddSoldItem, EntrustedShopBuyItemReq, EntrustedShopBuyResult,
+            EntrustedShopCheckResult, EntrustedShopCheckResultResp, EntrustedShopCreateReq,
+            EntrustedShopEmployee, EntrustedShopEnterData, EntrustedShopItem,
+            EntrustedShopOwnerInfo, EntrustedShopPutItemReq, EntrustedShopRefresh,
+            EntrustedShopSoldItem, MiniRoomChat, MiniRoomChatReq, MiniRoomEnterError,
+            MiniRoomEnterResult, MiniRoomLeave, MiniRoomLeaveReason, MiniRoomResp,
+            MiniRoomUserChat, MiniRoomUsers, UserEntrustedShopReq, ENTRUSTED_SHOP_MAX_ITEMS,
+            ENTRUSTED_SHOP_MAX_USERS,
+        },
+        script::{MsgParamFlags, SayMsg, ScriptMessage, ScriptMessageResp},
+    },
+    shared::item::Item,
+};
+use shroom_srv::{act::Context, game::inventory::InvItem};
+
+use crate::{
+    field,
+    game::{GameContext, GameMessage, GameSession},
+    life::employee::Employee,
+    services::{
+        employee::{ShopError, ShopView},
+        trade::TradeItem,
+    },
+};
+
+/// Npc, which keeps the items of closed merchants
+pub const FREDRICK: NpcId = NpcId(9030000);
+
+/// Open time, which is shown to the owner
+const SHOP_OPEN_TIME_SECS: u32 = 24 * 60 * 60;
+
+impl From<ShopError> for MiniRoomEnterError {
+    fn from(err: ShopError) -> Self {
+        match err {
+            ShopError::Full => MiniRoomEnterError::Full,
+            ShopError::AlreadyVisiting => MiniRoomEnterError::Busy,
+            ShopError::OtherField => MiniRoomEnterError::OnlyInSameField,
+            ShopError::NoShop | ShopError::Closed => MiniRoomEnterError::NoRoom,
+            _ => MiniRoomEnterError::Etc,
+        }
+    }
+}
+
+fn stock_to_trade_item(stock: MerchantStock, quantity: usize) -> TradeItem {
+    match stock {
+        MerchantStock::Equip(item) => TradeItem::Equip(item),
+        MerchantStock::Stack(id) => TradeItem::Stack(id, quantity),
+    }
+}
+
+fn shop_item(item: &MerchantItem) -> EntrustedShopItem {
+    let proto = match &item.stock {
+        MerchantStock::Equip(equip) => Item::Equip(equip.as_ref().into()),
+        MerchantStock::Stack(id) => Item::Stack(
+            (&StackItem {
+                info: ItemInfo::from_id(*id, 0, false),
+                quantity: item.per_bundle,
+            })
+                .into(),
+        ),
+    };
+
+    EntrustedShopItem {
+        bundles: item.bundles,
+        per_bundle: item.per_bundle,
+        price: item.price,
+        item: proto,
+    }
+}
+
+impl GameSession {
+    fn shop_enter_data(&self, view: ShopView) -> EntrustedShopEnterData {
+        let owner_info = (view.pos == 0).then(|| EntrustedShopOwnerInfo {
+            time_left: SHOP_OPEN_TIME_SECS,
+            first_time: false,
+            sold: view
+                .sold
+                .iter()
+                .map(|sale| EntrustedShopSoldItem {
+                    item_id: sale.item_id,
+                    quantity: sale.quantity,
+                    price: sale.price,
+                    buyer: sale.buyer.clone(),
+                })
+                .collect::<Vec<_>>()
+                .into(),
+            money: view.money,
+        });
+
+        EntrustedShopEnterData {
+            max_users: ENTRUSTED_SHOP_MAX_USERS,
+            my_pos: view.pos,
+            employee: EntrustedShopEmployee {
+                pos: 0,
+                tmpl_id: view.tmpl_id,
+                name: view.owner_name.clone(),
+            },
+            visitors: MiniRoomUsers(view.visitors),
+            msg_count: 0,
+            owner_name: view.owner_name,
+            owner_info: owner_info.into(),
+            title: view.title,
+            max_items: ENTRUSTED_SHOP_MAX_ITEMS,
+            money: self.session.char.money(),
+            items: view.items.iter().map(shop_item).collect::<Vec<_>>().into(),
+        }
+    }
+
+    /// Saves the merchant with the next save of the character, so the
+    /// inventory and the shop are written together
+    fn persist_shop(&mut self, owner: CharacterId) {
+        self.session.mark_shop_dirty(owner);
+        self.request_autosave();
+    }
+
+    fn is_shop_owner(&self) -> bool {
+        self.services
+            .game
+            .employee
+            .view(self.char_id())
+            .is_some_and(|view| view.pos == 0)
+    }
+
+    pub(crate) fn send_shop_refresh(&self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        let Some(view) = self.services.game.employee.view(self.char_id()) else {
+            return Ok(());
+        };
+
+        ctx.socket
+            .reply(MiniRoomResp::EntrustedShopRefresh(EntrustedShopRefresh {
+                money: self.session.char.money(),
+                items: view.items.iter().map(shop_item).collect::<Vec<_>>().into(),
+            }))?;
+        Ok(())
+    }
+
+    /// Refreshes the own shop window and the windows of the other users
+    fn refresh_shop(&self, ctx: &mut GameContext, audience: &[CharacterId]) -> anyhow::Result<()> {
+        let id = self.char_id();
+        for user in audience.iter().filter(|user| **user != id) {
+            self.services
+                .game
+                .sessions
+                .send_to(*user, GameMessage::ShopRefresh);
+        }
+        self.send_shop_refresh(ctx)
+    }
+
+    pub fn handle_entrusted_shop_req(
+        &mut self,
+        ctx: &mut GameContext,
+        req: UserEntrustedShopReq,
+    ) -> anyhow::Result<()> {
+        match req {
+            UserEntrustedShopReq::CheckOpenPossible(()) => {
+                let result = if self.services.game.employee.has_merchant(self.char_id()) {
+                    EntrustedShopCheckResult::RetrieveFirst
+                } else {
+                    EntrustedShopCheckResult::OpenPossible
+                };
+                ctx.socket.reply(EntrustedShopCheckResultResp { result })?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn shop_create(
+        &mut self,
+        ctx: &mut GameContext,
+        req: EntrustedShopCreateReq,
+    ) -> anyhow::Result<()> {
+        let id = self.char_id();
+        let chr = &self.session.char;
+        let has_permit = req.item_id.is_shop_employee()
+            && InventorySlot::try_from((InventoryType::Cash, req.slot as i16))
+                .ok()
+                .and_then(|slot| chr.inventory.invs.cash.get(slot.as_slot()))
+                .is_some_and(|item| item.id() == req.item_id);
+        if !has_permit || self.services.game.trade.get_member(id).is_some() {
+            ctx.socket
+                .reply(MiniRoomResp::EnterResult(MiniRoomEnterResult::Error(
+                    MiniRoomEnterError::Etc,
+                )))?;
+            return Ok(());
+        }
+
+        let res = self.services.game.employee.create(Merchant {
+            owner: id,
+            owner_name: chr.name.clone(),
+            field_id: self.field_id,
+            pos: chr.pos,
+            fh: chr.fh,
+            tmpl_id: req.item_id,
+            title: req.title,
+            money: 0,
+            open: false,
+            items: Vec::new(),
+        });
+
+        ctx.socket.reply(MiniRoomResp::EnterResult(match res {
+            Ok(view) => {
+                self.persist_shop(id);
+                MiniRoomEnterResult::EntrustedShop(self.shop_enter_data(view))
+            }
+            Err(err) => MiniRoomEnterResult::Error(err.into()),
+        }))?;
+        Ok(())
+    }
+
+    pub(crate) fn shop_enter(&mut self, ctx: &mut GameContext, room_id: u32) -> anyhow::Result<()> {
+        let id = self.char_id();
+        let res =
+            self.services
+                .game
+                .employee
+                .enter(id, room_id, self.mini_room_user(0), self.field_id);
+        let (view, balloon) = match res {
+            Ok(res) => res,
+            Err(err) => {
+                ctx.socket
+                    .reply(MiniRoomResp::EnterResult(MiniRoomEnterResult::Error(
+                        err.into(),
+                    )))?;
+                return Ok(());
+            }
+        };
+
+        if view.pos != 0 {
+            self.services.game.sessions.send_all_encode(
+                view.audience.iter().copied().filter(|user| *user != id),
+                MiniRoomResp::Enter(self.mini_room_user(view.pos)),
+            )?;
+            field!(ctx).update_employee_balloon(view.owner, balloon)?;
+        }
+
+        ctx.socket.reply(MiniRoomResp::EnterResult(
+            MiniRoomEnterResult::EntrustedShop(self.shop_enter_data(view)),
+        ))?;
+        Ok(())
+    }
+
+    pub(crate) fn shop_chat(
+        &mut self,
+        ctx: &mut GameContext,
+        req: MiniRoomChatReq,
+    ) -> anyhow::Result<()> {
+        let id = self.char_id();
+        let Some(view) = self.services.game.employee.view(id) else {
+            return Ok(());
+        };
+
+        let pkt = || {
+            MiniRoomResp::Chat(MiniRoomChat::UserChat(MiniRoomUserChat {
+                pos: view.pos,
+                msg: format!("{} : {}", self.session.char.name, req.msg),
+            }))
+        };
+        self.services
+            .game
+            .sessions
+            .send_all_encode(view.audience.iter().copied().filter(|u| *u != id), pkt())?;
+        ctx.socket.reply(pkt())?;
+        Ok(())
+    }
+
+    /// Leaves the visited or managed shop, returns the position of the user
+    pub(crate) fn leave_shop(&mut self, ctx: &mut GameContext) -> anyhow::Result<Option<u8>> {
+        let id = self.char_id();
+        let Some(leave) = self.services.game.employee.leave(id) else {
+            return Ok(None);
+        };
+
+        self.services.game.sessions.send_all_encode(
+            leave.audience.iter().copied(),
+            MiniRoomResp::Leave(MiniRoomLeave {
+                pos: leave.pos,
+                reason: MiniRoomLeaveReason::UserRequest,
+            }),
+        )?;
+        if leave.field == self.field_id {
+            field!(ctx).update_employee_balloon(leave.owner, leave.balloon)?;
+        }
+
+        // Shop was never opened, so the items are handed back
+        if let Some(merchant) = leave.cancelled {
+            self.session.char.update_mesos(merchant.money as i32);
+            for item in merchant.items {
+                let quantity = item.quantity();
+                self.restore_trade_item(stock_to_trade_item(item.stock, quantity))?;
+            }
+            self.persist_shop(id);
+        }
+        Ok(Some(leave.pos))
+    }
+
+    pub(crate) fn shop_leave_req(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        if let Some(pos) = self.leave_shop(ctx)? {
+            ctx.socket.reply(MiniRoomResp::Leave(MiniRoomLeave {
+                pos,
+                reason: MiniRoomLeaveReason::UserRequest,
+            }))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn shop_put_item(
+        &mut self,
+        ctx: &mut GameContext,
+        req: EntrustedShopPutItemReq,
+    ) -> anyhow::Result<()> {
+        let is_equip = req.inv_type == InventoryType::Equip;
+        let valid = req.bundles > 0
+            && req.per_bundle > 0
+            && req.price > 0
+            && (!is_equip || (req.bundles == 1 && req.per_bundle == 1));
+        if !valid || !self.is_shop_owner() {
+            return Ok(());
+        }
+
+        let Some(count) = req.bundles.checked_mul(req.per_bundle) else {
+            return Ok(());
+        };
+        if !self.is_tradable_slot(req.inv_type, req.slot) {
+            return self.reject_untradable(ctx);
+        }
+        let Some(item) = self.take_trade_item(req.inv_type, req.slot, count)? else {
+            return Ok(());
+        };
+
+        let stock = match item {
+            TradeItem::Equip(item) => MerchantStock::Equip(item),
+            TradeItem::Stack(id, _) => MerchantStock::Stack(id),
+        };
+        let id = self.char_id();
+        let res = self.services.game.employee.put_item(
+            id,
+            MerchantItem {
+                stock,
+                price: req.price,
+                bundles: req.bundles,
+                per_bundle: req.per_bundle,
+            },
+        );
+        if let Err((err, item)) = res {
+            log::info!("Unable to put shop item: {err}");
+            return self.restore_trade_item(stock_to_trade_item(item.stock, count as usize));
+        }
+
+        self.persist_shop(id);
+        self.send_shop_refresh(ctx)
+    }
+
+    pub(crate) fn shop_buy_item(
+        &mut self,
+        ctx: &mut GameContext,
+        req: EntrustedShopBuyItemReq,
+    ) -> anyhow::Result<()> {
+        let id = self.char_id();
+        let index = req.index as usize;
+        let employee = &self.services.game.employee;
+        let chr = &self.session.char;
+
+        let res = employee
+            .quote(id, index, req.bundles)
+            .and_then(|(item_id, quantity, price)| {
+                if price > chr.money() {
+                    return Err(ShopError::NoMoney);
+                }
+                Ok((item_id, quantity))
+            });
+        let err = match res {
+            Ok((item_id, quantity)) if !chr.inventory.can_add_all(&[(item_id, quantity)])? => {
+                Some(EntrustedShopBuyResult::NoSlot)
+            }
+            Ok(_) => None,
+            Err(ShopError::NoMoney) => Some(EntrustedShopBuyResult::NoMoney),
+            Err(ShopError::SoldOut | ShopError::InvalidItem) => {
+                Some(EntrustedShopBuyResult::SoldOut)
+            }
+            Err(_) => Some(EntrustedShopBuyResult::Unavailable),
+        };
+        if let Some(err) = err {
+            ctx.socket
+                .reply(MiniRoomResp::EntrustedShopBuyResult(err))?;
+            return Ok(());
+        }
+
+        let purchase = match employee.buy(id, index, req.bundles, chr.money(), &chr.name) {
+            Ok(purchase) => purchase,
+            Err(err) => {
+                log::info!("Unable to buy shop item: {err}");
+                ctx.socket.reply(MiniRoomResp::EntrustedShopBuyResult(
+                    EntrustedShopBuyResult::Unavailable,
+                ))?;
+                return Ok(());
+            }
+        };
+
+        let buyer = chr.name.clone();
+        self.session.char.update_mesos(-(purchase.price as i32));
+        self.restore_trade_item(stock_to_trade_item(purchase.stock, purchase.quantity))?;
+
+        if purchase.owner_inside {
+            self.services.game.sessions.send_to_encode(
+                purchase.owner,
+                MiniRoomResp::EntrustedShopAddSoldItem(EntrustedShopAddSoldItem {
+                    index: req.index,
+                    quantity: req.bundles,
+                    buyer,
+                }),
+            )?;
+        }
+        self.persist_shop(purchase.owner);
+        self.refresh_shop(ctx, &purchase.audience)
+    }
+
+    pub(crate) fn shop_move_item_to_inventory(
+        &mut self,
+        ctx: &mut GameContext,
+        index: u16,
+    ) -> anyhow::Result<()> {
+        let id = self.char_id();
+        let index = index as usize;
+        let Some(view) = self.services.game.employee.view(id).filter(|v| v.pos == 0) else {
+            return Ok(());
+        };
+        let Some(item) = view.items.get(index) else {
+            return Ok(());
+        };
+        if !self
+            .session
+            .char
+            .inventory
+            .can_add_all(&[(item.id(), item.quantity())])?
+        {
+            return Ok(());
+        }
+
+        let item = self.services.game.employee.take_item(id, index)?;
+        if !item.is_sold_out() {
+            let quantity = item.quantity();
+            self.restore_trade_item(stock_to_trade_item(item.stock, quantity))?;
+        }
+
+        self.persist_shop(id);
+        self.refresh_shop(ctx, &view.audience)
+    }
+
+    pub(crate) fn shop_arrange(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        let id = self.char_id();
+        if self.services.game.employee.arrange(id).is_err() {
+            return Ok(());
+        }
+
+        self.persist_shop(id);
+        self.send_shop_refresh(ctx)
+    }
+
+    pub(crate) fn shop_withdraw_money(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        let id = self.char_id();
+        let max = (i32::MAX as u32).saturating_sub(self.session.char.money());
+        let Ok(money) = self.services.game.employee.withdraw_money(id, max) else {
+            return Ok(());
+        };
+
+        self.session.char.update_mesos(money as i32);
+        self.persist_shop(id);
+        self.send_shop_refresh(ctx)
+    }
+
+    pub(crate) fn shop_open(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        let id = self.char_id();
+        let (merchant, balloon) = match self.services.game.employee.open(id) {
+            Ok(res) => res,
+            Err(err) => {
+                log::info!("Unable to open shop: {err}");
+                return Ok(());
+            }
+        };
+
+        field!(ctx).add_employee(Employee::from_merchant(&merchant, balloon))?;
+        self.persist_shop(id);
+        Ok(())
+    }
+
+    /// Closes the shop, the items and mesos are kept by Fredrick
+    pub(crate) fn shop_go_out(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        let id = self.char_id();
+        let visitors = match self.services.game.employee.close(id) {
+            Ok(visitors) => visitors,
+            Err(err) => {
+                log::info!("Unable to close shop: {err}");
+                return Ok(());
+            }
+        };
+
+        for (visitor, pos) in visitors {
+            self.services.game.sessions.send_to(
+                visitor,
+                GameMessage::MiniRoomLeave(pos, MiniRoomLeaveReason::Closed),
+            );
+        }
+        field!(ctx).remove_employee(id)?;
+        ctx.socket.reply(MiniRoomResp::Leave(MiniRoomLeave {
+            pos: 0,
+            reason: MiniRoomLeaveReason::Closed,
+        }))?;
+        self.persist_shop(id);
+        Ok(())
+    }
+
+    fn fredrick_say(&self, ctx: &mut GameContext, txt: &str) -> anyhow::Result<()> {
+        ctx.socket.reply(ScriptMessageResp {
+            script_flag: 0x4,
+            speaker_id: FREDRICK.0,
+            msg: ScriptMessage::Say(SayMsg {
+                param: MsgParamFlags::empty(),
+                speaker_tmpl_id: None.into(),
+                txt: txt.to_string(),
+                has_prev: false,
+                has_next: false,
+            }),
+        })?;
+        Ok(())
+    }
+
+    /// Hands the items and mesos of a closed merchant back to the owner
+    pub(crate) fn open_store_bank(
+        &mut self,
+        ctx: &mut GameContext,
+        npc: NpcId,
+    ) -> anyhow::Result<bool> {
+        if npc != FREDRICK {
+            return Ok(false);
+        }
+
+        let id = self.char_id();
+        let employee = &self.services.game.employee;
+        let (items, money) = match employee.stored(id) {
+            Ok(stored) => stored,
+            Err(ShopError::NoShop) => {
+                self.fredrick_say(ctx, "I don't keep any items or mesos for you.")?;
+                return Ok(true);
+            }
+            Err(_) => {
+                self.fredrick_say(ctx, "Your merchant is still open, close it first.")?;
+                return Ok(true);
+            }
+        };
+
+        let chr = &self.session.char;
+        let fits = chr.inventory.can_add_all(&items)?
+            && chr.money() as u64 + money as u64 <= i32::MAX as u64;
+        if !fits {
+            self.fredrick_say(ctx, "Please make some room in your inventory first.")?;
+            return Ok(true);
+        }
+
+        let merchant = employee.retrieve(id)?;
+        self.session.char.update_mesos(merchant.money as i32);
+        for item in merchant.items {
+            let quantity = item.quantity();
+            self.restore_trade_item(stock_to_trade_item(item.stock, quantity))?;
+        }
+        self.persist_shop(id);
+        self.fredrick_say(ctx, "Here are your items and mesos.")?;
+        Ok(true)
+    }
+}
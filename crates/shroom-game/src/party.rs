@@ -0,0 +1,323 @@
+use shroom_meta::id::{FieldId, SkillId};
+use shroom_proto95::game::{
+    party::{
+        ChangePartyLeader, InviteParty, JoinParty, MemberChangeLevelJob, MemberWithdraw, NewParty,
+        PartyReq, PartyResultReq, PartyResultResp, UserMigrationParty, WithdrawParty,
+    },
+    user::remote::UserReceiveHPResp,
+};
+
+use crate::{
+    game::{GameContext, GameMessage, GameSession},
+    services::party::{Party, PartyError, PartyMemberInfo, PartyWithdraw},
+};
+
+impl PartyError {
+    fn join_resp(&self) -> PartyResultResp {
+        match self {
+            PartyError::AlreadyJoined => PartyResultResp::JoinAlreadyJoined(()),
+            PartyError::Full => PartyResultResp::JoinAlreadyFull(()),
+            _ => PartyResultResp::JoinUnknown(()),
+        }
+    }
+}
+
+impl GameSession {
+    fn party_member_info(&self) -> PartyMemberInfo {
+        let chr = &self.session.char;
+        PartyMemberInfo {
+            id: chr.id,
+            name: chr.name.clone(),
+            job: chr.stats.job,
+            level: chr.stats.level,
+            channel: self.channel_id,
+            field: self.field_id,
+            online: true,
+        }
+    }
+
+    fn send_party(&self, party: &Party, msg: PartyResultResp) -> anyhow::Result<()> {
+        self.services
+            .game
+            .sessions
+            .send_all_encode(party.online_ids(), msg)?;
+        Ok(())
+    }
+
+    fn send_party_update(&self, party: &Party) -> anyhow::Result<()> {
+        self.send_party(
+            party,
+            PartyResultResp::UserMigration(UserMigrationParty {
+                party_id: party.id,
+                party_data: party.party_data(),
+            }),
+        )
+    }
+
+    /// Updates the channel and field of the member for the party
+    pub(crate) fn on_party_world_enter(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        let (id, channel, field) = (self.char_id(), self.channel_id, self.field_id);
+        let Some(party) = self.services.game.party.update_member(id, |m| {
+            m.channel = channel;
+            m.field = field;
+            m.online = true;
+        }) else {
+            return Ok(());
+        };
+
+        ctx.socket.reply(PartyResultResp::LoadParty(UserMigrationParty {
+            party_id: party.id,
+            party_data: party.party_data(),
+        }))?;
+        self.send_party_update(&party)?;
+        self.send_party_hp(ctx, &party)?;
+
+        Ok(())
+    }
+
+    /// Marks the member as offline for the party and drops pending invites
+    pub(crate) fn on_party_world_leave(&mut self) -> anyhow::Result<()> {
+        let id = self.char_id();
+        self.services.game.party.remove_invite(id);
+        if let Some(party) = self
+            .services
+            .game
+            .party
+            .update_member(id, |m| m.online = false)
+        {
+            self.send_party_update(&party)?;
+        }
+        Ok(())
+    }
+
+    /// Shares the hp with the party members in the same field and
+    /// receives the hp of those members
+    fn send_party_hp(&self, ctx: &mut GameContext, party: &Party) -> anyhow::Result<()> {
+        let id = self.char_id();
+        for member in party.online_ids().filter(|m| *m != id) {
+            if !ctx.room.tx.contains(&member) {
+                continue;
+            }
+
+            ctx.room.tx.send_to_encode(member, self.hp_resp())?;
+            ctx.room.tx.send_to(member, GameMessage::PartyHpRequest(id));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn hp_resp(&self) -> UserReceiveHPResp {
+        let hp = &self.session.char.stats.hp;
+        UserReceiveHPResp {
+            char_id: self.char_id(),
+            hp: hp.value,
+            max_hp: hp.max,
+        }
+    }
+
+    /// Updates the party about changed stats, must be called before the stats are flushed
+    pub fn update_party_stats(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        let stats = &self.session.char.stats;
+        let (hp_changed, level_job_changed) = (stats.is_hp_changed(), stats.is_level_job_changed());
+        if !hp_changed && !level_job_changed {
+            return Ok(());
+        }
+
+        let id = self.char_id();
+        let Some(party) = self.services.game.party.get_party_of(id) else {
+            return Ok(());
+        };
+
+        if hp_changed {
+            for member in party.online_ids().filter(|m| *m != id) {
+                ctx.room.tx.send_to_encode(member, self.hp_resp())?;
+            }
+        }
+
+        if level_job_changed {
+            let (level, job) = (self.session.char.stats.level, self.session.char.stats.job);
+            let Some(party) = self.services.game.party.update_member(id, |m| {
+                m.level = level;
+                m.job = job;
+            }) else {
+                return Ok(());
+            };
+            self.send_party(
+                &party,
+                PartyResultResp::ChangeLevelJob(MemberChangeLevelJob {
+                    char_id: id,
+                    level: level as u32,
+                    job: job as u32,
+                }),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_party_req(&mut self, ctx: &mut GameContext, req: PartyReq) -> anyhow::Result<()> {
+        let id = self.char_id();
+        let party_svc = &self.services.game.party;
+        match req {
+            PartyReq::CreateNewParty(()) => {
+                let resp = match party_svc.create(self.party_member_info()) {
+                    Ok(party) => PartyResultResp::CreateNewParty(NewParty {
+                        party_id: party.id,
+                        town_id: 999999999,
+                        field_id: FieldId::NONE,
+                        skill_id: SkillId(0),
+                        u1: 0,
+                        u2: 0,
+                    }),
+                    Err(PartyError::AlreadyJoined) => {
+                        PartyResultResp::CreateNewPartyAlreadyJoined(())
+                    }
+                    Err(_) => PartyResultResp::CreateNewPartyUnknown(()),
+                };
+                ctx.socket.reply(resp)?;
+            }
+            PartyReq::WithdrawParty(()) => match party_svc.withdraw(id) {
+                Ok(PartyWithdraw::Disbanded(party)) => {
+                    self.send_party(
+                        &party,
+                        PartyResultResp::Withdraw(WithdrawParty {
+                            party_id: party.id,
+                            char_id: id,
+                            member_withdraw: None.into(),
+                        }),
+                    )?;
+                }
+                Ok(PartyWithdraw::Left(party, member)) => {
+                    let msg = || {
+                        PartyResultResp::Withdraw(WithdrawParty {
+                            party_id: party.id,
+                            char_id: id,
+                            member_withdraw: Some(MemberWithdraw {
+                                kicked: false,
+                                name: member.name.clone(),
+                                party_data: party.party_data(),
+                            })
+                            .into(),
+                        })
+                    };
+                    ctx.socket.reply(msg())?;
+                    self.send_party(&party, msg())?;
+                }
+                Err(_) => {
+                    ctx.socket.reply(PartyResultResp::WithdrawNotJoined(()))?;
+                }
+            },
+            PartyReq::JoinParty(party_id) => {
+                match party_svc.join(self.party_member_info(), party_id) {
+                    Ok(party) => {
+                        self.send_party(
+                            &party,
+                            PartyResultResp::Join(JoinParty {
+                                party_id,
+                                name: self.session.char.name.clone(),
+                                party_data: party.party_data(),
+                            }),
+                        )?;
+                        self.send_party_hp(ctx, &party)?;
+                    }
+                    Err(err) => {
+                        ctx.socket.reply(err.join_resp())?;
+                    }
+                }
+            }
+            PartyReq::InviteParty(name) => {
+                let Some(target) = self.services.game.online.get_by_name(self.world_id, &name)
+                else {
+                    ctx.socket.reply(PartyResultResp::JoinUnknownUser(()))?;
+                    return Ok(());
+                };
+
+                let resp = match party_svc.invite(id, target.id) {
+                    Ok(party) => {
+                        let chr = &self.session.char;
+                        self.services.game.sessions.send_to_encode(
+                            target.id,
+                            PartyResultResp::Invite(InviteParty {
+                                party_id: party.id,
+                                inviter: chr.name.clone(),
+                                level: chr.stats.level as u32,
+                                job: chr.stats.job as u32,
+                                u1: 0,
+                            }),
+                        )?;
+                        PartyResultResp::InviteSent(target.name)
+                    }
+                    Err(PartyError::AlreadyJoined) => PartyResultResp::JoinAlreadyJoined(()),
+                    Err(PartyError::AlreadyInvited) => PartyResultResp::InviteAlreadyInvited(()),
+                    Err(PartyError::Full) => PartyResultResp::JoinAlreadyFull(()),
+                    Err(_) => PartyResultResp::JoinUnknown(()),
+                };
+                ctx.socket.reply(resp)?;
+            }
+            PartyReq::KickParty(target) => match party_svc.kick(id, target) {
+                Ok((party, member)) => {
+                    let msg = || {
+                        PartyResultResp::Withdraw(WithdrawParty {
+                            party_id: party.id,
+                            char_id: target,
+                            member_withdraw: Some(MemberWithdraw {
+                                kicked: true,
+                                name: member.name.clone(),
+                                party_data: party.party_data(),
+                            })
+                            .into(),
+                        })
+                    };
+                    self.services.game.sessions.send_to_encode(target, msg())?;
+                    self.send_party(&party, msg())?;
+                }
+                Err(_) => {
+                    ctx.socket.reply(PartyResultResp::WithdrawUnknown(()))?;
+                }
+            },
+            PartyReq::ChangePartyLeader(new_leader) => {
+                match party_svc.change_leader(id, new_leader) {
+                    Ok(party) => {
+                        self.send_party(
+                            &party,
+                            PartyResultResp::ChangeLeader(ChangePartyLeader {
+                                party_id: party.id,
+                                new_leader,
+                                disconnect: false,
+                            }),
+                        )?;
+                    }
+                    Err(_) => {
+                        ctx.socket.reply(PartyResultResp::ChangeLeaderUnknown(()))?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_party_result_req(
+        &mut self,
+        _ctx: &mut GameContext,
+        req: PartyResultReq,
+    ) -> anyhow::Result<()> {
+        let (PartyResultReq::InviteBlocked(res)
+        | PartyResultReq::InviteAlreadyInvited(res)
+        | PartyResultReq::InviteRejected(res)) = req;
+
+        let Some(party) = self
+            .services
+            .game
+            .party
+            .decline(self.char_id(), res.party_id)
+        else {
+            return Ok(());
+        };
+
+        self.services.game.sessions.send_to_encode(
+            party.leader,
+            PartyResultResp::InviteRejected(self.session.char.name.clone()),
+        )?;
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
-use clap::{Command, FromArgMatches, Parser, Subcommand};
+use clap::{Args, Command, FromArgMatches, Parser, Subcommand};
 
 use itertools::Itertools;
+use shroom_data::services::account::BanRequest;
 use shroom_meta::{
     id::{job_id::JobId, FieldId, ItemId, MobId, NpcId, QuestId, SkillId},
     twod::Rect32,
@@ -8,7 +9,6 @@ use shroom_meta::{
 use shroom_proto95::game::{
     field::{FieldEffectResp, TrembleEffectData},
     quest::{ConstantU8, QuestRecordMessageResp, QuestState},
-    shop::{OpenShopResp, ShopItem},
     user::pet::PetActionResp,
 };
 
@@ -23,6 +23,7 @@ use crate::{
 use crate::{
     game::{GameContext, GameSession},
     life::char::pet::Pet,
+    trunk::TRUNK_NPCS,
 };
 use shroom_srv::act::Context;
 
@@ -46,8 +47,11 @@ pub enum ReplCmd {
     StopSpamDrop,
     //Dialog,
     Shop,
+    Trunk,
+    TrunkExpand { slots: u8 },
+    GuildPoints { points: u32 },
     Img,
-    Stats { add: u16 },
+    Stats { add: Option<u16> },
     Freeze,
     Zakum,
     Go { q: String },
@@ -60,6 +64,68 @@ pub enum ReplCmd {
     UpdateQuest { id: u16, state: String },
     GiveScroll,
     EarthQuake,
+    BlockWhisper,
+    Hide,
+    God,
+    KillAll,
+    Ban(BanArgs),
+    Unban { name: String },
+}
+
+/// Bans the account of the character, permanently if no days are given
+#[derive(Args, Debug)]
+pub struct BanArgs {
+    name: String,
+    /// Reason code shown by the client
+    reason: u8,
+    days: Option<u32>,
+    /// Also ban the last ip of the account
+    #[arg(long)]
+    ip: bool,
+    /// Also ban the last machine of the account
+    #[arg(long)]
+    machine: bool,
+}
+
+/// Permission level of an account, stored as `gm_level`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GmLevel {
+    Player = 0,
+    Gm = 1,
+    Admin = 2,
+}
+
+impl From<i32> for GmLevel {
+    fn from(level: i32) -> Self {
+        match level {
+            i32::MIN..=0 => Self::Player,
+            1 => Self::Gm,
+            _ => Self::Admin,
+        }
+    }
+}
+
+impl ReplCmd {
+    /// Lowest level, which is allowed to run the command
+    pub fn required_gm_level(&self) -> GmLevel {
+        match self {
+            Self::Dispose
+            | Self::Stats { add: None }
+            | Self::Chat { .. }
+            | Self::BlockWhisper => GmLevel::Player,
+            Self::Teleport { .. }
+            | Self::Go { .. }
+            | Self::Hide
+            | Self::God
+            | Self::KillAll
+            | Self::Ban(_)
+            | Self::Unban { .. }
+            | Self::Aggro
+            | Self::Freeze
+            | Self::Img => GmLevel::Gm,
+            _ => GmLevel::Admin,
+        }
+    }
 }
 
 pub struct GameRepl {
@@ -108,6 +174,49 @@ impl GameSession {
     ) -> anyhow::Result<Option<String>> {
         let chr = &self.session.char;
         Ok(match cmd {
+            ReplCmd::Hide => {
+                let hidden = !self.session.char.hidden;
+                self.session.char.hidden = hidden;
+                if hidden {
+                    field!(ctx).hide_user(self.session.char.id)?;
+                } else {
+                    field!(ctx).show_user(&self.session.char)?;
+                }
+                Some(format!("Hidden: {hidden}"))
+            }
+            ReplCmd::God => {
+                let invincible = !self.session.char.invincible;
+                self.session.char.invincible = invincible;
+                Some(format!("Invincible: {invincible}"))
+            }
+            ReplCmd::KillAll => {
+                let killed = field!(ctx).kill_all_mobs(&self.session.char)?;
+                Some(format!("Killed {killed} mobs"))
+            }
+            ReplCmd::Ban(args) => {
+                self.ban_char(
+                    args.name,
+                    BanRequest {
+                        reason_code: args.reason,
+                        reason: Some(format!("Banned by {}", chr.name)),
+                        duration: args.days.map(|days| chrono::Duration::days(days as i64)),
+                        ip: args.ip,
+                        machine: args.machine,
+                    },
+                );
+                None
+            }
+            ReplCmd::Unban { name } => {
+                self.unban_char(name);
+                None
+            }
+            ReplCmd::BlockWhisper => {
+                let blocked = !self.session.char.whisper_blocked;
+                self.session.char.whisper_blocked = blocked;
+                self.session.char.dirty.char = true;
+                self.services.game.online.update(self.online_char());
+                Some(format!("Whispers blocked: {blocked}"))
+            }
             ReplCmd::EarthQuake => {
                 ctx.socket
                     .reply(FieldEffectResp::Tremble(TrembleEffectData {
@@ -259,13 +368,33 @@ impl GameSession {
                 self.session.char.add_sp(add);
                 None
             }
-            ReplCmd::Stats { add } => {
+            ReplCmd::Stats { add: Some(add) } => {
                 *self.session.char.stats.str_mut() += add;
                 *self.session.char.stats.int_mut() += add;
                 *self.session.char.stats.dex_mut() += add;
                 *self.session.char.stats.luk_mut() += add;
                 None
             }
+            ReplCmd::Stats { add: None } => {
+                let s = &chr.derived;
+                Some(format!(
+                    "STR {} DEX {} INT {} LUK {} HP {} MP {} ATT {} MATT {} DEF {} MDEF {} ACC {} AVO {} SPD {} JMP {}",
+                    s.str,
+                    s.dex,
+                    s.int,
+                    s.luk,
+                    s.max_hp,
+                    s.max_mp,
+                    s.pad,
+                    s.mad,
+                    s.pdd,
+                    s.mdd,
+                    s.acc,
+                    s.eva,
+                    s.speed,
+                    s.jump
+                ))
+            }
             ReplCmd::Job { id } => {
                 let job = JobId::try_from(id as u16)?;
                 self.session.char.change_job(job, true)?;
@@ -295,26 +424,22 @@ impl GameSession {
                 None
             }
             ReplCmd::Shop => {
-                let npc_tmpl_id: NpcId = 21000.into();
-                let shop = self.meta().get_npc_shop(npc_tmpl_id).unwrap();
-                ctx.socket.reply(OpenShopResp {
-                    npc_tmpl_id,
-                    items: shop
-                        .items
-                        .iter()
-                        .map(|item| ShopItem {
-                            item_id: ItemId(item.item_id),
-                            price: item.price,
-                            quantity: 10,
-                            discount_rate: 100,
-                            token_item_id: ItemId(0),
-                            token_price: 0,
-                            item_period: 0,
-                            level_limited: 0,
-                            max_per_slot: u8::MAX as u16,
-                        })
-                        .collect(),
-                })?;
+                self.open_shop(ctx, NpcId(21000))?;
+                None
+            }
+            ReplCmd::Trunk => {
+                self.open_trunk(ctx, TRUNK_NPCS[0])?;
+                None
+            }
+            ReplCmd::TrunkExpand { slots } => {
+                if self.session.expand_trunk(slots) {
+                    None
+                } else {
+                    Some("Trunk can't be expanded any further".to_string())
+                }
+            }
+            ReplCmd::GuildPoints { points } => {
+                self.add_guild_points(points)?;
                 None
             }
             ReplCmd::Freeze => {
@@ -399,9 +524,70 @@ impl GameSession {
         ctx: &mut GameContext,
         s: &str,
     ) -> anyhow::Result<Option<String>> {
-        Ok(match self.repl.match_cmd(s) {
-            Err(_) => Some(self.repl.help()),
-            Ok(cmd) => self.handle_repl_cmd(ctx, cmd)?,
-        })
+        let cmd = match self.repl.match_cmd(s) {
+            Err(_) => return Ok(Some(self.repl.help())),
+            Ok(cmd) => cmd,
+        };
+
+        let required = cmd.required_gm_level();
+        let allowed = self.gm_level() >= required;
+        if required > GmLevel::Player {
+            self.log_gm_command(s, allowed);
+        }
+
+        if !allowed {
+            log::warn!(
+                target: "audit",
+                "Character {} ({:?}) tried to use the {required:?} command: {s}",
+                self.session.char.name,
+                self.session.char.id
+            );
+            return Ok(Some("You are not allowed to use this command".to_string()));
+        }
+
+        self.handle_repl_cmd(ctx, cmd)
+    }
+
+    pub fn gm_level(&self) -> GmLevel {
+        GmLevel::from(self.session.acc.gm_level)
+    }
+
+    /// Records the GM command in the audit log in the background
+    fn log_gm_command(&self, cmd: &str, allowed: bool) {
+        let svc = self.services.game.clone();
+        let acc = self.session.acc.clone();
+        let char_id = self.session.char.id;
+        let cmd = cmd.to_string();
+        tokio::spawn(async move {
+            if let Err(err) = svc
+                .data
+                .account
+                .log_gm_command(&acc, char_id, &cmd, allowed)
+                .await
+            {
+                log::error!("Unable to log gm command of {char_id:?}: {err:?}");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gm_levels() {
+        assert_eq!(GmLevel::from(-1), GmLevel::Player);
+        assert_eq!(GmLevel::from(1), GmLevel::Gm);
+        assert_eq!(GmLevel::from(5), GmLevel::Admin);
+
+        let mut repl = GameRepl::new();
+        let level = |repl: &mut GameRepl, s: &str| repl.match_cmd(s).unwrap().required_gm_level();
+        assert_eq!(level(&mut repl, "dispose"), GmLevel::Player);
+        assert_eq!(level(&mut repl, "stats"), GmLevel::Player);
+        assert_eq!(level(&mut repl, "stats 5"), GmLevel::Admin);
+        assert_eq!(level(&mut repl, "hide"), GmLevel::Gm);
+        assert_eq!(level(&mut repl, "mesos 100"), GmLevel::Admin);
+        assert_eq!(level(&mut repl, "ban abc 1 3 --ip"), GmLevel::Gm);
     }
 }
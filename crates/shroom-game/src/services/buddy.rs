@@ -0,0 +1,44 @@
+use std::{collections::HashMap, future::Future, sync::Mutex};
+
+use shroom_meta::id::CharacterId;
+use tokio::{sync::oneshot, task::JoinHandle};
+
+/// Runs the background writes of the buddy lists, writes to the list of the
+/// same character are executed in the order they were spawned
+#[derive(Debug, Default)]
+pub struct BuddyWriteService {
+    writes: Mutex<HashMap<CharacterId, JoinHandle<()>>>,
+}
+
+impl BuddyWriteService {
+    /// Spawns the write to the buddy list of the owner, the write starts
+    /// after the previous write for the same owner finished
+    pub fn spawn<F>(&self, owner: CharacterId, task: F)
+    where
+        F: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        let mut writes = self.writes.lock().unwrap();
+        writes.retain(|_, handle| !handle.is_finished());
+
+        let prev = writes.remove(&owner);
+        let handle = tokio::spawn(async move {
+            if let Some(prev) = prev {
+                let _ = prev.await;
+            }
+            if let Err(err) = task.await {
+                log::error!("Unable to update buddy list of {owner:?}: {err:?}");
+            }
+        });
+        writes.insert(owner, handle);
+    }
+
+    /// Waits for all pending writes to the buddy list of the owner
+    pub async fn wait(&self, owner: CharacterId) {
+        let (tx, rx) = oneshot::channel();
+        self.spawn(owner, async move {
+            let _ = tx.send(());
+            Ok(())
+        });
+        let _ = rx.await;
+    }
+}
@@ -0,0 +1,28 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use shroom_data::services::char_save::SavedItemId;
+use shroom_meta::id::CharacterId;
+
+pub type CharSaveLock = Arc<tokio::sync::Mutex<Vec<SavedItemId>>>;
+
+/// Serializes the saves of a character, so the final save of a session waits
+/// for a running autosave. The lock keeps the ids of the items inserted by
+/// an autosave, until the session applied them
+#[derive(Debug, Default)]
+pub struct CharSaveLocks {
+    locks: Mutex<HashMap<CharacterId, CharSaveLock>>,
+}
+
+impl CharSaveLocks {
+    pub fn get(&self, id: CharacterId) -> CharSaveLock {
+        self.locks.lock().unwrap().entry(id).or_default().clone()
+    }
+
+    /// Removes the lock after the final save of the session
+    pub fn remove(&self, id: CharacterId) {
+        self.locks.lock().unwrap().remove(&id);
+    }
+}
@@ -0,0 +1,676 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use shroom_data::services::{
+    char_save::{CharSnapshot, SavedItemId},
+    merchant::{Merchant, MerchantItem, MerchantStock},
+    DataProvider,
+};
+use shroom_meta::id::{CharacterId, FieldId, ItemId};
+use shroom_proto95::game::{
+    life::employee::{EmployeeBalloon, EmployeeMiniRoomBalloon},
+    mini_room::{MiniRoomId, MiniRoomUser, ENTRUSTED_SHOP_MAX_ITEMS, ENTRUSTED_SHOP_MAX_USERS},
+};
+
+/// Visitors of a shop, the employee takes the first position
+pub const SHOP_MAX_VISITORS: usize = ENTRUSTED_SHOP_MAX_USERS as usize - 1;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ShopError {
+    #[error("Merchant must be retrieved first")]
+    RetrieveFirst,
+    #[error("No shop")]
+    NoShop,
+    #[error("Not the owner of the shop")]
+    NotOwner,
+    #[error("Shop is not open")]
+    Closed,
+    #[error("Shop is still open")]
+    Open,
+    #[error("Shop is full")]
+    Full,
+    #[error("Shop is in another field")]
+    OtherField,
+    #[error("Already visiting a shop")]
+    AlreadyVisiting,
+    #[error("Invalid shop item")]
+    InvalidItem,
+    #[error("Item is sold out")]
+    SoldOut,
+    #[error("Not enough money")]
+    NoMoney,
+}
+
+/// Sale, which is shown to the owner
+#[derive(Debug, Clone)]
+pub struct ShopSale {
+    pub item_id: ItemId,
+    pub quantity: u16,
+    pub price: u32,
+    pub buyer: String,
+}
+
+#[derive(Debug)]
+struct ShopVisitor {
+    id: CharacterId,
+    user: MiniRoomUser,
+}
+
+#[derive(Debug)]
+struct Shop {
+    merchant: Merchant,
+    /// The shop was created but not opened yet
+    setup: bool,
+    owner_inside: bool,
+    visitors: Vec<ShopVisitor>,
+    sold: Vec<ShopSale>,
+}
+
+impl Shop {
+    fn new(merchant: Merchant, setup: bool) -> Self {
+        Self {
+            merchant,
+            setup,
+            owner_inside: setup,
+            visitors: Vec::new(),
+            sold: Vec::new(),
+        }
+    }
+
+    fn free_pos(&self) -> Option<u8> {
+        (1..=SHOP_MAX_VISITORS as u8).find(|pos| self.visitors.iter().all(|v| v.user.pos != *pos))
+    }
+
+    /// Users, which see the shop window
+    fn audience(&self) -> Vec<CharacterId> {
+        let owner = self.owner_inside.then_some(self.merchant.owner);
+        self.visitors.iter().map(|v| v.id).chain(owner).collect()
+    }
+
+    fn balloon(&self) -> EmployeeBalloon {
+        if !self.merchant.open {
+            return EmployeeBalloon::None(());
+        }
+
+        EmployeeBalloon::EntrustedShop(EmployeeMiniRoomBalloon {
+            sn: self.merchant.owner.0,
+            text: self.merchant.title.clone(),
+            spec: 0,
+            cur_users: self.visitors.len() as u8 + 1,
+            max_users: ENTRUSTED_SHOP_MAX_USERS,
+        })
+    }
+
+    fn view(&self, pos: u8) -> ShopView {
+        ShopView {
+            owner: self.merchant.owner,
+            pos,
+            owner_name: self.merchant.owner_name.clone(),
+            tmpl_id: self.merchant.tmpl_id,
+            title: self.merchant.title.clone(),
+            money: self.merchant.money,
+            visitors: self.visitors.iter().map(|v| v.user.clone()).collect(),
+            items: self.merchant.items.clone(),
+            sold: self.sold.clone(),
+            audience: self.audience(),
+        }
+    }
+}
+
+/// View of a shop for a single user
+#[derive(Debug, Clone)]
+pub struct ShopView {
+    pub owner: CharacterId,
+    /// 0 for the owner
+    pub pos: u8,
+    pub owner_name: String,
+    pub tmpl_id: ItemId,
+    pub title: String,
+    pub money: u32,
+    pub visitors: Vec<MiniRoomUser>,
+    pub items: Vec<MerchantItem>,
+    pub sold: Vec<ShopSale>,
+    pub audience: Vec<CharacterId>,
+}
+
+/// Result of a purchase, the stock must be put into the inventory of the buyer
+#[derive(Debug)]
+pub struct ShopPurchase {
+    pub stock: MerchantStock,
+    pub quantity: usize,
+    pub price: u32,
+    pub owner: CharacterId,
+    pub owner_inside: bool,
+    pub audience: Vec<CharacterId>,
+}
+
+/// Result of leaving a shop
+#[derive(Debug)]
+pub struct ShopLeave {
+    pub owner: CharacterId,
+    pub pos: u8,
+    pub field: FieldId,
+    pub balloon: EmployeeBalloon,
+    pub audience: Vec<CharacterId>,
+    /// Shop was left by the owner before it was opened, the items are handed back
+    pub cancelled: Option<Merchant>,
+}
+
+#[derive(Debug, Default)]
+struct EmployeeState {
+    shops: HashMap<CharacterId, Shop>,
+    /// Visitor to the owner of the shop
+    visiting: HashMap<CharacterId, CharacterId>,
+}
+
+impl EmployeeState {
+    fn shop_mut(&mut self, owner: CharacterId) -> Result<&mut Shop, ShopError> {
+        self.shops.get_mut(&owner).ok_or(ShopError::NoShop)
+    }
+
+    /// Shop, which is managed by the owner right now
+    fn owned_shop_mut(&mut self, owner: CharacterId) -> Result<&mut Shop, ShopError> {
+        let shop = self.shop_mut(owner)?;
+        if !shop.owner_inside {
+            return Err(ShopError::NotOwner);
+        }
+        Ok(shop)
+    }
+
+    /// Validates buying the bundles of the item at the index
+    fn quote(
+        &self,
+        id: CharacterId,
+        index: usize,
+        bundles: u16,
+    ) -> Result<(CharacterId, ItemId, usize, u32), ShopError> {
+        let owner = *self.visiting.get(&id).ok_or(ShopError::NoShop)?;
+        let shop = self.shops.get(&owner).ok_or(ShopError::NoShop)?;
+        if !shop.merchant.open {
+            return Err(ShopError::Closed);
+        }
+
+        let item = shop
+            .merchant
+            .items
+            .get(index)
+            .ok_or(ShopError::InvalidItem)?;
+        if bundles == 0 || bundles > item.bundles {
+            return Err(ShopError::SoldOut);
+        }
+
+        let price = item
+            .price
+            .checked_mul(bundles as u32)
+            .ok_or(ShopError::NoMoney)?;
+        let quantity = bundles as usize * item.per_bundle as usize;
+        Ok((owner, item.id(), quantity, price))
+    }
+}
+
+/// World wide registry of the hired merchants, every change is saved with
+/// the character which caused it via `save_char`, so the shops survive restarts
+#[derive(Debug, Default)]
+pub struct EmployeeService {
+    state: Mutex<EmployeeState>,
+    persist: tokio::sync::Mutex<()>,
+}
+
+impl EmployeeService {
+    pub async fn load(&self, data: &DataProvider) -> anyhow::Result<()> {
+        let merchants = data.merchant().load_all().await?;
+        let mut state = self.state.lock().unwrap();
+        for merchant in merchants {
+            state
+                .shops
+                .insert(merchant.owner, Shop::new(merchant, false));
+        }
+        Ok(())
+    }
+
+    /// Saves the character together with the current state of the shops, so the
+    /// inventory and the shops are written in a single transaction,
+    /// a removed shop is deleted
+    pub async fn save_char(
+        &self,
+        data: &DataProvider,
+        snapshot: &mut CharSnapshot,
+        shops: &[CharacterId],
+    ) -> anyhow::Result<Vec<SavedItemId>> {
+        if shops.is_empty() {
+            return data.char_save().save(snapshot).await;
+        }
+
+        let _guard = self.persist.lock().await;
+        for owner in shops {
+            snapshot.merchants.push((*owner, self.snapshot(*owner)));
+        }
+        let ids = data.char_save().save(snapshot).await?;
+
+        // Assign the ids of newly inserted equips
+        let mut state = self.state.lock().unwrap();
+        for (owner, merchant) in snapshot.merchants.iter() {
+            let (Some(shop), Some(merchant)) = (state.shops.get_mut(owner), merchant) else {
+                continue;
+            };
+            for item in shop.merchant.items.iter_mut() {
+                let MerchantStock::Equip(equip) = &mut item.stock else {
+                    continue;
+                };
+                let saved = merchant.items.iter().find_map(|saved| match &saved.stock {
+                    MerchantStock::Equip(saved) if saved.game_id == equip.game_id => Some(saved),
+                    _ => None,
+                });
+                if let Some(saved) = saved {
+                    equip.db_id = saved.db_id;
+                }
+            }
+        }
+        Ok(ids)
+    }
+
+    fn snapshot(&self, owner: CharacterId) -> Option<Merchant> {
+        let state = self.state.lock().unwrap();
+        let mut merchant = state.shops.get(&owner)?.merchant.clone();
+        merchant.items.retain(|item| !item.is_sold_out());
+        Some(merchant)
+    }
+
+    pub fn has_merchant(&self, owner: CharacterId) -> bool {
+        self.state.lock().unwrap().shops.contains_key(&owner)
+    }
+
+    /// Open shops of the field
+    pub fn shops_in_field(&self, field: FieldId) -> Vec<(Merchant, EmployeeBalloon)> {
+        self.state
+            .lock()
+            .unwrap()
+            .shops
+            .values()
+            .filter(|shop| shop.merchant.open && shop.merchant.field_id == field)
+            .map(|shop| (shop.merchant.clone(), shop.balloon()))
+            .collect()
+    }
+
+    pub fn balloon(&self, owner: CharacterId) -> Option<EmployeeBalloon> {
+        Some(self.state.lock().unwrap().shops.get(&owner)?.balloon())
+    }
+
+    pub fn view(&self, id: CharacterId) -> Option<ShopView> {
+        let state = self.state.lock().unwrap();
+        if let Some(owner) = state.visiting.get(&id) {
+            let shop = state.shops.get(owner)?;
+            let pos = shop.visitors.iter().find(|v| v.id == id)?.user.pos;
+            return Some(shop.view(pos));
+        }
+
+        let shop = state.shops.get(&id)?;
+        shop.owner_inside.then(|| shop.view(0))
+    }
+
+    /// Creates the shop, which is managed by the owner until it's opened
+    pub fn create(&self, merchant: Merchant) -> Result<ShopView, ShopError> {
+        let mut state = self.state.lock().unwrap();
+        let owner = merchant.owner;
+        if state.shops.contains_key(&owner) {
+            return Err(ShopError::RetrieveFirst);
+        }
+        if state.visiting.contains_key(&owner) {
+            return Err(ShopError::AlreadyVisiting);
+        }
+
+        let shop = Shop::new(merchant, true);
+        let view = shop.view(0);
+        state.shops.insert(owner, shop);
+        Ok(view)
+    }
+
+    /// Lists the item, the item is handed back on failure
+    pub fn put_item(
+        &self,
+        owner: CharacterId,
+        item: MerchantItem,
+    ) -> Result<(), (ShopError, MerchantItem)> {
+        let mut state = self.state.lock().unwrap();
+        let shop = match state.owned_shop_mut(owner) {
+            Ok(shop) => shop,
+            Err(err) => return Err((err, item)),
+        };
+        if shop.merchant.items.len() >= ENTRUSTED_SHOP_MAX_ITEMS as usize {
+            return Err((ShopError::Full, item));
+        }
+        shop.merchant.items.push(item);
+        Ok(())
+    }
+
+    /// Takes the listed item at the index back
+    pub fn take_item(&self, owner: CharacterId, index: usize) -> Result<MerchantItem, ShopError> {
+        let mut state = self.state.lock().unwrap();
+        let shop = state.owned_shop_mut(owner)?;
+        if index >= shop.merchant.items.len() {
+            return Err(ShopError::InvalidItem);
+        }
+        Ok(shop.merchant.items.remove(index))
+    }
+
+    /// Removes the sold out items
+    pub fn arrange(&self, owner: CharacterId) -> Result<(), ShopError> {
+        let mut state = self.state.lock().unwrap();
+        let shop = state.owned_shop_mut(owner)?;
+        shop.merchant.items.retain(|item| !item.is_sold_out());
+        Ok(())
+    }
+
+    /// Opens the shop for visitors
+    pub fn open(&self, owner: CharacterId) -> Result<(Merchant, EmployeeBalloon), ShopError> {
+        let mut state = self.state.lock().unwrap();
+        let shop = state.owned_shop_mut(owner)?;
+        if shop.merchant.open {
+            return Err(ShopError::Open);
+        }
+        shop.setup = false;
+        shop.merchant.open = true;
+        Ok((shop.merchant.clone(), shop.balloon()))
+    }
+
+    pub fn enter(
+        &self,
+        id: CharacterId,
+        room_id: MiniRoomId,
+        mut user: MiniRoomUser,
+        field: FieldId,
+    ) -> Result<(ShopView, EmployeeBalloon), ShopError> {
+        let mut state = self.state.lock().unwrap();
+        if state.visiting.contains_key(&id) {
+            return Err(ShopError::AlreadyVisiting);
+        }
+
+        let owner = CharacterId(room_id);
+        let shop = state.shop_mut(owner)?;
+        if !shop.merchant.open {
+            return Err(ShopError::Closed);
+        }
+        if shop.merchant.field_id != field {
+            return Err(ShopError::OtherField);
+        }
+
+        // The owner manages the shop from the employee position
+        if id == owner {
+            shop.owner_inside = true;
+            return Ok((shop.view(0), shop.balloon()));
+        }
+
+        let pos = shop.free_pos().ok_or(ShopError::Full)?;
+        user.pos = pos;
+        shop.visitors.push(ShopVisitor { id, user });
+        let res = (shop.view(pos), shop.balloon());
+        state.visiting.insert(id, owner);
+        Ok(res)
+    }
+
+    /// Item id, quantity and price of buying the bundles
+    pub fn quote(
+        &self,
+        id: CharacterId,
+        index: usize,
+        bundles: u16,
+    ) -> Result<(ItemId, usize, u32), ShopError> {
+        let state = self.state.lock().unwrap();
+        let (_, item_id, quantity, price) = state.quote(id, index, bundles)?;
+        Ok((item_id, quantity, price))
+    }
+
+    pub fn buy(
+        &self,
+        id: CharacterId,
+        index: usize,
+        bundles: u16,
+        money: u32,
+        buyer: &str,
+    ) -> Result<ShopPurchase, ShopError> {
+        let mut state = self.state.lock().unwrap();
+        let (owner, item_id, quantity, price) = state.quote(id, index, bundles)?;
+        if price > money {
+            return Err(ShopError::NoMoney);
+        }
+
+        let shop = state.shop_mut(owner)?;
+        let item = &mut shop.merchant.items[index];
+        item.bundles -= bundles;
+        let stock = item.stock.clone();
+
+        shop.merchant.money = shop.merchant.money.saturating_add(price);
+        shop.sold.push(ShopSale {
+            item_id,
+            quantity: quantity as u16,
+            price,
+            buyer: buyer.to_string(),
+        });
+        Ok(ShopPurchase {
+            stock,
+            quantity,
+            price,
+            owner,
+            owner_inside: shop.owner_inside,
+            audience: shop.audience(),
+        })
+    }
+
+    /// Leaves the shop, which is visited or managed by the user
+    pub fn leave(&self, id: CharacterId) -> Option<ShopLeave> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(owner) = state.visiting.remove(&id) {
+            let shop = state.shops.get_mut(&owner)?;
+            let ix = shop.visitors.iter().position(|v| v.id == id)?;
+            let visitor = shop.visitors.remove(ix);
+            return Some(ShopLeave {
+                owner,
+                pos: visitor.user.pos,
+                field: shop.merchant.field_id,
+                balloon: shop.balloon(),
+                audience: shop.audience(),
+                cancelled: None,
+            });
+        }
+
+        let shop = state.shops.get_mut(&id)?;
+        if !shop.owner_inside {
+            return None;
+        }
+        shop.owner_inside = false;
+        let (field, balloon, audience) = (shop.merchant.field_id, shop.balloon(), shop.audience());
+        let cancelled = if shop.setup {
+            state.shops.remove(&id).map(|shop| shop.merchant)
+        } else {
+            None
+        };
+
+        Some(ShopLeave {
+            owner: id,
+            pos: 0,
+            field,
+            balloon,
+            audience,
+            cancelled,
+        })
+    }
+
+    /// Closes the shop, the items and mesos are kept until the owner
+    /// retrieves them, returns the kicked visitors
+    pub fn close(&self, owner: CharacterId) -> Result<Vec<(CharacterId, u8)>, ShopError> {
+        let mut state = self.state.lock().unwrap();
+        let shop = state.owned_shop_mut(owner)?;
+        if !shop.merchant.open {
+            return Err(ShopError::Closed);
+        }
+
+        shop.merchant.open = false;
+        shop.owner_inside = false;
+        let visitors: Vec<_> = shop
+            .visitors
+            .drain(..)
+            .map(|v| (v.id, v.user.pos))
+            .collect();
+        for (visitor, _) in visitors.iter() {
+            state.visiting.remove(visitor);
+        }
+        Ok(visitors)
+    }
+
+    pub fn withdraw_money(&self, owner: CharacterId, max: u32) -> Result<u32, ShopError> {
+        let mut state = self.state.lock().unwrap();
+        let shop = state.owned_shop_mut(owner)?;
+        let money = shop.merchant.money.min(max);
+        shop.merchant.money -= money;
+        Ok(money)
+    }
+
+    /// Items and mesos, which are stored for the owner of a closed shop
+    pub fn stored(&self, owner: CharacterId) -> Result<(Vec<(ItemId, usize)>, u32), ShopError> {
+        let state = self.state.lock().unwrap();
+        let shop = state.shops.get(&owner).ok_or(ShopError::NoShop)?;
+        if shop.merchant.open || shop.setup {
+            return Err(ShopError::Open);
+        }
+
+        let items = shop
+            .merchant
+            .items
+            .iter()
+            .filter(|item| !item.is_sold_out())
+            .map(|item| (item.id(), item.quantity()))
+            .collect();
+        Ok((items, shop.merchant.money))
+    }
+
+    /// Removes the closed shop, the remaining items must be put into the inventory of the owner
+    pub fn retrieve(&self, owner: CharacterId) -> Result<Merchant, ShopError> {
+        let mut state = self.state.lock().unwrap();
+        let shop = state.shops.get(&owner).ok_or(ShopError::NoShop)?;
+        if shop.merchant.open || shop.setup {
+            return Err(ShopError::Open);
+        }
+
+        let mut merchant = state.shops.remove(&owner).expect("shop").merchant;
+        merchant.items.retain(|item| !item.is_sold_out());
+        Ok(merchant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shroom_meta::{
+        id::{FaceId, FootholdId, HairId, Skin},
+        twod::Vec2,
+    };
+    use shroom_pkt::ShroomIndexList8;
+    use shroom_proto95::shared::{
+        char::{AvatarData, AvatarEquips},
+        Gender,
+    };
+
+    use super::*;
+
+    const FIELD: FieldId = FieldId(910000001);
+    const OWNER: CharacterId = CharacterId(1);
+
+    fn user(id: u32) -> MiniRoomUser {
+        MiniRoomUser {
+            pos: 0,
+            avatar: AvatarData {
+                gender: Gender::Male,
+                skin: Skin::Normal,
+                face: FaceId::MOTIVATED_LOOK_M,
+                mega: false,
+                hair: HairId::BLACK_TOBEN,
+                equips: AvatarEquips {
+                    equips: ShroomIndexList8::from(vec![]),
+                    masked_equips: ShroomIndexList8::from(vec![]),
+                    weapon_sticker_id: ItemId(0),
+                },
+                pets: [ItemId(0); 3],
+            },
+            name: format!("user{id}"),
+            job: 0,
+        }
+    }
+
+    fn merchant(owner: CharacterId) -> Merchant {
+        Merchant {
+            owner,
+            owner_name: format!("user{}", owner.0),
+            field_id: FIELD,
+            pos: Vec2::new(0, 0),
+            fh: FootholdId(0),
+            tmpl_id: ItemId(5030000),
+            title: "shop".to_string(),
+            money: 0,
+            open: false,
+            items: vec![],
+        }
+    }
+
+    fn open_shop(svc: &EmployeeService) {
+        svc.create(merchant(OWNER)).unwrap();
+        svc.put_item(
+            OWNER,
+            MerchantItem {
+                stock: MerchantStock::Stack(ItemId(2000000)),
+                price: 100,
+                bundles: 5,
+                per_bundle: 10,
+            },
+        )
+        .unwrap();
+        svc.open(OWNER).unwrap();
+        svc.leave(OWNER).unwrap();
+    }
+
+    #[test]
+    fn shop_buy_and_retrieve() {
+        let svc = EmployeeService::default();
+        open_shop(&svc);
+        let buyer = CharacterId(2);
+
+        let (view, _) = svc.enter(buyer, OWNER.0, user(2), FIELD).unwrap();
+        assert_eq!(view.pos, 1);
+        assert_eq!(
+            svc.buy(buyer, 0, 2, 199, "buyer").unwrap_err(),
+            ShopError::NoMoney
+        );
+        let purchase = svc.buy(buyer, 0, 2, 200, "buyer").unwrap();
+        assert_eq!(purchase.quantity, 20);
+        assert_eq!(purchase.owner, OWNER);
+        assert_eq!(
+            svc.buy(buyer, 0, 4, u32::MAX, "buyer").unwrap_err(),
+            ShopError::SoldOut
+        );
+
+        // Items can only be retrieved after the shop was closed
+        assert_eq!(svc.retrieve(OWNER).unwrap_err(), ShopError::Open);
+        svc.enter(OWNER, OWNER.0, user(1), FIELD).unwrap();
+        assert_eq!(svc.close(OWNER).unwrap(), vec![(buyer, 1)]);
+        assert!(svc.view(buyer).is_none());
+
+        let (items, money) = svc.stored(OWNER).unwrap();
+        assert_eq!(items, vec![(ItemId(2000000), 30)]);
+        assert_eq!(money, 200);
+        svc.retrieve(OWNER).unwrap();
+        assert!(!svc.has_merchant(OWNER));
+    }
+
+    #[test]
+    fn shop_setup_cancel() {
+        let svc = EmployeeService::default();
+        open_shop(&svc);
+        assert_eq!(
+            svc.create(merchant(OWNER)).unwrap_err(),
+            ShopError::RetrieveFirst
+        );
+
+        let owner = CharacterId(3);
+        svc.create(merchant(owner)).unwrap();
+        let leave = svc.leave(owner).unwrap();
+        assert!(leave.cancelled.is_some());
+        assert!(!svc.has_merchant(owner));
+    }
+}
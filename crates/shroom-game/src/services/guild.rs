@@ -0,0 +1,513 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use shroom_data::services::{
+    guild::{GuildData, GuildId, GuildMark, GuildMemberData, GUILD_GRADES},
+    DataProvider,
+};
+use shroom_meta::id::CharacterId;
+use shroom_proto95::game::{
+    guild::{self as proto, GuildMember, GuildMembers},
+    user::remote::GuildMarkData,
+};
+
+pub const GUILD_NAME_LEN: std::ops::RangeInclusive<usize> = 4..=12;
+/// Lowest grade, which is still allowed to manage members
+const GUILD_STAFF_GRADE: u8 = 2;
+const GUILD_MASTER_GRADE: u8 = 1;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum GuildError {
+    #[error("Already joined a guild")]
+    AlreadyJoined,
+    #[error("Not in a guild")]
+    NotJoined,
+    #[error("Missing guild permission")]
+    NoPermission,
+    #[error("Guild is full")]
+    Full,
+    #[error("Not invited to the guild")]
+    NotInvited,
+    #[error("Already invited to a guild")]
+    AlreadyInvited,
+    #[error("Guild name is already taken")]
+    NameTaken,
+    #[error("Invalid guild name")]
+    InvalidName,
+    #[error("Unknown guild member")]
+    UnknownMember,
+    #[error("Invalid guild grade")]
+    InvalidGrade,
+}
+
+pub fn guild_mark_to_proto(mark: &GuildMark) -> GuildMarkData {
+    GuildMarkData {
+        bg: mark.bg,
+        bg_color: mark.bg_color,
+        mark: mark.mark,
+        mark_color: mark.mark_color,
+    }
+}
+
+pub fn guild_mark_from_proto(mark: &GuildMarkData) -> GuildMark {
+    GuildMark {
+        bg: mark.bg,
+        bg_color: mark.bg_color,
+        mark: mark.mark,
+        mark_color: mark.mark_color,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Guild {
+    pub data: GuildData,
+    pub online: HashSet<CharacterId>,
+}
+
+impl Guild {
+    pub fn id(&self) -> GuildId {
+        self.data.id
+    }
+
+    pub fn member_ids(&self) -> impl Iterator<Item = CharacterId> + '_ {
+        self.data.members.iter().map(|m| m.id)
+    }
+
+    pub fn online_ids(&self) -> impl Iterator<Item = CharacterId> + '_ {
+        self.member_ids().filter(|id| self.online.contains(id))
+    }
+
+    pub fn get_member(&self, id: CharacterId) -> Option<&GuildMemberData> {
+        self.data.members.iter().find(|m| m.id == id)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.data.members.len() >= self.data.capacity as usize
+    }
+
+    pub fn mark_data(&self) -> GuildMarkData {
+        guild_mark_to_proto(&self.data.mark)
+    }
+
+    pub fn member_to_proto(&self, member: &GuildMemberData) -> GuildMember {
+        GuildMember {
+            name: member.name.as_str().try_into().expect("Name"),
+            job: member.job as u32,
+            level: member.level as u32,
+            grade: member.grade as u32,
+            status: self.online.contains(&member.id) as u32,
+            commitment: member.commitment,
+            alliance_grade: 0,
+        }
+    }
+
+    pub fn to_proto(&self) -> proto::GuildData {
+        let data = &self.data;
+        proto::GuildData {
+            id: data.id,
+            name: data.name.clone(),
+            grades: data.grades.clone(),
+            members: GuildMembers(
+                data.members
+                    .iter()
+                    .map(|m| (m.id, self.member_to_proto(m)))
+                    .collect(),
+            ),
+            capacity: data.capacity as u32,
+            mark: self.mark_data(),
+            notice: data.notice.clone(),
+            points: data.points,
+            alliance_id: 0,
+            level: 1,
+            skills: 0,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct GuildState {
+    guilds: HashMap<GuildId, Guild>,
+    members: HashMap<CharacterId, GuildId>,
+    invites: HashMap<CharacterId, GuildId>,
+    next_id: GuildId,
+}
+
+impl GuildState {
+    fn guild_of_mut(&mut self, id: CharacterId) -> Result<&mut Guild, GuildError> {
+        let guild_id = self.members.get(&id).ok_or(GuildError::NotJoined)?;
+        Ok(self.guilds.get_mut(guild_id).expect("guild"))
+    }
+
+    /// Guild of the character, if the grade of the character is at most `grade`
+    fn guild_with_grade_mut(
+        &mut self,
+        id: CharacterId,
+        grade: u8,
+    ) -> Result<&mut Guild, GuildError> {
+        let guild = self.guild_of_mut(id)?;
+        let member = guild.get_member(id).ok_or(GuildError::UnknownMember)?;
+        if member.grade > grade {
+            return Err(GuildError::NoPermission);
+        }
+        Ok(guild)
+    }
+
+    fn remove_member(&mut self, id: CharacterId) -> Result<(Guild, GuildMemberData), GuildError> {
+        let guild = self.guild_of_mut(id)?;
+        let ix = guild
+            .data
+            .members
+            .iter()
+            .position(|m| m.id == id)
+            .ok_or(GuildError::UnknownMember)?;
+        let member = guild.data.members.remove(ix);
+        guild.online.remove(&id);
+        let guild = guild.clone();
+        self.members.remove(&id);
+        Ok((guild, member))
+    }
+}
+
+/// World wide registry of the guilds, every change must be persisted
+/// via `persist`, so the guilds survive restarts
+#[derive(Debug, Default)]
+pub struct GuildService {
+    state: Mutex<GuildState>,
+    persist: tokio::sync::Mutex<()>,
+}
+
+impl GuildService {
+    pub async fn load(&self, data: &DataProvider) -> anyhow::Result<()> {
+        let guilds = data.guild.load_all().await?;
+        let mut state = self.state.lock().unwrap();
+        for guild in guilds {
+            state.next_id = state.next_id.max(guild.id);
+            for member in guild.members.iter() {
+                state.members.insert(member.id, guild.id);
+            }
+            state.guilds.insert(
+                guild.id,
+                Guild {
+                    data: guild,
+                    online: HashSet::new(),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Saves the current state of the guild, a removed guild is deleted
+    pub async fn persist(&self, data: &DataProvider, id: GuildId) -> anyhow::Result<()> {
+        let _guard = self.persist.lock().await;
+        let Some(guild) = self.get_guild(id) else {
+            return data.guild.remove(id).await;
+        };
+        data.guild.save(&guild.data).await
+    }
+
+    pub fn get_guild(&self, id: GuildId) -> Option<Guild> {
+        self.state.lock().unwrap().guilds.get(&id).cloned()
+    }
+
+    pub fn get_guild_of(&self, id: CharacterId) -> Option<Guild> {
+        let state = self.state.lock().unwrap();
+        let guild_id = state.members.get(&id)?;
+        state.guilds.get(guild_id).cloned()
+    }
+
+    pub fn create(&self, name: &str, master: GuildMemberData) -> Result<Guild, GuildError> {
+        if !GUILD_NAME_LEN.contains(&name.len()) || !name.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(GuildError::InvalidName);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.members.contains_key(&master.id) {
+            return Err(GuildError::AlreadyJoined);
+        }
+        if state
+            .guilds
+            .values()
+            .any(|g| g.data.name.eq_ignore_ascii_case(name))
+        {
+            return Err(GuildError::NameTaken);
+        }
+
+        state.next_id += 1;
+        let id = state.next_id;
+        let master_id = master.id;
+        let guild = Guild {
+            data: GuildData::new(id, name.to_string(), master),
+            online: HashSet::from([master_id]),
+        };
+        state.members.insert(master_id, id);
+        state.invites.remove(&master_id);
+        state.guilds.insert(id, guild.clone());
+        Ok(guild)
+    }
+
+    pub fn is_name_taken(&self, name: &str) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .guilds
+            .values()
+            .any(|g| g.data.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Invites the target into the guild of the inviter
+    pub fn invite(&self, inviter: CharacterId, target: CharacterId) -> Result<Guild, GuildError> {
+        let mut state = self.state.lock().unwrap();
+        if state.members.contains_key(&target) {
+            return Err(GuildError::AlreadyJoined);
+        }
+        if state.invites.contains_key(&target) {
+            return Err(GuildError::AlreadyInvited);
+        }
+
+        let guild = state.guild_with_grade_mut(inviter, GUILD_STAFF_GRADE)?;
+        if guild.is_full() {
+            return Err(GuildError::Full);
+        }
+        let guild = guild.clone();
+        state.invites.insert(target, guild.id());
+        Ok(guild)
+    }
+
+    /// Removes a pending invite, returns the guild if the invite existed
+    pub fn decline(&self, target: CharacterId) -> Option<Guild> {
+        let mut state = self.state.lock().unwrap();
+        let guild_id = state.invites.remove(&target)?;
+        state.guilds.get(&guild_id).cloned()
+    }
+
+    pub fn join(&self, member: GuildMemberData, guild_id: GuildId) -> Result<Guild, GuildError> {
+        let mut state = self.state.lock().unwrap();
+        if state.members.contains_key(&member.id) {
+            return Err(GuildError::AlreadyJoined);
+        }
+        if state.invites.get(&member.id) != Some(&guild_id) {
+            return Err(GuildError::NotInvited);
+        }
+        state.invites.remove(&member.id);
+
+        let id = member.id;
+        let guild = state
+            .guilds
+            .get_mut(&guild_id)
+            .ok_or(GuildError::NotInvited)?;
+        if guild.is_full() {
+            return Err(GuildError::Full);
+        }
+        guild.data.members.push(GuildMemberData {
+            grade: GUILD_GRADES as u8,
+            ..member
+        });
+        guild.online.insert(id);
+        let guild = guild.clone();
+        state.members.insert(id, guild_id);
+        Ok(guild)
+    }
+
+    /// Leaves the guild, the master has to disband the guild instead
+    pub fn withdraw(&self, id: CharacterId) -> Result<(Guild, GuildMemberData), GuildError> {
+        let mut state = self.state.lock().unwrap();
+        let guild = state.guild_of_mut(id)?;
+        if guild.get_member(id).map(|m| m.grade) == Some(GUILD_MASTER_GRADE) {
+            return Err(GuildError::NoPermission);
+        }
+        state.remove_member(id)
+    }
+
+    pub fn kick(
+        &self,
+        id: CharacterId,
+        target: CharacterId,
+    ) -> Result<(Guild, GuildMemberData), GuildError> {
+        let mut state = self.state.lock().unwrap();
+        let guild = state.guild_with_grade_mut(id, GUILD_STAFF_GRADE)?;
+        let grade = guild.get_member(id).expect("member").grade;
+        let target_grade = guild
+            .get_member(target)
+            .ok_or(GuildError::UnknownMember)?
+            .grade;
+        if target_grade <= grade {
+            return Err(GuildError::NoPermission);
+        }
+        state.remove_member(target)
+    }
+
+    /// Disbands the guild, only allowed for the master
+    pub fn disband(&self, id: CharacterId) -> Result<Guild, GuildError> {
+        let mut state = self.state.lock().unwrap();
+        let guild_id = state.guild_with_grade_mut(id, GUILD_MASTER_GRADE)?.id();
+        let guild = state.guilds.remove(&guild_id).expect("guild");
+        for member in guild.member_ids() {
+            state.members.remove(&member);
+        }
+        state.invites.retain(|_, invite| *invite != guild_id);
+        Ok(guild)
+    }
+
+    pub fn set_grade_names(
+        &self,
+        id: CharacterId,
+        grades: [String; GUILD_GRADES],
+    ) -> Result<Guild, GuildError> {
+        let mut state = self.state.lock().unwrap();
+        let guild = state.guild_with_grade_mut(id, GUILD_MASTER_GRADE)?;
+        guild.data.grades = grades;
+        Ok(guild.clone())
+    }
+
+    /// Changes the grade of a member, the master grade can't be assigned
+    /// and only members with a lower rank can be promoted or demoted
+    pub fn set_member_grade(
+        &self,
+        id: CharacterId,
+        target: CharacterId,
+        grade: u8,
+    ) -> Result<Guild, GuildError> {
+        if !(GUILD_STAFF_GRADE..=GUILD_GRADES as u8).contains(&grade) {
+            return Err(GuildError::InvalidGrade);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let guild = state.guild_with_grade_mut(id, GUILD_STAFF_GRADE)?;
+        let own_grade = guild.get_member(id).expect("member").grade;
+        let member = guild
+            .data
+            .members
+            .iter_mut()
+            .find(|m| m.id == target)
+            .ok_or(GuildError::UnknownMember)?;
+        if member.grade <= own_grade || grade <= own_grade {
+            return Err(GuildError::NoPermission);
+        }
+        member.grade = grade;
+        Ok(guild.clone())
+    }
+
+    pub fn set_mark(&self, id: CharacterId, mark: GuildMark) -> Result<Guild, GuildError> {
+        let mut state = self.state.lock().unwrap();
+        let guild = state.guild_with_grade_mut(id, GUILD_MASTER_GRADE)?;
+        guild.data.mark = mark;
+        Ok(guild.clone())
+    }
+
+    pub fn set_notice(&self, id: CharacterId, notice: String) -> Result<Guild, GuildError> {
+        let mut state = self.state.lock().unwrap();
+        let guild = state.guild_with_grade_mut(id, GUILD_STAFF_GRADE)?;
+        guild.data.notice = notice;
+        Ok(guild.clone())
+    }
+
+    /// Adds guild points, the contributing member gains the same commitment
+    pub fn add_points(&self, id: CharacterId, points: u32) -> Result<Guild, GuildError> {
+        let mut state = self.state.lock().unwrap();
+        let guild = state.guild_of_mut(id)?;
+        guild.data.points = guild.data.points.saturating_add(points);
+        if let Some(member) = guild.data.members.iter_mut().find(|m| m.id == id) {
+            member.commitment = member.commitment.saturating_add(points);
+        }
+        Ok(guild.clone())
+    }
+
+    /// Updates the member data, returns the updated guild if the character is in a guild
+    pub fn update_member(
+        &self,
+        id: CharacterId,
+        f: impl FnOnce(&mut GuildMemberData),
+    ) -> Option<Guild> {
+        let mut state = self.state.lock().unwrap();
+        let guild = state.guild_of_mut(id).ok()?;
+        let member = guild.data.members.iter_mut().find(|m| m.id == id)?;
+        f(member);
+        Some(guild.clone())
+    }
+
+    /// Sets the online state of the member, returns the guild if the character is in a guild
+    pub fn set_online(&self, id: CharacterId, online: bool) -> Option<Guild> {
+        let mut state = self.state.lock().unwrap();
+        let guild = state.guild_of_mut(id).ok()?;
+        if online {
+            guild.online.insert(id);
+        } else {
+            guild.online.remove(&id);
+        }
+        Some(guild.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shroom_meta::id::job_id::JobId;
+
+    use super::*;
+
+    fn member(id: u32) -> GuildMemberData {
+        GuildMemberData {
+            id: CharacterId(id),
+            name: format!("member{id}"),
+            job: JobId::Beginner,
+            level: 10,
+            grade: 0,
+            commitment: 0,
+        }
+    }
+
+    #[test]
+    fn guild_flow() {
+        let svc = GuildService::default();
+        assert_eq!(
+            svc.create("a b", member(1)).unwrap_err(),
+            GuildError::InvalidName
+        );
+        let guild = svc.create("Shrooms", member(1)).unwrap();
+        assert_eq!(
+            svc.create("shrooms", member(2)).unwrap_err(),
+            GuildError::NameTaken
+        );
+
+        // Joining requires an invite
+        assert_eq!(
+            svc.join(member(2), guild.id()).unwrap_err(),
+            GuildError::NotInvited
+        );
+        svc.invite(CharacterId(1), CharacterId(2)).unwrap();
+        svc.join(member(2), guild.id()).unwrap();
+        svc.invite(CharacterId(1), CharacterId(3)).unwrap();
+        let guild = svc.join(member(3), guild.id()).unwrap();
+        assert_eq!(guild.get_member(CharacterId(3)).unwrap().grade, 5);
+
+        // Regular members can't manage the guild
+        assert_eq!(
+            svc.kick(CharacterId(2), CharacterId(3)).unwrap_err(),
+            GuildError::NoPermission
+        );
+        svc.set_member_grade(CharacterId(1), CharacterId(2), 2)
+            .unwrap();
+        assert_eq!(
+            svc.set_member_grade(CharacterId(2), CharacterId(1), 3)
+                .unwrap_err(),
+            GuildError::NoPermission
+        );
+        let (guild, kicked) = svc.kick(CharacterId(2), CharacterId(3)).unwrap();
+        assert_eq!(kicked.id, CharacterId(3));
+        assert_eq!(guild.data.members.len(), 2);
+
+        // The master can only disband the guild
+        assert_eq!(
+            svc.withdraw(CharacterId(1)).unwrap_err(),
+            GuildError::NoPermission
+        );
+        svc.withdraw(CharacterId(2)).unwrap();
+        let guild = svc.add_points(CharacterId(1), 10).unwrap();
+        assert_eq!(guild.data.points, 10);
+        svc.disband(CharacterId(1)).unwrap();
+        assert!(svc.get_guild_of(CharacterId(1)).is_none());
+        assert!(svc.get_guild(guild.id()).is_none());
+    }
+}
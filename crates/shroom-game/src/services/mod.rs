@@ -1 +1,8 @@
-pub mod shared;
\ No newline at end of file
+pub mod buddy;
+pub mod char_save;
+pub mod employee;
+pub mod guild;
+pub mod online;
+pub mod party;
+pub mod trade;
+pub mod shared;
@@ -0,0 +1,63 @@
+use dashmap::DashMap;
+use shroom_meta::id::{CharacterId, FieldId};
+use shroom_proto95::login::{ChannelId, WorldId};
+
+#[derive(Debug, Clone)]
+pub struct OnlineChar {
+    pub id: CharacterId,
+    pub name: String,
+    pub world: WorldId,
+    pub channel: ChannelId,
+    pub field: FieldId,
+    pub whisper_blocked: bool,
+}
+
+/// Directory of all characters, which are currently online,
+/// lookups only find the characters of the given world
+#[derive(Debug, Default)]
+pub struct OnlineService {
+    chars: DashMap<CharacterId, OnlineChar>,
+    names: DashMap<String, CharacterId>,
+}
+
+fn name_key(name: &str) -> String {
+    name.to_lowercase()
+}
+
+impl OnlineService {
+    /// Inserts or updates the character, returns the previous entry
+    pub fn update(&self, chr: OnlineChar) -> Option<OnlineChar> {
+        self.names.insert(name_key(&chr.name), chr.id);
+        self.chars.insert(chr.id, chr)
+    }
+
+    pub fn remove(&self, id: CharacterId) -> Option<OnlineChar> {
+        let (_, chr) = self.chars.remove(&id)?;
+        self.names.remove_if(&name_key(&chr.name), |_, v| *v == id);
+        Some(chr)
+    }
+
+    pub fn get(&self, world: WorldId, id: CharacterId) -> Option<OnlineChar> {
+        self.chars
+            .get(&id)
+            .filter(|chr| chr.world == world)
+            .map(|chr| chr.clone())
+    }
+
+    pub fn get_by_name(&self, world: WorldId, name: &str) -> Option<OnlineChar> {
+        let id = *self.names.get(&name_key(name))?;
+        self.get(world, id)
+    }
+
+    pub fn is_online(&self, id: CharacterId) -> bool {
+        self.chars.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+}
@@ -0,0 +1,408 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use shroom_meta::id::{job_id::JobId, CharacterId, FieldId};
+use shroom_proto95::{
+    game::party::{
+        PartyData, PartyID, PartyMember, PartyMemberFieldId, PartyMemberStatus, MAX_PARTY_MEMBERS,
+    },
+    login::ChannelId,
+};
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum PartyError {
+    #[error("Already joined a party")]
+    AlreadyJoined,
+    #[error("Not in a party")]
+    NotJoined,
+    #[error("Not the party leader")]
+    NotLeader,
+    #[error("Party is full")]
+    Full,
+    #[error("Not invited to the party")]
+    NotInvited,
+    #[error("Already invited to a party")]
+    AlreadyInvited,
+    #[error("Unknown party member")]
+    UnknownMember,
+}
+
+#[derive(Debug, Clone)]
+pub struct PartyMemberInfo {
+    pub id: CharacterId,
+    pub name: String,
+    pub job: JobId,
+    pub level: u8,
+    pub channel: ChannelId,
+    pub field: FieldId,
+    pub online: bool,
+}
+
+impl PartyMemberInfo {
+    fn to_proto(&self) -> PartyMember {
+        let (status, field_id) = if self.online {
+            (
+                PartyMemberStatus::Online(self.channel),
+                PartyMemberFieldId::Online(self.field),
+            )
+        } else {
+            (PartyMemberStatus::Offline, PartyMemberFieldId::Offline)
+        };
+
+        PartyMember {
+            id: self.id,
+            name: self.name.as_str().try_into().expect("Name"),
+            job: self.job as u32,
+            status,
+            field_id,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Party {
+    pub id: PartyID,
+    pub leader: CharacterId,
+    pub members: Vec<PartyMemberInfo>,
+}
+
+impl Party {
+    pub fn member_ids(&self) -> impl Iterator<Item = CharacterId> + '_ {
+        self.members.iter().map(|m| m.id)
+    }
+
+    pub fn online_ids(&self) -> impl Iterator<Item = CharacterId> + '_ {
+        self.members.iter().filter(|m| m.online).map(|m| m.id)
+    }
+
+    pub fn get_member(&self, id: CharacterId) -> Option<&PartyMemberInfo> {
+        self.members.iter().find(|m| m.id == id)
+    }
+
+    pub fn get_member_by_name(&self, name: &str) -> Option<&PartyMemberInfo> {
+        self.members.iter().find(|m| m.name == name)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.members.len() >= MAX_PARTY_MEMBERS
+    }
+
+    pub fn party_data(&self) -> PartyData {
+        let mut data = PartyData {
+            leader: self.leader,
+            ..Default::default()
+        };
+        let members: Vec<_> = self.members.iter().map(PartyMemberInfo::to_proto).collect();
+        data.set_members(&members);
+        data
+    }
+}
+
+/// Result of a member leaving the party
+#[derive(Debug)]
+pub enum PartyWithdraw {
+    /// The leader left, so the party is gone
+    Disbanded(Party),
+    /// A member left, the party contains the remaining members
+    Left(Party, PartyMemberInfo),
+}
+
+#[derive(Debug, Default)]
+struct PartyState {
+    parties: HashMap<PartyID, Party>,
+    members: HashMap<CharacterId, PartyID>,
+    invites: HashMap<CharacterId, PartyID>,
+    next_id: PartyID,
+}
+
+impl PartyState {
+    fn party_of(&self, id: CharacterId) -> Result<PartyID, PartyError> {
+        self.members.get(&id).copied().ok_or(PartyError::NotJoined)
+    }
+
+    fn leader_party_mut(&mut self, id: CharacterId) -> Result<&mut Party, PartyError> {
+        let party_id = self.party_of(id)?;
+        let party = self.parties.get_mut(&party_id).expect("party");
+        if party.leader != id {
+            return Err(PartyError::NotLeader);
+        }
+        Ok(party)
+    }
+}
+
+/// World wide registry of the parties, since the state is not bound
+/// to a session It's kept across field changes and migrations
+#[derive(Debug, Default)]
+pub struct PartyService {
+    state: Mutex<PartyState>,
+}
+
+impl PartyService {
+    pub fn get_party(&self, id: PartyID) -> Option<Party> {
+        self.state.lock().unwrap().parties.get(&id).cloned()
+    }
+
+    pub fn get_party_of(&self, id: CharacterId) -> Option<Party> {
+        let state = self.state.lock().unwrap();
+        let party_id = state.members.get(&id)?;
+        state.parties.get(party_id).cloned()
+    }
+
+    pub fn create(&self, leader: PartyMemberInfo) -> Result<Party, PartyError> {
+        let mut state = self.state.lock().unwrap();
+        if state.members.contains_key(&leader.id) {
+            return Err(PartyError::AlreadyJoined);
+        }
+
+        state.next_id += 1;
+        let id = state.next_id;
+        let party = Party {
+            id,
+            leader: leader.id,
+            members: vec![leader],
+        };
+        state.members.insert(party.leader, id);
+        state.invites.remove(&party.leader);
+        state.parties.insert(id, party.clone());
+        Ok(party)
+    }
+
+    /// Invites the target into the party of the leader
+    pub fn invite(&self, leader: CharacterId, target: CharacterId) -> Result<Party, PartyError> {
+        let mut state = self.state.lock().unwrap();
+        if state.members.contains_key(&target) {
+            return Err(PartyError::AlreadyJoined);
+        }
+        if state.invites.contains_key(&target) {
+            return Err(PartyError::AlreadyInvited);
+        }
+
+        let party = state.leader_party_mut(leader)?;
+        if party.is_full() {
+            return Err(PartyError::Full);
+        }
+        let party = party.clone();
+        state.invites.insert(target, party.id);
+        Ok(party)
+    }
+
+    /// Removes a pending invite, returns the party if the invite existed
+    pub fn decline(&self, target: CharacterId, party_id: PartyID) -> Option<Party> {
+        let mut state = self.state.lock().unwrap();
+        if state.invites.get(&target) != Some(&party_id) {
+            return None;
+        }
+        state.invites.remove(&target);
+        state.parties.get(&party_id).cloned()
+    }
+
+    /// Drops the pending invite of the target, used when the target leaves the world
+    pub fn remove_invite(&self, target: CharacterId) -> Option<PartyID> {
+        self.state.lock().unwrap().invites.remove(&target)
+    }
+
+    pub fn join(&self, member: PartyMemberInfo, party_id: PartyID) -> Result<Party, PartyError> {
+        let mut state = self.state.lock().unwrap();
+        if state.members.contains_key(&member.id) {
+            return Err(PartyError::AlreadyJoined);
+        }
+        if state.invites.get(&member.id) != Some(&party_id) {
+            return Err(PartyError::NotInvited);
+        }
+        state.invites.remove(&member.id);
+
+        let id = member.id;
+        let party = state
+            .parties
+            .get_mut(&party_id)
+            .ok_or(PartyError::NotInvited)?;
+        if party.is_full() {
+            return Err(PartyError::Full);
+        }
+        party.members.push(member);
+        let party = party.clone();
+        state.members.insert(id, party_id);
+        Ok(party)
+    }
+
+    pub fn withdraw(&self, id: CharacterId) -> Result<PartyWithdraw, PartyError> {
+        let mut state = self.state.lock().unwrap();
+        let party_id = state.party_of(id)?;
+        let party = state.parties.get_mut(&party_id).expect("party");
+
+        if party.leader == id {
+            let party = state.parties.remove(&party_id).expect("party");
+            for member in party.member_ids() {
+                state.members.remove(&member);
+            }
+            state.invites.retain(|_, invite| *invite != party_id);
+            return Ok(PartyWithdraw::Disbanded(party));
+        }
+
+        let ix = party
+            .members
+            .iter()
+            .position(|m| m.id == id)
+            .ok_or(PartyError::UnknownMember)?;
+        let member = party.members.remove(ix);
+        let party = party.clone();
+        state.members.remove(&id);
+        Ok(PartyWithdraw::Left(party, member))
+    }
+
+    pub fn kick(
+        &self,
+        leader: CharacterId,
+        target: CharacterId,
+    ) -> Result<(Party, PartyMemberInfo), PartyError> {
+        let mut state = self.state.lock().unwrap();
+        if leader == target {
+            return Err(PartyError::UnknownMember);
+        }
+
+        let party = state.leader_party_mut(leader)?;
+        let ix = party
+            .members
+            .iter()
+            .position(|m| m.id == target)
+            .ok_or(PartyError::UnknownMember)?;
+        let member = party.members.remove(ix);
+        let party = party.clone();
+        state.members.remove(&target);
+        Ok((party, member))
+    }
+
+    pub fn change_leader(
+        &self,
+        leader: CharacterId,
+        new_leader: CharacterId,
+    ) -> Result<Party, PartyError> {
+        let mut state = self.state.lock().unwrap();
+        let party = state.leader_party_mut(leader)?;
+        if party.get_member(new_leader).is_none() {
+            return Err(PartyError::UnknownMember);
+        }
+        party.leader = new_leader;
+        Ok(party.clone())
+    }
+
+    /// Updates the member info, returns the updated party if the character is in a party
+    pub fn update_member(
+        &self,
+        id: CharacterId,
+        f: impl FnOnce(&mut PartyMemberInfo),
+    ) -> Option<Party> {
+        let mut state = self.state.lock().unwrap();
+        let party_id = *state.members.get(&id)?;
+        let party = state.parties.get_mut(&party_id)?;
+        let member = party.members.iter_mut().find(|m| m.id == id)?;
+        f(member);
+        Some(party.clone())
+    }
+}
+
+/// Calculates the exp each of the `members` gets, every additional member
+/// adds a bonus of 10% to the total exp
+pub fn party_exp_share(exp: u32, members: usize) -> u32 {
+    if members <= 1 {
+        return exp;
+    }
+
+    let total = exp as u64 * (10 + members as u64 - 1) / 10;
+    (total / members as u64).max(1) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(id: u32) -> PartyMemberInfo {
+        PartyMemberInfo {
+            id: CharacterId(id),
+            name: format!("member{id}"),
+            job: JobId::Beginner,
+            level: 10,
+            channel: 0,
+            field: FieldId(100000000),
+            online: true,
+        }
+    }
+
+    #[test]
+    fn party_flow() {
+        let svc = PartyService::default();
+        let party = svc.create(member(1)).unwrap();
+        assert_eq!(svc.create(member(1)).unwrap_err(), PartyError::AlreadyJoined);
+
+        // Joining requires an invite
+        assert_eq!(
+            svc.join(member(2), party.id).unwrap_err(),
+            PartyError::NotInvited
+        );
+        svc.invite(CharacterId(1), CharacterId(2)).unwrap();
+        let party = svc.join(member(2), party.id).unwrap();
+        assert_eq!(party.members.len(), 2);
+
+        // Only the leader can invite
+        assert_eq!(
+            svc.invite(CharacterId(2), CharacterId(3)).unwrap_err(),
+            PartyError::NotLeader
+        );
+
+        let party = svc.change_leader(CharacterId(1), CharacterId(2)).unwrap();
+        assert_eq!(party.leader, CharacterId(2));
+
+        let PartyWithdraw::Left(party, left) = svc.withdraw(CharacterId(1)).unwrap() else {
+            panic!("Party should not be disbanded");
+        };
+        assert_eq!(left.id, CharacterId(1));
+        assert_eq!(party.members.len(), 1);
+        assert!(svc.get_party_of(CharacterId(1)).is_none());
+
+        assert!(matches!(
+            svc.withdraw(CharacterId(2)).unwrap(),
+            PartyWithdraw::Disbanded(_)
+        ));
+        assert!(svc.get_party(party.id).is_none());
+    }
+
+    #[test]
+    fn party_full() {
+        let svc = PartyService::default();
+        let party = svc.create(member(1)).unwrap();
+        for id in 2..=MAX_PARTY_MEMBERS as u32 {
+            svc.invite(CharacterId(1), CharacterId(id)).unwrap();
+            svc.join(member(id), party.id).unwrap();
+        }
+
+        assert_eq!(
+            svc.invite(CharacterId(1), CharacterId(99)).unwrap_err(),
+            PartyError::Full
+        );
+    }
+
+    #[test]
+    fn party_invite_removed() {
+        let svc = PartyService::default();
+        let party = svc.create(member(1)).unwrap();
+        svc.invite(CharacterId(1), CharacterId(2)).unwrap();
+        assert_eq!(
+            svc.invite(CharacterId(1), CharacterId(2)).unwrap_err(),
+            PartyError::AlreadyInvited
+        );
+
+        assert_eq!(svc.remove_invite(CharacterId(2)), Some(party.id));
+        assert_eq!(
+            svc.join(member(2), party.id).unwrap_err(),
+            PartyError::NotInvited
+        );
+        svc.invite(CharacterId(1), CharacterId(2)).unwrap();
+    }
+
+    #[test]
+    fn exp_share() {
+        assert_eq!(party_exp_share(100, 1), 100);
+        assert_eq!(party_exp_share(100, 2), 55);
+        assert_eq!(party_exp_share(1, 6), 1);
+    }
+}
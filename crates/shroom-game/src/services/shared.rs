@@ -15,12 +15,21 @@ use shroom_data::services::{
 use shroom_meta::{id::CharacterId, MetaService};
 use shroom_pkt::{error::EOFErrorData, PacketReader};
 use shroom_proto95::recv_opcodes::RecvOpcodes;
-use shroom_srv::GameTime;
+use shroom_srv::{act::SessionRegistry, GameTime};
 
-use crate::session::{ShroomSessionBackend, ShroomSessionManager};
+use crate::{
+    game::GameMessage,
+    session::{ShroomSessionBackend, ShroomSessionManager},
+};
+
+use super::{
+    buddy::BuddyWriteService, char_save::CharSaveLocks, employee::EmployeeService,
+    guild::GuildService, online::OnlineService, party::PartyService, trade::TradeService,
+};
 
 pub type SharedServices = Arc<Services>;
 pub type SharedGameServices = Arc<GameServices>;
+pub type GameSessionRegistry = SessionRegistry<CharacterId, GameMessage>;
 
 #[derive(Debug)]
 pub struct PacketEOFHandler {
@@ -60,14 +69,49 @@ impl PacketEOFHandler {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct GameConfig {
+    /// Default capacity of the buddy list
+    pub buddy_capacity: u8,
+    /// Mesos charged for storing an item in the trunk
+    pub trunk_put_fee: u32,
+    /// Mesos charged for taking an item out of the trunk
+    pub trunk_get_fee: u32,
+    /// Interval of the periodic character save
+    pub autosave_interval: Duration,
+    /// Port of the cash shop server, shared by all worlds
+    pub cash_shop_port: u16,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            buddy_capacity: 20,
+            trunk_put_fee: 100,
+            trunk_get_fee: 0,
+            autosave_interval: Duration::from_secs(5 * 60),
+            cash_shop_port: 8600,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GameServices {
+    pub config: GameConfig,
     pub data: DataProvider,
     pub server_info: ServerService,
     pub meta: &'static MetaService,
     pub eof_handler: Option<PacketEOFHandler>,
     pub scripts: ScriptService,
     pub current_time: AtomicCell<GameTime>,
+    pub sessions: GameSessionRegistry,
+    pub online: OnlineService,
+    pub party: PartyService,
+    pub trade: TradeService,
+    pub employee: EmployeeService,
+    pub guild: GuildService,
+    pub buddy_writes: BuddyWriteService,
+    pub char_saves: CharSaveLocks,
 }
 
 impl Deref for GameServices {
@@ -97,14 +141,24 @@ impl Services {
         data: DataProvider,
         servers: impl IntoIterator<Item = ServerInfo>,
         meta: &'static MetaService,
+        config: GameConfig,
     ) -> Self {
         let game = Arc::new(GameServices {
+            config,
             data,
             server_info: ServerService::new(servers),
             meta,
             eof_handler: None,
             scripts: ScriptService::default(),
-            current_time: AtomicCell::new(GameTime::default())
+            current_time: AtomicCell::new(GameTime::default()),
+            sessions: GameSessionRegistry::default(),
+            online: OnlineService::default(),
+            party: PartyService::default(),
+            trade: TradeService::default(),
+            employee: EmployeeService::default(),
+            guild: GuildService::default(),
+            buddy_writes: BuddyWriteService::default(),
+            char_saves: CharSaveLocks::default(),
         });
 
         let session_backend = ShroomSessionBackend::new(game.clone());
@@ -119,15 +173,25 @@ impl Services {
         data: DataProvider,
         servers: impl IntoIterator<Item = ServerInfo>,
         meta: &'static MetaService,
+        config: GameConfig,
         eof_handler: PacketEOFHandler,
     ) -> Self {
         let game = Arc::new(GameServices {
+            config,
             data,
             server_info: ServerService::new(servers),
             meta,
             eof_handler: Some(eof_handler),
             scripts: ScriptService::default(),
-            current_time: AtomicCell::new(GameTime::default())
+            current_time: AtomicCell::new(GameTime::default()),
+            sessions: GameSessionRegistry::default(),
+            online: OnlineService::default(),
+            party: PartyService::default(),
+            trade: TradeService::default(),
+            employee: EmployeeService::default(),
+            guild: GuildService::default(),
+            buddy_writes: BuddyWriteService::default(),
+            char_saves: CharSaveLocks::default(),
         });
 
         let session_backend = ShroomSessionBackend::new(game.clone());
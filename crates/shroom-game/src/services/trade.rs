@@ -0,0 +1,521 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use shroom_meta::{
+    id::{CharacterId, FieldId, ItemId},
+    item::it::EquipItem,
+};
+use shroom_proto95::game::mini_room::{MiniRoomId, MiniRoomUser, TRADE_MAX_ITEMS};
+
+pub const TRADE_MAX_USERS: usize = 2;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum TradeError {
+    #[error("Already in a trade")]
+    AlreadyTrading,
+    #[error("Not in a trade")]
+    NotTrading,
+    #[error("Not the owner of the trade")]
+    NotOwner,
+    #[error("Not invited to the trade")]
+    NotInvited,
+    #[error("Trade is full")]
+    Full,
+    #[error("Trade is in another field")]
+    OtherField,
+    #[error("Trade is locked")]
+    Locked,
+    #[error("Invalid trade position")]
+    InvalidPos,
+    #[error("No trade partner")]
+    NoPartner,
+}
+
+/// Item, which was taken from the inventory and is offered in a trade
+#[derive(Debug)]
+pub enum TradeItem {
+    Equip(Box<EquipItem>),
+    Stack(ItemId, usize),
+}
+
+impl TradeItem {
+    pub fn id(&self) -> ItemId {
+        match self {
+            TradeItem::Equip(item) => item.item_id,
+            TradeItem::Stack(id, _) => *id,
+        }
+    }
+
+    pub fn quantity(&self) -> usize {
+        match self {
+            TradeItem::Equip(_) => 1,
+            TradeItem::Stack(_, quantity) => *quantity,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TradeOffer {
+    pub items: Vec<(u8, TradeItem)>,
+    pub money: u32,
+}
+
+impl TradeOffer {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty() && self.money == 0
+    }
+
+    pub fn item_quantities(&self) -> Vec<(ItemId, usize)> {
+        self.items
+            .iter()
+            .map(|(_, item)| (item.id(), item.quantity()))
+            .collect()
+    }
+
+    fn merge(&mut self, other: TradeOffer) {
+        self.items.extend(other.items);
+        self.money = self.money.saturating_add(other.money);
+    }
+}
+
+#[derive(Debug)]
+struct TradeUser {
+    id: CharacterId,
+    user: MiniRoomUser,
+    offer: TradeOffer,
+    confirmed: bool,
+}
+
+#[derive(Debug)]
+struct TradeRoom {
+    id: MiniRoomId,
+    field: FieldId,
+    users: Vec<TradeUser>,
+    invited: Option<CharacterId>,
+}
+
+impl TradeRoom {
+    fn member(&self, id: CharacterId) -> Option<TradeMember> {
+        let pos = self.users.iter().position(|u| u.id == id)?;
+        Some(TradeMember {
+            room_id: self.id,
+            pos: pos as u8,
+            users: self.users.iter().map(|u| u.id).collect(),
+        })
+    }
+
+    fn user_mut(&mut self, id: CharacterId) -> Option<&mut TradeUser> {
+        self.users.iter_mut().find(|u| u.id == id)
+    }
+
+    fn partner(&self, id: CharacterId) -> Option<&TradeUser> {
+        self.users.iter().find(|u| u.id != id)
+    }
+
+    /// Offers can't be changed after one user confirmed the trade
+    fn is_locked(&self) -> bool {
+        self.users.iter().any(|u| u.confirmed)
+    }
+
+    fn is_confirmed(&self) -> bool {
+        self.users.len() == TRADE_MAX_USERS && self.users.iter().all(|u| u.confirmed)
+    }
+}
+
+/// View of a trade room for a single member
+#[derive(Debug, Clone)]
+pub struct TradeMember {
+    pub room_id: MiniRoomId,
+    pub pos: u8,
+    pub users: Vec<CharacterId>,
+}
+
+impl TradeMember {
+    pub fn others(&self, id: CharacterId) -> impl Iterator<Item = CharacterId> + '_ {
+        self.users.iter().copied().filter(move |u| *u != id)
+    }
+}
+
+/// Result of confirming a trade
+#[derive(Debug)]
+pub struct TradeConfirm {
+    pub member: TradeMember,
+    pub partner_items: Vec<(ItemId, usize)>,
+    pub partner_money: u32,
+    /// Both users confirmed, so the trade can be completed
+    pub done: bool,
+}
+
+#[derive(Debug, Default)]
+struct TradeState {
+    rooms: HashMap<MiniRoomId, TradeRoom>,
+    members: HashMap<CharacterId, MiniRoomId>,
+    deliveries: HashMap<CharacterId, TradeOffer>,
+    next_id: MiniRoomId,
+}
+
+impl TradeState {
+    fn room_of(&self, id: CharacterId) -> Result<&TradeRoom, TradeError> {
+        let room_id = self.members.get(&id).ok_or(TradeError::NotTrading)?;
+        Ok(self.rooms.get(room_id).expect("room"))
+    }
+
+    fn room_of_mut(&mut self, id: CharacterId) -> Result<&mut TradeRoom, TradeError> {
+        let room_id = self.members.get(&id).ok_or(TradeError::NotTrading)?;
+        Ok(self.rooms.get_mut(room_id).expect("room"))
+    }
+
+    fn remove_room(&mut self, room_id: MiniRoomId) -> Option<TradeRoom> {
+        let room = self.rooms.remove(&room_id)?;
+        for user in room.users.iter() {
+            self.members.remove(&user.id);
+        }
+        Some(room)
+    }
+
+    fn deliver(&mut self, id: CharacterId, offer: TradeOffer) {
+        if offer.is_empty() {
+            return;
+        }
+        self.deliveries.entry(id).or_default().merge(offer);
+    }
+}
+
+/// World wide registry of the trades. Offered items and mesos are held
+/// by the service until the trade is closed, then every user
+/// collects the delivery within its own session via `take_delivery`
+#[derive(Debug, Default)]
+pub struct TradeService {
+    state: Mutex<TradeState>,
+}
+
+impl TradeService {
+    pub fn get_member(&self, id: CharacterId) -> Option<TradeMember> {
+        self.state.lock().unwrap().room_of(id).ok()?.member(id)
+    }
+
+    pub fn create(
+        &self,
+        id: CharacterId,
+        user: MiniRoomUser,
+        field: FieldId,
+    ) -> Result<TradeMember, TradeError> {
+        let mut state = self.state.lock().unwrap();
+        if state.members.contains_key(&id) {
+            return Err(TradeError::AlreadyTrading);
+        }
+
+        state.next_id += 1;
+        let room_id = state.next_id;
+        let room = TradeRoom {
+            id: room_id,
+            field,
+            users: vec![TradeUser {
+                id,
+                user,
+                offer: TradeOffer::default(),
+                confirmed: false,
+            }],
+            invited: None,
+        };
+        let member = room.member(id).expect("member");
+        state.members.insert(id, room_id);
+        state.rooms.insert(room_id, room);
+        Ok(member)
+    }
+
+    pub fn invite(
+        &self,
+        owner: CharacterId,
+        target: CharacterId,
+    ) -> Result<MiniRoomId, TradeError> {
+        let mut state = self.state.lock().unwrap();
+        if state.members.contains_key(&target) {
+            return Err(TradeError::AlreadyTrading);
+        }
+
+        let room = state.room_of_mut(owner)?;
+        if room.users[0].id != owner {
+            return Err(TradeError::NotOwner);
+        }
+        if room.users.len() >= TRADE_MAX_USERS {
+            return Err(TradeError::Full);
+        }
+        room.invited = Some(target);
+        Ok(room.id)
+    }
+
+    pub fn is_invited(&self, id: CharacterId, room_id: MiniRoomId) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .rooms
+            .get(&room_id)
+            .is_some_and(|room| room.invited == Some(id))
+    }
+
+    /// Removes the invite, returns the owner of the room if the invite existed
+    pub fn decline(&self, target: CharacterId, room_id: MiniRoomId) -> Option<CharacterId> {
+        let mut state = self.state.lock().unwrap();
+        let room = state.rooms.get_mut(&room_id)?;
+        if room.invited != Some(target) {
+            return None;
+        }
+        room.invited = None;
+        Some(room.users[0].id)
+    }
+
+    /// Joins the room, returns all users of the room
+    pub fn join(
+        &self,
+        id: CharacterId,
+        mut user: MiniRoomUser,
+        room_id: MiniRoomId,
+        field: FieldId,
+    ) -> Result<(TradeMember, Vec<MiniRoomUser>), TradeError> {
+        let mut state = self.state.lock().unwrap();
+        if state.members.contains_key(&id) {
+            return Err(TradeError::AlreadyTrading);
+        }
+
+        let room = state
+            .rooms
+            .get_mut(&room_id)
+            .ok_or(TradeError::NotInvited)?;
+        if room.invited != Some(id) {
+            return Err(TradeError::NotInvited);
+        }
+        if room.field != field {
+            return Err(TradeError::OtherField);
+        }
+        if room.users.len() >= TRADE_MAX_USERS {
+            return Err(TradeError::Full);
+        }
+
+        user.pos = room.users.len() as u8;
+        room.invited = None;
+        room.users.push(TradeUser {
+            id,
+            user,
+            offer: TradeOffer::default(),
+            confirmed: false,
+        });
+        let member = room.member(id).expect("member");
+        let users = room.users.iter().map(|u| u.user.clone()).collect();
+        state.members.insert(id, room_id);
+        Ok((member, users))
+    }
+
+    /// Puts the item into the trade, the item is handed back on failure
+    pub fn put_item(
+        &self,
+        id: CharacterId,
+        pos: u8,
+        item: TradeItem,
+    ) -> Result<TradeMember, (TradeError, TradeItem)> {
+        let mut state = self.state.lock().unwrap();
+        let room = match state.room_of_mut(id) {
+            Ok(room) => room,
+            Err(err) => return Err((err, item)),
+        };
+        if room.users.len() < TRADE_MAX_USERS {
+            return Err((TradeError::NoPartner, item));
+        }
+        if room.is_locked() {
+            return Err((TradeError::Locked, item));
+        }
+
+        let user = room.user_mut(id).expect("user");
+        if pos == 0 || pos > TRADE_MAX_ITEMS || user.offer.items.iter().any(|(p, _)| *p == pos) {
+            return Err((TradeError::InvalidPos, item));
+        }
+        user.offer.items.push((pos, item));
+        Ok(room.member(id).expect("member"))
+    }
+
+    /// Adds mesos to the trade, returns the total offered mesos
+    pub fn put_money(&self, id: CharacterId, money: u32) -> Result<(TradeMember, u32), TradeError> {
+        let mut state = self.state.lock().unwrap();
+        let room = state.room_of_mut(id)?;
+        if room.users.len() < TRADE_MAX_USERS {
+            return Err(TradeError::NoPartner);
+        }
+        if room.is_locked() {
+            return Err(TradeError::Locked);
+        }
+
+        let user = room.user_mut(id).expect("user");
+        user.offer.money = user.offer.money.saturating_add(money);
+        let total = user.offer.money;
+        Ok((room.member(id).expect("member"), total))
+    }
+
+    /// Confirms the trade, which locks all offers
+    pub fn confirm(&self, id: CharacterId) -> Result<TradeConfirm, TradeError> {
+        let mut state = self.state.lock().unwrap();
+        let room = state.room_of_mut(id)?;
+        if room.users.len() < TRADE_MAX_USERS {
+            return Err(TradeError::NoPartner);
+        }
+
+        room.user_mut(id).expect("user").confirmed = true;
+        let partner = room.partner(id).expect("partner");
+        Ok(TradeConfirm {
+            member: room.member(id).expect("member"),
+            partner_items: partner.offer.item_quantities(),
+            partner_money: partner.offer.money,
+            done: room.is_confirmed(),
+        })
+    }
+
+    /// Swaps the offers of a confirmed trade, returns the users of the trade
+    pub fn complete(&self, room_id: MiniRoomId) -> Option<TradeMember> {
+        let mut state = self.state.lock().unwrap();
+        if !state.rooms.get(&room_id)?.is_confirmed() {
+            return None;
+        }
+
+        let mut room = state.remove_room(room_id)?;
+        let member = room.member(room.users[0].id).expect("member");
+        let b = room.users.pop().expect("user");
+        let a = room.users.pop().expect("user");
+        state.deliver(a.id, b.offer);
+        state.deliver(b.id, a.offer);
+        Some(member)
+    }
+
+    /// Closes the trade of the user and hands the offers back,
+    /// returns the members of the trade
+    pub fn close(&self, id: CharacterId) -> Option<TradeMember> {
+        let mut state = self.state.lock().unwrap();
+        let room_id = *state.members.get(&id)?;
+        let room = state.remove_room(room_id)?;
+        let member = room.member(id).expect("member");
+        for user in room.users {
+            state.deliver(user.id, user.offer);
+        }
+        Some(member)
+    }
+
+    /// Takes the items and mesos, which must be put into the inventory of the user
+    pub fn take_delivery(&self, id: CharacterId) -> Option<TradeOffer> {
+        self.state.lock().unwrap().deliveries.remove(&id)
+    }
+
+    /// Puts back the part of a delivery, which could not be claimed
+    pub fn keep_delivery(&self, id: CharacterId, offer: TradeOffer) {
+        self.state.lock().unwrap().deliver(id, offer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shroom_meta::id::{FaceId, HairId, ItemId, Skin};
+    use shroom_pkt::ShroomIndexList8;
+    use shroom_proto95::shared::{
+        char::{AvatarData, AvatarEquips},
+        Gender,
+    };
+
+    use super::*;
+
+    const FIELD: FieldId = FieldId(100000000);
+
+    fn user(id: u32) -> (CharacterId, MiniRoomUser) {
+        (
+            CharacterId(id),
+            MiniRoomUser {
+                pos: 0,
+                avatar: AvatarData {
+                    gender: Gender::Male,
+                    skin: Skin::Normal,
+                    face: FaceId::MOTIVATED_LOOK_M,
+                    mega: false,
+                    hair: HairId::BLACK_TOBEN,
+                    equips: AvatarEquips {
+                        equips: ShroomIndexList8::from(vec![]),
+                        masked_equips: ShroomIndexList8::from(vec![]),
+                        weapon_sticker_id: ItemId(0),
+                    },
+                    pets: [ItemId(0); 3],
+                },
+                name: format!("user{id}"),
+                job: 0,
+            },
+        )
+    }
+
+    fn open_trade(svc: &TradeService) -> MiniRoomId {
+        let (a, user_a) = user(1);
+        let (b, user_b) = user(2);
+        svc.create(a, user_a, FIELD).unwrap();
+        let room_id = svc.invite(a, b).unwrap();
+        let (member, users) = svc.join(b, user_b, room_id, FIELD).unwrap();
+        assert_eq!(member.pos, 1);
+        assert_eq!(users.len(), 2);
+        room_id
+    }
+
+    #[test]
+    fn trade_swap() {
+        let svc = TradeService::default();
+        let room_id = open_trade(&svc);
+        let (a, b) = (CharacterId(1), CharacterId(2));
+
+        svc.put_item(a, 1, TradeItem::Stack(ItemId(2000000), 10))
+            .unwrap();
+        assert!(svc
+            .put_item(a, 1, TradeItem::Stack(ItemId(2000001), 1))
+            .is_err());
+        svc.put_money(b, 1000).unwrap();
+
+        let confirm = svc.confirm(a).unwrap();
+        assert!(!confirm.done);
+        assert_eq!(confirm.partner_money, 1000);
+
+        // Offers are locked after the first confirm
+        assert_eq!(svc.put_money(b, 1).unwrap_err(), TradeError::Locked);
+        assert!(svc.complete(room_id).is_none());
+
+        let confirm = svc.confirm(b).unwrap();
+        assert!(confirm.done);
+        assert_eq!(confirm.partner_items, vec![(ItemId(2000000), 10)]);
+        svc.complete(room_id).unwrap();
+
+        assert_eq!(svc.take_delivery(a).unwrap().money, 1000);
+        assert_eq!(svc.take_delivery(b).unwrap().items.len(), 1);
+        assert!(svc.get_member(a).is_none());
+    }
+
+    #[test]
+    fn trade_cancel() {
+        let svc = TradeService::default();
+        open_trade(&svc);
+        let (a, b) = (CharacterId(1), CharacterId(2));
+
+        svc.put_item(a, 3, TradeItem::Stack(ItemId(2000000), 1))
+            .unwrap();
+        let member = svc.close(b).unwrap();
+        assert_eq!(member.pos, 1);
+
+        // Offers are handed back to their owners
+        assert_eq!(svc.take_delivery(a).unwrap().items.len(), 1);
+        assert!(svc.take_delivery(b).is_none());
+        assert!(svc.close(a).is_none());
+    }
+
+    #[test]
+    fn trade_keep_delivery() {
+        let svc = TradeService::default();
+        let a = CharacterId(1);
+        let offer = TradeOffer {
+            items: vec![(0, TradeItem::Stack(ItemId(2000000), 1))],
+            money: 0,
+        };
+        svc.keep_delivery(a, offer);
+        svc.keep_delivery(a, TradeOffer::default());
+
+        let delivery = svc.take_delivery(a).unwrap();
+        assert_eq!(delivery.item_quantities(), vec![(ItemId(2000000), 1)]);
+        assert!(svc.take_delivery(a).is_none());
+    }
+}
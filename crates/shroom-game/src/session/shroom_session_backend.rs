@@ -1,6 +1,13 @@
+use std::net::IpAddr;
+
 use dashmap::DashSet;
-use shroom_data::services::account::{AccountId, AccountServiceError};
+use shroom_data::services::{
+    account::{AccountId, AccountServiceError, HardwareInfo},
+    char_save::{CharSaveParts, CharSnapshot, SavedItemId},
+    trunk::Trunk,
+};
 use shroom_meta::id::CharacterId;
+use shroom_proto95::login::{ChannelId, WorldId};
 use thiserror::Error;
 
 use shroom_srv::session::Backend;
@@ -25,7 +32,7 @@ pub enum ShroomSessionError {
 
 #[derive(Debug)]
 pub enum AccountAuth {
-    UsernamePassword(String, String),
+    UsernamePassword(String, String, IpAddr, HardwareInfo),
     Token(AccountId, CharacterId, [u8; 32]),
 }
 
@@ -33,6 +40,74 @@ pub enum AccountAuth {
 pub struct SessionIngameData {
     pub acc: entities::account::Model,
     pub char: Character,
+    pub trunk: Trunk,
+    /// Channel the character returns to, when leaving the cash shop
+    pub cash_shop_return: Option<(WorldId, ChannelId)>,
+    /// Owners of the merchants, which were changed by this session,
+    /// they are saved together with the character
+    pub dirty_shops: Vec<CharacterId>,
+}
+
+impl SessionIngameData {
+    /// Copies the given parts of the character, so they can be saved in the background
+    pub fn snapshot(&mut self, parts: CharSaveParts) -> CharSnapshot {
+        let chr = &mut self.char;
+        let mut snapshot = CharSnapshot::new(chr.id, self.acc.id);
+        if parts.char {
+            snapshot = snapshot.with_char(chr.db_model());
+        }
+        if parts.inventory {
+            snapshot = snapshot.with_inventory(chr.inventory.invs.snapshot());
+        }
+        if parts.skills {
+            snapshot = snapshot.with_skills(chr.last_update, &chr.skills);
+        }
+        if parts.key_map && chr.key_map.is_changed() {
+            snapshot = snapshot.with_key_map(chr.key_map.clone());
+            chr.key_map.clear_changed();
+        }
+        if parts.quests {
+            snapshot = snapshot.with_quests(chr.quests.to_data());
+        }
+        if parts.trunk {
+            snapshot = snapshot.with_trunk(self.trunk.clone());
+        }
+        snapshot
+    }
+
+    /// Copies the parts, which changed since the last snapshot
+    pub fn dirty_snapshot(&mut self) -> Option<CharSnapshot> {
+        let mut parts = std::mem::take(&mut self.char.dirty);
+        parts.key_map = self.char.key_map.is_changed();
+        if parts.is_empty() && self.dirty_shops.is_empty() {
+            return None;
+        }
+        Some(self.snapshot(parts))
+    }
+
+    /// Expands the trunk by the given slots, returns false if the max is reached
+    pub fn expand_trunk(&mut self, slots: u8) -> bool {
+        if !self.trunk.expand(slots) {
+            return false;
+        }
+        self.char.dirty.trunk = true;
+        true
+    }
+
+    pub fn mark_shop_dirty(&mut self, owner: CharacterId) {
+        if !self.dirty_shops.contains(&owner) {
+            self.dirty_shops.push(owner);
+        }
+    }
+
+    /// Assigns the db ids of the items, which were inserted by a save
+    pub fn assign_db_ids(&mut self, ids: &[SavedItemId]) {
+        if ids.is_empty() {
+            return;
+        }
+        self.char.inventory.invs.assign_db_ids(ids);
+        self.trunk.assign_db_ids(ids);
+    }
 }
 
 #[derive(Debug)]
@@ -83,6 +158,9 @@ impl ShroomSessionData {
             return Err(ShroomSessionError::CharNotBelongingToAccount);
         }
 
+        // Writes to the buddy list, which happened while being offline
+        svc.buddy_writes.wait(char_id).await;
+
         let t = svc.current_time.load();
         let char = Character::new(
             svc.clone(),
@@ -112,11 +190,25 @@ impl ShroomSessionData {
                 .load_quests(char_id)
                 .await
                 .map_err(ShroomSessionError::Other)?,
+            svc.data
+                .buddy
+                .load(char_id)
+                .await
+                .map_err(ShroomSessionError::Other)?,
         );
+        let trunk = svc
+            .data
+            .trunk()
+            .load(acc_id)
+            .await
+            .map_err(ShroomSessionError::Other)?;
 
         *self = Self::Ingame(SessionIngameData {
             acc: login.acc.clone(),
             char,
+            trunk,
+            cash_shop_return: None,
+            dirty_shops: Vec::new(),
         });
 
         Ok(())
@@ -153,12 +245,12 @@ impl Backend for ShroomSessionBackend {
 
     async fn load(&self, param: Self::LoadParam) -> Result<Self::Data, ShroomSessionError> {
         match param {
-            AccountAuth::UsernamePassword(username, password) => {
+            AccountAuth::UsernamePassword(username, password, ip, hw_info) => {
                 let acc = self
                     .game
                     .data
                     .account
-                    .try_login(&username, &password)
+                    .try_login(&username, &password, ip, &hw_info)
                     .await?;
 
                 if !self.logged_in.insert(acc.id) {
@@ -170,6 +262,8 @@ impl Backend for ShroomSessionBackend {
             AccountAuth::Token(acc_id, chr, _token) => {
                 //TODO verify the token
                 let acc = self.game.account.get(acc_id).await.unwrap().unwrap();
+                // The ban might have been issued after the login
+                self.game.data.account.check_ban(acc.id, None, None).await?;
                 if !self.logged_in.insert(acc_id) {
                     return Err(AccountServiceError::AccountAlreadyLoggedIn.into());
                 }
@@ -190,29 +284,25 @@ impl Backend for ShroomSessionBackend {
 
         match session.as_mut() {
             ShroomSessionData::Ingame(ingame) => {
-                let char_id = ingame.char.id;
-                let d = &self.game.data;
-                d.item
-                    .save_inventory(&mut ingame.char.inventory.invs, char_id)
-                    .await
-                    .map_err(ShroomSessionError::Other)?;
-                d.char()
-                    .save_skills(ingame.char.last_update, char_id, &ingame.char.skills)
-                    .await
-                    .map_err(ShroomSessionError::Other)?;
-                d.char()
-                    .save_key_map(char_id, &ingame.char.key_map)
-                    .await
-                    .map_err(ShroomSessionError::Other)?;
-                let q = ingame.char.quests.to_data();
-                d.char()
-                    .save_quest(char_id, q)
-                    .await
-                    .map_err(ShroomSessionError::Other)?;
-                d.char()
-                    .save_char(ingame.char.db_model())
+                // Wait for a running autosave and apply the ids of the items it
+                // inserted, so they are not inserted twice
+                let lock = self.game.char_saves.get(ingame.char.id);
+                let mut saved = lock.lock().await;
+                ingame.assign_db_ids(&saved);
+                saved.clear();
+
+                let mut snapshot = ingame.snapshot(CharSaveParts::ALL);
+                let ids = self
+                    .game
+                    .employee
+                    .save_char(&self.game.data, &mut snapshot, &ingame.dirty_shops)
                     .await
                     .map_err(ShroomSessionError::Other)?;
+                ingame.assign_db_ids(&ids);
+                ingame.char.dirty = CharSaveParts::default();
+                ingame.dirty_shops.clear();
+                drop(saved);
+                self.game.char_saves.remove(ingame.char.id);
             }
             ShroomSessionData::Login(_login) => {}
         };
@@ -1,5 +1,6 @@
 use std::{fmt::Debug, net::IpAddr, time::Duration};
 
+use dashmap::DashMap;
 use uuid::Uuid;
 
 use super::shroom_session_backend::{
@@ -38,8 +39,9 @@ impl ShroomMigrationKey {
 /// Manages all sessions
 pub struct ShroomSessionManager<B: Backend> {
     session_man: SessionManager<Uuid, B>,
-    migration: MigrationManager<ShroomMigrationKey, OwnedSession<Uuid, B::Data>>
-
+    migration: MigrationManager<ShroomMigrationKey, OwnedSession<Uuid, B::Data>>,
+    /// Sessions changing the channel, which are migrated once they are dropped
+    channel_migrations: DashMap<Uuid, ShroomMigrationKey>,
 }
 
 impl<B: Backend + std::fmt::Debug> Debug for ShroomSessionManager<B>
@@ -62,6 +64,7 @@ where
         Self {
             session_man: SessionManager::new(backend),
             migration: MigrationManager::new(migration_timeout),
+            channel_migrations: DashMap::new(),
         }
     }
 
@@ -92,12 +95,23 @@ where
         Ok(())
     }
 
-    /// Attempts to close a session by id
+    /// Attempts to close a session by id,
+    /// sessions which change the channel are saved and put into migration instead
     pub async fn close_session_by_key(&self, id: uuid::Uuid) -> anyhow::Result<()> {
-        let session = self.session_man.try_claim_session(id)?;
+        let mut session = self.session_man.try_claim_session(id)?;
+        if let Some((_, migration_key)) = self.channel_migrations.remove(&id) {
+            self.session_man.save(&mut session).await?;
+            return self.migrate_session(migration_key, session);
+        }
         self.close_session(session).await
     }
 
+    /// Marks the session for a channel change, once the current owner drops the session
+    /// It will be migrated with the key instead of being closed
+    pub fn migrate_on_drop(&self, id: uuid::Uuid, migration_key: ShroomMigrationKey) {
+        self.channel_migrations.insert(id, migration_key);
+    }
+
     /// Closes a the session
     pub async fn close_session(
         &self,
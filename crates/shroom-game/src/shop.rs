@@ -0,0 +1,257 @@
+use either::Either;
+use shroom_data::model::inv::InventorySlot;
+use shroom_meta::{
+    drops::{NpcShop, NpcShopItem},
+    id::{item_id::InventoryType, ItemId, Money, NpcId},
+    MetaService,
+};
+use shroom_proto95::game::shop::{
+    OpenShopResp, ShopBuy, ShopItem, ShopResultResp, ShopSell, ShopUserReq,
+};
+
+use crate::game::{GameContext, GameSession};
+
+/// Shop, which is currently opened by a session
+#[derive(Debug, Clone, Copy)]
+pub struct OpenedShop {
+    pub npc_id: NpcId,
+    pub shop: &'static NpcShop,
+}
+
+impl OpenedShop {
+    pub fn get_item(&self, pos: u16, id: ItemId) -> Option<&'static NpcShopItem> {
+        self.shop
+            .items
+            .get(pos as usize)
+            .filter(|item| item.item_id == id.0)
+    }
+
+    /// Shops which sell rechargeable items also allow recharging them
+    pub fn can_recharge(&self) -> bool {
+        self.shop
+            .items
+            .iter()
+            .any(|item| ItemId(item.item_id).is_rechargable())
+    }
+
+    pub fn open_shop_resp(&self, meta: &MetaService) -> OpenShopResp {
+        let items = meta.items();
+        OpenShopResp {
+            npc_tmpl_id: self.npc_id,
+            items: self
+                .shop
+                .items
+                .iter()
+                .map(|item| {
+                    let id = ItemId(item.item_id);
+                    let quantity = if id.is_rechargable() {
+                        Either::Left((items.get_unit_price(id).unwrap_or(0.) as f64).to_bits())
+                    } else {
+                        Either::Right(1)
+                    };
+
+                    ShopItem {
+                        item_id: id,
+                        price: item.price,
+                        discount_rate: 100,
+                        token_item_id: ItemId(0),
+                        token_price: 0,
+                        item_period: item.item_period as u32,
+                        level_limited: 0,
+                        quantity: quantity.into(),
+                        max_per_slot: items.get_slot_max(id).unwrap_or(u8::MAX as u16),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Calculates the price for recharging the given amount of units
+fn recharge_price(unit_price: f32, quantity: usize) -> Money {
+    (unit_price as f64 * quantity as f64).ceil() as Money
+}
+
+impl GameSession {
+    pub fn open_shop(&mut self, ctx: &mut GameContext, npc_id: NpcId) -> anyhow::Result<bool> {
+        let Some(shop) = self.meta().get_npc_shop(npc_id) else {
+            return Ok(false);
+        };
+
+        let shop = OpenedShop { npc_id, shop };
+        ctx.socket.reply(shop.open_shop_resp(self.meta()))?;
+        self.shop = Some(shop);
+        Ok(true)
+    }
+
+    pub fn handle_shop_req(&mut self, ctx: &mut GameContext, req: ShopUserReq) -> anyhow::Result<()> {
+        let Some(shop) = self.shop else {
+            anyhow::bail!("No shop opened");
+        };
+
+        let resp = match req {
+            ShopUserReq::Buy(buy) => self.shop_buy(shop, buy)?,
+            ShopUserReq::Sell(sell) => self.shop_sell(sell)?,
+            ShopUserReq::Recharge(slot) => self.shop_recharge(shop, slot)?,
+            ShopUserReq::Close(()) => {
+                self.shop = None;
+                return Ok(());
+            }
+        };
+        ctx.socket.reply(resp)?;
+
+        Ok(())
+    }
+
+    fn shop_buy(&mut self, shop: OpenedShop, buy: ShopBuy) -> anyhow::Result<ShopResultResp> {
+        let id = ItemId(buy.tmpl_id);
+        let Some(item) = shop.get_item(buy.pos, id) else {
+            return Ok(ShopResultResp::BuyUnknown(()));
+        };
+
+        // Rechargeable items are always sold as a full slot
+        let slot_max = self.meta().items().get_slot_max(id).unwrap_or(1) as usize;
+        let count = if id.is_rechargable() {
+            slot_max
+        } else if id.get_inv_type()?.is_stack() {
+            buy.count as usize
+        } else {
+            1
+        };
+
+        // A single purchase can't exceed one stack
+        if count == 0 || count > slot_max {
+            return Ok(ShopResultResp::BuyUnknown(()));
+        }
+
+        let price = if id.is_rechargable() {
+            Some(item.price)
+        } else {
+            item.price.checked_mul(count as Money)
+        };
+        let Some(price) = price.filter(|price| *price <= i32::MAX as Money) else {
+            return Ok(ShopResultResp::BuyNoMoney(()));
+        };
+
+        let chr = &mut self.session.char;
+        if chr.money() < price {
+            return Ok(ShopResultResp::BuyNoMoney(()));
+        }
+
+        if !chr.inventory.can_add(id, count)? {
+            return Ok(ShopResultResp::BuyUnknown(()));
+        }
+
+        chr.add_items(id, Some(count))?;
+        chr.update_mesos(-(price as i32));
+
+        Ok(ShopResultResp::BuySuccess(()))
+    }
+
+    fn shop_sell(&mut self, sell: ShopSell) -> anyhow::Result<ShopResultResp> {
+        let id = ItemId(sell.tmpl_id);
+        let inv_ty = id.get_inv_type()?;
+        let Ok(slot) = InventorySlot::try_from((inv_ty, sell.slot as i16)) else {
+            return Ok(ShopResultResp::SellIncorrectRequest(()));
+        };
+
+        let items = self.meta().items();
+        let Some(price) = items.get_price(id) else {
+            return Ok(ShopResultResp::SellIncorrectRequest(()));
+        };
+
+        let inv = &mut self.session.char.inventory;
+        let money = match inv_ty {
+            InventoryType::Equip => {
+                if inv.invs.equip.get(slot.as_slot()).map(|eq| eq.item_id) != Some(id) {
+                    return Ok(ShopResultResp::SellIncorrectRequest(()));
+                }
+
+                inv.drop_equip_item(slot)?;
+                price as u64
+            }
+            InventoryType::Consume | InventoryType::Install | InventoryType::Etc => {
+                let stack = inv.invs.get_stack_inventory(inv_ty)?;
+                let Some(quantity) = stack
+                    .get(slot.as_slot())
+                    .filter(|item| item.item_id == id)
+                    .map(|item| item.quantity as usize)
+                else {
+                    return Ok(ShopResultResp::SellIncorrectRequest(()));
+                };
+
+                // Rechargeable items always sell the whole slot
+                let count = if id.is_rechargable() {
+                    quantity
+                } else {
+                    sell.count as usize
+                };
+
+                if count == 0 || count > quantity {
+                    return Ok(ShopResultResp::SellIncorrectRequest(()));
+                }
+
+                inv.drop_stack_item(inv_ty, slot, Some(count))?;
+                if id.is_rechargable() {
+                    let unit_price = items.get_unit_price(id).unwrap_or(0.);
+                    price as u64 + recharge_price(unit_price, count) as u64
+                } else {
+                    price as u64 * count as u64
+                }
+            }
+            _ => return Ok(ShopResultResp::SellIncorrectRequest(())),
+        };
+
+        self.session
+            .char
+            .update_mesos(money.min(i32::MAX as u64) as i32);
+
+        Ok(ShopResultResp::SellSuccess(()))
+    }
+
+    fn shop_recharge(&mut self, shop: OpenedShop, slot: u16) -> anyhow::Result<ShopResultResp> {
+        if !shop.can_recharge() {
+            return Ok(ShopResultResp::RechargeIncorrectRequest(()));
+        }
+
+        let Ok(slot) = InventorySlot::try_from((InventoryType::Consume, slot as i16)) else {
+            return Ok(ShopResultResp::RechargeIncorrectRequest(()));
+        };
+
+        let items = self.meta().items();
+        let chr = &mut self.session.char;
+        let Some((id, quantity)) = chr
+            .inventory
+            .invs
+            .consume
+            .get(slot.as_slot())
+            .map(|item| (item.item_id, item.quantity as usize))
+            .filter(|(id, _)| id.is_rechargable())
+        else {
+            return Ok(ShopResultResp::RechargeIncorrectRequest(()));
+        };
+
+        let (Some(slot_max), Some(unit_price)) = (items.get_slot_max(id), items.get_unit_price(id))
+        else {
+            return Ok(ShopResultResp::RechargeIncorrectRequest(()));
+        };
+
+        let missing = (slot_max as usize).saturating_sub(quantity);
+        if missing == 0 {
+            return Ok(ShopResultResp::RechargeIncorrectRequest(()));
+        }
+
+        let price = recharge_price(unit_price, missing);
+        if chr.money() < price {
+            return Ok(ShopResultResp::RechargeNoMoney(()));
+        }
+
+        chr.inventory
+            .invs
+            .consume
+            .add_quantity(slot.as_slot(), missing)?;
+        chr.update_mesos(-(price as i32));
+
+        Ok(ShopResultResp::RechargeSuccess(()))
+    }
+}
@@ -3,15 +3,19 @@ use std::{num::Wrapping, sync::Arc};
 use shroom_meta::id::{CharacterId, FieldId};
 
 use shroom_srv::{
-    act::system::SystemHandler,
+    act::system::{SystemHandler, SystemSessionRegistry},
     net::{session::NetSession, system::NetSystemHandler},
     ClockHandle,
 };
 
-use shroom_proto95::game::MigrateInGameReq;
+use shroom_proto95::{
+    game::MigrateInGameReq,
+    login::{ChannelId, WorldId},
+};
 use tokio::net::TcpStream;
 
 use crate::{
+    autosave::AutosaveState,
     field::{FieldHandler, SharedFieldState},
     game::GameSession,
     repl::GameRepl,
@@ -33,8 +37,11 @@ pub type GameCodec = shroom_net::codec::websocket::WebSocketCodec<TcpStream>;
 #[cfg(not(feature = "websockets"))]
 pub type GameCodec = shroom_net::codec::legacy::LegacyCodecShanda<TcpStream>;
 
+/// System of a single channel, every channel has It's own field rooms
 pub struct GameSystem {
     pub services: Arc<Services>,
+    pub world_id: WorldId,
+    pub channel_id: ChannelId,
 }
 
 impl SystemHandler for GameSystem {
@@ -45,16 +52,22 @@ impl SystemHandler for GameSystem {
     type Room = FieldHandler;
 
     fn create_room(&mut self, id: Self::RoomId) -> Result<Self::Room, Self::Error> {
-        log::info!("Creating room: {id}");
+        log::info!(
+            "Creating room: {id} on world: {} channel: {}",
+            self.world_id,
+            self.channel_id
+        );
         let meta = self.services.game.meta;
         let field_meta = meta.get_field(id).unwrap();
         let field_fh = meta.get_field_fh_data(id).unwrap();
+        let world = self.services.game.server_info.get_server(self.world_id)?;
         Ok(FieldHandler::new(
-            meta,
+            self.services.game.clone(),
             self.services.current_time.load(),
             SharedFieldState {
                 field_meta,
                 field_fh,
+                drop_rate: world.drop_multiplier(),
             }
             .into(),
         ))
@@ -64,6 +77,10 @@ impl SystemHandler for GameSystem {
         self.services.current_time.store(t);
         Ok(())
     }
+
+    fn session_registry(&self) -> SystemSessionRegistry<Self> {
+        self.services.game.sessions.clone()
+    }
 }
 
 impl NetSystemHandler for GameSystem {
@@ -131,15 +148,24 @@ impl NetSystemHandler for GameSystem {
         log::info!("Spawning");
 
         let field_id = session.char.field;
+        self.services
+            .game
+            .server_info
+            .get_channel(self.world_id, self.channel_id)?
+            .user_enter();
 
         let sess = GameSession {
             services: self.services.clone(),
             session,
             addr: sck.peer_addr(),
-            channel_id: 0,
-            world_id: 0,
+            channel_id: self.channel_id,
+            world_id: self.world_id,
             client_key,
             current_script: None,
+            shop: None,
+            trunk: None,
+            guild_npc: None,
+            autosave: AutosaveState::default(),
             field_id,
             field_meta: self.services.game.meta.get_field(field_id).unwrap(),
             repl: GameRepl::new(),
@@ -0,0 +1,529 @@
+use shroom_data::model::inv::InventorySlot;
+use shroom_meta::{
+    id::{item_id::InventoryType, CharacterId},
+    item::it::{ItemInfo, StackItem},
+};
+use shroom_proto95::{
+    game::{
+        mini_room::{
+            MiniRoomChat, MiniRoomChatReq, MiniRoomCreateReq, MiniRoomEnterData,
+            MiniRoomEnterError, MiniRoomEnterResult, MiniRoomInvite, MiniRoomInviteResult,
+            MiniRoomInviteResultReq, MiniRoomInviteResultResp, MiniRoomLeave, MiniRoomLeaveReason,
+            MiniRoomReq, MiniRoomResp, MiniRoomType, MiniRoomUser, MiniRoomUserChat, MiniRoomUsers,
+            TradePutItem, TradePutItemReq, TradePutMoney,
+        },
+        BroadcastMessageResp,
+    },
+    shared::item::Item,
+};
+
+use crate::{
+    game::{GameContext, GameMessage, GameSession},
+    services::trade::{TradeError, TradeItem, TradeMember, TradeOffer, TRADE_MAX_USERS},
+};
+
+impl TradeItem {
+    fn to_proto(&self) -> Item {
+        match self {
+            TradeItem::Equip(item) => Item::Equip(item.as_ref().into()),
+            TradeItem::Stack(id, quantity) => Item::Stack(
+                (&StackItem {
+                    info: ItemInfo::from_id(*id, 0, false),
+                    quantity: *quantity as u16,
+                })
+                    .into(),
+            ),
+        }
+    }
+}
+
+impl From<TradeError> for MiniRoomEnterError {
+    fn from(err: TradeError) -> Self {
+        match err {
+            TradeError::Full => MiniRoomEnterError::Full,
+            TradeError::AlreadyTrading => MiniRoomEnterError::Busy,
+            TradeError::OtherField => MiniRoomEnterError::OnlyInSameField,
+            _ => MiniRoomEnterError::NoRoom,
+        }
+    }
+}
+
+impl GameSession {
+    pub(crate) fn mini_room_user(&self, pos: u8) -> MiniRoomUser {
+        let chr = &self.session.char;
+        MiniRoomUser {
+            pos,
+            avatar: chr.get_avatar_data(),
+            name: chr.name.clone(),
+            job: chr.stats.job as u16,
+        }
+    }
+
+    /// Sends the packet to all trade members, the own packet is sent directly
+    fn send_trade(
+        &self,
+        ctx: &mut GameContext,
+        member: &TradeMember,
+        pkt: impl Fn() -> MiniRoomResp,
+    ) -> anyhow::Result<()> {
+        self.services
+            .game
+            .sessions
+            .send_all_encode(member.others(self.char_id()), pkt())?;
+        ctx.socket.reply(pkt())?;
+        Ok(())
+    }
+
+    /// Puts the items and mesos of a closed trade into the inventory,
+    /// items which don't fit anymore stay pending for the next claim
+    pub(crate) fn claim_trade(&mut self) -> anyhow::Result<()> {
+        let Some(offer) = self.services.game.trade.take_delivery(self.char_id()) else {
+            return Ok(());
+        };
+
+        let chr = &mut self.session.char;
+        chr.update_mesos(offer.money.min(i32::MAX as u32) as i32);
+        let mut pending = TradeOffer::default();
+        for (pos, item) in offer.items {
+            let id = item.id();
+            if !chr.inventory.can_add(id, item.quantity())? {
+                pending.items.push((pos, item));
+                continue;
+            }
+
+            let res = match &item {
+                TradeItem::Equip(equip) => chr
+                    .inventory
+                    .try_add_equip(equip.as_ref().clone())
+                    .map(|_| ()),
+                TradeItem::Stack(id, quantity) => {
+                    chr.inventory
+                        .try_add_stack_item(*id, *quantity, id.get_inv_type()?)
+                }
+            };
+            if let Err(err) = res {
+                log::error!(
+                    "Unable to deliver trade item {id:?} to {:?}: {err:?}",
+                    chr.id
+                );
+                pending.items.push((pos, item));
+            }
+        }
+
+        if !pending.is_empty() {
+            log::info!(
+                "Inventory of {:?} is full, keeping {} trade items pending",
+                chr.id,
+                pending.items.len()
+            );
+            self.services.game.trade.keep_delivery(chr.id, pending);
+        }
+        Ok(())
+    }
+
+    /// Cancels the current trade, the offers are handed back
+    pub(crate) fn cancel_trade(&mut self) -> anyhow::Result<Option<u8>> {
+        let id = self.char_id();
+        let member = self.services.game.trade.close(id);
+        if let Some(member) = member.as_ref() {
+            for (pos, user) in member.users.iter().enumerate() {
+                if *user != id {
+                    self.services.game.sessions.send_to(
+                        *user,
+                        GameMessage::TradeClosed(pos as u8, MiniRoomLeaveReason::HostOut),
+                    );
+                }
+            }
+        }
+
+        // Also claims deliveries of trades, which were closed by the partner
+        self.claim_trade()?;
+        Ok(member.map(|member| member.pos))
+    }
+
+    fn close_trade(
+        &mut self,
+        ctx: &mut GameContext,
+        reason: MiniRoomLeaveReason,
+    ) -> anyhow::Result<()> {
+        let Some(pos) = self.cancel_trade()? else {
+            return Ok(());
+        };
+        ctx.socket
+            .reply(MiniRoomResp::Leave(MiniRoomLeave { pos, reason }))?;
+        Ok(())
+    }
+
+    pub(crate) fn handle_trade_closed(
+        &mut self,
+        ctx: &mut GameContext,
+        pos: u8,
+        reason: MiniRoomLeaveReason,
+    ) -> anyhow::Result<()> {
+        self.claim_trade()?;
+        ctx.socket
+            .reply(MiniRoomResp::Leave(MiniRoomLeave { pos, reason }))?;
+        Ok(())
+    }
+
+    pub fn handle_mini_room_req(
+        &mut self,
+        ctx: &mut GameContext,
+        req: MiniRoomReq,
+    ) -> anyhow::Result<()> {
+        let in_trade = self
+            .services
+            .game
+            .trade
+            .get_member(self.char_id())
+            .is_some();
+        match req {
+            MiniRoomReq::Create(MiniRoomCreateReq::TradingRoom(())) => self.trade_create(ctx),
+            MiniRoomReq::Create(MiniRoomCreateReq::EntrustedShop(req)) => {
+                self.shop_create(ctx, req)
+            }
+            MiniRoomReq::Invite(target) => self.trade_invite(ctx, target),
+            MiniRoomReq::InviteResult(req) => self.trade_invite_result(req),
+            // Shops use the id of the owner as room id
+            MiniRoomReq::Enter(req) => {
+                if self
+                    .services
+                    .game
+                    .trade
+                    .is_invited(self.char_id(), req.room_id)
+                {
+                    self.trade_enter(ctx, req.room_id)
+                } else {
+                    self.shop_enter(ctx, req.room_id)
+                }
+            }
+            MiniRoomReq::Chat(req) if in_trade => self.trade_chat(ctx, req),
+            MiniRoomReq::Chat(req) => self.shop_chat(ctx, req),
+            MiniRoomReq::Leave(()) if in_trade => {
+                self.close_trade(ctx, MiniRoomLeaveReason::UserRequest)
+            }
+            MiniRoomReq::Leave(()) => self.shop_leave_req(ctx),
+            MiniRoomReq::TradePutItem(req) => self.trade_put_item(ctx, req),
+            MiniRoomReq::TradePutMoney(money) => self.trade_put_money(ctx, money),
+            MiniRoomReq::Trade(()) => self.trade_confirm(ctx),
+            // Items are validated on the server
+            MiniRoomReq::TradeItemCrc(()) => Ok(()),
+            MiniRoomReq::Open(()) => self.shop_open(ctx),
+            MiniRoomReq::EntrustedShopPutItem(req) => self.shop_put_item(ctx, req),
+            MiniRoomReq::EntrustedShopBuyItem(req) => self.shop_buy_item(ctx, req),
+            MiniRoomReq::EntrustedShopMoveItemToInventory(index) => {
+                self.shop_move_item_to_inventory(ctx, index)
+            }
+            MiniRoomReq::EntrustedShopGoOut(()) => self.shop_go_out(ctx),
+            MiniRoomReq::EntrustedShopArrangeItem(()) => self.shop_arrange(ctx),
+            MiniRoomReq::EntrustedShopWithdrawMoney(()) => self.shop_withdraw_money(ctx),
+        }
+    }
+
+    fn trade_create(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        let user = self.mini_room_user(0);
+        let res = self
+            .services
+            .game
+            .trade
+            .create(self.char_id(), user.clone(), self.field_id);
+
+        ctx.socket.reply(MiniRoomResp::EnterResult(match res {
+            Ok(_) => MiniRoomEnterResult::TradingRoom(MiniRoomEnterData {
+                max_users: TRADE_MAX_USERS as u8,
+                my_pos: 0,
+                users: MiniRoomUsers(vec![user]),
+            }),
+            Err(err) => MiniRoomEnterResult::Error(err.into()),
+        }))?;
+        Ok(())
+    }
+
+    fn trade_invite(&mut self, ctx: &mut GameContext, target: CharacterId) -> anyhow::Result<()> {
+        // Trades are only possible within the same field
+        let target = self
+            .services
+            .game
+            .online
+            .get(self.world_id, target)
+            .filter(|chr| chr.id != self.char_id() && ctx.room.tx.contains(&chr.id));
+        let Some(target) = target else {
+            ctx.socket
+                .reply(MiniRoomResp::InviteResult(MiniRoomInviteResultResp {
+                    result: MiniRoomInviteResult::NoCharacter,
+                    target: String::new(),
+                }))?;
+            return Ok(());
+        };
+
+        let room_id = match self.services.game.trade.invite(self.char_id(), target.id) {
+            Ok(room_id) => room_id,
+            Err(_) => {
+                ctx.socket
+                    .reply(MiniRoomResp::InviteResult(MiniRoomInviteResultResp {
+                        result: MiniRoomInviteResult::CannotInvite,
+                        target: target.name,
+                    }))?;
+                return Ok(());
+            }
+        };
+
+        self.services.game.sessions.send_to_encode(
+            target.id,
+            MiniRoomResp::Invite(MiniRoomInvite {
+                ty: MiniRoomType::TradingRoom,
+                inviter: self.session.char.name.clone(),
+                room_id,
+            }),
+        )?;
+        Ok(())
+    }
+
+    fn trade_invite_result(&mut self, req: MiniRoomInviteResultReq) -> anyhow::Result<()> {
+        let Some(owner) = self
+            .services
+            .game
+            .trade
+            .decline(self.char_id(), req.room_id)
+        else {
+            return Ok(());
+        };
+
+        self.services.game.sessions.send_to_encode(
+            owner,
+            MiniRoomResp::InviteResult(MiniRoomInviteResultResp {
+                result: req.result,
+                target: self.session.char.name.clone(),
+            }),
+        )?;
+        Ok(())
+    }
+
+    fn trade_enter(&mut self, ctx: &mut GameContext, room_id: u32) -> anyhow::Result<()> {
+        let res = self.services.game.trade.join(
+            self.char_id(),
+            self.mini_room_user(0),
+            room_id,
+            self.field_id,
+        );
+        let (member, users) = match res {
+            Ok(res) => res,
+            Err(err) => {
+                ctx.socket
+                    .reply(MiniRoomResp::EnterResult(MiniRoomEnterResult::Error(
+                        err.into(),
+                    )))?;
+                return Ok(());
+            }
+        };
+
+        let me = users[member.pos as usize].clone();
+        self.services
+            .game
+            .sessions
+            .send_all_encode(member.others(self.char_id()), MiniRoomResp::Enter(me))?;
+        ctx.socket
+            .reply(MiniRoomResp::EnterResult(MiniRoomEnterResult::TradingRoom(
+                MiniRoomEnterData {
+                    max_users: TRADE_MAX_USERS as u8,
+                    my_pos: member.pos,
+                    users: MiniRoomUsers(users),
+                },
+            )))?;
+        Ok(())
+    }
+
+    fn trade_chat(&mut self, ctx: &mut GameContext, req: MiniRoomChatReq) -> anyhow::Result<()> {
+        let Some(member) = self.services.game.trade.get_member(self.char_id()) else {
+            return Ok(());
+        };
+
+        let msg = format!("{} : {}", self.session.char.name, req.msg);
+        self.send_trade(ctx, &member, || {
+            MiniRoomResp::Chat(MiniRoomChat::UserChat(MiniRoomUserChat {
+                pos: member.pos,
+                msg: msg.clone(),
+            }))
+        })
+    }
+
+    /// Whether the item in the slot can be traded, checked by the flags of the template
+    pub(crate) fn is_tradable_slot(&self, inv_type: InventoryType, slot: u16) -> bool {
+        let Ok(slot @ InventorySlot::Slot(_, _)) = InventorySlot::try_from((inv_type, slot as i16))
+        else {
+            return false;
+        };
+
+        let inv = &self.session.char.inventory;
+        let id = match inv_type {
+            InventoryType::Equip => inv.invs.equip.get(slot.as_slot()).map(|eq| eq.item_id),
+            _ => inv.get_stack_item_id(slot).ok(),
+        };
+        id.is_some_and(|id| self.meta().items().is_tradable(id))
+    }
+
+    /// Tells the player that the item can't be traded
+    pub(crate) fn reject_untradable(&self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        ctx.socket.reply(BroadcastMessageResp::Alert(
+            "This item cannot be traded.".to_string(),
+        ))?;
+        Ok(())
+    }
+
+    /// Takes the item out of the inventory, so it can be offered
+    pub(crate) fn take_trade_item(
+        &mut self,
+        inv_type: InventoryType,
+        slot: u16,
+        count: u16,
+    ) -> anyhow::Result<Option<TradeItem>> {
+        // Equipped and cash items can't be traded
+        let Ok(slot @ InventorySlot::Slot(_, _)) = InventorySlot::try_from((inv_type, slot as i16))
+        else {
+            return Ok(None);
+        };
+
+        let inv = &mut self.session.char.inventory;
+        Ok(match inv_type {
+            InventoryType::Equip => {
+                if inv.invs.equip.get(slot.as_slot()).is_none() {
+                    return Ok(None);
+                }
+                Some(TradeItem::Equip(inv.drop_equip_item(slot)?.item))
+            }
+            InventoryType::Consume | InventoryType::Install | InventoryType::Etc => {
+                let count = count as usize;
+                let quantity = inv
+                    .invs
+                    .get_stack_inventory(inv_type)?
+                    .get(slot.as_slot())
+                    .map(|item| item.quantity as usize);
+                if count == 0 || quantity.map_or(true, |q| count > q) {
+                    return Ok(None);
+                }
+
+                let stack = inv.drop_stack_item(inv_type, slot, Some(count))?;
+                Some(TradeItem::Stack(stack.0, stack.1))
+            }
+            _ => None,
+        })
+    }
+
+    pub(crate) fn restore_trade_item(&mut self, item: TradeItem) -> anyhow::Result<()> {
+        let inv = &mut self.session.char.inventory;
+        match item {
+            TradeItem::Equip(item) => {
+                inv.try_add_equip(*item)?;
+            }
+            TradeItem::Stack(id, quantity) => {
+                inv.try_add_stack_item(id, quantity, id.get_inv_type()?)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn trade_put_item(
+        &mut self,
+        ctx: &mut GameContext,
+        req: TradePutItemReq,
+    ) -> anyhow::Result<()> {
+        if self
+            .services
+            .game
+            .trade
+            .get_member(self.char_id())
+            .is_none()
+        {
+            return Ok(());
+        }
+        if !self.is_tradable_slot(req.inv_type, req.slot) {
+            return self.reject_untradable(ctx);
+        }
+        let Some(item) = self.take_trade_item(req.inv_type, req.slot, req.count)? else {
+            return Ok(());
+        };
+
+        // The item is encoded for both users before it's moved into the trade
+        let (own_item, other_item) = (item.to_proto(), item.to_proto());
+        let id = self.char_id();
+        match self.services.game.trade.put_item(id, req.pos, item) {
+            Ok(member) => {
+                self.services.game.sessions.send_all_encode(
+                    member.others(id),
+                    MiniRoomResp::TradePutItem(TradePutItem {
+                        user_pos: member.pos,
+                        pos: req.pos,
+                        item: other_item,
+                    }),
+                )?;
+                ctx.socket.reply(MiniRoomResp::TradePutItem(TradePutItem {
+                    user_pos: member.pos,
+                    pos: req.pos,
+                    item: own_item,
+                }))?;
+                Ok(())
+            }
+            Err((err, item)) => {
+                log::info!("Unable to put trade item: {err}");
+                self.restore_trade_item(item)
+            }
+        }
+    }
+
+    fn trade_put_money(&mut self, ctx: &mut GameContext, money: u32) -> anyhow::Result<()> {
+        if money == 0 || money > self.session.char.money() {
+            return Ok(());
+        }
+
+        self.session.char.update_mesos(-(money as i32));
+        match self.services.game.trade.put_money(self.char_id(), money) {
+            Ok((member, total)) => self.send_trade(ctx, &member, || {
+                MiniRoomResp::TradePutMoney(TradePutMoney {
+                    user_pos: member.pos,
+                    money: total,
+                })
+            }),
+            Err(err) => {
+                log::info!("Unable to put trade money: {err}");
+                self.session.char.update_mesos(money as i32);
+                Ok(())
+            }
+        }
+    }
+
+    fn trade_confirm(&mut self, ctx: &mut GameContext) -> anyhow::Result<()> {
+        let id = self.char_id();
+        let Ok(confirm) = self.services.game.trade.confirm(id) else {
+            return Ok(());
+        };
+
+        let chr = &self.session.char;
+        let fits = chr.inventory.can_add_all(&confirm.partner_items)?
+            && chr.money() as u64 + confirm.partner_money as u64 <= i32::MAX as u64;
+        if !fits {
+            return self.close_trade(ctx, MiniRoomLeaveReason::TradeFail);
+        }
+
+        self.services
+            .game
+            .sessions
+            .send_all_encode(confirm.member.others(id), MiniRoomResp::Trade(()))?;
+
+        if confirm.done {
+            let Some(member) = self.services.game.trade.complete(confirm.member.room_id) else {
+                return Ok(());
+            };
+
+            for (pos, user) in member.users.iter().enumerate() {
+                if *user != id {
+                    self.services.game.sessions.send_to(
+                        *user,
+                        GameMessage::TradeClosed(pos as u8, MiniRoomLeaveReason::TradeDone),
+                    );
+                }
+            }
+            self.handle_trade_closed(ctx, confirm.member.pos, MiniRoomLeaveReason::TradeDone)?;
+        }
+        Ok(())
+    }
+}
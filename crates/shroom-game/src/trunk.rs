@@ -0,0 +1,200 @@
+use shroom_data::services::trunk::{TrunkError, TrunkItem};
+use shroom_meta::id::{item_id::InventoryType, NpcId};
+use shroom_proto95::{
+    game::trunk::{
+        TrunkData, TrunkGetItemReq, TrunkOpenData, TrunkPutItemReq, TrunkResultResp, UserTrunkReq,
+    },
+    shared::item::Item,
+};
+
+use crate::{
+    game::{GameContext, GameSession},
+    services::trade::TradeItem,
+};
+
+/// Npcs, which open the account trunk
+pub const TRUNK_NPCS: &[NpcId] = &[
+    NpcId(1002005),
+    NpcId(1012009),
+    NpcId(1022005),
+    NpcId(1032006),
+    NpcId(1052017),
+    NpcId(1061008),
+    NpcId(2010006),
+    NpcId(2020004),
+    NpcId(2041008),
+    NpcId(2050004),
+    NpcId(2060008),
+    NpcId(2070000),
+    NpcId(2080005),
+    NpcId(2090000),
+    NpcId(2093003),
+    NpcId(9120009),
+    NpcId(9270042),
+];
+
+const TRUNK_INV_TYPES: [InventoryType; 5] = [
+    InventoryType::Equip,
+    InventoryType::Consume,
+    InventoryType::Install,
+    InventoryType::Etc,
+    InventoryType::Cash,
+];
+
+fn trunk_item_to_proto(item: &TrunkItem) -> Item {
+    match item {
+        TrunkItem::Equip(item) => Item::Equip(item.as_ref().into()),
+        TrunkItem::Stack(item) => Item::Stack(item.as_ref().into()),
+    }
+}
+
+impl GameSession {
+    /// Builds the trunk data with the money and the items of the given types
+    fn trunk_data(&self, types: &[InventoryType]) -> TrunkData {
+        let trunk = &self.session.trunk;
+        types.iter().fold(
+            TrunkData::new(trunk.slots).with_money(trunk.money),
+            |data, ty| {
+                data.with_items(
+                    *ty,
+                    trunk.items_by_type(*ty).map(trunk_item_to_proto).collect(),
+                )
+            },
+        )
+    }
+
+    pub fn open_trunk(&mut self, ctx: &mut GameContext, npc_id: NpcId) -> anyhow::Result<bool> {
+        if !TRUNK_NPCS.contains(&npc_id) {
+            return Ok(false);
+        }
+
+        ctx.socket
+            .reply(TrunkResultResp::OpenTrunkDlg(TrunkOpenData {
+                npc_tmpl_id: npc_id,
+                data: self.trunk_data(&TRUNK_INV_TYPES),
+            }))?;
+        self.trunk = Some(npc_id);
+        Ok(true)
+    }
+
+    pub fn handle_trunk_req(
+        &mut self,
+        ctx: &mut GameContext,
+        req: UserTrunkReq,
+    ) -> anyhow::Result<()> {
+        if self.trunk.is_none() {
+            anyhow::bail!("No trunk opened");
+        }
+
+        let resp = match req {
+            UserTrunkReq::GetItem(req) => self.trunk_get_item(req)?,
+            UserTrunkReq::PutItem(req) => self.trunk_put_item(req)?,
+            UserTrunkReq::SortItem(()) => {
+                self.session.trunk.sort();
+                TrunkResultResp::SortItem(self.trunk_data(&TRUNK_INV_TYPES))
+            }
+            UserTrunkReq::Money(amount) => self.trunk_money(amount)?,
+            UserTrunkReq::Close(()) => {
+                self.trunk = None;
+                return Ok(());
+            }
+        };
+        self.session.char.dirty.trunk = true;
+        ctx.socket.reply(resp)?;
+
+        Ok(())
+    }
+
+    fn trunk_get_item(&mut self, req: TrunkGetItemReq) -> anyhow::Result<TrunkResultResp> {
+        let fee = self.services.game.config.trunk_get_fee;
+        let Some(item) = self.session.trunk.get(req.inv_type, req.index as usize) else {
+            return Ok(TrunkResultResp::GetUnknown(()));
+        };
+        let (id, quantity) = match item {
+            TrunkItem::Equip(item) => (item.item_id, 1),
+            TrunkItem::Stack(item) => (item.item_id, item.quantity as usize),
+        };
+
+        let chr = &self.session.char;
+        if chr.money() < fee {
+            return Ok(TrunkResultResp::GetNoMoney(()));
+        }
+        if !chr.inventory.can_add(id, quantity)? {
+            return Ok(TrunkResultResp::GetUnknown(()));
+        }
+
+        let item = match self.session.trunk.take(req.inv_type, req.index as usize)? {
+            TrunkItem::Equip(item) => TradeItem::Equip(item),
+            TrunkItem::Stack(item) => TradeItem::Stack(item.item_id, item.quantity as usize),
+        };
+        self.restore_trade_item(item)?;
+        self.session.char.update_mesos(-(fee as i32));
+
+        Ok(TrunkResultResp::GetSuccess(
+            self.trunk_data(&[req.inv_type]),
+        ))
+    }
+
+    fn trunk_put_item(&mut self, req: TrunkPutItemReq) -> anyhow::Result<TrunkResultResp> {
+        let fee = self.services.game.config.trunk_put_fee;
+        if self.session.trunk.is_full() {
+            return Ok(TrunkResultResp::PutNoSpace(()));
+        }
+        if self.session.char.money() < fee {
+            return Ok(TrunkResultResp::PutNoMoney(()));
+        }
+
+        let inv_type = req.item_id.get_inv_type()?;
+        let Some(item) = self.take_trade_item(inv_type, req.slot, req.count)? else {
+            return Ok(TrunkResultResp::PutIncorrectRequest(()));
+        };
+        if item.id() != req.item_id {
+            self.restore_trade_item(item)?;
+            return Ok(TrunkResultResp::PutIncorrectRequest(()));
+        }
+
+        let item = match item {
+            TradeItem::Equip(item) => TrunkItem::Equip(item),
+            TradeItem::Stack(id, quantity) => TrunkItem::Stack(Box::new(
+                self.services
+                    .game
+                    .data
+                    .item
+                    .create_stack(id, quantity as u16)?,
+            )),
+        };
+        match self.session.trunk.put(item) {
+            Ok(()) => {}
+            Err(TrunkError::Full) => return Ok(TrunkResultResp::PutNoSpace(())),
+            Err(err) => return Err(err.into()),
+        }
+        self.session.char.update_mesos(-(fee as i32));
+
+        Ok(TrunkResultResp::PutSuccess(self.trunk_data(&[inv_type])))
+    }
+
+    fn trunk_money(&mut self, amount: i32) -> anyhow::Result<TrunkResultResp> {
+        let sess = &mut *self.session;
+        let (chr, trunk) = (&mut sess.char, &mut sess.trunk);
+        let money = amount.unsigned_abs();
+        let res = if amount > 0 {
+            // Withdraw, the character must be able to hold the mesos
+            if chr.money() as u64 + money as u64 > i32::MAX as u64 {
+                return Ok(TrunkResultResp::MoneyUnknown(()));
+            }
+            trunk.withdraw(money)
+        } else {
+            if chr.money() < money {
+                return Ok(TrunkResultResp::MoneyUnknown(()));
+            }
+            trunk.deposit(money)
+        };
+
+        if res.is_err() {
+            return Ok(TrunkResultResp::MoneyUnknown(()));
+        }
+        chr.update_mesos(amount);
+
+        Ok(TrunkResultResp::MoneySuccess(self.trunk_data(&[])))
+    }
+}
@@ -0,0 +1,93 @@
+use shroom_proto95::game::chat::{
+    WhiperMsgReq, WhisperData, WhisperFieldLocation, WhisperFindData, WhisperLocation,
+    WhisperLocationResult, WhisperReceive, WhisperResp, WhisperResult,
+};
+
+use crate::{
+    game::{GameContext, GameSession},
+    repl::GmLevel,
+};
+
+impl GameSession {
+    pub fn handle_whisper(&mut self, ctx: &mut GameContext, req: WhiperMsgReq) -> anyhow::Result<()> {
+        match req {
+            WhiperMsgReq::Whisper(req) | WhiperMsgReq::Unknown(req) => self.whisper(ctx, req),
+            WhiperMsgReq::WhisperFind(req) => {
+                let location = self.find(&req);
+                ctx.socket.reply(WhisperResp::LocationResult(location))?;
+                Ok(())
+            }
+            WhiperMsgReq::WhisperFindFriend(req) => {
+                let location = self.find(&req);
+                ctx.socket.reply(WhisperResp::LocationFriendResult(location))?;
+                Ok(())
+            }
+        }
+    }
+
+    fn whisper(&mut self, ctx: &mut GameContext, req: WhisperData) -> anyhow::Result<()> {
+        let Some(target) = self
+            .services
+            .game
+            .online
+            .get_by_name(self.world_id, &req.target)
+        else {
+            ctx.socket.reply(WhisperResp::WhisperResult(WhisperResult {
+                target: req.target,
+                success: false,
+            }))?;
+            return Ok(());
+        };
+
+        if target.whisper_blocked {
+            ctx.socket.reply(WhisperResp::WhisperBlocked(target.name))?;
+            return Ok(());
+        }
+
+        let delivered = self.services.game.sessions.send_to_encode(
+            target.id,
+            WhisperResp::WhisperReceive(WhisperReceive {
+                sender: self.session.char.name.clone(),
+                channel: self.channel_id as u8,
+                is_admin: self.gm_level() > GmLevel::Player,
+                msg: req.msg,
+            }),
+        )?;
+
+        ctx.socket.reply(WhisperResp::WhisperResult(WhisperResult {
+            target: target.name,
+            success: delivered,
+        }))?;
+        Ok(())
+    }
+
+    fn find(&self, req: &WhisperFindData) -> WhisperLocationResult {
+        let Some(target) = self
+            .services
+            .game
+            .online
+            .get_by_name(self.world_id, &req.target)
+        else {
+            return WhisperLocationResult {
+                target: req.target.clone(),
+                location: WhisperLocation::NotFound(-1),
+            };
+        };
+
+        // The exact field is only shown within the same channel
+        let location = if target.channel == self.channel_id {
+            WhisperLocation::Field(WhisperFieldLocation {
+                field_id: target.field,
+                x: 0,
+                y: 0,
+            })
+        } else {
+            WhisperLocation::Channel(target.channel as u32)
+        };
+
+        WhisperLocationResult {
+            target: target.name,
+            location,
+        }
+    }
+}
@@ -3,6 +3,7 @@ pub mod login_state;
 
 use config::LoginConfig;
 use login_state::LoginState;
+use shroom_data::proto_mapper::{db_to_shroom_time, permanent_shroom_time};
 use shroom_data::services::account::AccountServiceError;
 use shroom_data::services::character::{CharWithEquips, CharacterCreateDTO, ItemStarterSet};
 use shroom_game::services::shared::SharedServices;
@@ -44,7 +45,7 @@ use shroom_proto95::{
     },
 };
 
-use shroom_pkt::{ShroomList8, ShroomTime};
+use shroom_pkt::ShroomList8;
 use shroom_srv::rpc::{RpcCtx, RpcResponse, RpcService};
 use shroom_srv::session::Error;
 
@@ -345,7 +346,12 @@ impl<C: ShroomCodec> LoginService<C> {
         let login_result = self
             .services
             .session_manager
-            .create_claimed_session(AccountAuth::UsernamePassword(req.id, req.pw))
+            .create_claimed_session(AccountAuth::UsernamePassword(
+                req.id,
+                req.pw,
+                ctx.peer_addr(),
+                req.machine_id,
+            ))
             .await;
         let hdr = LoginResultHeader::default();
 
@@ -353,11 +359,15 @@ impl<C: ShroomCodec> LoginService<C> {
             Err(Error::Backend(ShroomSessionError::Account(acc))) => match acc {
                 AccountServiceError::UsernameNotFound => CheckPasswordResp::InvalidUserName(hdr),
                 AccountServiceError::PasswordMismatch => CheckPasswordResp::InvalidPassword(hdr),
-                AccountServiceError::AccountBanned => CheckPasswordResp::BlockedIp(BlockedIp {
-                    hdr,
-                    reason: 0,
-                    ban_time: ShroomTime::now(), // TODO
-                }),
+                AccountServiceError::AccountBanned(ban) => {
+                    CheckPasswordResp::BlockedIp(BlockedIp {
+                        hdr,
+                        reason: ban.reason,
+                        ban_time: ban
+                            .until
+                            .map_or_else(permanent_shroom_time, db_to_shroom_time),
+                    })
+                }
                 AccountServiceError::AccountAlreadyLoggedIn => {
                     CheckPasswordResp::AlreadyLoggedIn(hdr)
                 }
@@ -409,12 +419,18 @@ impl<C: ShroomCodec> LoginService<C> {
 
     async fn handle_select_world(&mut self, ctx: &mut RpcCtx<C>, req: SelectWorldReq) -> LoginResponse {
         let acc = self.login_state.get_server_selection()?;
+        let world_id = req.world_id as WorldId;
+        if self.services.game.server_info.get_server(world_id).is_err() {
+            ctx.send(SelectWorldResp::Err(())).await?;
+            return Ok(RpcResponse::Ok);
+        }
+
         let char_list = self
             .services
             .game
             .data
             .char()
-            .get_characters_with_equips(acc.id)
+            .get_characters_with_equips(acc.id, world_id)
             .await?;
 
         let select_char_list = SelectWorldCharList {
@@ -427,7 +443,7 @@ impl<C: ShroomCodec> LoginService<C> {
         };
 
         self.login_state.transition_char_select(
-            req.world_id as WorldId,
+            world_id,
             req.channel_id as ChannelId,
             char_list,
         )?;
@@ -462,7 +478,17 @@ impl<C: ShroomCodec> LoginService<C> {
     }
 
     async fn handle_create_char(&mut self, ctx: &mut RpcCtx<C>, req: CreateCharReq) -> LoginResponse {
-        let (acc, _, _, _) = self.login_state.get_char_select()?;
+        let (acc, world_id, _, _) = self.login_state.get_char_select()?;
+        if self
+            .services
+            .game
+            .server_info
+            .get_server(world_id)?
+            .block_char_creation
+        {
+            ctx.send(CreateCharResp::SystemError(())).await?;
+            return Ok(RpcResponse::Ok);
+        }
 
         let starter_set = ItemStarterSet {
             shoes: req.starter_set.shoes,
@@ -484,7 +510,8 @@ impl<C: ShroomCodec> LoginService<C> {
                 starter_set,
                 gender: req.gender,
                 max_skills: false,
-                level: None
+                level: None,
+                world_id,
             },
             &self.services.game.data.item,
         ))
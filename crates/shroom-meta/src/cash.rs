@@ -0,0 +1,52 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::id::ItemId;
+
+/// Serial number of a commodity in the cash shop catalog
+pub type CommoditySn = u32;
+
+/// Gender restriction of a commodity as stored in `Commodity.img`
+pub const COMMODITY_GENDER_BOTH: u8 = 2;
+
+/// Item sold in the cash shop, the client ships the same catalog
+/// so the server only validates the purchase against it
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Commodity {
+    pub sn: CommoditySn,
+    pub item_id: ItemId,
+    pub count: u16,
+    pub price: u32,
+    /// Days until the item expires, 0 for permanent items
+    pub period: u16,
+    pub gender: u8,
+    pub on_sale: bool,
+}
+
+impl Commodity {
+    pub fn is_permanent(&self) -> bool {
+        self.period == 0
+    }
+
+    /// Gender is either 0 for male or 1 for female
+    pub fn allows_gender(&self, gender: u8) -> bool {
+        self.gender == COMMODITY_GENDER_BOTH || self.gender == gender
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Commodities(BTreeMap<CommoditySn, Commodity>);
+
+impl Commodities {
+    pub fn get(&self, sn: CommoditySn) -> Option<&Commodity> {
+        self.0.get(&sn)
+    }
+}
+
+impl FromIterator<Commodity> for Commodities {
+    fn from_iter<T: IntoIterator<Item = Commodity>>(iter: T) -> Self {
+        Self(iter.into_iter().map(|c| (c.sn, c)).collect())
+    }
+}
@@ -98,7 +98,8 @@ impl ArcherSkillData {
                     summon: SummonSkill {
                         move_ability: SummonMoveAbility::CircleFollow,
                         assist_type: SummonAssistType::Attack,
-                        dur: skill.time_dur(lvl)
+                        dur: skill.time_dur(lvl),
+                        hp: None,
                     },
                     buff: CharBuffBeholder::from_skill(skill, lvl, 1),
                     stun: MobDebuff::new(
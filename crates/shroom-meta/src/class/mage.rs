@@ -268,7 +268,8 @@ impl MageSkillData {
                 summon: SummonSkill {
                     move_ability: SummonMoveAbility::CircleFollow,
                     assist_type: SummonAssistType::Attack,
-                    dur: skill.time_dur(lvl)
+                    dur: skill.time_dur(lvl),
+                    hp: None,
                 },
                 buff: CharBuffBeholder::from_skill(skill, lvl, 1),
                 damage: skill.damage(lvl),
@@ -299,7 +300,8 @@ impl MageSkillData {
                 summon: SummonSkill {
                     move_ability: SummonMoveAbility::Follow,
                     assist_type: SummonAssistType::Attack,
-                    dur: skill.time_dur(lvl)
+                    dur: skill.time_dur(lvl),
+                    hp: None,
                 },
                 buff: CharBuffBeholder::from_skill(skill, lvl, 1),
                 damage: skill.damage(lvl),
@@ -316,7 +318,8 @@ impl MageSkillData {
                 summon: SummonSkill {
                     move_ability: SummonMoveAbility::Follow,
                     assist_type: SummonAssistType::Attack,
-                    dur: skill.time_dur(lvl)
+                    dur: skill.time_dur(lvl),
+                    hp: None,
                 },
                 buff: CharBuffBeholder::from_skill(skill, lvl, 1),
                 damage: skill.damage(lvl),
@@ -330,7 +333,8 @@ impl MageSkillData {
                 summon: SummonSkill {
                     move_ability: SummonMoveAbility::Follow,
                     assist_type: SummonAssistType::Attack,
-                    dur: skill.time_dur(lvl)
+                    dur: skill.time_dur(lvl),
+                    hp: None,
                 },
                 buff: CharBuffBeholder::from_skill(skill, lvl, 1),
                 damage: skill.damage(lvl),
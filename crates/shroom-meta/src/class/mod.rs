@@ -132,6 +132,18 @@ pub enum SummonMoveAbility {
     None
 }
 
+impl SummonMoveAbility {
+    /// Summons, which stay at the position they were spawned at
+    pub fn is_stationary(&self) -> bool {
+        matches!(self, Self::None)
+    }
+
+    /// Summons, which stay next to the owner and follow them into another field
+    pub fn follows_owner(&self) -> bool {
+        matches!(self, Self::Follow | Self::CircleFollow | Self::Escort)
+    }
+}
+
 
 #[derive(Debug, Clone)]
 pub enum SummonAssistType {
@@ -148,6 +160,8 @@ pub struct SummonSkill {
     pub move_ability: SummonMoveAbility,
     pub assist_type: SummonAssistType,
     pub dur: Duration,
+    /// HP of summons, which can be hit by mobs
+    pub hp: Option<u32>,
 }
 
 #[derive(Debug)]
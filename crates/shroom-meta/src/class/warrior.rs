@@ -318,6 +318,7 @@ impl WarriorSkillData {
                     move_ability: SummonMoveAbility::Follow,
                     assist_type: SummonAssistType::Heal,
                     dur: skill.time_dur_min(lvl),
+                    hp: None,
                 },
             }),
             DRK_AURA_OF_THE_BEHOLDER => Self::BeholderHeal(BeholderHeal {
@@ -87,9 +87,11 @@ pub struct DropList {
 }
 
 impl DropEntry {
+    /// Rolls the entry, the chance is scaled by the drop rate
     pub fn get_with_rand<R: Rng + ?Sized>(
         &self,
         flags: &QuestDropFlags,
+        rate: f32,
         rng: &mut R,
     ) -> Option<(ItemId, usize)> {
         // Check for flag
@@ -98,7 +100,7 @@ impl DropEntry {
         }
 
         // Check for chance
-        if !rng.gen_bool(self.chance.into()) {
+        if !rng.gen_bool((self.chance * rate).clamp(0., 1.).into()) {
             return None;
         }
 
@@ -125,10 +127,11 @@ impl DropList {
         &'a self,
         rng: &'b mut R,
         flags: &'a QuestDropFlags,
+        rate: f32,
     ) -> impl Iterator<Item = (ItemId, usize)> + 'a {
         self.entries
             .iter()
-            .filter_map(move |entry| entry.get_with_rand(flags, rng))
+            .filter_map(move |entry| entry.get_with_rand(flags, rate, rng))
     }
 }
 
@@ -210,11 +213,12 @@ impl DropPool {
         &self,
         mob_id: MobId,
         flags: &QuestDropFlags,
+        rate: f32,
         r: &mut impl Rng,
     ) -> Vec<(ItemId, usize)> {
         self.mob_drops
             .get(&mob_id)
-            .map(move |drops| drops.get_with_rand(r, flags).collect())
+            .map(move |drops| drops.get_with_rand(r, flags, rate).collect())
             .unwrap_or_default()
     }
 
@@ -222,11 +226,12 @@ impl DropPool {
         &self,
         reactor_id: ReactorId,
         flags: &QuestDropFlags,
+        rate: f32,
         r: &mut impl Rng,
     ) -> Vec<(ItemId, usize)> {
         self.reactor_drops
             .get(&reactor_id)
-            .map(move |drops| drops.get_with_rand(r, flags).collect())
+            .map(move |drops| drops.get_with_rand(r, flags, rate).collect())
             .unwrap_or_default()
     }
 
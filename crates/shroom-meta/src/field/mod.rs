@@ -107,6 +107,18 @@ pub struct Field {
     pub fh_tree: FhTree,
 }
 
+/// Scripts of a field, which run when users enter the field
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldScripts {
+    pub id: FieldId,
+    /// Script, which runs when the first user enters the empty field
+    #[serde(default)]
+    pub on_first_user_enter: Option<String>,
+    /// Script, which runs for every user entering the field
+    #[serde(default)]
+    pub on_user_enter: Option<String>,
+}
+
 impl Field {
     pub fn get_return_field_id(&self) -> FieldId {
         self.return_field
@@ -440,6 +440,10 @@ impl ItemId {
         self.0 / 10000 == 233 || self.0 / 10000 == 207
     }
 
+    pub fn is_shop_employee(&self) -> bool {
+        self.0 / 10000 == 503
+    }
+
     pub fn is_exp_increase(&self) -> bool {
         (2022450..=2022452).contains(&self.0)
     }
@@ -62,6 +62,41 @@ impl SkillId {
         )
     }
 
+    pub fn is_hp_boost(&self) -> bool {
+        matches!(
+            *self,
+            WARRIOR_HP_BOOST | BRAWLER_HP_BOOST | DW1_HP_BOOST | TB2_HP_BOOST
+        )
+    }
+
+    pub fn is_mp_boost(&self) -> bool {
+        matches!(*self, MAGE_MP_BOOST | BW1_MP_BOOST)
+    }
+
+    pub fn is_critical_passive(&self) -> bool {
+        matches!(
+            *self,
+            BOWMAN_CRITICAL_SHOT
+                | ASSASSIN_CRITICAL_THROW
+                | BRAWLER_CRITICAL_PUNCH
+                | GUNSLINGER_CRITICAL_SHOT
+                | WA1_CRITICAL_SHOT
+                | NW2_CRITICAL_THROW
+                | TB3_CRITICAL_PUNCH
+                | EVAN6_CRITICAL_MAGIC
+        )
+    }
+
+    pub fn is_element_amplification(&self) -> bool {
+        matches!(
+            *self,
+            FP2_ELEMENT_AMPLIFICATION
+                | IL2_ELEMENT_AMPLIFICATION
+                | BW3_ELEMENT_AMPLIFICATION
+                | EVAN7_MAGIC_AMPLIFICATION
+        )
+    }
+
     pub fn is_not_using_shooting_weapon(&self) -> bool {
         matches!(
             self.0,
@@ -100,14 +100,14 @@ impl From<Durability> for i32 {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct EquipOptions(pub [ItemOptionId; 3]);
 
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct EquipSockets(pub [u16; 2]);
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EquipItem {
     pub info: ItemInfo,
     pub stats: EquipBaseStats,
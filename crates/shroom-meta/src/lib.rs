@@ -1,4 +1,5 @@
 pub mod buffs;
+pub mod cash;
 pub mod class;
 pub mod drops;
 pub mod exp_table;
@@ -16,6 +17,7 @@ pub mod tmpl;
 pub mod item;
 pub mod field;
 pub mod npc;
+pub mod reactor;
 
 use num_enum::TryFromPrimitive;
 use serde::{Deserialize, Serialize};
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    id::{ItemId, ReactorId, SkillId},
+    twod::{Box2, Vec2},
+};
+
+/// Input which might advance the state of a reactor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReactorInput {
+    /// Hit by an attack, the skill is 0 for regular attacks
+    Hit(SkillId),
+    Touch,
+    ItemDrop {
+        item: ItemId,
+        quantity: usize,
+        pos: Vec2,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ReactorTrigger {
+    Hit,
+    SkillHit(Vec<SkillId>),
+    Touch,
+    /// Item dropped in the area, the area is relative to the reactor
+    ItemDrop {
+        item: ItemId,
+        quantity: usize,
+        area: Box2,
+    },
+}
+
+impl ReactorTrigger {
+    pub fn matches(&self, input: &ReactorInput, reactor_pos: Vec2) -> bool {
+        match (self, input) {
+            (Self::Hit, ReactorInput::Hit(_)) => true,
+            (Self::SkillHit(skills), ReactorInput::Hit(skill)) => skills.contains(skill),
+            (Self::Touch, ReactorInput::Touch) => true,
+            (
+                Self::ItemDrop {
+                    item,
+                    quantity,
+                    area,
+                },
+                ReactorInput::ItemDrop {
+                    item: drop_item,
+                    quantity: drop_quantity,
+                    pos,
+                },
+            ) => {
+                item == drop_item
+                    && drop_quantity >= quantity
+                    && area.contains((*pos - reactor_pos).to_point())
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReactorEvent {
+    pub trigger: ReactorTrigger,
+    pub next_state: u8,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReactorState {
+    pub events: Vec<ReactorEvent>,
+    /// Duration of the animation, which is played when entering this state
+    pub anim_delay_ms: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reactor {
+    pub id: ReactorId,
+    /// Script, which runs once the reactor reaches its final state
+    pub action: Option<String>,
+    pub states: Vec<ReactorState>,
+}
+
+impl Reactor {
+    pub fn get_state(&self, state: u8) -> Option<&ReactorState> {
+        self.states.get(state as usize)
+    }
+
+    /// The final state has no events, reaching it destroys the reactor
+    pub fn is_final_state(&self, state: u8) -> bool {
+        match self.get_state(state) {
+            Some(state) => state.events.is_empty(),
+            None => true,
+        }
+    }
+
+    /// Finds the event matching the input, returns the event index and the next state
+    pub fn next_state(&self, state: u8, input: &ReactorInput, pos: Vec2) -> Option<(u8, u8)> {
+        self.get_state(state)?
+            .events
+            .iter()
+            .enumerate()
+            .find(|(_, ev)| ev.trigger.matches(input, pos))
+            .map(|(ix, ev)| (ix as u8, ev.next_state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn box_reactor() -> Reactor {
+        let event = |trigger, next_state| ReactorEvent {
+            trigger,
+            next_state,
+        };
+        Reactor {
+            id: ReactorId(2000),
+            action: None,
+            states: vec![
+                ReactorState {
+                    events: vec![event(ReactorTrigger::Hit, 1)],
+                    anim_delay_ms: 0,
+                },
+                ReactorState {
+                    events: vec![event(
+                        ReactorTrigger::ItemDrop {
+                            item: ItemId(4001022),
+                            quantity: 2,
+                            area: Box2::new((-50, -50).into(), (50, 50).into()),
+                        },
+                        2,
+                    )],
+                    anim_delay_ms: 300,
+                },
+                ReactorState::default(),
+            ],
+        }
+    }
+
+    #[test]
+    fn reactor_states() {
+        let reactor = box_reactor();
+        let pos = Vec2::new(100, 100);
+        let hit = ReactorInput::Hit(SkillId(0));
+        let drop = |quantity, pos| ReactorInput::ItemDrop {
+            item: ItemId(4001022),
+            quantity,
+            pos,
+        };
+
+        assert_eq!(reactor.next_state(0, &hit, pos), Some((0, 1)));
+        assert_eq!(reactor.next_state(0, &ReactorInput::Touch, pos), None);
+        assert_eq!(reactor.next_state(1, &hit, pos), None);
+        assert_eq!(reactor.next_state(1, &drop(1, pos), pos), None);
+        assert_eq!(
+            reactor.next_state(1, &drop(2, Vec2::new(300, 100)), pos),
+            None
+        );
+        assert_eq!(
+            reactor.next_state(1, &drop(2, Vec2::new(120, 90)), pos),
+            Some((0, 2))
+        );
+        assert!(!reactor.is_final_state(1));
+        assert!(reactor.is_final_state(2));
+    }
+}
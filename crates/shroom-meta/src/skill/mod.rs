@@ -145,6 +145,16 @@ impl Skill {
     }
 
 
+    /// Cooldown after using the skill, skills without a cooldown return `None`
+    pub fn cooltime_dur(&self, lvl: SkillLevel) -> Option<Duration> {
+        self.cost
+            .cooltime
+            .as_ref()
+            .map(|e| e.eval(lvl as i32))
+            .filter(|&secs| secs > 0)
+            .map(|secs| Duration::from_secs(secs as u64))
+    }
+
     pub fn sub_time_dur(&self, lvl: SkillLevel) -> Duration {
         Duration::from_secs(self.stats.sub_time.as_ref().unwrap().eval(lvl as i32) as u64)
     }
@@ -10,19 +10,20 @@ use rand::thread_rng;
 use rayon::prelude::{ParallelBridge, ParallelExtend, ParallelIterator};
 
 use crate::{
+    cash::{Commodities, Commodity, CommoditySn},
     drops::{DropPool, NpcShop, NpcShops, QuestDropFlags},
     exp_table::ExpTable,
-    field::{FhTree, Field},
+    field::{FhTree, Field, FieldScripts},
     id::{
-        job_id::JobId, FieldId, ItemId, ItemOptionId, MobId, MobSkillId, NpcId, QuestId, ReactorId,
-        SkillId,
+        job_id::JobId, FieldId, ItemId, ItemOptionId, MobId, MobSkillId, Money, NpcId, QuestId,
+        ReactorId, SkillId,
     },
     mob::{Mob, MobSkill, MobSkills},
-    quest, skill,
+    quest, reactor, skill,
     srv::{GoToFields, ItemSets},
     tmpl::{
-        equip::{EquipItemTmpl, WeaponItemTmpl},
-        item::{ItemOption, BundleItemTmpl},
+        equip::{EquipItemFlags, EquipItemTmpl, SetId, SetItemTmpl, WeaponItemTmpl},
+        item::{ItemOption, BundleItemTmpl, ItemFlags, ItemNpc},
     }, FIELD_REGIONS,
 };
 
@@ -108,6 +109,39 @@ impl MetaItems {
             .or_else(|| self.etc.get(&id))
             .or_else(|| self.install.get(&id))
     }
+
+    /// Base price of an item, which is the price a shop pays for a single unit
+    pub fn get_price(&self, id: ItemId) -> Option<Money> {
+        self.get_equip(id)
+            .map(|eq| eq.price)
+            .or_else(|| self.get_bundle(id).map(|item| item.info.price))
+    }
+
+    pub fn get_slot_max(&self, id: ItemId) -> Option<u16> {
+        self.get_equip(id)
+            .map(|_| 1)
+            .or_else(|| self.get_bundle(id).map(|item| item.info.slot_max))
+    }
+
+    /// Whether the item can be traded with other characters,
+    /// quest and unique items stay with their owner
+    pub fn is_tradable(&self, id: ItemId) -> bool {
+        if let Some(equip) = self.get_equip(id) {
+            let blocked = EquipItemFlags::QUEST_ITEM | EquipItemFlags::UNIQUE;
+            return equip.flags.contains(EquipItemFlags::CAN_TRADE)
+                && !equip.flags.intersects(blocked);
+        }
+
+        let blocked = ItemFlags::QUEST_ITEM | ItemFlags::PARTY_QUEST_ITEM | ItemFlags::UNIQUE;
+        self.get_bundle(id).is_some_and(|item| {
+            item.info.flag.contains(ItemFlags::CAN_TRADE) && !item.info.flag.intersects(blocked)
+        })
+    }
+
+    /// Price per unit for rechargeable items like throwing stars and bullets
+    pub fn get_unit_price(&self, id: ItemId) -> Option<f32> {
+        self.get_bundle(id).and_then(|item| item.info.unit_price)
+    }
 }
 
 #[derive(Debug)]
@@ -119,7 +153,12 @@ pub struct MetaData {
     pub mob_skills: MobSkills,
     pub mobs: BTreeMap<MobId, Mob>,
     pub npc_shops: NpcShops,
+    pub commodities: Commodities,
+    pub set_items: BTreeMap<SetId, SetItemTmpl>,
     pub drop_pool: DropPool,
+    pub reactors: BTreeMap<ReactorId, reactor::Reactor>,
+    pub field_scripts: BTreeMap<FieldId, FieldScripts>,
+    pub item_npcs: BTreeMap<ItemId, NpcId>,
     pub goto_fields: GoToFields,
     pub item_sets: ItemSets,
     pub exp_table: ExpTable,
@@ -132,6 +171,7 @@ pub type MobMeta = &'static Mob;
 pub type MobSkillMeta = Meta<MobSkill>;
 pub type DropsMeta = &'static DropPool;
 pub type SkillMeta = &'static skill::Skill;
+pub type ReactorMeta = &'static reactor::Reactor;
 
 #[derive(Debug, Clone, Copy)]
 pub enum MetaOption {
@@ -189,6 +229,65 @@ impl MetaData {
         let goto_fields =
             Self::load_from_json(dir.join("fields_goto.json")).context("Fields goto")?;
 
+        // The catalog is optional, without one the cash shop has nothing for sale
+        let commodities_file = dir.join("ext/commodities.json");
+        let commodities: Commodities = if commodities_file.exists() {
+            Self::load_from_json::<Vec<Commodity>>(commodities_file)
+                .context("Commodities")?
+                .into_iter()
+                .collect()
+        } else {
+            Commodities::default()
+        };
+
+        // Set bonuses are optional as well, equipment sets just give no bonus then
+        let set_items_file = dir.join("ext/set_items.json");
+        let set_items = if set_items_file.exists() {
+            Self::load_from_json::<Vec<SetItemTmpl>>(set_items_file)
+                .context("Set items")?
+                .into_iter()
+                .map(|set| (set.id, set))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        // Without reactor states every reactor is destroyed by the first hit
+        let reactors_file = dir.join("ext/reactors.json");
+        let reactors = if reactors_file.exists() {
+            Self::load_from_json::<Vec<reactor::Reactor>>(reactors_file)
+                .context("Reactors")?
+                .into_iter()
+                .map(|reactor| (reactor.id, reactor))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        // Fields without an entry run no enter scripts
+        let field_scripts_file = dir.join("ext/field_scripts.json");
+        let field_scripts = if field_scripts_file.exists() {
+            Self::load_from_json::<Vec<FieldScripts>>(field_scripts_file)
+                .context("Field scripts")?
+                .into_iter()
+                .map(|scripts| (scripts.id, scripts))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        // Without item npcs the npc summoning items do nothing
+        let item_npcs_file = dir.join("ext/item_npcs.json");
+        let item_npcs = if item_npcs_file.exists() {
+            Self::load_from_json::<Vec<ItemNpc>>(item_npcs_file)
+                .context("Item npcs")?
+                .into_iter()
+                .map(|item| (item.id, item.npc))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+
         Ok(Self {
             fields,
             mobs,
@@ -200,7 +299,12 @@ impl MetaData {
                 .collect(),
             mob_skills,
             npc_shops: Self::load_from_json(dir.join("ext/npc_shop.json")).context("Shops")?,
+            commodities,
+            set_items,
             drop_pool,
+            reactors,
+            field_scripts,
+            item_npcs,
             goto_fields,
             item_sets,
             exp_table: ExpTable::build(),
@@ -265,16 +369,39 @@ impl MetaService {
         self.meta_data.mobs.get(&mob_id)
     }
 
-    pub fn get_reactor_drops(&self, id: ReactorId, flags: &QuestDropFlags) -> Vec<(ItemId, usize)> {
+    pub fn get_reactor(&self, id: ReactorId) -> Option<&reactor::Reactor> {
+        self.meta_data.reactors.get(&id)
+    }
+
+    pub fn get_field_scripts(&self, id: FieldId) -> Option<&FieldScripts> {
+        self.meta_data.field_scripts.get(&id)
+    }
+
+    /// Npc, which is summoned by the item
+    pub fn get_item_npc(&self, id: ItemId) -> Option<NpcId> {
+        self.meta_data.item_npcs.get(&id).copied()
+    }
+
+    pub fn get_reactor_drops(
+        &self,
+        id: ReactorId,
+        flags: &QuestDropFlags,
+        rate: f32,
+    ) -> Vec<(ItemId, usize)> {
         self.meta_data
             .drop_pool
-            .get_reactor_drops(id, flags, &mut thread_rng())
+            .get_reactor_drops(id, flags, rate, &mut thread_rng())
     }
 
-    pub fn get_drops_for_mob(&self, id: MobId, flags: &QuestDropFlags) -> Vec<(ItemId, usize)> {
+    pub fn get_drops_for_mob(
+        &self,
+        id: MobId,
+        flags: &QuestDropFlags,
+        rate: f32,
+    ) -> Vec<(ItemId, usize)> {
         self.meta_data
             .drop_pool
-            .get_drops_for_mob(id, flags, &mut thread_rng())
+            .get_drops_for_mob(id, flags, rate, &mut thread_rng())
     }
 
     pub fn get_money_drops_for_mob(&self, _id: MobId) -> u32 {
@@ -287,9 +414,10 @@ impl MetaService {
         &self,
         id: MobId,
         flags: &QuestDropFlags,
+        rate: f32,
     ) -> (Vec<(ItemId, usize)>, u32) {
-        let drops = self.get_drops_for_mob(id, flags);
-        let money = self.get_money_drops_for_mob(id);
+        let drops = self.get_drops_for_mob(id, flags, rate);
+        let money = (self.get_money_drops_for_mob(id) as f32 * rate) as u32;
         (drops, money)
     }
 
@@ -308,6 +436,14 @@ impl MetaService {
         self.meta_data.npc_shops.get(&npc_id)
     }
 
+    pub fn get_commodity(&self, sn: CommoditySn) -> Option<&Commodity> {
+        self.meta_data.commodities.get(sn)
+    }
+
+    pub fn get_set_item(&self, id: SetId) -> Option<&SetItemTmpl> {
+        self.meta_data.set_items.get(&id)
+    }
+
     pub fn get_quest_mob_drop_flags(&self, quest_id: QuestId) -> Option<&HashSet<MobId>> {
         self.meta_data.drop_pool.mob_quest_flags.get(&quest_id)
     }
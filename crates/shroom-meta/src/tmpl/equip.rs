@@ -4,13 +4,13 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     id::{job_id::JobId, ItemId, Money, SkillId},
-    item::{EquipStat, EquipStats, ItemStat, ItemStatRange, ItemStatRatio, JobFlag},
+    item::{EquipBaseStats, EquipStat, EquipStats, ItemStat, ItemStatRange, ItemStatRatio, JobFlag},
     shared::ElementAttribute,
     skill::SkillLevel,
     CharLevel, Pop, ProcChance,
 };
 
-#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SetId(pub u8);
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -201,3 +201,29 @@ pub struct WeaponItemTmpl {
     pub attack_action: AttackAction,
     pub equip_increase_magic_elem: EquipIncreaseMagicElems,
 }
+
+/// Bonus of an equipment set, which applies once enough parts are equipped
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SetItemEffect {
+    pub count: u8,
+    pub stats: EquipBaseStats,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SetItemTmpl {
+    pub id: SetId,
+    pub name: String,
+    pub effects: Vec<SetItemEffect>,
+}
+
+impl SetItemTmpl {
+    /// Sums up the effects, which are active with the given number of equipped parts
+    pub fn active_stats(&self, count: usize) -> EquipBaseStats {
+        self.effects
+            .iter()
+            .filter(|effect| usize::from(effect.count) <= count)
+            .fold(EquipBaseStats::default(), |acc, effect| {
+                acc + effect.stats.clone()
+            })
+    }
+}
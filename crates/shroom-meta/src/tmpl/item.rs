@@ -6,7 +6,7 @@ use std::ops::RangeInclusive;
 use std::time::Duration;
 
 use crate::id::item_id::EquipType;
-use crate::id::{FieldId, ItemId, ItemOptionId, MobId, Money, QuestId, SkillId};
+use crate::id::{FieldId, ItemId, ItemOptionId, MobId, Money, NpcId, QuestId, SkillId};
 use crate::item::{EquipBaseStats, EquipStat, ItemStat, ItemStatRatio, ScrollChance};
 use crate::skill::SkillLevel;
 use crate::{CharLevel, ProcChance};
@@ -242,6 +242,24 @@ pub struct BundleItemTmpl {
     pub value: BundleItemValue,
 }
 
+impl BundleItemTmpl {
+    /// Script, which is launched when the item is used
+    pub fn script(&self) -> Option<&str> {
+        match self.value {
+            BundleItemValue::Consumable(ref item) => item.script.as_deref(),
+            BundleItemValue::StateChange(ref item) => item.script.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Npc, which is summoned when the item is used
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ItemNpc {
+    pub id: ItemId,
+    pub npc: NpcId,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ItemOptionLevel {
     pub attack_type: Option<u8>,
@@ -1,17 +1,20 @@
-use schemas::item_mapper::ItemOptWithId;
+use schemas::field_mapper::map_field_scripts;
+use schemas::item_mapper::{map_item_npc, ItemOptWithId};
 use schemas::quest_mapper::SchQuest;
+use schemas::reactor_mapper::{ReactorWithId, SchReactor};
 use schemas::shroom_schemas::Skill;
 use schemas::skill_mapper::SkillWithId;
-use shroom_meta::field::Field;
+use shroom_meta::field::{Field, FieldScripts};
 use shroom_meta::mob::{Mob, MobSkills};
 
 use crate::schemas::item_mapper::{EquipWithId, ItemWithId};
 use rayon::prelude::*;
 use serde::de::DeserializeOwned;
-use shroom_meta::id::{FieldId, ItemId, ItemOptionId, MobId, QuestId, SkillId};
+use shroom_meta::id::{FieldId, ItemId, ItemOptionId, MobId, QuestId, ReactorId, SkillId};
 use shroom_meta::quest::Quest;
+use shroom_meta::reactor::Reactor;
 use shroom_meta::tmpl::equip::{EquipItemTmpl, WeaponItemTmpl};
-use shroom_meta::tmpl::item::{BundleItemTmpl, ItemOption, EQ_TY, ITEM_TY};
+use shroom_meta::tmpl::item::{BundleItemTmpl, ItemNpc, ItemOption, EQ_TY, ITEM_TY};
 use shroom_meta::{skill, FIELD_REGIONS};
 use std::collections::{BTreeMap, HashMap};
 use std::io::BufWriter;
@@ -81,11 +84,12 @@ fn parse_mob(p: impl AsRef<Path>, id: MobId) -> anyhow::Result<(MobId, Mob)> {
     Ok((id, mob))
 }
 
-fn parse_field(p: impl AsRef<Path>, id: u32) -> anyhow::Result<(u32, Field)> {
+fn parse_field(p: impl AsRef<Path>, id: u32) -> anyhow::Result<(u32, Field, Option<FieldScripts>)> {
     let img: schemas::shroom_schemas::Field = load_json(p).unwrap();
     let mut field = Field::try_from(&img)?;
     field.id = FieldId(id);
-    Ok((id, field))
+    let scripts = map_field_scripts(field.id, &img);
+    Ok((id, field, scripts))
 }
 
 fn gen_skills(skill_dir: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> anyhow::Result<()> {
@@ -121,7 +125,12 @@ fn gen_skills(skill_dir: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> anyhow:
     Ok(())
 }
 
-fn gen_fields(dir: impl AsRef<Path>, region: u8, out_dir: impl AsRef<Path>) -> anyhow::Result<()> {
+/// Generates the fields of the region and returns the scripts of the fields
+fn gen_fields(
+    dir: impl AsRef<Path>,
+    region: u8,
+    out_dir: impl AsRef<Path>,
+) -> anyhow::Result<Vec<FieldScripts>> {
     dbg!(dir.as_ref());
     let data = std::fs::read_dir(dir)?
         .filter_map(|f| {
@@ -135,15 +144,22 @@ fn gen_fields(dir: impl AsRef<Path>, region: u8, out_dir: impl AsRef<Path>) -> a
         })
         .par_bridge()
         .map(|(id, f)| parse_field(f, id as u32))
-        .collect::<anyhow::Result<BTreeMap<u32, Field>>>()?;
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut fields = BTreeMap::new();
+    let mut scripts = Vec::new();
+    for (id, field, field_scripts) in data {
+        fields.insert(id, field);
+        scripts.extend(field_scripts);
+    }
 
     save(
         &format!("fields{region}"),
-        &data,
+        &fields,
         out_dir.as_ref().join("fields"),
     )?;
 
-    Ok(())
+    Ok(scripts)
 }
 
 fn gen_mobs(mob_dir: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> anyhow::Result<()> {
@@ -160,6 +176,30 @@ fn gen_mobs(mob_dir: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> anyhow::Res
     Ok(())
 }
 
+fn gen_reactors(reactor_dir: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> anyhow::Result<()> {
+    let imgs = std::fs::read_dir(reactor_dir)?
+        .map(|dir| {
+            let f = dir.unwrap().path();
+            let id: u32 = f.file_stem().unwrap().to_str().unwrap().parse().unwrap();
+            Ok((ReactorId(id), load_json::<SchReactor>(f)?))
+        })
+        .collect::<anyhow::Result<BTreeMap<ReactorId, SchReactor>>>()?;
+
+    let reactors = imgs
+        .iter()
+        .map(|(id, img)| {
+            // Linked reactors share the states of another reactor
+            let states = img.link().and_then(|link| imgs.get(&link)).unwrap_or(img);
+            let mut reactor = Reactor::try_from(ReactorWithId(*id, states))?;
+            reactor.action = img.action.clone().or(reactor.action);
+            Ok(reactor)
+        })
+        .collect::<anyhow::Result<Vec<Reactor>>>()?;
+
+    write_json("reactors", &reactors, out_dir.as_ref().join("ext"))?;
+    Ok(())
+}
+
 /*
 fn parse_quest(id: QuestId, q: SchQuest) -> anyhow::Result<Option<(QuestId, Quest)>> {
     if img.quest_info.is_none() {
@@ -271,6 +311,25 @@ fn gen_item(dir: impl AsRef<Path>, item_ty: &str, out_dir: impl AsRef<Path>) ->
     Ok(())
 }
 
+fn gen_item_npcs(dir: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> anyhow::Result<()> {
+    let npcs = std::fs::read_dir(dir.as_ref().join("Consume"))?
+        .flat_map(|dir| {
+            let f = dir.unwrap().path();
+            let items: schemas::shroom_schemas::Item = load_json(f).unwrap();
+            items.0.into_iter()
+        })
+        .filter_map(|(id, item)| {
+            Some(ItemNpc {
+                id: ItemId(id.parse::<u32>().unwrap()),
+                npc: map_item_npc(&item)?,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    write_json("item_npcs", &npcs, out_dir.as_ref().join("ext"))?;
+    Ok(())
+}
+
 fn gen_item_opt(dir: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> anyhow::Result<()> {
     let ops: schemas::shroom_schemas::ItemOptions =
         load_json(dir.as_ref().join("ItemOption.json"))?;
@@ -295,9 +354,14 @@ fn main() -> anyhow::Result<()> {
     let out_dir = PathBuf::from_str("/home/jonas/projects/shroom/ShroomMS/shroom-metadata")?;
     
     //    gen_skills(p.join("skills"), &out_dir)?;
+        let mut field_scripts = Vec::new();
         for i in FIELD_REGIONS {
-            gen_fields(p.join(format!("maps/Map/Map{i}")), i, &out_dir)?;
+            field_scripts.extend(gen_fields(p.join(format!("maps/Map/Map{i}")), i, &out_dir)?);
         }
+        // Scripts are kept out of the field data, so the fields don't have to be regenerated
+        write_json("field_scripts", &field_scripts, out_dir.join("ext"))?;
+        gen_reactors(p.join("reactor"), &out_dir)?;
+        gen_item_npcs(p.join("item"), &out_dir)?;
 
       /*   gen_mobs(p.join("mobs"), &out_dir)?;
 
@@ -24,7 +24,9 @@ use std::{collections::BTreeMap, time::Duration};
     shared::{FootholdId, Rect2D, Vec2}, game::life::{npc::NpcId, mob::MobId, reactor::ReactorId},
 };*/
 use shroom_meta::{
-    field::{Field, FieldLife, FieldMob, FieldNpc, FieldPortal, FieldReactor, Foothold},
+    field::{
+        Field, FieldLife, FieldMob, FieldNpc, FieldPortal, FieldReactor, FieldScripts, Foothold,
+    },
     id::{FieldId, FootholdId, MobId, NpcId, ReactorId},
     twod::{Rect2D, Vec2},
 };
@@ -125,6 +127,20 @@ impl From<&sch::Fh> for Foothold {
     }
 }
 
+/// Enter scripts of the field, none if the field has no scripts
+pub fn map_field_scripts(id: FieldId, value: &sch::Field) -> Option<FieldScripts> {
+    let info = value.info.as_ref()?;
+    if info.on_first_user_enter.is_none() && info.on_user_enter.is_none() {
+        return None;
+    }
+
+    Some(FieldScripts {
+        id,
+        on_first_user_enter: info.on_first_user_enter.clone(),
+        on_user_enter: info.on_user_enter.clone(),
+    })
+}
+
 impl TryFrom<&sch::Field> for Field {
     type Error = anyhow::Error;
 
@@ -4,7 +4,7 @@ use crate::schemas::quest_mapper::map_to_list;
 use crate::schemas::shroom_schemas::{ItemValueInfo, StrOrInt};
 use shroom_meta::id::item_id::ItemType;
 use shroom_meta::id::job_id::JobId;
-use shroom_meta::id::{FieldId, ItemId, ItemOptionId, MobId, Money, QuestId, SkillId};
+use shroom_meta::id::{FieldId, ItemId, ItemOptionId, MobId, Money, NpcId, QuestId, SkillId};
 use shroom_meta::item::{
     EquipBaseStats, EquipStat, EquipStats, ItemStat, ItemStatRatio, JobFlag, ScrollChance,
 };
@@ -429,6 +429,16 @@ impl<'a> TryFrom<&'a ItemValue> for ConsumableItem {
     }
 }
 
+/// Npc, which is summoned by the consumable item
+pub fn map_item_npc(value: &ItemValue) -> Option<NpcId> {
+    value
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.npc)
+        .or(value.info.as_ref().and_then(|info| info.npc))
+        .map(|v| NpcId(v as u32))
+}
+
 impl<'a> TryFrom<&'a ItemValue> for StateChangeItem {
     type Error = anyhow::Error;
 
@@ -15,6 +15,7 @@ pub mod skill_mapper;
 
 pub mod mob_skill_mapper;
 pub mod quest_mapper;
+pub mod reactor_mapper;
 
 use shroom_schemas as sch;
 
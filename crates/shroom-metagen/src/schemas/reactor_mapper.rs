@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use serde::Deserialize;
+use shroom_meta::{
+    id::{ItemId, ReactorId, SkillId},
+    reactor::{Reactor, ReactorEvent, ReactorState, ReactorTrigger},
+    twod::Box2,
+};
+
+use super::{sch, IntoBool, IntoNum};
+
+/// Event types of the reactor data, every other type is triggered by a hit
+const EVENT_SKILL_HIT: i64 = 5;
+const EVENT_TOUCH: i64 = 6;
+const EVENT_UNTOUCH: i64 = 7;
+const EVENT_ITEM_DROP: i64 = 100;
+
+/// Delay of an animation frame without a delay
+const DEFAULT_FRAME_DELAY: i64 = 100;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SchReactorInfo {
+    #[serde(default)]
+    pub link: Option<sch::StrOrInt>,
+    #[serde(rename = "activateByTouch", default)]
+    pub activate_by_touch: Option<sch::Bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SchReactorEvent {
+    #[serde(rename = "type")]
+    pub ty: sch::StrOrInt,
+    pub state: sch::StrOrInt,
+    /// Item and quantity of item drop events
+    #[serde(rename = "0", default)]
+    pub item: Option<sch::StrOrInt>,
+    #[serde(rename = "1", default)]
+    pub quantity: Option<sch::StrOrInt>,
+    #[serde(default)]
+    pub lt: Option<sch::Vec2>,
+    #[serde(default)]
+    pub rb: Option<sch::Vec2>,
+    #[serde(rename = "activeSkillID", default)]
+    pub active_skill_id: BTreeMap<String, sch::StrOrInt>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SchReactor {
+    #[serde(default)]
+    pub info: SchReactorInfo,
+    #[serde(default)]
+    pub action: Option<String>,
+    /// States are stored by their index, next to the other nodes
+    #[serde(flatten)]
+    pub nodes: BTreeMap<String, serde_json::Value>,
+}
+
+impl SchReactor {
+    /// Id of the reactor, which provides the states of this reactor
+    pub fn link(&self) -> Option<ReactorId> {
+        self.info
+            .link
+            .as_ref()
+            .map(|link| ReactorId(link.into_num() as u32))
+    }
+}
+
+fn num(v: &serde_json::Value) -> Option<i64> {
+    match v {
+        serde_json::Value::Number(n) => n.as_i64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Numbered child nodes sorted by their index
+fn indexed(nodes: &serde_json::Map<String, serde_json::Value>) -> BTreeMap<u8, &serde_json::Value> {
+    nodes
+        .iter()
+        .filter_map(|(key, node)| key.parse::<u8>().ok().map(|ix| (ix, node)))
+        .collect()
+}
+
+fn map_trigger(ev: &SchReactorEvent, touch: bool) -> ReactorTrigger {
+    let point = |v: &Option<sch::Vec2>| v.as_ref().map_or((0, 0), |v| (v.x as i16, v.y as i16));
+    match ev.ty.into_num() {
+        EVENT_ITEM_DROP => ReactorTrigger::ItemDrop {
+            item: ItemId(ev.item.into_num() as u32),
+            quantity: ev.quantity.into_num().max(1) as usize,
+            area: Box2::new(point(&ev.lt).into(), point(&ev.rb).into()),
+        },
+        EVENT_SKILL_HIT => ReactorTrigger::SkillHit(
+            ev.active_skill_id
+                .values()
+                .map(|id| SkillId(id.into_num() as u32))
+                .collect(),
+        ),
+        EVENT_TOUCH | EVENT_UNTOUCH => ReactorTrigger::Touch,
+        _ if touch => ReactorTrigger::Touch,
+        _ => ReactorTrigger::Hit,
+    }
+}
+
+fn map_state(node: &serde_json::Value, touch: bool) -> anyhow::Result<ReactorState> {
+    let Some(node) = node.as_object() else {
+        return Ok(ReactorState::default());
+    };
+
+    let mut events = Vec::new();
+    if let Some(event) = node.get("event").and_then(|ev| ev.as_object()) {
+        for (_, ev) in indexed(event) {
+            let ev = SchReactorEvent::deserialize(ev).context("Reactor event")?;
+            events.push(ReactorEvent {
+                trigger: map_trigger(&ev, touch),
+                next_state: ev.state.into_num() as u8,
+            });
+        }
+    }
+
+    // Frames of the animation, which is played when entering the state
+    let anim_delay_ms = indexed(node)
+        .values()
+        .map(|frame| {
+            frame
+                .get("delay")
+                .and_then(num)
+                .unwrap_or(DEFAULT_FRAME_DELAY)
+        })
+        .sum::<i64>();
+
+    Ok(ReactorState {
+        events,
+        anim_delay_ms: anim_delay_ms.clamp(0, u16::MAX as i64) as u16,
+    })
+}
+
+pub struct ReactorWithId<'a>(pub ReactorId, pub &'a SchReactor);
+
+impl TryFrom<ReactorWithId<'_>> for Reactor {
+    type Error = anyhow::Error;
+
+    fn try_from(ReactorWithId(id, value): ReactorWithId<'_>) -> Result<Self, Self::Error> {
+        let touch = value.info.activate_by_touch.into_bool();
+        let nodes = value
+            .nodes
+            .iter()
+            .filter_map(|(key, node)| key.parse::<u8>().ok().map(|ix| (ix, node)))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut states = Vec::new();
+        for (ix, node) in nodes {
+            states.resize_with(ix as usize, ReactorState::default);
+            states.push(map_state(node, touch).with_context(|| format!("State {ix}"))?);
+        }
+
+        Ok(Reactor {
+            id,
+            action: value.action.clone(),
+            states,
+        })
+    }
+}
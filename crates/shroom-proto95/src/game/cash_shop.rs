@@ -0,0 +1,234 @@
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use shroom_meta::id::{item_id::InventoryType, CharacterId, ItemId};
+use shroom_pkt::{
+    mark_shroom_enum, time::Ticks, with_opcode, CondOption, ShroomExpirationTime, ShroomList16,
+    ShroomList32, ShroomList8, ShroomPacket, ShroomPacketEnum,
+};
+
+use crate::{
+    recv_opcodes::RecvOpcodes,
+    send_opcodes::SendOpcodes,
+    shared::{char::CharDataHeader, item::Item, NameStr},
+};
+
+use super::user::char::{CharDataAll, CharDataFlags};
+
+/// Number of best seller entries on the cash shop front page
+pub const CASH_SHOP_BEST_ITEMS: usize = 90;
+
+#[derive(ShroomPacket, Debug)]
+pub struct MigrateToCashShopReq {
+    pub ticks: Ticks,
+}
+with_opcode!(
+    MigrateToCashShopReq,
+    RecvOpcodes::UserMigrateToCashShopRequest
+);
+
+/// Sent when the client leaves the cash shop, the same opcode as a field transfer
+#[derive(ShroomPacket, Debug)]
+pub struct CashShopLeaveReq;
+with_opcode!(CashShopLeaveReq, RecvOpcodes::UserTransferFieldRequest);
+
+#[derive(ShroomPacket, Debug, Default, Clone, Copy)]
+pub struct CashShopBestItem {
+    pub category: u32,
+    pub gender: u32,
+    pub commodity_sn: u32,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct SetCashShopResp {
+    pub char_data_flags: CharDataFlags,
+    pub char_data_hdr: CharDataHeader,
+    pub char_data: CharDataAll,
+    pub authorized: bool,
+    pub account_name: String,
+    pub not_sale: ShroomList32<u32>,
+    /// Commodities overriding the client catalog, always empty
+    pub modified_commodities: u16,
+    pub discount_rates: u8,
+    pub best: [CashShopBestItem; CASH_SHOP_BEST_ITEMS],
+    pub stock: u16,
+    pub limit_goods: u16,
+    pub zero_goods: u16,
+    pub event_on: bool,
+    pub highest_char_level: u32,
+}
+with_opcode!(SetCashShopResp, SendOpcodes::SetCashShop);
+
+#[derive(ShroomPacket, Debug)]
+pub struct CashShopQueryCashReq;
+with_opcode!(CashShopQueryCashReq, RecvOpcodes::CashShopQueryCashRequest);
+
+#[derive(ShroomPacket, Debug)]
+pub struct CashShopQueryCashResp {
+    pub nx_credit: u32,
+    pub shroom_points: u32,
+    pub nx_prepaid: u32,
+}
+with_opcode!(CashShopQueryCashResp, SendOpcodes::CashShopQueryCashResult);
+
+#[derive(ShroomPacket, Debug)]
+pub struct CashShopCheckCouponReq {
+    pub unknown: u16,
+    pub code: String,
+}
+with_opcode!(
+    CashShopCheckCouponReq,
+    RecvOpcodes::CashShopCheckCouponRequest
+);
+
+/// Item in the cash locker
+#[derive(ShroomPacket, Debug)]
+pub struct CashItemInfo {
+    pub sn: u64,
+    pub acc_id: u32,
+    pub char_id: CharacterId,
+    pub item_id: ItemId,
+    pub commodity_sn: u32,
+    pub quantity: u16,
+    pub gift_from: NameStr,
+    pub expiration: ShroomExpirationTime,
+    pub payback_rate: u32,
+    pub discount_rate: u32,
+}
+
+/// How the price of a commodity is paid
+#[derive(Debug, Eq, PartialEq, Copy, Clone, TryFromPrimitive, IntoPrimitive)]
+#[repr(u32)]
+pub enum CashShopPayment {
+    NxCredit = 1,
+    ShroomPoints = 2,
+    NxPrepaid = 4,
+}
+mark_shroom_enum!(CashShopPayment);
+
+#[derive(ShroomPacket, Debug)]
+pub struct CashItemBuyReq {
+    pub unknown: u8,
+    pub payment: CashShopPayment,
+    pub commodity_sn: u32,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct CashItemGiftReq {
+    pub spw: String,
+    pub commodity_sn: u32,
+    pub receiver: String,
+    pub message: String,
+}
+
+fn is_by_item(by_item: &bool) -> bool {
+    *by_item
+}
+
+/// Expands the trunk, either directly or by the commodity of a storage expansion item
+#[derive(ShroomPacket, Debug)]
+pub struct CashItemIncTrunkCountReq {
+    pub unknown: u8,
+    pub payment: CashShopPayment,
+    pub by_item: bool,
+    #[pkt(check(field = "by_item", cond = "is_by_item"))]
+    pub commodity_sn: CondOption<u32>,
+}
+
+/// Moves an item from the locker into the cash inventory
+#[derive(ShroomPacket, Debug)]
+pub struct CashItemMoveLtoSReq {
+    pub sn: u64,
+    pub inv_type: InventoryType,
+    pub slot: u16,
+}
+
+/// Moves an item from the cash inventory into the locker
+#[derive(ShroomPacket, Debug)]
+pub struct CashItemMoveStoLReq {
+    pub sn: u64,
+    pub inv_type: InventoryType,
+}
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum CashItemReq {
+    LoadLocker(()) = 1,
+    Buy(CashItemBuyReq) = 3,
+    Gift(CashItemGiftReq) = 4,
+    IncTrunkCount(CashItemIncTrunkCountReq) = 7,
+    MoveLtoS(CashItemMoveLtoSReq) = 14,
+    MoveStoL(CashItemMoveStoLReq) = 15,
+}
+with_opcode!(CashItemReq, RecvOpcodes::CashShopCashItemRequest);
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum CashItemFailReason {
+    Unknown = 0,
+    NoCash = 0xA5,
+    GiftToSelf = 0xA6,
+    InvalidReceiver = 0xA9,
+    GenderMismatch = 0xAB,
+    InvalidCoupon = 0xB0,
+    CouponUsed = 0xB2,
+    InventoryFull = 0xB4,
+    NotForSale = 0xBB,
+}
+mark_shroom_enum!(CashItemFailReason);
+
+#[derive(ShroomPacket, Debug)]
+pub struct CashLockerData {
+    pub items: ShroomList16<CashItemInfo>,
+    pub trunk_slots: u16,
+    pub char_slots: u16,
+    pub buy_char_count: u16,
+    pub char_count: u16,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct CashGiftDone {
+    pub receiver: String,
+    pub item_id: ItemId,
+    pub count: u16,
+    pub price: u32,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct CashCouponNormalItem {
+    pub count: u32,
+    pub item_id: ItemId,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct CashCouponDone {
+    pub items: ShroomList8<CashItemInfo>,
+    pub cash: u32,
+    pub normal_items: ShroomList32<CashCouponNormalItem>,
+    pub money: u32,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct CashMoveLtoSDone {
+    pub slot: u16,
+    pub item: Item,
+}
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum CashItemResultResp {
+    LoadLockerDone(CashLockerData) = 0x5A,
+    LoadLockerFailed(CashItemFailReason) = 0x5B,
+    BuyDone(CashItemInfo) = 0x66,
+    BuyFailed(CashItemFailReason) = 0x67,
+    UseCouponDone(CashCouponDone) = 0x68,
+    UseCouponFailed(CashItemFailReason) = 0x6B,
+    GiftDone(CashGiftDone) = 0x6D,
+    GiftFailed(CashItemFailReason) = 0x6E,
+    /// New slot count of the trunk
+    IncTrunkCountDone(u16) = 0x73,
+    IncTrunkCountFailed(CashItemFailReason) = 0x74,
+    MoveLtoSDone(CashMoveLtoSDone) = 0x79,
+    MoveLtoSFailed(CashItemFailReason) = 0x7A,
+    MoveStoLDone(CashItemInfo) = 0x7B,
+    MoveStoLFailed(CashItemFailReason) = 0x7C,
+}
+with_opcode!(CashItemResultResp, SendOpcodes::CashShopCashItemResult);
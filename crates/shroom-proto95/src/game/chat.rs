@@ -1,4 +1,4 @@
-use shroom_meta::id::{CharacterId, ItemId};
+use shroom_meta::id::{CharacterId, FieldId, ItemId};
 use shroom_pkt::{
     shroom_enum_code, time::Ticks, with_opcode, ShroomList8, ShroomPacket, ShroomPacketEnum,
 };
@@ -19,18 +19,28 @@ pub struct SpouseChatPacket {
 shroom_enum_code!(
     MultiChatPacketType,
     u8,
-    Buddy = 1,
-    Party = 2,
-    Guild = 3,
-    Alliance = 4
+    Buddy = 0,
+    Party = 1,
+    Guild = 2,
+    Alliance = 3
 );
 
 #[derive(Debug, ShroomPacket)]
 pub struct MultiChatPacket {
-    ty: MultiChatPacketType,
-    recipients: ShroomList8<u32>,
-    message: String,
+    pub ticks: Ticks,
+    pub ty: MultiChatPacketType,
+    pub recipients: ShroomList8<CharacterId>,
+    pub message: String,
+}
+with_opcode!(MultiChatPacket, RecvOpcodes::GroupMessage);
+
+#[derive(Debug, ShroomPacket)]
+pub struct GroupMessageResp {
+    pub ty: MultiChatPacketType,
+    pub name: String,
+    pub message: String,
 }
+with_opcode!(GroupMessageResp, SendOpcodes::GroupMessage);
 
 #[derive(Debug, ShroomPacket)]
 pub struct WispherData {
@@ -109,9 +119,59 @@ pub enum WhiperMsgReq {
     Unknown(WhisperData) = 0x86,
     Whisper(WhisperData) = 6,
     WhisperFind(WhisperFindData) = 5,
+    /// Find from the buddy list
+    WhisperFindFriend(WhisperFindData) = 0x44,
 }
 with_opcode!(WhiperMsgReq, RecvOpcodes::Whisper);
 
+#[derive(Debug, ShroomPacket)]
+pub struct WhisperReceive {
+    pub sender: String,
+    pub channel: u8,
+    pub is_admin: bool,
+    pub msg: String,
+}
+
+#[derive(Debug, ShroomPacket)]
+pub struct WhisperResult {
+    pub target: String,
+    pub success: bool,
+}
+
+#[derive(Debug, ShroomPacket)]
+pub struct WhisperFieldLocation {
+    pub field_id: FieldId,
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, ShroomPacketEnum)]
+#[repr(u8)]
+pub enum WhisperLocation {
+    NotFound(i32) = 0,
+    /// Target is in the same channel
+    Field(WhisperFieldLocation) = 1,
+    CashShop(i32) = 2,
+    Channel(u32) = 3,
+}
+
+#[derive(Debug, ShroomPacket)]
+pub struct WhisperLocationResult {
+    pub target: String,
+    pub location: WhisperLocation,
+}
+
+#[derive(Debug, ShroomPacketEnum)]
+#[repr(u8)]
+pub enum WhisperResp {
+    LocationResult(WhisperLocationResult) = 0x09,
+    WhisperResult(WhisperResult) = 0x0A,
+    WhisperReceive(WhisperReceive) = 0x12,
+    WhisperBlocked(String) = 0x22,
+    LocationFriendResult(WhisperLocationResult) = 0x48,
+}
+with_opcode!(WhisperResp, SendOpcodes::Whisper);
+
 #[derive(ShroomPacket, Debug)]
 pub struct UserChatMsgResp {
     pub char: CharacterId,
@@ -182,4 +182,27 @@ pub enum FieldEffectResp {
     ChangeBgm(String) = 6,
     RewardBullet(FieldEffectData) = 7,
 }
-with_opcode!(FieldEffectResp, SendOpcodes::FieldEffect);
\ No newline at end of file
+with_opcode!(FieldEffectResp, SendOpcodes::FieldEffect);
+
+#[derive(ShroomPacket, Debug)]
+pub struct ClockTime {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+#[derive(Debug, ShroomPacketEnum)]
+#[repr(u8)]
+pub enum ClockResp {
+    /// Event timer with the remaining seconds
+    EventTimer(u32) = 0,
+    /// Shows a clock with the time of day
+    Time(ClockTime) = 1,
+    /// Countdown with the remaining seconds
+    Timer(u32) = 2,
+}
+with_opcode!(ClockResp, SendOpcodes::Clock);
+
+#[derive(ShroomPacket, Debug)]
+pub struct DestroyClockResp;
+with_opcode!(DestroyClockResp, SendOpcodes::DestroyClock);
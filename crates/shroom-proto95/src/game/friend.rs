@@ -3,7 +3,9 @@ use shroom_pkt::{
     string::FixedPacketString, with_opcode, ShroomOption8, ShroomPacket, ShroomPacketEnum,
 };
 
-use crate::{send_opcodes::SendOpcodes, shared::NameStr};
+use crate::{recv_opcodes::RecvOpcodes, send_opcodes::SendOpcodes, shared::NameStr};
+
+pub const FRIEND_CHANNEL_OFFLINE: u32 = u32::MAX;
 
 //TODO in_shop is an u8 idk
 
@@ -62,6 +64,8 @@ pub struct FriendReq {
     pub friend_name: String,
     pub level: u32,
     pub job_code: u32, //TODO: job id?
+    pub record: FriendRecord,
+    pub in_shop: bool,
 }
 
 #[derive(ShroomPacketEnum, Debug)]
@@ -71,18 +75,35 @@ pub enum FriendResultResp {
     Update(FriendUpdate) = 1,
     Req(FriendReq) = 2,
     Reset3(FriendList) = 3,
-    Unknown4(()) = 4,
-    Unknown5(()) = 5,
-    Unknown6(()) = 6,
-    Unknown7(()) = 7,
-    Unknown8(()) = 8,
-    Unknown9(ShroomOption8<String>) = 9,
-    UnknownA(ShroomOption8<String>) = 0xa,
+    FullMe(()) = 4,
+    FullOther(()) = 5,
+    AlreadySet(()) = 6,
+    Master(()) = 7,
+    UnknownUser(()) = 8,
+    SetUnknown(ShroomOption8<String>) = 9,
+    AcceptUnknown(ShroomOption8<String>) = 0xa,
     // Blocked is alwayws true fo this
     ResetB(FriendList) = 0xb,
-    UnknownC(ShroomOption8<String>) = 0xc,
+    DeleteUnknown(ShroomOption8<String>) = 0xc,
     ChangeChannel(FriendChangeChannel) = 0xd,
     MaxFriends(u8) = 0xe,
-    UnknownF(ShroomOption8<String>) = 0xf,
+    IncMaxCountUnknown(ShroomOption8<String>) = 0xf,
 }
 with_opcode!(FriendResultResp, SendOpcodes::FriendResult);
+
+#[derive(ShroomPacket, Debug)]
+pub struct FriendSetReq {
+    pub name: String,
+    pub group: String,
+}
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum FriendUserReq {
+    Load(()) = 0,
+    /// Adds a friend or changes the group of an existing one
+    Set(FriendSetReq) = 1,
+    Accept(CharacterId) = 2,
+    Delete(CharacterId) = 3,
+}
+with_opcode!(FriendUserReq, RecvOpcodes::FriendRequest);
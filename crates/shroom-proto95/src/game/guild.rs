@@ -0,0 +1,224 @@
+use bytes::BufMut;
+use shroom_meta::id::CharacterId;
+use shroom_pkt::{
+    with_opcode, DecodePacket, EncodePacket, PacketReader, PacketResult, PacketWriter,
+    ShroomOption8, ShroomPacket, ShroomPacketEnum, SizeHint,
+};
+
+use crate::{recv_opcodes::RecvOpcodes, send_opcodes::SendOpcodes, shared::NameStr};
+
+use super::user::remote::GuildMarkData;
+
+pub type GuildId = u32;
+
+pub const GUILD_GRADES: usize = 5;
+
+#[derive(ShroomPacket, Debug, Clone)]
+pub struct GuildMember {
+    pub name: NameStr,
+    pub job: u32,
+    pub level: u32,
+    pub grade: u32,
+    /// 1 if online
+    pub status: u32,
+    pub commitment: u32,
+    pub alliance_grade: u32,
+}
+
+/// Members of a guild, all ids are encoded before the member data
+#[derive(Debug, Clone, Default)]
+pub struct GuildMembers(pub Vec<(CharacterId, GuildMember)>);
+
+impl EncodePacket for GuildMembers {
+    const SIZE_HINT: SizeHint = SizeHint::NONE;
+
+    fn encode<B: BufMut>(&self, pw: &mut PacketWriter<B>) -> PacketResult<()> {
+        (self.0.len() as u8).encode(pw)?;
+        for (id, _) in self.0.iter() {
+            id.encode(pw)?;
+        }
+        for (_, member) in self.0.iter() {
+            member.encode(pw)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> DecodePacket<'de> for GuildMembers {
+    fn decode(pr: &mut PacketReader<'de>) -> PacketResult<Self> {
+        let n = u8::decode(pr)? as usize;
+        let ids = (0..n)
+            .map(|_| CharacterId::decode(pr))
+            .collect::<PacketResult<Vec<_>>>()?;
+        let members = ids
+            .into_iter()
+            .map(|id| Ok((id, GuildMember::decode(pr)?)))
+            .collect::<PacketResult<Vec<_>>>()?;
+        Ok(Self(members))
+    }
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildData {
+    pub id: GuildId,
+    pub name: String,
+    pub grades: [String; GUILD_GRADES],
+    pub members: GuildMembers,
+    pub capacity: u32,
+    pub mark: GuildMarkData,
+    pub notice: String,
+    pub points: u32,
+    pub alliance_id: u32,
+    pub level: u8,
+    /// Guild skills, always empty
+    pub skills: u16,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildInvite {
+    pub guild_id: GuildId,
+    pub inviter: String,
+    pub level: u32,
+    pub job: u32,
+    pub u1: u32,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildJoin {
+    pub guild_id: GuildId,
+    pub char_id: CharacterId,
+    pub member: GuildMember,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildMemberLeave {
+    pub guild_id: GuildId,
+    pub char_id: CharacterId,
+    pub name: String,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildMemberLevelJob {
+    pub guild_id: GuildId,
+    pub char_id: CharacterId,
+    pub level: u32,
+    pub job: u32,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildMemberLogin {
+    pub guild_id: GuildId,
+    pub char_id: CharacterId,
+    pub online: bool,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildGradeNames {
+    pub guild_id: GuildId,
+    pub grades: [String; GUILD_GRADES],
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildMemberGrade {
+    pub guild_id: GuildId,
+    pub char_id: CharacterId,
+    pub grade: u8,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildMarkChanged {
+    pub guild_id: GuildId,
+    pub mark: GuildMarkData,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildNotice {
+    pub guild_id: GuildId,
+    pub notice: String,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildPoints {
+    pub guild_id: GuildId,
+    pub points: u32,
+}
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum GuildResultResp {
+    /// Opens the dialog to enter the name of a new guild
+    InputGuildName(()) = 0x1,
+    Invite(GuildInvite) = 0x5,
+    /// Opens the dialog to select the guild mark
+    InputMark(()) = 0x11,
+    /// Guild of the user, None if not in a guild
+    LoadGuildDone(ShroomOption8<GuildData>) = 0x1A,
+    CheckGuildNameAlreadyUsed(()) = 0x1C,
+    CreateNewGuildAlreadyJoined(()) = 0x21,
+    CreateNewGuildNameAlreadyExists(()) = 0x22,
+    CreateNewGuildBeginner(()) = 0x23,
+    CreateNewGuildUnknown(()) = 0x26,
+    JoinGuildDone(GuildJoin) = 0x27,
+    JoinGuildAlreadyJoined(()) = 0x28,
+    JoinGuildAlreadyFull(()) = 0x29,
+    JoinGuildUnknownUser(()) = 0x2A,
+    JoinGuildUnknown(()) = 0x2B,
+    WithdrawGuildDone(GuildMemberLeave) = 0x2C,
+    WithdrawGuildNotJoined(()) = 0x2D,
+    KickGuildDone(GuildMemberLeave) = 0x2F,
+    KickGuildNotJoined(()) = 0x30,
+    RemoveGuildDone(GuildId) = 0x32,
+    InviteGuildAlreadyInvited(String) = 0x36,
+    InviteGuildRejected(String) = 0x37,
+    ChangeLevelOrJob(GuildMemberLevelJob) = 0x3C,
+    NotifyLoginOrLogout(GuildMemberLogin) = 0x3D,
+    SetGradeNameDone(GuildGradeNames) = 0x3E,
+    SetMemberGradeDone(GuildMemberGrade) = 0x40,
+    SetMarkDone(GuildMarkChanged) = 0x43,
+    SetNoticeDone(GuildNotice) = 0x45,
+    IncPointDone(GuildPoints) = 0x49,
+}
+with_opcode!(GuildResultResp, SendOpcodes::GuildResult);
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildJoinReq {
+    pub guild_id: GuildId,
+    pub char_id: CharacterId,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildMemberReq {
+    pub char_id: CharacterId,
+    pub name: String,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct GuildSetMemberGradeReq {
+    pub char_id: CharacterId,
+    pub grade: u8,
+}
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum GuildReq {
+    LoadGuild(()) = 0x0,
+    CheckGuildName(String) = 0x2,
+    InviteGuild(String) = 0x5,
+    JoinGuild(GuildJoinReq) = 0x6,
+    WithdrawGuild(GuildMemberReq) = 0x7,
+    KickGuild(GuildMemberReq) = 0x8,
+    RemoveGuild(()) = 0x9,
+    SetGradeName([String; GUILD_GRADES]) = 0xD,
+    SetMemberGrade(GuildSetMemberGradeReq) = 0xE,
+    SetMark(GuildMarkData) = 0xF,
+    SetNotice(String) = 0x10,
+}
+with_opcode!(GuildReq, RecvOpcodes::GuildRequest);
+
+/// Reply of an invited user
+#[derive(ShroomPacket, Debug)]
+pub struct GuildResultReq {
+    pub ty: u8,
+    pub inviter: String,
+}
+with_opcode!(GuildResultReq, RecvOpcodes::GuildResult);
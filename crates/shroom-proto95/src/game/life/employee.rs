@@ -2,7 +2,7 @@ use shroom_meta::{
     id::{FootholdId, ObjectId},
     twod::Vec2,
 };
-use shroom_pkt::{with_opcode, ShroomOption8, ShroomPacket};
+use shroom_pkt::{with_opcode, ShroomPacket, ShroomPacketEnum};
 
 use crate::send_opcodes::SendOpcodes;
 
@@ -15,6 +15,15 @@ pub struct EmployeeMiniRoomBalloon {
     pub max_users: u8,
 }
 
+/// Balloon above the employee, prefixed by the mini room type
+#[derive(ShroomPacketEnum, Debug, Clone)]
+#[repr(u8)]
+pub enum EmployeeBalloon {
+    None(()) = 0,
+    PersonalShop(EmployeeMiniRoomBalloon) = 4,
+    EntrustedShop(EmployeeMiniRoomBalloon) = 5,
+}
+
 #[derive(ShroomPacket, Debug)]
 pub struct EmployeeCreateResp {
     pub id: ObjectId,
@@ -22,16 +31,14 @@ pub struct EmployeeCreateResp {
     pub pos: Vec2,
     pub fh: FootholdId,
     pub char_name: String,
-    // TODO: the u8 is mini room ty
-    pub balloon: ShroomOption8<EmployeeMiniRoomBalloon>,
+    pub balloon: EmployeeBalloon,
 }
 with_opcode!(EmployeeCreateResp, SendOpcodes::EmployeeEnterField);
 
 #[derive(ShroomPacket, Debug)]
 pub struct EmployeeMiniRoomBalloonResp {
     pub employee_id: ObjectId,
-    // TODO: the u8 is mini room ty
-    pub balloon: ShroomOption8<EmployeeMiniRoomBalloon>,
+    pub balloon: EmployeeBalloon,
 }
 with_opcode!(
     EmployeeMiniRoomBalloonResp,
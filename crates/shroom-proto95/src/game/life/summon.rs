@@ -1,6 +1,6 @@
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use shroom_meta::{
-    id::{CharacterId, FootholdId, ObjectId, SkillId},
+    id::{CharacterId, FootholdId, MobId, ObjectId, SkillId},
     twod::Vec2,
 };
 use shroom_pkt::{
@@ -203,6 +203,60 @@ pub struct SummonHitResp {
 }
 with_opcode!(SummonHitResp, SendOpcodes::SummonedHit);
 
+#[derive(Debug, ShroomPacket)]
+pub struct SummonMoveReq {
+    pub summon_id: SummonId,
+    pub path: MovePath,
+}
+with_opcode!(SummonMoveReq, RecvOpcodes::SummonedMove);
+
+#[derive(Debug, ShroomPacket)]
+pub struct SummonHitMob {
+    pub tmpl_id: MobId,
+    pub left: bool,
+}
+
+#[derive(Debug, ShroomEncodePacket)]
+pub struct SummonHitReq {
+    pub summon_id: SummonId,
+    pub atk_index: i8,
+    pub damage: u32,
+    // Only encoded if atk_index > -2
+    pub mob: Option<SummonHitMob>,
+}
+
+impl<'de> DecodePacket<'de> for SummonHitReq {
+    fn decode(pr: &mut shroom_pkt::PacketReader<'de>) -> shroom_pkt::PacketResult<Self> {
+        let summon_id = SummonId::decode(pr)?;
+        let atk_index = i8::decode(pr)?;
+        let damage = u32::decode(pr)?;
+        let mob = if atk_index > -2 {
+            Some(SummonHitMob::decode(pr)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            summon_id,
+            atk_index,
+            damage,
+            mob,
+        })
+    }
+}
+with_opcode!(SummonHitReq, RecvOpcodes::SummonedHit);
+
+#[derive(Debug, ShroomPacket)]
+pub struct SummonRemoveReq {
+    pub summon_id: SummonId,
+}
+with_opcode!(SummonRemoveReq, RecvOpcodes::Remove);
+
+#[derive(Debug, ShroomPacket)]
+pub struct SummonEndReq {
+    pub summon_id: SummonId,
+}
+with_opcode!(SummonEndReq, RecvOpcodes::EndSummoned);
+
 #[derive(Debug, ShroomEncodePacket)]
 pub struct SummonAttackReq {
     pub summon_id: SummonId,
@@ -270,4 +324,19 @@ mod tests {
         let mut pr = PacketReader::new(&pkt);
         let _ = SummonAttackReq::decode_complete(&mut pr).unwrap();
     }
+
+    #[test]
+    fn summon_hit() {
+        // Body attack of a mob
+        let pkt = hex!("0e 00 00 00 ff 2a 00 00 00 c8 c2 87 00 01");
+        let mut pr = PacketReader::new(&pkt);
+        let hit = SummonHitReq::decode_complete(&mut pr).unwrap();
+        assert_eq!(hit.damage, 42);
+        assert_eq!(hit.mob.unwrap().tmpl_id, MobId(8_897_224));
+
+        // Without a mob
+        let pkt = hex!("0e 00 00 00 fe 2a 00 00 00");
+        let mut pr = PacketReader::new(&pkt);
+        assert!(SummonHitReq::decode_complete(&mut pr).unwrap().mob.is_none());
+    }
 }
@@ -0,0 +1,384 @@
+use bytes::BufMut;
+use shroom_meta::id::{item_id::InventoryType, CharacterId, ItemId};
+use shroom_pkt::{
+    shroom_enum_code, time::Ticks, with_opcode, CondOption, DecodePacket, EncodePacket,
+    PacketReader, PacketResult, PacketWriter, ShroomList8, ShroomPacket, ShroomPacketEnum,
+    SizeHint,
+};
+
+use crate::{
+    recv_opcodes::RecvOpcodes,
+    send_opcodes::SendOpcodes,
+    shared::{char::AvatarData, item::Item},
+};
+
+pub type MiniRoomId = u32;
+
+/// Max items, which can be offered by a single user in a trade
+pub const TRADE_MAX_ITEMS: u8 = 9;
+
+/// Max items, which can be listed in an entrusted shop
+pub const ENTRUSTED_SHOP_MAX_ITEMS: u8 = 16;
+
+/// Max users of an entrusted shop, including the employee
+pub const ENTRUSTED_SHOP_MAX_USERS: u8 = 4;
+
+shroom_enum_code!(
+    MiniRoomType,
+    u8,
+    Omok = 1,
+    MemoryGame = 2,
+    TradingRoom = 3,
+    PersonalShop = 4,
+    EntrustedShop = 5,
+    CashTradingRoom = 6
+);
+
+shroom_enum_code!(
+    MiniRoomInviteResult,
+    u8,
+    NoCharacter = 1,
+    CannotInvite = 2,
+    Rejected = 3,
+    Blocked = 4
+);
+
+shroom_enum_code!(
+    MiniRoomEnterError,
+    u8,
+    NoRoom = 1,
+    Full = 2,
+    Busy = 3,
+    Dead = 4,
+    Event = 5,
+    PermissionDenied = 6,
+    NoTrade = 7,
+    Etc = 8,
+    OnlyInSameField = 9
+);
+
+shroom_enum_code!(
+    MiniRoomLeaveReason,
+    u8,
+    UserRequest = 0,
+    WrongPosition = 1,
+    Closed = 2,
+    HostOut = 3,
+    Booked = 4,
+    Kicked = 5,
+    OpenTimeOver = 6,
+    TradeDone = 7,
+    TradeFail = 8
+);
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum MiniRoomCreateReq {
+    TradingRoom(()) = 3,
+    EntrustedShop(EntrustedShopCreateReq) = 5,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct EntrustedShopCreateReq {
+    pub title: String,
+    pub private: bool,
+    /// Slot of the shop permit in the cash inventory
+    pub slot: u16,
+    pub item_id: ItemId,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct MiniRoomInviteResultReq {
+    pub room_id: MiniRoomId,
+    pub result: MiniRoomInviteResult,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct MiniRoomEnterReq {
+    pub room_id: MiniRoomId,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct MiniRoomChatReq {
+    pub ticks: Ticks,
+    pub msg: String,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct TradePutItemReq {
+    pub inv_type: InventoryType,
+    pub slot: u16,
+    pub count: u16,
+    /// Position in the trade window, starting at 1
+    pub pos: u8,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct EntrustedShopPutItemReq {
+    pub inv_type: InventoryType,
+    pub slot: u16,
+    pub bundles: u16,
+    pub per_bundle: u16,
+    pub price: u32,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct EntrustedShopBuyItemReq {
+    pub index: u8,
+    pub bundles: u16,
+}
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum MiniRoomReq {
+    Create(MiniRoomCreateReq) = 0,
+    Invite(CharacterId) = 2,
+    InviteResult(MiniRoomInviteResultReq) = 3,
+    Enter(MiniRoomEnterReq) = 4,
+    Chat(MiniRoomChatReq) = 6,
+    Leave(()) = 0xA,
+    TradePutItem(TradePutItemReq) = 0xF,
+    TradePutMoney(u32) = 0x10,
+    Trade(()) = 0x11,
+    TradeItemCrc(()) = 0x14,
+    /// Opens the shop for visitors
+    Open(()) = 0xB,
+    EntrustedShopPutItem(EntrustedShopPutItemReq) = 0x21,
+    EntrustedShopBuyItem(EntrustedShopBuyItemReq) = 0x22,
+    /// Moves a listed item at the index back into the inventory
+    EntrustedShopMoveItemToInventory(u16) = 0x26,
+    /// Closes the shop, the remaining items are kept by Fredrick
+    EntrustedShopGoOut(()) = 0x27,
+    /// Removes sold out items
+    EntrustedShopArrangeItem(()) = 0x28,
+    EntrustedShopWithdrawMoney(()) = 0x2B,
+}
+with_opcode!(MiniRoomReq, RecvOpcodes::MiniRoom);
+
+#[derive(ShroomPacket, Debug)]
+pub struct MiniRoomInvite {
+    pub ty: MiniRoomType,
+    pub inviter: String,
+    pub room_id: MiniRoomId,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct MiniRoomInviteResultResp {
+    pub result: MiniRoomInviteResult,
+    pub target: String,
+}
+
+#[derive(ShroomPacket, Debug, Clone)]
+pub struct MiniRoomUser {
+    pub pos: u8,
+    pub avatar: AvatarData,
+    pub name: String,
+    pub job: u16,
+}
+
+/// Users of a room, terminated by 0xFF
+#[derive(Debug, Clone, Default)]
+pub struct MiniRoomUsers(pub Vec<MiniRoomUser>);
+
+const MINI_ROOM_USERS_END: u8 = 0xFF;
+
+impl EncodePacket for MiniRoomUsers {
+    const SIZE_HINT: SizeHint = SizeHint::NONE;
+
+    fn encode<B: BufMut>(&self, pw: &mut PacketWriter<B>) -> PacketResult<()> {
+        for user in self.0.iter() {
+            user.encode(pw)?;
+        }
+        MINI_ROOM_USERS_END.encode(pw)
+    }
+}
+
+impl<'de> DecodePacket<'de> for MiniRoomUsers {
+    fn decode(pr: &mut PacketReader<'de>) -> PacketResult<Self> {
+        let mut users = Vec::new();
+        loop {
+            let pos = u8::decode(pr)?;
+            if pos == MINI_ROOM_USERS_END {
+                break;
+            }
+            users.push(MiniRoomUser {
+                pos,
+                avatar: AvatarData::decode(pr)?,
+                name: String::decode(pr)?,
+                job: u16::decode(pr)?,
+            });
+        }
+        Ok(Self(users))
+    }
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct MiniRoomEnterData {
+    pub max_users: u8,
+    pub my_pos: u8,
+    pub users: MiniRoomUsers,
+}
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum MiniRoomEnterResult {
+    Error(MiniRoomEnterError) = 0,
+    TradingRoom(MiniRoomEnterData) = 3,
+    EntrustedShop(EntrustedShopEnterData) = 5,
+}
+
+/// The employee always takes the first position of the shop
+#[derive(ShroomPacket, Debug)]
+pub struct EntrustedShopEmployee {
+    pub pos: u8,
+    pub tmpl_id: ItemId,
+    pub name: String,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct EntrustedShopSoldItem {
+    pub item_id: ItemId,
+    pub quantity: u16,
+    pub price: u32,
+    pub buyer: String,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct EntrustedShopOwnerInfo {
+    /// Remaining open time in seconds
+    pub time_left: u32,
+    pub first_time: bool,
+    pub sold: ShroomList8<EntrustedShopSoldItem>,
+    pub money: u32,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct EntrustedShopItem {
+    pub bundles: u16,
+    pub per_bundle: u16,
+    pub price: u32,
+    pub item: Item,
+}
+
+fn is_owner_pos(pos: &u8) -> bool {
+    *pos == 0
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct EntrustedShopEnterData {
+    pub max_users: u8,
+    /// 0 for the owner, visitors start at 1
+    pub my_pos: u8,
+    pub employee: EntrustedShopEmployee,
+    pub visitors: MiniRoomUsers,
+    pub msg_count: u16,
+    pub owner_name: String,
+    #[pkt(check(field = "my_pos", cond = "is_owner_pos"))]
+    pub owner_info: CondOption<EntrustedShopOwnerInfo>,
+    pub title: String,
+    pub max_items: u8,
+    /// Money of the entering user
+    pub money: u32,
+    pub items: ShroomList8<EntrustedShopItem>,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct MiniRoomUserChat {
+    pub pos: u8,
+    pub msg: String,
+}
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum MiniRoomChat {
+    UserChat(MiniRoomUserChat) = 8,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct MiniRoomLeave {
+    pub pos: u8,
+    pub reason: MiniRoomLeaveReason,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct TradePutItem {
+    /// Position of the user, who offers the item
+    pub user_pos: u8,
+    pub pos: u8,
+    pub item: Item,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct TradePutMoney {
+    pub user_pos: u8,
+    pub money: u32,
+}
+
+shroom_enum_code!(
+    EntrustedShopBuyResult,
+    u8,
+    Success = 0,
+    NoMoney = 2,
+    SoldOut = 3,
+    Unavailable = 4,
+    NoSlot = 5
+);
+
+#[derive(ShroomPacket, Debug)]
+pub struct EntrustedShopRefresh {
+    /// Money of the receiving user
+    pub money: u32,
+    pub items: ShroomList8<EntrustedShopItem>,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct EntrustedShopAddSoldItem {
+    pub index: u8,
+    pub quantity: u16,
+    pub buyer: String,
+}
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum MiniRoomResp {
+    Invite(MiniRoomInvite) = 2,
+    InviteResult(MiniRoomInviteResultResp) = 3,
+    Enter(MiniRoomUser) = 4,
+    EnterResult(MiniRoomEnterResult) = 5,
+    Chat(MiniRoomChat) = 6,
+    Leave(MiniRoomLeave) = 0xA,
+    TradePutItem(TradePutItem) = 0xF,
+    TradePutMoney(TradePutMoney) = 0x10,
+    /// The other user confirmed the trade
+    Trade(()) = 0x11,
+    EntrustedShopBuyResult(EntrustedShopBuyResult) = 0x23,
+    EntrustedShopRefresh(EntrustedShopRefresh) = 0x24,
+    /// Notifies the owner about a sale
+    EntrustedShopAddSoldItem(EntrustedShopAddSoldItem) = 0x25,
+}
+with_opcode!(MiniRoomResp, SendOpcodes::MiniRoom);
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum UserEntrustedShopReq {
+    /// Sent before the shop permit is used
+    CheckOpenPossible(()) = 0,
+}
+with_opcode!(UserEntrustedShopReq, RecvOpcodes::UserEntrustedShopRequest);
+
+shroom_enum_code!(
+    EntrustedShopCheckResult,
+    u8,
+    OpenPossible = 7,
+    Unavailable = 8,
+    RetrieveFirst = 9
+);
+
+#[derive(ShroomPacket, Debug)]
+pub struct EntrustedShopCheckResultResp {
+    pub result: EntrustedShopCheckResult,
+}
+with_opcode!(
+    EntrustedShopCheckResultResp,
+    SendOpcodes::EntrustedShopCheckResult
+);
@@ -1,18 +1,23 @@
+pub mod cash_shop;
 pub mod chat;
 pub mod drop;
 pub mod field;
 pub mod friend;
+pub mod guild;
 pub mod key_map;
 pub mod life;
 pub mod macros;
+pub mod mini_room;
 pub mod party;
 pub mod script;
 pub mod shop;
+pub mod trunk;
 pub mod user;
 pub mod quest;
 
 use shroom_meta::{id::{job_id::JobId, CharacterId, NpcId}, twod::Vec2};
-use shroom_pkt::{with_opcode, time::Ticks, ShroomList32, ShroomPacket, ShroomPacketEnum};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use shroom_pkt::{mark_shroom_enum, with_opcode, time::Ticks, ShroomList32, ShroomPacket, ShroomPacketEnum};
 
 use crate::{
     login::MachineId,
@@ -84,6 +89,21 @@ pub struct MigrateCommandResp {
 }
 with_opcode!(MigrateCommandResp, SendOpcodes::MigrateCommand);
 
+#[derive(Debug, Eq, PartialEq, Copy, Clone, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum TransferChannelIgnoredReason {
+    ChannelOffline = 1,
+    CashShopUnavailable = 2,
+    ItcUnavailable = 3,
+}
+mark_shroom_enum!(TransferChannelIgnoredReason);
+
+#[derive(ShroomPacket, Debug)]
+pub struct TransferChannelReqIgnoredResp {
+    pub reason: TransferChannelIgnoredReason,
+}
+with_opcode!(TransferChannelReqIgnoredResp, SendOpcodes::TransferChannelReqIgnored);
+
 #[derive(ShroomPacket, Debug)]
 pub struct UpdateGMBoardReq {
     board_id: u32,
@@ -2,16 +2,18 @@
 // TODO fix the clippy warning in the packet_try_wrap macro
 
 use shroom_meta::id::{CharacterId, FieldId, MobId, SkillId};
-use shroom_pkt::{ShroomOption8, ShroomPacket, ShroomPacketEnum};
+use shroom_pkt::{with_opcode, ShroomOption8, ShroomPacket, ShroomPacketEnum};
 
 use crate::{
     login::ChannelId,
+    recv_opcodes::RecvOpcodes,
+    send_opcodes::SendOpcodes,
     shared::NameStr,
 };
 
 
 pub type PartyID = u32;
-const MAX_PARTY_MEMBERS: usize = 6;
+pub const MAX_PARTY_MEMBERS: usize = 6;
 
 #[derive(Debug, Default, Copy, Clone)]
 pub enum PartyMemberStatus {
@@ -89,6 +91,7 @@ pub struct PartyMemberTownPortal {
     pub pos: euclid::default::Vector2D<i32>,
 }
 
+#[derive(Debug, Default, Clone)]
 pub struct PartyMember {
     pub id: CharacterId,
     pub name: NameStr,
@@ -204,15 +207,19 @@ pub struct MemberChangeLevelJob {
 #[derive(ShroomPacket, Debug)]
 pub struct JoinParty {
     pub party_id: PartyID,
-    pub name: NameStr,
-    pub member_withdraw: ShroomOption8<MemberWithdraw>,
+    pub name: String,
+    pub party_data: PartyData,
 }
 
 #[derive(ShroomPacketEnum, Debug)]
 #[repr(u8)]
 pub enum PartyResultResp {
     Invite(InviteParty) = 4,
+    LoadParty(UserMigrationParty) = 7,
     CreateNewParty(NewParty) = 8,
+    CreateNewPartyAlreadyJoined(()) = 9,
+    CreateNewPartyBeginner(()) = 0xA,
+    CreateNewPartyUnknown(()) = 0xB,
 
     Withdraw(WithdrawParty) = 0xC,
     WithdrawNotJoined(()) = 0xD,
@@ -239,6 +246,34 @@ pub enum PartyResultResp {
     ChangeLeaderNotSameChannel(()) = 0x22,
     ChangeLeaderUnknown(()) = 0x23,
 
-    UserMigration(()) = 0x26,
+    UserMigration(UserMigrationParty) = 0x26,
     ChangeLevelJob(MemberChangeLevelJob) = 0x27,
 }
+with_opcode!(PartyResultResp, SendOpcodes::PartyResult);
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum PartyReq {
+    CreateNewParty(()) = 1,
+    WithdrawParty(()) = 2,
+    JoinParty(PartyID) = 3,
+    InviteParty(String) = 4,
+    KickParty(CharacterId) = 5,
+    ChangePartyLeader(CharacterId) = 6,
+}
+with_opcode!(PartyReq, RecvOpcodes::PartyRequest);
+
+#[derive(ShroomPacket, Debug)]
+pub struct PartyInviteResult {
+    pub party_id: PartyID,
+}
+
+/// Answer of an invited user, accepting is done via `PartyReq::JoinParty`
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum PartyResultReq {
+    InviteBlocked(PartyInviteResult) = 0x17,
+    InviteAlreadyInvited(PartyInviteResult) = 0x18,
+    InviteRejected(PartyInviteResult) = 0x1A,
+}
+with_opcode!(PartyResultReq, RecvOpcodes::PartyResult);
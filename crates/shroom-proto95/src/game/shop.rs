@@ -1,5 +1,7 @@
 use shroom_meta::id::{ItemId, NpcId};
-use shroom_pkt::{with_opcode, ShroomList16, ShroomOption8, ShroomPacket, ShroomPacketEnum};
+use shroom_pkt::{
+    with_opcode, CondEither, ShroomList16, ShroomOption8, ShroomPacket, ShroomPacketEnum,
+};
 
 use crate::{recv_opcodes::RecvOpcodes, send_opcodes::SendOpcodes};
 
@@ -12,7 +14,9 @@ pub struct ShopItem {
     pub token_price: u32,
     pub item_period: u32,
     pub level_limited: u32,
-    pub quantity: u16, // If rechargeable encode unit price as u64
+    /// Rechargeable items encode the unit price(f64 bits) instead of the quantity
+    #[pkt(either(field = "item_id", cond = "ItemId::is_rechargable"))]
+    pub quantity: CondEither<u64, u16>,
     pub max_per_slot: u16,
 }
 
@@ -0,0 +1,161 @@
+use shroom_meta::id::{item_id::InventoryType, ItemId, NpcId};
+use shroom_pkt::{
+    mark_shroom_bitflags, with_opcode, CondOption, ShroomList8, ShroomPacket, ShroomPacketEnum,
+};
+
+use crate::{recv_opcodes::RecvOpcodes, send_opcodes::SendOpcodes, shared::item::Item};
+
+bitflags::bitflags! {
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct TrunkFlags : u64 {
+        const Money = 1 << 1;
+        const Equip = 1 << 2;
+        const Consume = 1 << 3;
+        const Install = 1 << 4;
+        const Etc = 1 << 5;
+        const Cash = 1 << 6;
+    }
+}
+mark_shroom_bitflags!(TrunkFlags);
+
+impl TrunkFlags {
+    pub fn from_inv_type(ty: InventoryType) -> Self {
+        match ty {
+            InventoryType::Equip => Self::Equip,
+            InventoryType::Consume => Self::Consume,
+            InventoryType::Install => Self::Install,
+            InventoryType::Etc => Self::Etc,
+            InventoryType::Cash => Self::Cash,
+            _ => Self::empty(),
+        }
+    }
+
+    fn has_money(&self) -> bool {
+        self.contains(Self::Money)
+    }
+
+    fn has_equip(&self) -> bool {
+        self.contains(Self::Equip)
+    }
+
+    fn has_consume(&self) -> bool {
+        self.contains(Self::Consume)
+    }
+
+    fn has_install(&self) -> bool {
+        self.contains(Self::Install)
+    }
+
+    fn has_etc(&self) -> bool {
+        self.contains(Self::Etc)
+    }
+
+    fn has_cash(&self) -> bool {
+        self.contains(Self::Cash)
+    }
+}
+
+/// Trunk content, only the parts set in the flags are encoded
+#[derive(ShroomPacket, Debug)]
+pub struct TrunkData {
+    pub slots: u8,
+    pub flags: TrunkFlags,
+    #[pkt(check(field = "flags", cond = "TrunkFlags::has_money"))]
+    pub money: CondOption<u32>,
+    #[pkt(check(field = "flags", cond = "TrunkFlags::has_equip"))]
+    pub equip: CondOption<ShroomList8<Item>>,
+    #[pkt(check(field = "flags", cond = "TrunkFlags::has_consume"))]
+    pub consume: CondOption<ShroomList8<Item>>,
+    #[pkt(check(field = "flags", cond = "TrunkFlags::has_install"))]
+    pub install: CondOption<ShroomList8<Item>>,
+    #[pkt(check(field = "flags", cond = "TrunkFlags::has_etc"))]
+    pub etc: CondOption<ShroomList8<Item>>,
+    #[pkt(check(field = "flags", cond = "TrunkFlags::has_cash"))]
+    pub cash: CondOption<ShroomList8<Item>>,
+}
+
+impl TrunkData {
+    pub fn new(slots: u8) -> Self {
+        Self {
+            slots,
+            flags: TrunkFlags::empty(),
+            money: None.into(),
+            equip: None.into(),
+            consume: None.into(),
+            install: None.into(),
+            etc: None.into(),
+            cash: None.into(),
+        }
+    }
+
+    pub fn with_money(mut self, money: u32) -> Self {
+        self.flags |= TrunkFlags::Money;
+        self.money = Some(money).into();
+        self
+    }
+
+    pub fn with_items(mut self, ty: InventoryType, items: Vec<Item>) -> Self {
+        let items = Some(items.into()).into();
+        match ty {
+            InventoryType::Equip => self.equip = items,
+            InventoryType::Consume => self.consume = items,
+            InventoryType::Install => self.install = items,
+            InventoryType::Etc => self.etc = items,
+            InventoryType::Cash => self.cash = items,
+            _ => return self,
+        }
+        self.flags |= TrunkFlags::from_inv_type(ty);
+        self
+    }
+}
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum TrunkResultResp {
+    GetSuccess(TrunkData) = 0x9,
+    GetUnknown(()) = 0xA,
+    GetNoMoney(()) = 0xB,
+    GetHavingOnlyItem(()) = 0xC,
+    PutSuccess(TrunkData) = 0xD,
+    PutIncorrectRequest(()) = 0xE,
+    SortItem(TrunkData) = 0xF,
+    PutNoMoney(()) = 0x10,
+    PutNoSpace(()) = 0x11,
+    PutUnknown(()) = 0x12,
+    MoneySuccess(TrunkData) = 0x13,
+    MoneyUnknown(()) = 0x14,
+    OpenTrunkDlg(TrunkOpenData) = 0x16,
+}
+with_opcode!(TrunkResultResp, SendOpcodes::TrunkResult);
+
+#[derive(ShroomPacket, Debug)]
+pub struct TrunkOpenData {
+    pub npc_tmpl_id: NpcId,
+    pub data: TrunkData,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct TrunkGetItemReq {
+    pub inv_type: InventoryType,
+    /// Index in the list of the inventory type
+    pub index: u8,
+}
+
+#[derive(ShroomPacket, Debug)]
+pub struct TrunkPutItemReq {
+    pub slot: u16,
+    pub item_id: ItemId,
+    pub count: u16,
+}
+
+#[derive(ShroomPacketEnum, Debug)]
+#[repr(u8)]
+pub enum UserTrunkReq {
+    GetItem(TrunkGetItemReq) = 4,
+    PutItem(TrunkPutItemReq) = 5,
+    SortItem(()) = 6,
+    /// Positive amounts are withdrawn, negative amounts are deposited
+    Money(i32) = 7,
+    Close(()) = 8,
+}
+with_opcode!(UserTrunkReq, RecvOpcodes::UserTrunkRequest);
@@ -172,10 +172,20 @@ pub enum UserHit {
     Stat(HitInfoStat) = -4,
 }
 
+impl UserHit {
+    pub fn dmg(&self) -> u32 {
+        match self {
+            Self::MobPhysical(hit) | Self::MobMagic(hit) => hit.dmg,
+            Self::Counter(hit) | Self::Obstacle(hit) => hit.dmg,
+            Self::Stat(hit) => hit.dmg,
+        }
+    }
+}
+
 #[derive(ShroomPacket, Debug)]
 pub struct UserHitReq {
     pub damaged_ticks: Ticks,
-    //pub hit: UserHit,
+    pub hit: UserHit,
 }
 with_opcode!(UserHitReq, RecvOpcodes::UserHit);
 
@@ -883,7 +893,8 @@ mod tests {
             52, 0, 232, 211, 221, 3, 255, 0, 1, 0, 0, 0, 160, 134, 1, 0, 18, 0, 0, 0, 0, 0, 0, 0, 0,
         ];
         let hit = UserHitReq::decode_complete(&mut PacketReader::new(&data[2..])).unwrap();
-        dbg!(hit);
+        assert!(matches!(hit.hit, super::UserHit::MobMagic(_)));
+        assert_eq!(hit.hit.dmg(), 1);
     }
 
     #[test]
@@ -17,12 +17,12 @@ use super::{
     ActionDir,
 };
 
-#[derive(ShroomPacket, Default, Debug)]
+#[derive(ShroomPacket, Default, Debug, Clone, Copy)]
 pub struct GuildMarkData {
-    bg: u16,
-    bg_color: u8,
-    mark: u16,
-    mark_color: u8,
+    pub bg: u16,
+    pub bg_color: u8,
+    pub mark: u16,
+    pub mark_color: u8,
 }
 
 #[derive(ShroomPacket, Debug)]
@@ -256,6 +256,7 @@ with_opcode!(
 
 #[derive(ShroomPacket, Debug)]
 pub struct UserReceiveHPResp {
+    pub char_id: CharacterId,
     pub hp: u32,
     pub max_hp: u32,
 }
@@ -263,12 +264,14 @@ with_opcode!(UserReceiveHPResp, SendOpcodes::UserHP);
 
 #[derive(ShroomPacket, Debug)]
 pub struct UserGuildNameChangedResp {
+    pub char_id: CharacterId,
     pub guild_name: String,
 }
 with_opcode!(UserGuildNameChangedResp, SendOpcodes::UserGuildNameChanged);
 
 #[derive(ShroomPacket, Debug)]
 pub struct UserGuildMarkChangedResp {
+    pub char_id: CharacterId,
     pub guild_mark: GuildMarkData,
 }
 with_opcode!(UserGuildMarkChangedResp, SendOpcodes::UserGuildMarkChanged);
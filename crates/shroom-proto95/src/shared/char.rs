@@ -1,5 +1,5 @@
 use shroom_pkt::{
-    partial::PartialFlag, partial_data, with_opcode, CondEither, CondOption, ShroomDurationMs16,
+    partial::PartialFlag, partial_data, with_opcode, CondEither, CondOption,
     ShroomExpirationTime, ShroomIndexList8, ShroomIndexListZ16, ShroomList16, ShroomList32,
     ShroomOption8, ShroomPacket, ShroomTime,
 };
@@ -116,7 +116,7 @@ pub struct SkillInfo {
 #[derive(Debug, ShroomPacket)]
 pub struct SkillCooltime {
     pub id: SkillId,
-    pub time_left: ShroomDurationMs16,
+    pub time_left_s: u16, //TODO ShroomDurationSec16
 }
 
 /*
@@ -384,6 +384,25 @@ pub struct ItemLearnSkillReq {
 }
 with_opcode!(ItemLearnSkillReq, RecvOpcodes::UserSkillLearnItemUseRequest);
 
+#[derive(Debug, ShroomPacket)]
+pub struct ItemScriptUseReq {
+    pub timestamp: Ticks,
+    pub slot: u16,
+    pub item_id: ItemId,
+}
+with_opcode!(ItemScriptUseReq, RecvOpcodes::UserScriptItemUseRequest);
+
+#[derive(Debug, ShroomPacket)]
+pub struct ItemSelectNpcUseReq {
+    pub timestamp: Ticks,
+    pub slot: u16,
+    pub item_id: ItemId,
+}
+with_opcode!(
+    ItemSelectNpcUseReq,
+    RecvOpcodes::UserSelectNpcItemUseRequest
+);
+
 #[derive(Debug, ShroomPacket)]
 pub struct UserSitReq {
     pub seat_id: u16,
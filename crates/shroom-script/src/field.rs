@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use shroom_meta::{
+    id::{CharacterId, FieldId, MobId},
+    twod::Vec2,
+};
+use shroom_proto95::game::field::FieldEffectResp;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldScriptEvent {
+    /// The first user entered the empty field
+    FirstUserEnter,
+    /// A user entered the field, this also runs for the first user
+    UserEnter,
+}
+
+/// Field side of a field script, which runs when a user enters the field
+pub trait FieldCtx {
+    fn field_id(&self) -> FieldId;
+    fn event(&self) -> FieldScriptEvent;
+    /// Character, which entered the field
+    fn char_id(&self) -> CharacterId;
+
+    /// Shows the effect to all users in the field
+    fn show_effect(&mut self, effect: FieldEffectResp) -> anyhow::Result<()>;
+    fn spawn_mob(&mut self, id: MobId, pos: Vec2) -> anyhow::Result<()>;
+    /// Starts a countdown clock for all users in the field
+    fn start_clock(&mut self, duration: Duration) -> anyhow::Result<()>;
+    /// Shows a message to the character, which entered the field
+    fn message(&mut self, msg: &str) -> anyhow::Result<()>;
+    /// Shows a message to all users in the field
+    fn broadcast_message(&mut self, msg: &str) -> anyhow::Result<()>;
+}
+
+pub trait FieldPlugin {
+    fn enter(&mut self, ctx: &mut dyn FieldCtx) -> anyhow::Result<()>;
+}
+
+impl<F> FieldPlugin for F
+where
+    F: FnMut(&mut dyn FieldCtx) -> anyhow::Result<()>,
+{
+    fn enter(&mut self, ctx: &mut dyn FieldCtx) -> anyhow::Result<()> {
+        self(ctx)
+    }
+}
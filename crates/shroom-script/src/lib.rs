@@ -1,12 +1,19 @@
+use field::FieldPlugin;
 use npc::NpcPlugin;
+use portal::PortalPlugin;
+use reactor::ReactorPlugin;
 use shroom_meta::{
     id::{job_id::JobId, FieldId, ItemId, Money, NpcId, QuestId},
+    item::EquipStat,
     MetaService, QuestDataId,
 };
 use shroom_proto95::game::script::ScriptMessage;
 
+pub mod field;
 pub mod npc;
 pub mod poll_state;
+pub mod portal;
+pub mod reactor;
 
 pub type PluginId = usize;
 pub trait SessionCtx {
@@ -20,6 +27,9 @@ pub trait SessionCtx {
     fn job(&self) -> JobId;
     fn set_job(&mut self, job: JobId);
 
+    /// Stat after applying equips, set bonuses, passive skills and buffs
+    fn derived_stat(&self, stat: EquipStat) -> u32;
+
     fn has_item(&self, id: ItemId) -> bool;
     fn has_item_quantity(&self, id: ItemId, count: usize) -> bool;
     fn try_take_item(&mut self, item: ItemId, count: usize) -> anyhow::Result<bool>;
@@ -38,9 +48,27 @@ pub trait SessionCtx {
     fn has_completed_quest(&self, id: QuestId) -> bool;
     fn is_active_quest(&self, id: QuestId) -> bool;
 
+    /// Quest of the running quest script
+    fn set_quest_id(&mut self, id: Option<QuestId>);
+    fn current_quest_id(&self) -> Option<QuestId>;
+    /// Starts the quest, returns false if the requirements are not met
+    fn start_quest(&mut self, id: QuestId) -> anyhow::Result<bool>;
+    fn forfeit_quest(&mut self, id: QuestId) -> anyhow::Result<()>;
+    /// Completes an active quest and grants the rewards
+    fn complete_quest(&mut self, id: QuestId) -> anyhow::Result<bool>;
+
+    /// Item of the running item script
+    fn set_item_id(&mut self, id: Option<ItemId>);
+    fn current_item_id(&self) -> Option<ItemId>;
+    /// Takes one of the item, which launched the script, from its slot
+    fn consume_script_item(&mut self) -> anyhow::Result<bool>;
+
     fn transfer_field(&mut self, field_id: FieldId);
+    fn transfer_field_portal(&mut self, field_id: FieldId, portal: &str);
 
     fn say(&self, msg: &str);
+    /// Shows a system message to the character
+    fn notice(&mut self, msg: &str);
 
     fn meta(&self) -> &'static MetaService;
     fn search_fields(&self, query: &str) -> Result<FieldId, Vec<(FieldId, String)>>;
@@ -48,8 +76,21 @@ pub trait SessionCtx {
 
 pub type BoxedSessionCtx = Box<dyn SessionCtx + Send>;
 pub type BoxedNpcPlugin = Box<dyn NpcPlugin + Send>;
+pub type BoxedPortalPlugin = Box<dyn PortalPlugin + Send>;
+pub type BoxedReactorPlugin = Box<dyn ReactorPlugin + Send>;
+pub type BoxedFieldPlugin = Box<dyn FieldPlugin + Send>;
 pub trait PluginBundle {
     fn get_id_by_name(&self, name: &str) -> Option<PluginId>;
     fn get_npc_plugin(&self, id: PluginId) -> Option<BoxedNpcPlugin>;
     fn get_fallback_npc_plugin(&self) -> BoxedNpcPlugin;
+    /// Quest scripts are npc dialogs, looked up by the start or end script name of the quest
+    fn get_quest_plugin(&self, script: &str) -> Option<BoxedNpcPlugin>;
+    /// Looks up the item plugin by the script name of the item
+    fn get_item_plugin(&self, script: &str) -> Option<BoxedNpcPlugin>;
+    /// Looks up the portal plugin by the script name of the portal
+    fn get_portal_plugin(&self, script: &str) -> Option<BoxedPortalPlugin>;
+    /// Looks up the reactor plugin by the action name of the reactor
+    fn get_reactor_plugin(&self, script: &str) -> Option<BoxedReactorPlugin>;
+    /// Looks up the field plugin by the enter script name of the field or `field_<id>`
+    fn get_field_plugin(&self, script: &str) -> Option<BoxedFieldPlugin>;
 }
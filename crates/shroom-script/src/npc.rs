@@ -374,6 +374,41 @@ impl NpcCtx {
     pub fn has_completed_quest(&self, id: QuestId) -> bool {
         self.with(|c| c.has_completed_quest(id))
     }
+
+    pub fn is_active_quest(&self, id: QuestId) -> bool {
+        self.with(|c| c.is_active_quest(id))
+    }
+
+    /// Quest of a quest script, none for regular npc scripts
+    pub fn quest_id(&self) -> Option<QuestId> {
+        self.with(|c| c.current_quest_id())
+    }
+
+    pub fn start_quest(&mut self, id: QuestId) -> anyhow::Result<bool> {
+        self.with_mut(|c| c.start_quest(id))
+    }
+
+    pub fn forfeit_quest(&mut self, id: QuestId) -> anyhow::Result<()> {
+        self.with_mut(|c| c.forfeit_quest(id))
+    }
+
+    pub fn complete_quest(&mut self, id: QuestId) -> anyhow::Result<bool> {
+        self.with_mut(|c| c.complete_quest(id))
+    }
+
+    /// Item of an item script, none for regular npc scripts
+    pub fn item_id(&self) -> Option<ItemId> {
+        self.with(|c| c.current_item_id())
+    }
+
+    /// Uses up the item, which launched the script.
+    /// Item scripts must call this once the item was used successfully
+    pub fn consume_item(&mut self) -> anyhow::Result<bool> {
+        if self.item_id().is_none() {
+            return Ok(false);
+        }
+        self.with_mut(|c| c.consume_script_item())
+    }
 }
 
 pub trait NpcPlugin {
@@ -0,0 +1,69 @@
+use shroom_meta::id::{FieldId, ItemId, QuestId};
+
+use crate::SessionCtx;
+
+/// Context passed to a portal script, portal scripts run to completion
+/// when a character enters the portal, so unlike npc scripts there's no dialog state
+pub struct PortalCtx<'a> {
+    session: &'a mut dyn SessionCtx,
+    portal: &'a str,
+}
+
+impl<'a> PortalCtx<'a> {
+    pub fn new(session: &'a mut dyn SessionCtx, portal: &'a str) -> Self {
+        Self { session, portal }
+    }
+
+    /// Name of the portal which was entered
+    pub fn portal_name(&self) -> &str {
+        self.portal
+    }
+
+    pub fn session(&mut self) -> &mut dyn SessionCtx {
+        self.session
+    }
+
+    /// Warp to the default spawn point of the field
+    pub fn warp(&mut self, field: FieldId) {
+        self.session.transfer_field(field);
+    }
+
+    /// Warp to the portal with the given name in the field
+    pub fn warp_to(&mut self, field: FieldId, portal: &str) {
+        self.session.transfer_field_portal(field, portal);
+    }
+
+    pub fn has_item(&self, id: ItemId) -> bool {
+        self.session.has_item(id)
+    }
+
+    pub fn has_item_quantity(&self, id: ItemId, count: usize) -> bool {
+        self.session.has_item_quantity(id, count)
+    }
+
+    pub fn has_completed_quest(&self, id: QuestId) -> bool {
+        self.session.has_completed_quest(id)
+    }
+
+    pub fn is_active_quest(&self, id: QuestId) -> bool {
+        self.session.is_active_quest(id)
+    }
+
+    /// Show a message to the character
+    pub fn message(&mut self, msg: impl Into<String>) {
+        self.session.notice(&msg.into());
+    }
+}
+
+pub trait PortalPlugin {
+    fn enter(&mut self, ctx: &mut PortalCtx) -> anyhow::Result<()>;
+}
+
+impl<F> PortalPlugin for F
+where
+    F: FnMut(&mut PortalCtx) -> anyhow::Result<()>,
+{
+    fn enter(&mut self, ctx: &mut PortalCtx) -> anyhow::Result<()> {
+        self(ctx)
+    }
+}
@@ -0,0 +1,29 @@
+use shroom_meta::{
+    id::{MobId, ReactorId},
+    twod::Vec2,
+};
+
+/// Field side of a reactor script, which runs once a reactor reached its final state
+pub trait ReactorCtx {
+    fn reactor_id(&self) -> ReactorId;
+    fn reactor_pos(&self) -> Vec2;
+
+    fn spawn_mob(&mut self, id: MobId, pos: Vec2) -> anyhow::Result<()>;
+    /// Drops the items of the reactor drop table
+    fn drop_items(&mut self) -> anyhow::Result<()>;
+    /// Opens a portal object of the field, like the gates in party quests
+    fn open_portal(&mut self, name: &str) -> anyhow::Result<()>;
+}
+
+pub trait ReactorPlugin {
+    fn act(&mut self, ctx: &mut dyn ReactorCtx) -> anyhow::Result<()>;
+}
+
+impl<F> ReactorPlugin for F
+where
+    F: FnMut(&mut dyn ReactorCtx) -> anyhow::Result<()>,
+{
+    fn act(&mut self, ctx: &mut dyn ReactorCtx) -> anyhow::Result<()> {
+        self(ctx)
+    }
+}
@@ -49,6 +49,10 @@ impl<I: Id, M> BroadcastSet<I, M> {
         self.tx.remove(&id);
     }
 
+    pub fn contains(&self, id: &I) -> bool {
+        self.tx.contains_key(id)
+    }
+
     pub(crate) fn add_error(&mut self, id: I) {
         self.err_ids.insert(id);
     }
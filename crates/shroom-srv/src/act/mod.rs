@@ -12,12 +12,14 @@ use tokio::sync::mpsc;
 use crate::{time::interval::Interval, Instant};
 
 pub mod broadcast;
+pub mod registry;
 pub mod room;
 pub mod session;
 pub mod system;
 pub mod tick;
 
 pub use broadcast::{BroadcastSet, RoomSessionContext, SessionSet};
+pub use registry::SessionRegistry;
 
 pub const MESSAGES_PER_TICK: usize = 100;
 
@@ -38,6 +40,14 @@ impl<T> Sender<T> {
     pub fn try_send(&self, msg: T) -> Result<(), mpsc::error::TrySendError<T>> {
         self.0.try_send(msg)
     }
+
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+
+    pub fn same_channel(&self, other: &Self) -> bool {
+        self.0.same_channel(&other.0)
+    }
 }
 
 #[derive(Debug)]
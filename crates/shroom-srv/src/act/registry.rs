@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use shroom_pkt::{pkt::EncodeMessage, util::encode_buf::EncodeBuf};
+
+use crate::{
+    net::{session::NetMsg, socket::PktMsg},
+    Id,
+};
+
+use super::Sender;
+
+/// Registry of all active sessions in the system, which allows
+/// sending messages to a session regardless of the room It's currently in
+pub struct SessionRegistry<I: Id, M> {
+    sessions: Arc<DashMap<I, Sender<M>>>,
+}
+
+impl<I: Id, M> Clone for SessionRegistry<I, M> {
+    fn clone(&self) -> Self {
+        Self {
+            sessions: self.sessions.clone(),
+        }
+    }
+}
+
+impl<I: Id, M> Default for SessionRegistry<I, M> {
+    fn default() -> Self {
+        Self {
+            sessions: Arc::new(DashMap::new()),
+        }
+    }
+}
+
+impl<I: Id, M> std::fmt::Debug for SessionRegistry<I, M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionRegistry")
+            .field("sessions", &self.sessions.len())
+            .finish()
+    }
+}
+
+impl<I: Id, M> SessionRegistry<I, M> {
+    pub(crate) fn insert(&self, id: I, tx: Sender<M>) {
+        self.sessions.insert(id, tx);
+    }
+
+    pub(crate) fn remove(&self, id: &I) {
+        self.sessions.remove(id);
+    }
+
+    /// Removes the session only, if It's still the same channel,
+    /// so a re-connected session is not removed by accident
+    fn remove_closed(&self, id: &I, tx: &Sender<M>) {
+        self.sessions
+            .remove_if(id, |_, cur| cur.same_channel(tx) && cur.is_closed());
+    }
+
+    pub fn contains(&self, id: &I) -> bool {
+        self.sessions
+            .get(id)
+            .is_some_and(|tx| !tx.is_closed())
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    pub fn ids(&self) -> Vec<I> {
+        self.sessions
+            .iter()
+            .filter(|entry| !entry.value().is_closed())
+            .map(|entry| *entry.key())
+            .collect()
+    }
+
+    /// Sends the message to the session, returns false
+    /// if the session is not online or the message could not be delivered
+    pub fn send_to(&self, id: I, msg: M) -> bool {
+        let Some(tx) = self.sessions.get(&id).map(|tx| tx.clone()) else {
+            return false;
+        };
+
+        if tx.try_send(msg).is_ok() {
+            return true;
+        }
+
+        if tx.is_closed() {
+            self.remove_closed(&id, &tx);
+        }
+        false
+    }
+}
+
+impl<I: Id, M> SessionRegistry<I, M>
+where
+    M: NetMsg + Clone,
+{
+    pub fn send_to_encode(
+        &self,
+        id: I,
+        msg: impl EncodeMessage,
+    ) -> Result<bool, shroom_pkt::Error> {
+        let msg = PktMsg::Packet(EncodeBuf::new().encode_onto(msg)?);
+        Ok(self.send_to(id, msg.into()))
+    }
+
+    /// Encodes the message once and sends It to all given sessions
+    pub fn send_all_encode(
+        &self,
+        ids: impl IntoIterator<Item = I>,
+        msg: impl EncodeMessage,
+    ) -> Result<(), shroom_pkt::Error> {
+        let msg: M = PktMsg::Packet(EncodeBuf::new().encode_onto(msg)?).into();
+        for id in ids {
+            self.send_to(id, msg.clone());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::act::channel;
+
+    use super::*;
+
+    #[test]
+    fn registry_send() {
+        let reg = SessionRegistry::<u32, u32>::default();
+        let (tx, mut rx) = channel(4);
+        reg.insert(1, tx);
+
+        assert!(reg.contains(&1));
+        assert!(reg.send_to(1, 42));
+        assert!(!reg.send_to(2, 42));
+        assert_eq!(rx.try_recv().unwrap(), 42);
+
+        // Closed sessions are removed lazily
+        drop(rx);
+        assert!(!reg.contains(&1));
+        assert!(!reg.send_to(1, 42));
+        assert!(reg.is_empty());
+    }
+}
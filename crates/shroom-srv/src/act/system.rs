@@ -5,7 +5,9 @@ use tokio::sync::mpsc;
 use crate::{
     act::{
         room::{RoomActor, RoomActorRunner, RoomConfig, RoomController, RoomHandle},
+        registry::SessionRegistry,
         session::{SessionActor, SessionCell, SessionHandle},
+        TickActor,
     }, Clock, GameTime, Id
 };
 
@@ -54,8 +56,18 @@ pub trait SystemHandler: Sized + Send + 'static {
 
     fn on_tick(&mut self, t: GameTime) -> Result<(), Self::Error>;
     fn create_room(&mut self, id: Self::RoomId) -> Result<Self::Room, Self::Error>;
+
+    /// Registry, which is filled with all sessions of the system
+    fn session_registry(&self) -> SystemSessionRegistry<Self> {
+        SessionRegistry::default()
+    }
 }
 
+pub type SystemSessionRegistry<H> = SessionRegistry<
+    <H as SystemHandler>::SessionId,
+    <<H as SystemHandler>::Session as TickActor>::Msg,
+>;
+
 pub struct SystemHandle<H: SystemHandler> {
     tx: mpsc::UnboundedSender<Message<H>>,
 }
@@ -109,6 +121,7 @@ pub struct System<H: SystemHandler> {
     handler: H,
     rooms: HashMap<H::RoomId, (usize, RoomHandle<H::Room>)>,
     sessions: HashMap<H::SessionId, SessionHandle<H::Room, H::Session>>,
+    registry: SystemSessionRegistry<H>,
     cfg: SystemConfig,
     epoch: usize,
 }
@@ -138,6 +151,7 @@ impl<H: SystemHandler> RoomController<H::Room> for SystemRoomController<H> {
 impl<H: SystemHandler> System<H> {
     pub fn new(handler: H, cfg: SystemConfig) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
+        let registry = handler.session_registry();
         Self {
             clock: Clock::default(),
             rx,
@@ -145,11 +159,16 @@ impl<H: SystemHandler> System<H> {
             handler,
             rooms: HashMap::new(),
             sessions: HashMap::new(),
+            registry,
             cfg,
             epoch: 0,
         }
     }
 
+    pub fn registry(&self) -> &SystemSessionRegistry<H> {
+        &self.registry
+    }
+
     pub fn handle(&self) -> SystemHandle<H> {
         SystemHandle {
             tx: self.tx.clone(),
@@ -209,6 +228,7 @@ impl<H: SystemHandler> System<H> {
         let session = SessionCell::new(session, self.cfg.session_message_cap);
         let handle = session.handle();
         let id = session.id();
+        self.registry.insert(id, session.tx());
         self.add_session_to_room(room_id, session).await?;
         self.sessions.insert(id, handle);
         Ok(())
@@ -229,6 +249,7 @@ impl<H: SystemHandler> System<H> {
 
     fn remove_session(&mut self, session_id: H::SessionId) -> Result<(), H::Error> {
         self.sessions.remove(&session_id);
+        self.registry.remove(&session_id);
         Ok(())
     }
 
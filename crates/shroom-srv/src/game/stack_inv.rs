@@ -86,18 +86,22 @@ impl<T: InvStackItem, H: StackInvEventHandler<Item = T>> StackInventory<T, H> {
         self.inv.try_add(item)
     }
 
-    /// Adds a given amount of stack items
-    pub fn try_add_stack(&mut self, id: T::Id, quantity: usize) -> InvResult<()> {
+    /// Adds a given amount of stack items, a stack holds at most `slot_max` items
+    pub fn try_add_stack(&mut self, id: T::Id, quantity: usize, slot_max: usize) -> InvResult<()> {
         // TODO check unique here
         if quantity == 0 {
             return Ok(());
         }
 
+        if !self.can_add_stack(id, quantity, slot_max) {
+            return Err(InvError::Full);
+        }
+
         let mut remaining = quantity;
 
         for slot in self.inv.id_slots.indices_iter(&id) {
             let item = &mut self.inv.slots[slot.to_ix()];
-            let free_space = item.free_space();
+            let free_space = Self::stack_space(item, slot_max);
             if free_space > 0 {
                 let delta = free_space.min(remaining);
                 item.add_quantity(delta).expect("merge add quantity");
@@ -110,15 +114,38 @@ impl<T: InvStackItem, H: StackInvEventHandler<Item = T>> StackInventory<T, H> {
             }
         }
 
-        // Add the rest as a new stack
-        if remaining > 0 {
+        // Add the rest as new stacks
+        while remaining > 0 {
+            let quantity = remaining.min(slot_max);
             let stack = self.inv.handler.new_stack(id, quantity);
-            self.inv.try_add(stack).expect("try add stack");
+            self.inv.try_add(stack)?;
+            remaining -= quantity;
         }
 
         Ok(())
     }
 
+    fn stack_space(item: &T, slot_max: usize) -> usize {
+        item.max_stack_size()
+            .min(slot_max)
+            .saturating_sub(item.quantity())
+    }
+
+    /// Checks if the given quantity fits into the existing stacks and the free slots
+    pub fn can_add_stack(&self, id: T::Id, quantity: usize, slot_max: usize) -> bool {
+        if slot_max == 0 {
+            return false;
+        }
+
+        let free_space: usize = self
+            .inv
+            .items_by_id(&id)
+            .map(|item| Self::stack_space(item, slot_max))
+            .sum();
+        let remaining = quantity.saturating_sub(free_space);
+        remaining.div_ceil(slot_max) <= self.inv.capacity() - self.inv.len()
+    }
+
     pub fn r#move(
         &mut self,
         src: T::SlotIndex,
@@ -427,6 +454,36 @@ mod tests {
         assert_eq!(inv.get(1).unwrap().quantity(), 10);
     }
 
+    #[test]
+    fn stack_add_slot_max() {
+        let mut inv = inv(3);
+        inv.set(0, DummyItem(1, 90)).unwrap();
+
+        // The existing stack is filled up to the slot max, the rest is split
+        assert!(!inv.can_add_stack(1, 211, 100));
+        inv.try_add_stack(1, 210, 100).expect("Add");
+        assert_eq!(inv.get(0).unwrap().quantity(), 100);
+        assert_eq!(inv.get(1).unwrap().quantity(), 100);
+        assert_eq!(inv.get(2).unwrap().quantity(), 100);
+        assert!(matches!(inv.try_add_stack(1, 1, 100), Err(InvError::Full)));
+    }
+
+    #[test]
+    fn stack_add_full() {
+        let mut inv = inv(1);
+        inv.set(0, DummyItem(1, 250)).unwrap();
+
+        // Still fits into the existing stack
+        inv.try_add_stack(1, 5, 255).expect("Add");
+        assert_eq!(inv.get(0).unwrap().quantity(), 255);
+
+        // No space left in the stack and no free slot
+        assert!(!inv.can_add_stack(1, 1, 255));
+        assert!(matches!(inv.try_add_stack(1, 1, 255), Err(InvError::Full)));
+        assert!(matches!(inv.try_add_stack(2, 1, 255), Err(InvError::Full)));
+        assert_eq!(inv.get(0).unwrap().quantity(), 255);
+    }
+
     #[test]
     fn stack_move_partial() {
         let mut inv = inv(10);
@@ -1,4 +1,4 @@
-use std::{marker::PhantomData, net::IpAddr, ops::RangeInclusive, time::Duration};
+use std::{marker::PhantomData, net::IpAddr, time::Duration};
 use crate::{
     net::system::{NetSystem, NetSystemHandler}, rpc::{RpcListener, RpcService}, util::supervised_task::{SupervisedTask, SupervisedTaskHandle}
 };
@@ -6,15 +6,20 @@ use crate::{
 pub struct RuntimeConfig {
     pub bind_addr: IpAddr,
     pub login_port: u16,
-    pub game_ports: RangeInclusive<u16>,
+    pub cash_shop_port: u16,
+    /// Port of every channel, in the same order as the channel systems
+    pub game_ports: Vec<u16>,
 }
 
 pub trait RuntimeHandler: Send + 'static {
-    type Ctx: Send + Sync + 'static;
+    type Ctx: Clone + Send + Sync + 'static;
     type NetHandler: NetSystemHandler;
     type LoginService: RpcService<Ctx = Self::Ctx, Codec = <Self::NetHandler as NetSystemHandler>::Codec>
         + Send
         + 'static;
+    type CashShopService: RpcService<Ctx = Self::Ctx, Codec = <Self::NetHandler as NetSystemHandler>::Codec>
+        + Send
+        + 'static;
 }
 
 pub struct LoginTask<H: RuntimeHandler> {
@@ -31,6 +36,20 @@ impl<H: RuntimeHandler> SupervisedTask for LoginTask<H> {
     }
 }
 
+pub struct CashShopTask<H: RuntimeHandler> {
+    cash_shop: RpcListener<H::CashShopService>,
+    bind_addr: IpAddr,
+    port: u16,
+}
+impl<H: RuntimeHandler> SupervisedTask for CashShopTask<H> {
+    type Context = ();
+
+    async fn run(&mut self, _ctx: &mut Self::Context) -> anyhow::Result<()> {
+        self.cash_shop.run_tcp((self.bind_addr, self.port)).await?;
+        Ok(())
+    }
+}
+
 /*
 pub struct ChannelTask<H: NetSystemHandler> {
     acceptor: ServerAcceptor<H>,
@@ -49,28 +68,36 @@ impl<H: NetSystemHandler> SupervisedTask for ChannelTask<H>
 
 pub struct ServerRuntime<H: RuntimeHandler> {
     _handler: PhantomData<H>,
-    net_sys: NetSystem<H::NetHandler>,
+    /// One system per channel, each channel owns It's own rooms
+    channels: Vec<NetSystem<H::NetHandler>>,
     login_task: LoginTask<H>,
+    cash_shop_task: CashShopTask<H>,
     addr: IpAddr,
-    game_ports: RangeInclusive<u16>,
+    game_ports: Vec<u16>,
 }
 
 impl<H: RuntimeHandler> ServerRuntime<H> {
     pub fn new(
         cfg: &RuntimeConfig,
-        net_sys: NetSystem<H::NetHandler>,
+        channels: Vec<NetSystem<H::NetHandler>>,
         cdc: <H::NetHandler as NetSystemHandler>::Codec,
+        cash_shop_cdc: <H::NetHandler as NetSystemHandler>::Codec,
         ctx: H::Ctx,
     ) -> Self {
         //let acceptor = ServerAcceptor::new(net, cdc.clone(), sys.handle());
         Self {
             _handler: PhantomData,
+            cash_shop_task: CashShopTask {
+                cash_shop: RpcListener::new(cash_shop_cdc, ctx.clone()),
+                bind_addr: cfg.bind_addr,
+                port: cfg.cash_shop_port,
+            },
             login_task: LoginTask {
                 login: RpcListener::new(cdc, ctx),
                 bind_addr: cfg.bind_addr,
                 port: cfg.login_port,
             },
-            net_sys,
+            channels,
             addr: cfg.bind_addr,
             game_ports: cfg.game_ports.clone(),
             /*channel_task: ChannelTask {
@@ -82,10 +109,25 @@ impl<H: RuntimeHandler> ServerRuntime<H> {
         }
     }
 
-    pub async fn run(mut self) -> anyhow::Result<()> {
+    pub async fn run(self) -> anyhow::Result<()> {
         let _login = SupervisedTaskHandle::spawn(self.login_task, (), Duration::from_secs(1));
-        self.net_sys.spawn_acceptors(self.addr, self.game_ports);
-        self.net_sys.run().await.unwrap();
+        let _cash_shop =
+            SupervisedTaskHandle::spawn(self.cash_shop_task, (), Duration::from_secs(1));
+        anyhow::ensure!(
+            self.channels.len() == self.game_ports.len(),
+            "Expected a game port for each of the {} channels",
+            self.channels.len()
+        );
+
+        let systems = self
+            .channels
+            .into_iter()
+            .zip(self.game_ports)
+            .map(|(mut net_sys, port)| {
+                net_sys.spawn_acceptors(self.addr, port..=port);
+                net_sys.run()
+            });
+        futures::future::try_join_all(systems).await.unwrap();
         Ok(())
     }
 }
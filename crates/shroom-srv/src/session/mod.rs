@@ -147,6 +147,11 @@ impl<Key: SessionKey, Data, Mapped> OwnedMappedSession<Key, Data, Mapped> {
     pub fn unmap(self) -> OwnedSession<Key, Data> {
         self.session
     }
+
+    /// Obtain the key of the owned session
+    pub fn key(&self) -> &Key {
+        self.session.key()
+    }
 }
 
 impl<Key: SessionKey, Data, Mapped> Deref for OwnedMappedSession<Key, Data, Mapped> {
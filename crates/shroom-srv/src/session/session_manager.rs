@@ -112,6 +112,14 @@ where
         Ok(())
     }
 
+    /// Saves the session data, without closing the session
+    pub async fn save(
+        &self,
+        session: &mut OwnedSession<Key, B::Data>,
+    ) -> Result<(), Error<B::Error>> {
+        self.backend.save(session).await.map_err(Error::Backend)
+    }
+
     /// Remove all un-owned session
     /// acts essentially like a life-cycle
     pub async fn remove_unowned_session(&self) -> Result<(), Error<B::Error>> {